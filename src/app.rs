@@ -16,28 +16,38 @@
 
 use ::serde::{Deserialize, Serialize};
 use aws_config::{
-    meta::region::RegionProviderChain, retry::RetryConfig, BehaviorVersion, Region, SdkConfig,
+    meta::region::RegionProviderChain, profile::ProfileFileRegionProvider, retry::RetryConfig,
+    sts::AssumeRoleProvider, BehaviorVersion, Region, SdkConfig,
+};
+use aws_sdk_dynamodb::config::{Credentials, SharedCredentialsProvider};
+use aws_sdk_dynamodb::types::{
+    AttributeDefinition, AttributeValue, ConsumedCapacity, ReturnConsumedCapacity, TableDescription,
 };
-use aws_sdk_dynamodb::types::{AttributeDefinition, TableDescription};
 use aws_smithy_runtime_api::client::result::SdkError;
 use aws_smithy_types::error::metadata::ProvideErrorMetadata;
-use log::{debug, error, info};
-use serde_yaml::Error as SerdeYAMLError;
+use chrono::{DateTime, Utc};
+use dialoguer::{Confirm, Input};
+use fd_lock::RwLock;
+use log::{debug, error, info, warn};
+use serde_yaml::{Error as SerdeYAMLError, Mapping, Value};
 use std::convert::{TryFrom, TryInto};
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, VecDeque},
     env, error,
     fmt::{self, Formatter},
     fs,
-    io::Error as IOError,
+    io::{self, Error as IOError, Write},
     path,
 };
 use tempfile::NamedTempFile;
 use thiserror::Error;
+use tokio::sync::OnceCell;
 
 use super::control;
 use super::ddb::{key, table};
+use super::output::{ColorMode, OutputFormat};
 
 /* =================================================
 struct / enum / const
@@ -46,14 +56,29 @@ struct / enum / const
 const CONFIG_DIR: &str = ".dynein";
 const CONFIG_PATH_ENV_VAR_NAME: &str = "DYNEIN_CONFIG_DIR";
 const CONFIG_FILE_NAME: &str = "config.yml";
+const PROJECT_CONFIG_FILE_NAME: &str = ".dynein.yml";
 const CACHE_FILE_NAME: &str = "cache.yml";
+const HISTORY_FILE_NAME: &str = "history";
 const LOCAL_REGION: &str = "local";
+const MAX_RECENT_ATTRIBUTE_NAMES: usize = 256;
+const ROLE_SESSION_NAME: &str = "dynein";
+const DEFAULT_CACHE_EXPIRATION_TIME: u64 = 300; // in second (= 5 minutes)
 
+#[derive(Clone, Copy)]
 pub enum DyneinFileType {
     ConfigFile,
     CacheFile,
 }
 
+/// A config file which is layered to build the effective config.
+/// `.dynein.yml` found in the current directory or its parents is layered over `~/.dynein/config.yml`.
+struct ConfigLayer {
+    /// Name of the layer shown by `dy config dump`, i.e. "user" or "project".
+    name: &'static str,
+    path: String,
+    value: Value,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TableSchema {
     pub region: String,
@@ -62,6 +87,10 @@ pub struct TableSchema {
     pub sk: Option<key::Key>,
     pub indexes: Option<Vec<IndexSchema>>,
     pub mode: table::Mode,
+    /// When the schema was retrieved by DescribeTable API, in RFC 3339 format.
+    /// Schemas without this field (e.g. cached by an older version) are treated as expired.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_at: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -99,6 +128,7 @@ To find all tables in all regions, try:
 
 /// Config is saved at `~/.dynein/config.yml`.
 /// using_region and using_table are changed when you execute `dy use` command.
+/// When a named context is selected by `dy config use-context`, the context is used (and updated by `dy use`) instead of them.
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Config {
     pub using_region: Option<String>,
@@ -106,11 +136,52 @@ pub struct Config {
     pub using_port: Option<u32>,
     #[serde(default)]
     pub query: QueryConfig,
-    // pub cache_expiration_time: Option<i64>, // in second. default 300 (= 5 minutes)
+    /// Default output format of read commands, which is used unless --output is given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputFormat>,
+    /// Cached table schemas older than this are refreshed by DescribeTable API. In second, default 300 (= 5 minutes).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_expiration_time: Option<u64>,
     pub retry: Option<RetrySettingGlobal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_context: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub contexts: BTreeMap<String, ContextConfig>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+/// A named set of settings for an environment, e.g. `dev`, `staging` or `local-docker`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ContextConfig {
+    /// Region name, or `local` for DynamoDB Local on localhost.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Port of DynamoDB Local. This has an effect only when region is `local`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u32>,
+    /// Named profile in AWS config/credentials files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Custom endpoint such as LocalStack, ScyllaDB Alternator or DynamoDB Local on a remote host.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint_url: Option<String>,
+    /// IAM role to assume with STS, using credentials of the profile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role_arn: Option<String>,
+    /// External ID to assume the role.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    /// Serial number (or ARN) of the MFA device to assume the role.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mfa_serial: Option<String>,
+    /// Table to use by default, which is changed by `dy use`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
+    /// Overrides the top-level retry setting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetrySettingGlobal>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct RetrySettingGlobal {
     pub default: RetrySetting,
     pub batch_write_item: Option<RetrySetting>,
@@ -132,7 +203,7 @@ impl TryFrom<RetrySettingGlobal> for Retry {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct RetrySetting {
     pub initial_backoff: Option<Duration>,
     pub max_backoff: Option<Duration>,
@@ -149,6 +220,13 @@ impl Default for RetrySetting {
     }
 }
 
+/// Error of a command which has already printed why it failed, e.g. by `bye`.
+/// A command given on the command line exits with the code, while the shell continues to the next command.
+#[derive(Error, Debug)]
+#[must_use]
+#[error("exited with status {0}")]
+pub struct DyneinExit(pub i32);
+
 #[derive(Error, Debug)]
 pub enum RetryConfigError {
     #[error("max_attempts should be greater than zero")]
@@ -188,16 +266,13 @@ pub struct QueryConfig {
 }
 
 /// Cache is saved at `~/.dynein/cache.yml`
-/// Cache contains retrieved info of tables, and how fresh they are (cache_created_at).
-/// Currently Cache struct doesn't manage freshness of each table.
-/// i.e. Entire cache will be removed after cache_expiration_time in Config has passed.
+/// Cache contains retrieved info of tables, and how fresh they are (cached_at of each table).
+/// A table schema is refreshed when cache_expiration_time in Config has passed since it was cached.
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Cache {
     /// cached table schema information.
     /// table schemas are stored in keys to identify the target table "<Region>/<TableName>" -- e.g. "ap-northeast-1/Employee"
     pub tables: Option<HashMap<String, TableSchema>>,
-    // pub cache_updated_at: String,
-    // pub cache_created_at: String,
 }
 
 #[derive(Debug, Clone)]
@@ -206,6 +281,14 @@ pub struct Retry {
     pub batch_write_item: Option<RetryConfig>,
 }
 
+/// IAM role to assume with STS, given by --role-arn or `role_arn` of the current context.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssumeRoleSetting {
+    pub role_arn: String,
+    pub external_id: Option<String>,
+    pub mfa_serial: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Context {
     pub config: Option<Config>,
@@ -213,9 +296,332 @@ pub struct Context {
     pub overwritten_region: Option<Region>, // --region option
     pub overwritten_table_name: Option<String>, // --table option
     pub overwritten_port: Option<u32>,      // --port option
-    pub output: Option<String>,
+    pub overwritten_endpoint_url: Option<String>, // endpoint given to a table of `dy diff` and `dy copy`, e.g. Movie@http://localhost:4566
+    pub overwritten_profile: Option<String>,      // --profile option
+    pub overwritten_assume_role: Option<AssumeRoleSetting>, // --role-arn, --external-id and --mfa-serial options
+    pub assumed_role_credentials: Arc<OnceCell<SharedCredentialsProvider>>, // the role is assumed once and shared in the process
+    pub output: Option<OutputFormat>, // --output option of the command
+    pub default_output: Option<OutputFormat>, // top-level --output option, or `set output` in shell mode
+    pub color: ColorMode,
     pub should_strict_for_query: Option<bool>,
     pub retry: Option<Retry>,
+    pub non_interactive: bool, // --non-interactive option, or stdin is not a terminal
+    pub recent_attribute_names: Arc<Mutex<VecDeque<String>>>, // used for tab completion in shell mode
+    pub metrics: Option<Arc<Mutex<RequestMetrics>>>, // collected with --show-capacity, or while `\timing` is on in shell mode
+}
+
+/// Whether a request reads or writes items. Capacity units are reported as RCU or WCU accordingly
+/// when DynamoDB doesn't break them down, e.g. DynamoDB Local.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RequestKind {
+    Read,
+    Write,
+}
+
+/// Capacity units consumed by a table or an index.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CapacityUnits {
+    pub read: f64,
+    pub write: f64,
+}
+
+/// Metrics of the requests sent by a command, which are shown by --show-capacity and `\timing`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RequestMetrics {
+    pub requests: u32,
+    pub latency: Duration,                         // summed up over requests
+    pub capacity: BTreeMap<String, CapacityUnits>, // keyed by table name, or `<table> (index: <index>)`
+    pub scanned_count: i64,
+    pub returned_count: i64,
+    pub counted: bool, // whether any Scan or Query reported item counts
+}
+
+impl RequestMetrics {
+    fn add_capacity(&mut self, kind: RequestKind, capacity: &ConsumedCapacity) {
+        let table = capacity.table_name().unwrap_or("-");
+        // Breakdown by table and index is given only when ReturnConsumedCapacity is Indexes.
+        let mut entries = vec![];
+        match capacity.table() {
+            Some(c) => entries.push((
+                table.to_owned(),
+                c.read_capacity_units,
+                c.write_capacity_units,
+                c.capacity_units,
+            )),
+            None => entries.push((
+                table.to_owned(),
+                capacity.read_capacity_units,
+                capacity.write_capacity_units,
+                capacity.capacity_units,
+            )),
+        }
+        let indexes = capacity
+            .global_secondary_indexes()
+            .into_iter()
+            .chain(capacity.local_secondary_indexes())
+            .flatten();
+        for (name, c) in indexes {
+            entries.push((
+                format!("{} (index: {})", table, name),
+                c.read_capacity_units,
+                c.write_capacity_units,
+                c.capacity_units,
+            ));
+        }
+
+        for (name, read, write, total) in entries {
+            let (read, write) = match (read, write, kind) {
+                (None, None, RequestKind::Read) => (total, None),
+                (None, None, RequestKind::Write) => (None, total),
+                (read, write, _) => (read, write),
+            };
+            let units = self.capacity.entry(name).or_default();
+            units.read += read.unwrap_or(0.0);
+            units.write += write.unwrap_or(0.0);
+        }
+    }
+}
+
+impl fmt::Display for RequestMetrics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Requests: {}, latency: {:.3} ms",
+            self.requests,
+            self.latency.as_secs_f64() * 1000.0
+        )?;
+        if self.capacity.is_empty() {
+            writeln!(f, "Consumed capacity: not reported")?;
+        } else {
+            writeln!(f, "Consumed capacity:")?;
+            for (name, units) in &self.capacity {
+                writeln!(f, "  {}: {} RCU, {} WCU", name, units.read, units.write)?;
+            }
+        }
+        if self.counted {
+            writeln!(
+                f,
+                "Items: {} scanned, {} returned",
+                self.scanned_count, self.returned_count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A table given as `TABLE[@WHERE]` to commands working with two tables, e.g. `Movie@us-west-2`.
+/// WHERE is a region, `local` (or `local:PORT`) for DynamoDB Local, an endpoint such as `http://localhost:4566`,
+/// or a context in the config file. Without it, the current context is used.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TableLocation {
+    pub table: String,
+    /// Region name, or name of a context in the config file.
+    pub region: Option<String>,
+    pub port: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint_url: Option<String>,
+}
+
+impl std::str::FromStr for TableLocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (table, region) = match s.split_once('@') {
+            Some((table, region)) => (table, Some(region)),
+            None => (s, None),
+        };
+        if table.is_empty() || region == Some("") {
+            return Err(format!(
+                "'{}' is not a table. Give it as TABLE or TABLE@WHERE, e.g. Movie@us-west-2, Movie@local:8001 or Movie@http://localhost:4566.",
+                s
+            ));
+        }
+        let mut location = TableLocation {
+            table: table.to_owned(),
+            region: None,
+            port: None,
+            endpoint_url: None,
+        };
+        match region {
+            Some(r) if r.starts_with("http://") || r.starts_with("https://") => {
+                location.endpoint_url = Some(r.to_owned());
+            }
+            Some(r) => match r.split_once(':') {
+                Some((LOCAL_REGION, port)) => match port.parse() {
+                    Ok(port) => {
+                        location.region = Some(LOCAL_REGION.to_owned());
+                        location.port = Some(port);
+                    }
+                    Err(_) => return Err(format!("'{}' is not a port number.", port)),
+                },
+                Some(_) => return Err(String::from("A port can be given only to `@local`.")),
+                None => location.region = Some(r.to_owned()),
+            },
+            None => {}
+        }
+        Ok(location)
+    }
+}
+
+impl fmt::Display for TableLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.table)?;
+        if let Some(endpoint_url) = &self.endpoint_url {
+            return write!(f, "@{}", endpoint_url);
+        }
+        if let Some(region) = &self.region {
+            write!(f, "@{}", region)?;
+        }
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        Ok(())
+    }
+}
+
+impl TableLocation {
+    /// Returns a context whose target is the table.
+    pub fn context(&self, cx: &Context) -> Context {
+        let mut cx = cx.clone();
+        if let Some(region) = &self.region {
+            cx = if cx.has_context(region) {
+                cx.with_context(region)
+            } else {
+                cx.with_region(region)
+            };
+        }
+        if let Some(port) = self.port {
+            cx = cx.with_port(port);
+        }
+        if let Some(endpoint_url) = &self.endpoint_url {
+            cx = cx.with_endpoint_url(endpoint_url);
+        }
+        cx.with_table(&self.table)
+    }
+
+    /// Returns the location with the region, the port of DynamoDB Local and the endpoint in use filled in,
+    /// e.g. `Movie@us-east-1` for `Movie`, to show which table is used.
+    pub async fn resolve(&self, cx: &Context) -> TableLocation {
+        let cx = self.context(cx);
+        let region = cx.effective_region().await.to_string();
+        let endpoint_url = cx.custom_endpoint_url().map(str::to_owned);
+        TableLocation {
+            table: self.table.to_owned(),
+            port: (endpoint_url.is_none() && region == LOCAL_REGION).then(|| cx.effective_port()),
+            region: Some(region),
+            endpoint_url,
+        }
+    }
+}
+
+/// What a confirmation prompt does when it cannot be shown, i.e. in non-interactive mode.
+pub enum Unattended<'a> {
+    /// Proceed as if the user answered yes.
+    Proceed,
+    /// Fail with an error message which tells how to proceed without a prompt (e.g. passing --yes).
+    Fail(&'a str),
+}
+
+impl TableSchema {
+    /// Builds the schema from the result of DescribeTable API, marked as cached now.
+    pub fn from_description(region: &str, desc: &TableDescription) -> Self {
+        TableSchema {
+            region: String::from(region),
+            name: desc
+                .table_name
+                .to_owned()
+                .expect("desc should have table name"),
+            pk: key::typed_key("HASH", desc).expect("pk should exist"),
+            sk: key::typed_key("RANGE", desc),
+            indexes: index_schemas(desc),
+            mode: table::extract_mode(&desc.billing_mode_summary),
+            cached_at: Some(Utc::now().to_rfc3339()),
+        }
+    }
+
+    /// Returns true if the schema was cached within the given expiration time.
+    pub fn is_fresh(&self, expiration_time: Duration) -> bool {
+        let cached_at = match self
+            .cached_at
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        {
+            Some(cached_at) => cached_at,
+            None => return false,
+        };
+        // A negative elapsed time (i.e. the clock went back) is treated as fresh.
+        Utc::now()
+            .signed_duration_since(cached_at)
+            .to_std()
+            .map_or(true, |elapsed| elapsed < expiration_time)
+    }
+}
+
+impl Config {
+    /// Returns the context selected by `dy config use-context`, if any.
+    pub fn current_context(&self) -> Option<&ContextConfig> {
+        self.current_context
+            .as_ref()
+            .and_then(|name| self.contexts.get(name))
+    }
+
+    pub fn cache_expiration(&self) -> Duration {
+        Duration::from_secs(
+            self.cache_expiration_time
+                .unwrap_or(DEFAULT_CACHE_EXPIRATION_TIME),
+        )
+    }
+
+    pub fn using_region(&self) -> Option<&str> {
+        match self.current_context() {
+            Some(context) => context.region.as_deref(),
+            None => self.using_region.as_deref(),
+        }
+    }
+
+    pub fn using_table(&self) -> Option<&str> {
+        match self.current_context() {
+            Some(context) => context.table.as_deref(),
+            None => self.using_table.as_deref(),
+        }
+    }
+
+    pub fn using_port(&self) -> Option<u32> {
+        match self.current_context() {
+            Some(context) => context.port,
+            None => self.using_port,
+        }
+    }
+
+    /// Selects the context to use. `None` selects the top-level using_region/using_table/using_port.
+    pub fn use_context(&mut self, name: Option<String>) -> Result<(), DyneinConfigContentError> {
+        if let Some(name) = &name {
+            if !self.contexts.contains_key(name) {
+                return Err(DyneinConfigContentError::UnknownContext(name.to_owned()));
+            }
+        }
+        self.current_context = name;
+        Ok(())
+    }
+
+    /// Saves the target of `dy use` into the current context, or into top-level fields if no context is selected.
+    fn set_using_target(&mut self, region: String, table: String, port: u32) {
+        let current_context = self.current_context.clone();
+        match current_context.and_then(|name| self.contexts.get_mut(&name)) {
+            Some(context) => {
+                if region == LOCAL_REGION {
+                    context.port = Some(port);
+                }
+                context.region = Some(region);
+                context.table = Some(table);
+            }
+            None => {
+                self.using_region = Some(region);
+                self.using_table = Some(table);
+                self.using_port = Some(port);
+            }
+        }
+    }
 }
 
 /*
@@ -229,8 +635,21 @@ impl Context {
         port: Option<u32>,
         table: Option<String>,
     ) -> Result<Context, DyneinConfigError> {
-        let config = load_or_touch_config_file(true)?;
-        let retry = match &config.retry {
+        let config = load_layered_config()?;
+        if let Some(name) = &config.current_context {
+            if config.current_context().is_none() {
+                warn!(
+                    "context '{}' is not defined in the config file. Top-level settings are used instead.",
+                    name
+                );
+            }
+        }
+        // The retry setting of the current context takes precedence over the top-level one.
+        let retry_setting = config
+            .current_context()
+            .and_then(|c| c.retry.as_ref())
+            .or(config.retry.as_ref());
+        let retry = match retry_setting {
             Some(retry) => Some(Retry::try_from(retry.clone()).map_err(|e| {
                 DyneinConfigError::Content(DyneinConfigContentError::RetryConfig(e))
             })?),
@@ -242,9 +661,18 @@ impl Context {
             overwritten_region: region_from_str(region),
             overwritten_table_name: table,
             overwritten_port: port,
+            overwritten_endpoint_url: None,
+            overwritten_profile: None,
+            overwritten_assume_role: None,
+            assumed_role_credentials: Arc::new(OnceCell::new()),
             output: None,
+            default_output: None,
+            color: ColorMode::Auto,
             should_strict_for_query: None,
             retry,
+            non_interactive: false,
+            recent_attribute_names: Arc::new(Mutex::new(VecDeque::new())),
+            metrics: None,
         })
     }
 
@@ -278,8 +706,14 @@ impl Context {
 
         let provider = RegionProviderChain::first_try(sdk_region);
         let mut config = aws_config::defaults(BehaviorVersion::v2024_03_28()).region(provider);
-        if self.is_local().await {
-            config = config.endpoint_url(format!("http://localhost:{}", self.effective_port()));
+        if let Some(endpoint_url) = self.effective_endpoint_url().await {
+            config = config.endpoint_url(endpoint_url);
+        }
+        if let Some(profile) = self.effective_profile() {
+            config = config.profile_name(profile);
+        }
+        if let Some(credentials) = self.assumed_role_credentials(region_name).await {
+            config = config.credentials_provider(credentials);
         }
 
         if let Some(retry_config) = retry_config {
@@ -295,14 +729,27 @@ impl Context {
             return ow_region.to_owned();
         };
 
-        // next, if there's an `using_region` field in the config file, use it.
+        // next, if there's an `using_region` field (or region of the current context) in the config file, use it.
         if let Some(using_region_name_in_config) =
-            &self.config.to_owned().and_then(|x| x.using_region)
+            self.config.as_ref().and_then(|x| x.using_region())
         {
             return region_from_str(Some(using_region_name_in_config.to_owned())) // Option<Region>
                 .expect("Region name in the config file is invalid.");
         };
 
+        // next, region of the AWS profile given by the current context.
+        if let Some(profile) = self.effective_profile() {
+            let profile_region = ProfileFileRegionProvider::builder()
+                .profile_name(profile)
+                .build();
+            if let Some(region) = RegionProviderChain::first_try(profile_region)
+                .region()
+                .await
+            {
+                return region;
+            }
+        }
+
         // otherwise, come down to "default region" of your environment.
         // e.g. region set via AWS CLI (check: $ aws configure get region), or environment variable `AWS_DEFAULT_REGION`.
         //      ref: https://docs.aws.amazon.com/cli/latest/userguide/cli-configure-envvars.html
@@ -313,20 +760,18 @@ impl Context {
             .unwrap_or(Region::from_static("us-east-1"))
     }
 
-    pub fn effective_table_name(&self) -> String {
+    pub fn effective_table_name(&self) -> Result<String, DyneinExit> {
         // if table is overwritten by --table option, use it.
         if let Some(ow_table_name) = &self.overwritten_table_name {
-            return ow_table_name.to_owned();
+            return Ok(ow_table_name.to_owned());
         };
         // otherwise, retrieve an `using_table` from config file.
-        self.to_owned()
-            .config
-            .and_then(|x| x.using_table)
-            .unwrap_or_else(|| {
-                // if both --option nor config file are not available, raise error and exit the command.
-                error!("{}", Messages::NoEffectiveTable);
-                std::process::exit(1)
-            })
+        self.config
+            .as_ref()
+            .and_then(|x| x.using_table())
+            .map(str::to_owned)
+            // if both --option nor config file are not available, raise error and end the command.
+            .ok_or_else(|| bye_with_error(Messages::NoEffectiveTable))
     }
 
     pub fn effective_port(&self) -> u32 {
@@ -334,28 +779,174 @@ impl Context {
             return ow_port.to_owned();
         };
 
-        if let Some(using_port_in_config) = &self.config.to_owned().and_then(|x| x.using_port) {
-            return using_port_in_config.to_owned();
+        if let Some(using_port_in_config) = self.config.as_ref().and_then(|x| x.using_port()) {
+            return using_port_in_config;
         };
 
         8000
     }
 
-    pub async fn effective_cache_key(&self) -> String {
-        format!(
-            "{}/{}",
-            self.effective_region().await.as_ref(),
-            self.effective_table_name()
+    /// Returns the AWS profile given by --profile or the current context.
+    pub fn effective_profile(&self) -> Option<String> {
+        if let Some(profile) = &self.overwritten_profile {
+            return Some(profile.to_owned());
+        }
+        self.config
+            .as_ref()
+            .and_then(|c| c.current_context())
+            .and_then(|c| c.profile.clone())
+    }
+
+    /// Returns the role to assume given by --role-arn or the current context.
+    pub fn effective_assume_role(&self) -> Option<AssumeRoleSetting> {
+        if let Some(assume_role) = &self.overwritten_assume_role {
+            return Some(assume_role.to_owned());
+        }
+        let context = self.config.as_ref()?.current_context()?;
+        Some(AssumeRoleSetting {
+            role_arn: context.role_arn.clone()?,
+            external_id: context.external_id.clone(),
+            mfa_serial: context.mfa_serial.clone(),
+        })
+    }
+
+    /// Returns credentials of the assumed role if a role is given. The role is assumed with credentials of the profile
+    /// (or the default credentials) at the first call, and the credentials are shared by the following calls.
+    async fn assumed_role_credentials(
+        &self,
+        region_name: &str,
+    ) -> Option<SharedCredentialsProvider> {
+        let assume_role = self.effective_assume_role()?;
+        // DynamoDB Local doesn't verify credentials.
+        if region_name == LOCAL_REGION {
+            return None;
+        }
+
+        let credentials = self
+            .assumed_role_credentials
+            .get_or_init(|| async {
+                // STS is called without the custom endpoint, which is for DynamoDB.
+                let mut base_config = aws_config::defaults(BehaviorVersion::v2024_03_28())
+                    .region(Region::new(region_name.to_owned()));
+                if let Some(profile) = self.effective_profile() {
+                    base_config = base_config.profile_name(profile);
+                }
+                let base_config = base_config.load().await;
+
+                match &assume_role.mfa_serial {
+                    // AssumeRoleProvider refreshes credentials before they expire.
+                    None => {
+                        let mut builder = AssumeRoleProvider::builder(assume_role.role_arn)
+                            .session_name(ROLE_SESSION_NAME)
+                            .configure(&base_config);
+                        if let Some(external_id) = assume_role.external_id {
+                            builder = builder.external_id(external_id);
+                        }
+                        SharedCredentialsProvider::new(builder.build().await)
+                    }
+                    // AssumeRoleProvider doesn't support MFA, so the role is assumed once with the code given by the user.
+                    Some(mfa_serial) => SharedCredentialsProvider::new(
+                        self.assume_role_with_mfa(&base_config, &assume_role, mfa_serial)
+                            .await,
+                    ),
+                }
+            })
+            .await;
+        Some(credentials.clone())
+    }
+
+    async fn assume_role_with_mfa(
+        &self,
+        base_config: &SdkConfig,
+        assume_role: &AssumeRoleSetting,
+        mfa_serial: &str,
+    ) -> Credentials {
+        // The assumed role is shared by all commands of the process, so dynein exits when it cannot be assumed.
+        if self.non_interactive {
+            error!(
+                "An MFA code is required to assume the role '{}', but it cannot be asked in non-interactive mode.",
+                assume_role.role_arn
+            );
+            std::process::exit(1);
+        }
+        let token_code: String = Input::new()
+            .with_prompt(format!("MFA code for {}", mfa_serial))
+            .interact_text()
+            .unwrap_or_else(|e| {
+                error!("Failed to read the MFA code: {}", e);
+                std::process::exit(1);
+            });
+
+        let sts = aws_sdk_sts::Client::new(base_config);
+        let res = sts
+            .assume_role()
+            .role_arn(&assume_role.role_arn)
+            .role_session_name(ROLE_SESSION_NAME)
+            .set_external_id(assume_role.external_id.clone())
+            .serial_number(mfa_serial)
+            .token_code(token_code.trim())
+            .send()
+            .await
+            .unwrap_or_else(|e| std::process::exit(bye_with_sdk_error(1, e).0));
+        let credentials = res
+            .credentials
+            .expect("AssumeRole should return credentials");
+        Credentials::new(
+            credentials.access_key_id,
+            credentials.secret_access_key,
+            Some(credentials.session_token),
+            SystemTime::try_from(credentials.expiration).ok(),
+            "AssumeRoleWithMfa",
         )
     }
 
+    /// Returns the endpoint given by `with_endpoint_url`, or the endpoint of the current context.
+    /// The endpoint of the current context is ignored when --region is given.
+    fn custom_endpoint_url(&self) -> Option<&str> {
+        if let Some(endpoint_url) = &self.overwritten_endpoint_url {
+            return Some(endpoint_url);
+        }
+        if self.overwritten_region.is_some() {
+            return None;
+        }
+        self.config
+            .as_ref()
+            .and_then(|c| c.current_context())
+            .and_then(|c| c.endpoint_url.as_deref())
+    }
+
+    /// Returns the endpoint to send requests to, if it's not the default endpoint of the region.
+    pub async fn effective_endpoint_url(&self) -> Option<String> {
+        if let Some(endpoint_url) = self.custom_endpoint_url() {
+            return Some(endpoint_url.to_owned());
+        }
+        if self.is_local().await {
+            return Some(format!("http://localhost:{}", self.effective_port()));
+        }
+        None
+    }
+
+    pub async fn effective_cache_key(&self) -> Result<String, DyneinExit> {
+        Ok(self.cache_key(
+            self.effective_region().await.as_ref(),
+            &self.effective_table_name()?,
+        ))
+    }
+
+    /// Key of a table schema in the cache file, e.g. "ap-northeast-1/Employee".
+    /// Tables behind a custom endpoint are distinguished from tables of the same region in AWS,
+    /// e.g. "http://localhost:4566/us-east-1/Employee".
+    pub fn cache_key(&self, region: &str, table_name: &str) -> String {
+        match self.custom_endpoint_url() {
+            Some(endpoint_url) => format!("{}/{}/{}", endpoint_url, region, table_name),
+            None => format!("{}/{}", region, table_name),
+        }
+    }
+
     pub async fn cached_using_table_schema(&self) -> Option<TableSchema> {
         // return None if table name is not specified in both config and option.
         if self.overwritten_table_name.is_none() {
-            match self.config.to_owned() {
-                Some(c) => c.using_table?,
-                None => return None,
-            };
+            self.config.as_ref()?.using_table()?;
         }
 
         let cached_tables: HashMap<String, TableSchema> =
@@ -364,7 +955,7 @@ impl Context {
                 None => return None, // return None for this "cached_using_table_schema" function
             };
         let found_table_schema: Option<&TableSchema> =
-            cached_tables.get(&self.effective_cache_key().await);
+            cached_tables.get(&self.effective_cache_key().await.ok()?);
         // NOTE: HashMap's `get` returns a reference to the value / (&self, k: &Q) -> Option<&V>
         found_table_schema.map(|schema| schema.to_owned())
     }
@@ -379,11 +970,142 @@ impl Context {
         self
     }
 
+    pub fn with_port(mut self, port: u32) -> Self {
+        self.overwritten_port = Some(port);
+        self
+    }
+
+    pub fn with_endpoint_url(mut self, endpoint_url: &str) -> Self {
+        self.overwritten_endpoint_url = Some(endpoint_url.to_owned());
+        self
+    }
+
+    /// Switches to the context of the config file, dropping options given for the current context
+    /// such as --region and --profile. The table given by --table is kept.
+    pub fn with_context(mut self, name: &str) -> Self {
+        if let Some(config) = self.config.as_mut() {
+            config.current_context = Some(name.to_owned());
+        }
+        self.overwritten_region = None;
+        self.overwritten_port = None;
+        self.overwritten_endpoint_url = None;
+        self.overwritten_profile = None;
+        self.overwritten_assume_role = None;
+        self.assumed_role_credentials = Arc::new(OnceCell::new());
+        self
+    }
+
+    /// Returns true if a context with the name is defined in the config file.
+    pub fn has_context(&self, name: &str) -> bool {
+        self.config
+            .as_ref()
+            .map_or(false, |c| c.contexts.contains_key(name))
+    }
+
+    /// How long a cached table schema is used without calling DescribeTable API.
+    pub fn cache_expiration(&self) -> Duration {
+        self.config
+            .as_ref()
+            .map_or(Duration::from_secs(DEFAULT_CACHE_EXPIRATION_TIME), |c| {
+                c.cache_expiration()
+            })
+    }
+
+    /// Returns the output format for the command. --output of the command is used if given.
+    /// Otherwise the default (top-level --output, `set output` in shell mode, or `output` in config.yml) is used
+    /// if the command supports it, and lastly the command's own default.
+    pub fn output_format(&self, supported: &[OutputFormat], default: OutputFormat) -> OutputFormat {
+        if let Some(output) = self.output {
+            return output;
+        }
+        self.default_output
+            .or_else(|| self.config.as_ref().and_then(|c| c.output))
+            .filter(|output| supported.contains(output))
+            .unwrap_or(default)
+    }
+
     pub fn should_strict_for_query(&self) -> bool {
         self.should_strict_for_query
             .unwrap_or_else(|| self.config.as_ref().map_or(false, |c| c.query.strict_mode))
     }
 
+    /// Asks a yes/no question to the user. The prompt is skipped and treated as "yes" when `yes` (i.e. --yes) is given.
+    /// In non-interactive mode, the prompt is never shown and `unattended` decides whether to proceed or fail.
+    pub fn confirm(
+        &self,
+        msg: &str,
+        yes: bool,
+        unattended: Unattended,
+    ) -> Result<bool, DyneinExit> {
+        if yes {
+            return Ok(true);
+        }
+        if self.non_interactive {
+            return match unattended {
+                Unattended::Proceed => {
+                    eprintln!("{} -> proceeding in non-interactive mode.", msg);
+                    Ok(true)
+                }
+                Unattended::Fail(hint) => Err(bye_with_error(format!(
+                    "{} -> cannot ask in non-interactive mode. {}",
+                    msg, hint
+                ))),
+            };
+        }
+        Confirm::new()
+            .with_prompt(msg)
+            .interact()
+            .map_err(bye_with_error)
+    }
+
+    /// Remembers attribute names of items shown to the user, so that the shell can complete them.
+    /// The most recently seen names come first, and only MAX_RECENT_ATTRIBUTE_NAMES names are kept.
+    pub fn remember_attribute_names(&self, items: &[HashMap<String, AttributeValue>]) {
+        let mut names = self
+            .recent_attribute_names
+            .lock()
+            .expect("recent_attribute_names should not be poisoned");
+        for name in items.iter().flat_map(|item| item.keys()) {
+            if let Some(pos) = names.iter().position(|n| n == name) {
+                names.remove(pos);
+            }
+            names.push_front(name.to_owned());
+        }
+        names.truncate(MAX_RECENT_ATTRIBUTE_NAMES);
+    }
+
+    /// Returns `Some(Indexes)` to pass to ReturnConsumedCapacity of requests while metrics are collected.
+    pub fn return_consumed_capacity(&self) -> Option<ReturnConsumedCapacity> {
+        self.metrics
+            .as_ref()
+            .map(|_| ReturnConsumedCapacity::Indexes)
+    }
+
+    /// Records a request which started at `started_at`, if metrics are collected.
+    /// `counts` are ScannedCount and Count of Scan and Query.
+    pub fn add_request_metrics<'a>(
+        &self,
+        kind: RequestKind,
+        started_at: Instant,
+        capacity: impl IntoIterator<Item = &'a ConsumedCapacity>,
+        counts: Option<(i32, i32)>,
+    ) {
+        let Some(metrics) = &self.metrics else {
+            return;
+        };
+        let mut metrics = metrics.lock().expect("metrics should not be poisoned");
+        metrics.requests += 1;
+        metrics.latency += started_at.elapsed();
+        for c in capacity {
+            metrics.add_capacity(kind, c);
+        }
+        if let Some((scanned, returned)) = counts {
+            metrics.scanned_count += i64::from(scanned);
+            metrics.returned_count += i64::from(returned);
+            metrics.counted = true;
+        }
+    }
+
     pub async fn is_local(&self) -> bool {
         let region = self.effective_region();
         region.await.as_ref() == LOCAL_REGION
@@ -392,8 +1114,18 @@ impl Context {
 
 #[derive(Error, Debug)]
 pub enum DyneinConfigContentError {
-    #[error("retry config error ")]
+    #[error("retry config error: {0}")]
     RetryConfig(#[from] RetryConfigError),
+    #[error("context '{0}' is not defined in the config file")]
+    UnknownContext(String),
+    #[error("unknown key '{0}'")]
+    UnknownKey(String),
+    #[error("'{0}' is not set")]
+    NotSet(String),
+    #[error("invalid value for '{key}': {message}")]
+    InvalidValue { key: String, message: String },
+    #[error("{path}: {message}")]
+    InvalidFile { path: String, message: String },
 }
 
 // FYI: https://doc.rust-lang.org/rust-by-example/error/multiple_error_types/wrap_error.html
@@ -403,6 +1135,7 @@ pub enum DyneinConfigError {
     Yaml(SerdeYAMLError),
     HomeDir,
     Content(DyneinConfigContentError),
+    Exit(DyneinExit),
 }
 
 impl fmt::Display for DyneinConfigError {
@@ -412,6 +1145,7 @@ impl fmt::Display for DyneinConfigError {
             DyneinConfigError::Yaml(ref e) => e.fmt(f),
             DyneinConfigError::HomeDir => write!(f, "failed to find Home directory"),
             DyneinConfigError::Content(ref e) => e.fmt(f),
+            DyneinConfigError::Exit(ref e) => e.fmt(f),
         }
     }
 }
@@ -425,6 +1159,7 @@ impl error::Error for DyneinConfigError {
             DyneinConfigError::Yaml(ref e) => Some(e),
             DyneinConfigError::HomeDir => None,
             DyneinConfigError::Content(ref e) => Some(e),
+            DyneinConfigError::Exit(ref e) => Some(e),
         }
     }
 }
@@ -441,6 +1176,16 @@ impl From<SerdeYAMLError> for DyneinConfigError {
         Self::Yaml(e)
     }
 }
+impl From<DyneinConfigContentError> for DyneinConfigError {
+    fn from(e: DyneinConfigContentError) -> Self {
+        Self::Content(e)
+    }
+}
+impl From<DyneinExit> for DyneinConfigError {
+    fn from(e: DyneinExit) -> Self {
+        Self::Exit(e)
+    }
+}
 
 /* =================================================
 Public functions
@@ -480,12 +1225,49 @@ pub fn load_or_touch_config_file(first_try: bool) -> Result<Config, DyneinConfig
             })
             .unwrap();
 
-            write_dynein_file(DyneinFileType::ConfigFile, yaml_string)?;
+            create_dynein_file(DyneinFileType::ConfigFile, yaml_string)?;
             load_or_touch_config_file(false) // set fisrt_try flag to false in order to avoid infinite loop.
         }
     }
 }
 
+/// Loads config.yml, and layers `.dynein.yml` of the project over it if found. This is the config used by commands.
+/// Mappings are merged key by key, so the project file can override e.g. only `retry.default.max_attempts`.
+pub fn load_layered_config() -> Result<Config, DyneinConfigError> {
+    merge_layers(&config_layers()?)
+}
+
+/// Returns the config in YAML. When `.dynein.yml` is layered, each value is annotated with the layer it came from,
+/// i.e. "user" (config.yml), "project" (.dynein.yml) or "default" (in neither of them).
+pub fn dump_config() -> Result<String, DyneinConfigError> {
+    let layers = config_layers()?;
+    let config = merge_layers(&layers)?;
+    if layers.len() == 1 {
+        return Ok(serde_yaml::to_string(&config)?);
+    }
+
+    let mut origins = HashMap::new();
+    for (i, layer) in layers.iter().enumerate() {
+        collect_origins(&layer.value, "", i, &mut origins);
+    }
+    let mut dumped = String::new();
+    for layer in &layers {
+        dumped.push_str(&format!("# {}: {}\n", layer.name, layer.path));
+    }
+    let origin_names: HashMap<String, &str> = origins
+        .into_iter()
+        .map(|(path, i)| (path, layers[i].name))
+        .collect();
+    write_with_origins(
+        &serde_yaml::to_value(&config)?,
+        "",
+        0,
+        &origin_names,
+        &mut dumped,
+    )?;
+    Ok(dumped)
+}
+
 /// Loads dynein cache file (YAML format) and return Cache struct as a result.
 /// Creates the file with default if the file couldn't be found.
 pub fn load_or_touch_cache_file(first_try: bool) -> Result<Cache, DyneinConfigError> {
@@ -493,11 +1275,20 @@ pub fn load_or_touch_cache_file(first_try: bool) -> Result<Cache, DyneinConfigEr
     debug!("Loading Cache File: {}", path);
 
     match fs::read_to_string(&path) {
-        Ok(_str) => {
-            let cache: Cache = serde_yaml::from_str(&_str)?;
-            debug!("Loaded current cache: {:?}", cache);
-            Ok(cache)
-        }
+        Ok(_str) => match serde_yaml::from_str::<Cache>(&_str) {
+            Ok(cache) => {
+                debug!("Loaded current cache: {:?}", cache);
+                Ok(cache)
+            }
+            // The cache can be retrieved again, so a broken cache file is ignored rather than stopping every command.
+            Err(e) => {
+                warn!(
+                    "The cache file {} is broken and ignored, which will be recreated: {}",
+                    path, e
+                );
+                Ok(Cache::default())
+            }
+        },
         Err(e) => {
             if !first_try {
                 return Err(DyneinConfigError::from(e));
@@ -510,7 +1301,7 @@ pub fn load_or_touch_cache_file(first_try: bool) -> Result<Cache, DyneinConfigEr
                 ..Default::default()
             })?;
 
-            write_dynein_file(DyneinFileType::CacheFile, yaml_string)?;
+            create_dynein_file(DyneinFileType::CacheFile, yaml_string)?;
             load_or_touch_cache_file(false) // set fisrt_try flag to false in order to avoid infinite loop.
         }
     }
@@ -534,21 +1325,169 @@ pub async fn use_table(
         Some(tbl) => {
             debug!("describing the table: {}", tbl);
             let tbl = tbl.clone();
-            let desc: TableDescription = control::describe_table_api(cx, tbl.clone()).await;
+            let desc: TableDescription = control::describe_table_api(cx, tbl.clone()).await?;
             save_using_target(cx, desc).await?;
             println!("Now you're using the table '{}' ({}).", tbl, cx.effective_region().await.as_ref());
         },
-        None => bye(1, "You have to specify a table. How to use (1). 'dy use --table mytable', or (2) 'dy use mytable'."),
+        None => return Err(bye(1, "You have to specify a table. How to use (1). 'dy use --table mytable', or (2) 'dy use mytable'.").into()),
     };
 
     Ok(())
 }
 
-/// Inserts specified table description into cache file.
+/// Switches the table to use without calling DescribeTable API, when the table schema is found in the cache.
+/// Returns false if it's not cached for the current region or expired, so that the caller can fall back to `use_table`.
+pub async fn use_cached_table(
+    cx: &mut Context,
+    table_name: &str,
+) -> Result<bool, DyneinConfigError> {
+    let region = cx.effective_region().await;
+    let cache_key = cx.cache_key(region.as_ref(), table_name);
+    let is_cached = cx
+        .cache
+        .as_ref()
+        .and_then(|c| c.tables.as_ref())
+        .and_then(|tables| tables.get(&cache_key))
+        .map_or(false, |schema| schema.is_fresh(cx.cache_expiration()));
+    if !is_cached {
+        return Ok(false);
+    }
+
+    let port: u32 = cx.effective_port();
+    update_user_config(cx, |config| {
+        config.set_using_target(String::from(region.as_ref()), table_name.to_owned(), port)
+    })?;
+    warn_if_table_is_pinned(cx, table_name);
+
+    println!(
+        "Now you're using the table '{}' ({}).",
+        table_name,
+        region.as_ref()
+    );
+    Ok(true)
+}
+
+/// Switches the context of config.yml. `None` switches back to the top-level settings.
+/// Contexts defined in `.dynein.yml` can be used as well, although the switch is saved into config.yml.
+pub fn use_context(cx: &mut Context, name: Option<String>) -> Result<(), DyneinConfigError> {
+    let config = cx.config.as_mut().expect("cx should have config");
+    if let Err(e) = config.use_context(name.clone()) {
+        return Err(bye_with_error(e).into());
+    }
+    update_user_config(cx, |config| config.current_context = name.clone())?;
+
+    match name {
+        Some(name) => println!("Switched to context '{}'.", name),
+        None => println!("Switched to the default settings without a context."),
+    }
+    Ok(())
+}
+
+/// Lists contexts in config.yml. The current context is marked with `*`.
+pub fn list_contexts(cx: &Context) {
+    let config = cx.config.as_ref().expect("cx should have config");
+    if config.contexts.is_empty() {
+        return println!("No context is defined in the config file.");
+    }
+    for (name, context) in &config.contexts {
+        let marker = if config.current_context.as_ref() == Some(name) {
+            "*"
+        } else {
+            " "
+        };
+        let target = context
+            .endpoint_url
+            .as_deref()
+            .or(context.region.as_deref())
+            .unwrap_or("");
+        println!("{} {} {}", marker, name, target);
+    }
+}
+
+/// Returns the value of the dot-separated key in config.yml, e.g. `retry.default.max_attempts`.
+pub fn get_config_value(cx: &Context, key: &str) -> Result<Value, DyneinConfigError> {
+    let config = cx.config.as_ref().expect("cx should have config");
+    let value = serde_yaml::to_value(config)?;
+    match lookup_value(&value, key) {
+        Some(v) if !v.is_null() => Ok(v.to_owned()),
+        _ => Err(DyneinConfigContentError::NotSet(key.to_owned()).into()),
+    }
+}
+
+/// Sets the value (given in YAML, e.g. `5`, `true` or `us-west-2`) to the dot-separated key in config.yml.
+/// The config is saved only if it's still valid after the change. Other keys are kept as they are, even if they're unknown to this version.
+/// This works without Context, so that an invalid config.yml, which Context cannot load, can be fixed.
+pub fn set_config_value(key: &str, value: &str) -> Result<(), DyneinConfigError> {
+    let new_value: Value = serde_yaml::from_str(value).map_err(|e| invalid_value(key, e))?;
+    with_dynein_file_lock(DyneinFileType::ConfigFile, || {
+        let mut root = load_config_value()?;
+        insert_value(&mut root, key, new_value)?;
+
+        let config = config_from_value(root.clone(), key)?;
+        // Keys unknown to Config are dropped by deserialization.
+        if lookup_value(&serde_yaml::to_value(config)?, key).is_none() {
+            return Err(DyneinConfigContentError::UnknownKey(key.to_owned()).into());
+        }
+        save_config_value(&root)
+    })
+}
+
+/// Removes the dot-separated key from config.yml, so that its default is used.
+pub fn unset_config_value(key: &str) -> Result<(), DyneinConfigError> {
+    with_dynein_file_lock(DyneinFileType::ConfigFile, || {
+        let mut root = load_config_value()?;
+        let (parent_key, last) = match key.rsplit_once('.') {
+            Some((parent_key, last)) => (Some(parent_key), last),
+            None => (None, key),
+        };
+        let parent = match parent_key {
+            Some(parent_key) => lookup_value_mut(&mut root, parent_key),
+            None => Some(&mut root),
+        };
+        let removed = parent
+            .and_then(Value::as_mapping_mut)
+            .and_then(|m| m.remove(last));
+        if removed.map_or(true, |v| v.is_null()) {
+            return Err(DyneinConfigContentError::NotSet(key.to_owned()).into());
+        }
+
+        config_from_value(root.clone(), key)?;
+        save_config_value(&root)
+    })
+}
+
+/// Checks config.yml and `.dynein.yml`, and returns problems found with the file and the key path.
+/// Syntax errors and type errors are returned as errors, as the rest of the file cannot be checked.
+pub fn validate_config_file() -> Result<Vec<(String, DyneinConfigContentError)>, DyneinConfigError>
+{
+    let layers = config_layers()?;
+    let config = merge_layers(&layers)?;
+    let known = serde_yaml::to_value(&config)?;
+
+    let mut problems = vec![];
+    let mut origins = HashMap::new();
+    for (i, layer) in layers.iter().enumerate() {
+        let mut unknown_keys = vec![];
+        collect_unknown_keys(&layer.value, &known, "", &mut unknown_keys);
+        problems.extend(unknown_keys.into_iter().map(|p| (layer.path.clone(), p)));
+        collect_origins(&layer.value, "", i, &mut origins);
+    }
+    // Settings are validated after they are layered. A problem is reported for the file which has the key.
+    for problem in validate_config(&config) {
+        let layer = match &problem {
+            DyneinConfigContentError::InvalidValue { key, .. } => origins.get(key).copied(),
+            _ => None,
+        };
+        problems.push((layers[layer.unwrap_or(0)].path.clone(), problem));
+    }
+    Ok(problems)
+}
+
+/// Inserts specified table description into cache file, and returns the inserted schema.
 pub async fn insert_to_table_cache(
     cx: &Context,
     desc: &TableDescription,
-) -> Result<(), DyneinConfigError> {
+) -> Result<TableSchema, DyneinConfigError> {
     let table_name = desc
         .table_name
         .clone()
@@ -560,42 +1499,70 @@ pub async fn insert_to_table_cache(
         table_name
     );
 
-    // retrieve current cache from Context and update target table desc.
+    // update target table desc in the cache file.
     // key to save the table desc is "<RegionName>/<TableName>" -- e.g. "us-west-2/app_data"
-    let mut cache: Cache = cx.cache.clone().expect("cx should have cache");
-    let cache_key = format!("{}/{}", region.as_ref(), table_name);
+    let cache_key = cx.cache_key(region.as_ref(), &table_name);
+    let schema = TableSchema::from_description(region.as_ref(), desc);
+    update_cache_file(|cache| {
+        debug!("table schema cache before insert: {:#?}", cache.tables);
+        cache
+            .tables
+            .get_or_insert_with(HashMap::new)
+            .insert(cache_key, schema.clone());
+    })?;
+
+    Ok(schema)
+}
 
-    let mut table_schema_hashmap: HashMap<String, TableSchema> = match cache.tables {
-        Some(ts) => ts,
-        None => HashMap::<String, TableSchema>::new(),
+/// Removes the cached schema of the table in the current region, or all cached schemas if no table is given.
+/// Returns false if the table is not cached.
+pub async fn remove_from_table_cache(
+    cx: &mut Context,
+    table_name: Option<&str>,
+) -> Result<bool, DyneinConfigError> {
+    let cache_key = match table_name {
+        Some(table_name) => Some(cx.cache_key(cx.effective_region().await.as_ref(), table_name)),
+        None => None,
     };
-    debug!(
-        "table schema cache before insert: {:#?}",
-        table_schema_hashmap
-    );
+    let (cache, removed) = update_cache_file(|cache| match cache_key {
+        Some(cache_key) => cache
+            .tables
+            .as_mut()
+            .and_then(|tables| tables.remove(&cache_key))
+            .is_some(),
+        None => cache
+            .tables
+            .take()
+            .map_or(false, |tables| !tables.is_empty()),
+    })?;
+    cx.cache = Some(cache);
+    Ok(removed)
+}
 
-    table_schema_hashmap.insert(
-        cache_key,
-        TableSchema {
-            region: String::from(region.as_ref()),
-            name: table_name,
-            pk: key::typed_key("HASH", desc).expect("pk should exist"),
-            sk: key::typed_key("RANGE", desc),
-            indexes: index_schemas(desc),
-            mode: table::extract_mode(&desc.billing_mode_summary),
-        },
-    );
-    cache.tables = Some(table_schema_hashmap);
-
-    // write to cache file
-    let cache_yaml_string = serde_yaml::to_string(&cache)?;
-    debug!(
-        "this YAML will be written to the cache file: {:#?}",
-        &cache_yaml_string
-    );
-    write_dynein_file(DyneinFileType::CacheFile, cache_yaml_string)?;
+/// Retrieves the schema of the target table by DescribeTable API, and saves it into the cache.
+/// This is used when the cached schema is expired, or it doesn't match the table (e.g. an index has been added since then).
+pub async fn refresh_table_schema(cx: &Context) -> Result<TableSchema, DyneinExit> {
+    let desc: TableDescription =
+        control::describe_table_api(cx, cx.effective_table_name()?).await?;
+    match insert_to_table_cache(cx, &desc).await {
+        Ok(schema) => Ok(schema),
+        Err(e) => {
+            warn!("Failed to write table schema to the cache: {}", e);
+            Ok(TableSchema::from_description(
+                cx.effective_region().await.as_ref(),
+                &desc,
+            ))
+        }
+    }
+}
 
-    Ok(())
+/// Returns the cached schema of the target table if it's fresh, otherwise retrieves it by DescribeTable API and caches it.
+/// Unlike [`table_schema`], it doesn't call DescribeTable every time for a table given at runtime, e.g. picked in `dy tui`.
+pub async fn cached_table_schema(cx: &Context) -> Result<TableSchema, DyneinExit> {
+    match cx.cached_using_table_schema().await {
+        Some(schema) if schema.is_fresh(cx.cache_expiration()) => Ok(schema),
+        _ => refresh_table_schema(cx).await,
+    }
 }
 
 /// Physicall remove config and cache file.
@@ -608,7 +1575,7 @@ pub fn remove_dynein_files() -> Result<(), DyneinConfigError> {
 // If you explicitly specify target table by `--table/-t` option, this function executes DescribeTable API to gather table schema info.
 // Otherwise, load table schema info from config file.
 // fn table_schema(region: &Region, config: &config::Config, table_overwritten: Option<String>) -> TableSchema {
-pub async fn table_schema(cx: &Context) -> TableSchema {
+pub async fn table_schema(cx: &Context) -> Result<TableSchema, DyneinExit> {
     match cx.overwritten_table_name.to_owned() {
         // It's possible that users pass --table without calling `dy use` for any table. Thus collect all data from DescribeTable results.
         Some(table_name) => {
@@ -616,32 +1583,35 @@ pub async fn table_schema(cx: &Context) -> TableSchema {
             let desc: TableDescription = control::describe_table_api(
                 cx, table_name, /* should be equal to 'cx.effective_table_name()' */
             )
-            .await;
+            .await?;
 
-            TableSchema {
-                region: String::from(cx.effective_region().await.as_ref()),
-                name: desc.table_name.to_owned().unwrap(),
-                pk: key::typed_key("HASH", &desc).expect("pk should exist"),
-                sk: key::typed_key("RANGE", &desc),
-                indexes: index_schemas(&desc),
-                mode: table::extract_mode(&desc.billing_mode_summary),
-            }
+            Ok(TableSchema::from_description(
+                cx.effective_region().await.as_ref(),
+                &desc,
+            ))
         }
         None => {
             // simply maps config data into TableSchema struct.
             debug!("current context {:#?}", cx);
             let cache = cx.cache.as_ref().expect("Cache should exist in context"); // can refactor here using and_then
-            let cached_tables = cache.tables.as_ref().unwrap_or_else(|| {
-                error!("{}", Messages::NoEffectiveTable);
-                std::process::exit(1)
-            });
+            let cached_tables = cache
+                .tables
+                .as_ref()
+                .ok_or_else(|| bye_with_error(Messages::NoEffectiveTable))?;
             let schema_from_cache: Option<TableSchema> = cached_tables
-                .get(&cx.effective_cache_key().await)
+                .get(&cx.effective_cache_key().await?)
                 .map(|x| x.to_owned());
-            schema_from_cache.unwrap_or_else(|| {
-                error!("{}", Messages::NoEffectiveTable);
-                std::process::exit(1)
-            })
+            let schema =
+                schema_from_cache.ok_or_else(|| bye_with_error(Messages::NoEffectiveTable))?;
+            if schema.is_fresh(cx.cache_expiration()) {
+                Ok(schema)
+            } else {
+                debug!(
+                    "cached schema of '{}' is expired, refreshing it",
+                    schema.name
+                );
+                refresh_table_schema(cx).await
+            }
         }
     }
 }
@@ -682,12 +1652,34 @@ pub fn index_schemas(desc: &TableDescription) -> Option<Vec<IndexSchema>> {
     }
 }
 
-pub fn bye(code: i32, msg: &str) -> ! {
+/// Writes the content into a temporary file in the same directory, which then replaces the file at the path,
+/// so that the file is never left partially written even if dynein is interrupted.
+pub fn write_file_atomically(path: impl AsRef<path::Path>, content: &str) -> io::Result<()> {
+    let path = path.as_ref();
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => path::Path::new("."),
+    };
+    temp_file_in(dir, content)?
+        .persist(path)
+        .map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Prints the message and returns the error to end the command with the code.
+pub fn bye(code: i32, msg: &str) -> DyneinExit {
     println!("{}", msg);
-    std::process::exit(code);
+    DyneinExit(code)
+}
+
+/// Logs an error message and returns the error to end the command, for errors which the user can fix,
+/// e.g. an invalid value given to `dy config set`.
+pub fn bye_with_error(e: impl fmt::Display) -> DyneinExit {
+    error!("{}", e);
+    DyneinExit(1)
 }
 
-pub fn bye_with_sdk_error<E, R>(code: i32, error: SdkError<E, R>) -> !
+pub fn bye_with_sdk_error<E, R>(code: i32, error: SdkError<E, R>) -> DyneinExit
 where
     E: fmt::Debug + ProvideErrorMetadata,
     R: fmt::Debug,
@@ -696,7 +1688,19 @@ where
         Some(service_error) => error!("service error occurred: {:?}", service_error.meta()),
         None => error!("an error occurred: {:?}", error),
     };
-    std::process::exit(code);
+    DyneinExit(code)
+}
+
+/// Returns the exit code if the error, or one of its sources, is `DyneinExit`.
+pub fn exit_code(e: &(dyn error::Error + 'static)) -> Option<i32> {
+    let mut current = Some(e);
+    while let Some(e) = current {
+        if let Some(DyneinExit(code)) = e.downcast_ref::<DyneinExit>() {
+            return Some(*code);
+        }
+        current = e.source();
+    }
+    None
 }
 
 /* =================================================
@@ -712,6 +1716,15 @@ fn retrieve_dynein_file_path(file_type: DyneinFileType) -> Result<String, Dynein
     Ok(format!("{}/{}", retrieve_or_create_dynein_dir()?, filename))
 }
 
+/// History of the interactive shell is stored next to config/cache files.
+pub fn retrieve_history_file_path() -> Result<String, DyneinConfigError> {
+    Ok(format!(
+        "{}/{}",
+        retrieve_or_create_dynein_dir()?,
+        HISTORY_FILE_NAME
+    ))
+}
+
 fn retrieve_or_create_dynein_dir() -> Result<String, DyneinConfigError> {
     let full_path = env::var(CONFIG_PATH_ENV_VAR_NAME).unwrap_or(
         home::home_dir()
@@ -744,32 +1757,404 @@ async fn save_using_target(
 
     let port: u32 = cx.effective_port();
 
-    // retrieve current config from Context and update "using target".
-    let region = Some(String::from(cx.effective_region().await.as_ref()));
-    let config = cx.config.as_mut().expect("cx should have config");
-    config.using_region = region;
-    config.using_table = Some(table_name);
-    config.using_port = Some(port);
+    // update "using target" in config.yml.
+    let region = String::from(cx.effective_region().await.as_ref());
+    update_user_config(cx, |config| {
+        config.set_using_target(region, table_name.clone(), port)
+    })?;
+    warn_if_table_is_pinned(cx, &table_name);
+
+    // save target table info into cache, and reload it so that the context (e.g. a shell session) sees the new schema.
+    insert_to_table_cache(cx, &desc).await?;
+    cx.cache = Some(load_or_touch_cache_file(true)?);
+
+    Ok(())
+}
+
+/// Applies the change to config.yml, and reloads the layered config into the context.
+/// Only config.yml is written, so that values of `.dynein.yml` are not copied into it.
+fn update_user_config(
+    cx: &mut Context,
+    change: impl FnOnce(&mut Config),
+) -> Result<(), DyneinConfigError> {
+    with_dynein_file_lock(DyneinFileType::ConfigFile, || {
+        let mut config = load_or_touch_config_file(true)?;
+        change(&mut config);
+        save_config(&config)
+    })?;
+    cx.config = Some(load_layered_config()?);
+    Ok(())
+}
+
+/// `dy use` saves the table into config.yml, but it has no effect when `.dynein.yml` pins another table.
+fn warn_if_table_is_pinned(cx: &Context, table_name: &str) {
+    let using_table = cx.config.as_ref().and_then(|c| c.using_table());
+    if using_table.map_or(false, |t| t != table_name) {
+        warn!(
+            "'{}' is used instead, as it's set in {}. Use --table to access '{}'.",
+            using_table.unwrap_or_default(),
+            PROJECT_CONFIG_FILE_NAME,
+            table_name
+        );
+    }
+}
+
+/// Returns config.yml, and `.dynein.yml` of the project if found, in the order of precedence (lowest first).
+/// Each file is checked by deserialization, so that an error can be reported with the file.
+fn config_layers() -> Result<Vec<ConfigLayer>, DyneinConfigError> {
+    let user_path = retrieve_dynein_file_path(DyneinFileType::ConfigFile)?;
+    load_or_touch_config_file(true)?;
+    let mut layers = vec![ConfigLayer {
+        name: "user",
+        value: load_yaml_mapping(&fs::read_to_string(&user_path)?)?,
+        path: user_path,
+    }];
+
+    if let Some(project_path) = find_project_config_file() {
+        let path = project_path.display().to_string();
+        debug!("Loading project config file: {}", path);
+        let invalid_file = |e: SerdeYAMLError| DyneinConfigContentError::InvalidFile {
+            path: path.clone(),
+            message: e.to_string(),
+        };
+        let value =
+            load_yaml_mapping(&fs::read_to_string(&project_path)?).map_err(|e| match e {
+                DyneinConfigError::Yaml(e) => invalid_file(e).into(),
+                e => e,
+            })?;
+        serde_yaml::from_value::<Config>(value.clone()).map_err(invalid_file)?;
+        layers.push(ConfigLayer {
+            name: "project",
+            path,
+            value,
+        });
+    }
+    Ok(layers)
+}
+
+/// Finds `.dynein.yml` in the current directory or its parents.
+fn find_project_config_file() -> Option<path::PathBuf> {
+    let current_dir = env::current_dir().ok()?;
+    current_dir
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Parses YAML as a mapping. An empty file is loaded as an empty mapping.
+fn load_yaml_mapping(content: &str) -> Result<Value, DyneinConfigError> {
+    Ok(match serde_yaml::from_str(content)? {
+        Value::Null => Value::Mapping(Mapping::new()),
+        v => v,
+    })
+}
+
+fn merge_layers(layers: &[ConfigLayer]) -> Result<Config, DyneinConfigError> {
+    let mut merged = Value::Mapping(Mapping::new());
+    for layer in layers {
+        merge_value(&mut merged, layer.value.clone());
+    }
+    Ok(serde_yaml::from_value(merged)?)
+}
+
+/// Merges the overlay into the base. Mappings are merged recursively, and other values are replaced.
+fn merge_value(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (k, v) in overlay {
+                match base.get_mut(&k) {
+                    Some(base_value) => merge_value(base_value, v),
+                    None => {
+                        base.insert(k, v);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Records the index of the layer which has each key, keyed by the dot-separated path.
+fn collect_origins(
+    value: &Value,
+    prefix: &str,
+    layer: usize,
+    origins: &mut HashMap<String, usize>,
+) {
+    if let Some(mapping) = value.as_mapping() {
+        for (k, v) in mapping {
+            if let Some(k) = k.as_str() {
+                let path = join_key_path(prefix, k);
+                collect_origins(v, &path, layer, origins);
+                origins.insert(path, layer);
+            }
+        }
+    }
+}
+
+/// Writes the mapping in YAML, with the origin of each value as a comment.
+fn write_with_origins(
+    value: &Value,
+    prefix: &str,
+    indent: usize,
+    origins: &HashMap<String, &str>,
+    out: &mut String,
+) -> Result<(), DyneinConfigError> {
+    let mapping = match value.as_mapping() {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    for (k, v) in mapping {
+        let key = serde_yaml::to_string(k)?;
+        let key = key.trim_end();
+        let path = join_key_path(prefix, k.as_str().unwrap_or(key));
+        match v {
+            Value::Mapping(m) if !m.is_empty() => {
+                out.push_str(&format!("{:indent$}{}:\n", "", key, indent = indent));
+                write_with_origins(v, &path, indent + 2, origins, out)?;
+            }
+            _ => {
+                let origin = origins.get(&path).copied().unwrap_or("default");
+                let rendered = serde_yaml::to_string(v)?;
+                let lines: Vec<&str> = rendered.lines().collect();
+                if let [line] = lines.as_slice() {
+                    out.push_str(&format!(
+                        "{:indent$}{}: {}  # {}\n",
+                        "",
+                        key,
+                        line,
+                        origin,
+                        indent = indent
+                    ));
+                } else {
+                    out.push_str(&format!(
+                        "{:indent$}{}:  # {}\n",
+                        "",
+                        key,
+                        origin,
+                        indent = indent
+                    ));
+                    for line in lines {
+                        out.push_str(&format!("{:indent$}{}\n", "", line, indent = indent + 2));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn join_key_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// Checks settings which cannot be checked by deserialization, and returns problems found.
+/// Retry settings are validated with the same conversion used when they are applied.
+fn validate_config(config: &Config) -> Vec<DyneinConfigContentError> {
+    let mut problems = vec![];
+    let mut validate_retry = |key: &str, retry: &RetrySettingGlobal| {
+        let settings = [
+            ("default", Some(&retry.default)),
+            ("batch_write_item", retry.batch_write_item.as_ref()),
+        ];
+        for (name, setting) in settings {
+            if let Some(Err(e)) = setting.map(|s| RetryConfig::try_from(s.clone())) {
+                problems.push(invalid_value(&format!("{}.{}", key, name), e));
+            }
+        }
+    };
+
+    if let Some(retry) = &config.retry {
+        validate_retry("retry", retry);
+    }
+    for (name, context) in &config.contexts {
+        if let Some(retry) = &context.retry {
+            validate_retry(&format!("contexts.{}.retry", name), retry);
+        }
+    }
+    for (name, context) in &config.contexts {
+        if let Some(endpoint_url) = &context.endpoint_url {
+            if !endpoint_url.starts_with("http://") && !endpoint_url.starts_with("https://") {
+                problems.push(invalid_value(
+                    &format!("contexts.{}.endpoint_url", name),
+                    "endpoint URL should start with http:// or https://",
+                ));
+            }
+        }
+    }
+    if let Some(name) = &config.current_context {
+        if !config.contexts.contains_key(name) {
+            problems.push(invalid_value(
+                "current_context",
+                DyneinConfigContentError::UnknownContext(name.to_owned()),
+            ));
+        }
+    }
+    problems
+}
+
+fn invalid_value(key: &str, message: impl fmt::Display) -> DyneinConfigContentError {
+    DyneinConfigContentError::InvalidValue {
+        key: key.to_owned(),
+        message: message.to_string(),
+    }
+}
+
+/// Loads config.yml as a YAML value, to edit keys without the schema.
+fn load_config_value() -> Result<Value, DyneinConfigError> {
+    load_or_touch_config_file(true)?;
+    let path = retrieve_dynein_file_path(DyneinFileType::ConfigFile)?;
+    load_yaml_mapping(&fs::read_to_string(path)?)
+}
+
+/// Deserializes the edited config and validates it. Type errors are reported with the edited key.
+fn config_from_value(value: Value, key: &str) -> Result<Config, DyneinConfigError> {
+    let config: Config = serde_yaml::from_value(value).map_err(|e| invalid_value(key, e))?;
+    // Problems which already exist don't block the change, so that they can be fixed one by one.
+    let existing_problems: Vec<String> = load_or_touch_config_file(true)
+        .map(|c| validate_config(&c).iter().map(|p| p.to_string()).collect())
+        .unwrap_or_default();
+    if let Some(problem) = validate_config(&config)
+        .into_iter()
+        .find(|p| !existing_problems.contains(&p.to_string()))
+    {
+        return Err(problem.into());
+    }
+    Ok(config)
+}
+
+fn save_config(config: &Config) -> Result<(), DyneinConfigError> {
     debug!("config file will be updated with: {:?}", config);
+    write_dynein_file(DyneinFileType::ConfigFile, serde_yaml::to_string(config)?)?;
+    Ok(())
+}
 
-    // write to config file
-    let config_yaml_string = serde_yaml::to_string(config)?;
-    write_dynein_file(DyneinFileType::ConfigFile, config_yaml_string)?;
+/// Saves the edited YAML of config.yml as is, so that keys unknown to Config (e.g. of a newer version) are not dropped.
+fn save_config_value(value: &Value) -> Result<(), DyneinConfigError> {
+    debug!("config file will be updated with: {:?}", value);
+    write_dynein_file(DyneinFileType::ConfigFile, serde_yaml::to_string(value)?)?;
+    Ok(())
+}
 
-    // save target table info into cache.
-    insert_to_table_cache(cx, &desc).await?;
+fn lookup_value<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.').try_fold(value, |v, k| v.get(k))
+}
+
+fn lookup_value_mut<'a>(value: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    key.split('.').try_fold(value, |v, k| v.get_mut(k))
+}
 
+/// Inserts the value at the dot-separated key, creating intermediate mappings as needed.
+fn insert_value(root: &mut Value, key: &str, new_value: Value) -> Result<(), DyneinConfigError> {
+    if key.split('.').any(str::is_empty) {
+        return Err(DyneinConfigContentError::UnknownKey(key.to_owned()).into());
+    }
+    let mut current = root;
+    for (i, k) in key.split('.').enumerate() {
+        if current.is_null() {
+            *current = Value::Mapping(Mapping::new());
+        }
+        let mapping = current.as_mapping_mut().ok_or_else(|| {
+            let parent: Vec<&str> = key.split('.').take(i).collect();
+            invalid_value(&parent.join("."), "not a mapping")
+        })?;
+        current = mapping
+            .entry(Value::String(k.to_owned()))
+            .or_insert(Value::Null);
+    }
+    *current = new_value;
     Ok(())
 }
 
+/// Collects keys in the raw YAML which are not in the deserialized config, i.e. unknown keys.
+fn collect_unknown_keys(
+    raw: &Value,
+    known: &Value,
+    prefix: &str,
+    problems: &mut Vec<DyneinConfigContentError>,
+) {
+    let raw_mapping = match raw.as_mapping() {
+        Some(m) => m,
+        None => return,
+    };
+    for (k, v) in raw_mapping {
+        let k = match k.as_str() {
+            Some(k) => k,
+            None => continue,
+        };
+        let path = join_key_path(prefix, k);
+        match known.get(k) {
+            Some(known_value) => collect_unknown_keys(v, known_value, &path, problems),
+            // Keys with null are omitted when they are serialized, e.g. `profile: null` in a context.
+            None if v.is_null() => (),
+            None => problems.push(DyneinConfigContentError::UnknownKey(path)),
+        }
+    }
+}
+
+/// Writes the file atomically, i.e. the content is written into a temporary file which then replaces the file,
+/// so that other processes never see a partially written file. Use it under `with_dynein_file_lock` to update the file.
 fn write_dynein_file(file_type: DyneinFileType, content: String) -> Result<(), DyneinConfigError> {
-    let temp_file = NamedTempFile::new_in(retrieve_or_create_dynein_dir()?)?;
-    let temp_path = temp_file.path();
+    retrieve_or_create_dynein_dir()?;
+    write_file_atomically(retrieve_dynein_file_path(file_type)?, &content)?;
+    Ok(())
+}
 
-    fs::write(temp_path, content)?;
-    fs::rename(temp_path, retrieve_dynein_file_path(file_type)?)?;
+/// Creates the file with the content, unless another process has created it in the meantime.
+fn create_dynein_file(file_type: DyneinFileType, content: String) -> Result<(), DyneinConfigError> {
+    let temp_file = temp_file_in(retrieve_or_create_dynein_dir()?, &content)?;
+    match temp_file.persist_noclobber(retrieve_dynein_file_path(file_type)?) {
+        Err(e) if e.error.kind() != io::ErrorKind::AlreadyExists => Err(e.error.into()),
+        _ => Ok(()),
+    }
+}
 
-    Ok(())
+fn temp_file_in(dir: impl AsRef<path::Path>, content: &str) -> io::Result<NamedTempFile> {
+    let mut temp_file = NamedTempFile::new_in(dir)?;
+    temp_file.write_all(content.as_bytes())?;
+    temp_file.as_file().sync_all()?;
+    Ok(temp_file)
+}
+
+/// Runs the function holding an exclusive advisory lock of the file, so that parallel dynein processes
+/// (e.g. `dy use` in parallel tests) don't lose updates of each other.
+/// A separate `<file>.lock` is locked, as the file itself is replaced on every write.
+/// Note that the lock is not reentrant, so the function must not take the same lock.
+fn with_dynein_file_lock<T>(
+    file_type: DyneinFileType,
+    f: impl FnOnce() -> Result<T, DyneinConfigError>,
+) -> Result<T, DyneinConfigError> {
+    let lock_path = format!("{}.lock", retrieve_dynein_file_path(file_type)?);
+    let lock_file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path)?;
+    let mut lock = RwLock::new(lock_file);
+    let _guard = lock.write()?;
+    f()
+}
+
+/// Loads cache.yml, applies the change, and writes it back under the lock.
+/// As the latest file is loaded, entries written by other processes in the meantime are kept.
+fn update_cache_file<T>(
+    change: impl FnOnce(&mut Cache) -> T,
+) -> Result<(Cache, T), DyneinConfigError> {
+    with_dynein_file_lock(DyneinFileType::CacheFile, || {
+        let mut cache = load_or_touch_cache_file(true)?;
+        let result = change(&mut cache);
+        let cache_yaml_string = serde_yaml::to_string(&cache)?;
+        debug!(
+            "this YAML will be written to the cache file: {:#?}",
+            &cache_yaml_string
+        );
+        write_dynein_file(DyneinFileType::CacheFile, cache_yaml_string)?;
+        Ok((cache, result))
+    })
 }
 
 /* =================================================
@@ -782,6 +2167,39 @@ mod tests {
     use std::convert::TryInto;
     use std::error::Error;
 
+    /// Tests which touch dynein files hold this lock, as the directory is given by the process-wide environment variable.
+    static CONFIG_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Points DYNEIN_CONFIG_DIR to the directory while the guard is alive, and restores the previous value on drop.
+    struct ConfigDirGuard {
+        previous: Option<std::ffi::OsString>,
+        _lock: std::sync::MutexGuard<'static, ()>,
+    }
+
+    impl ConfigDirGuard {
+        fn new(dir: &path::Path) -> Self {
+            // A test failing with the lock doesn't matter to others, as the variable is restored anyway.
+            let lock = CONFIG_DIR_LOCK
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let previous = env::var_os(CONFIG_PATH_ENV_VAR_NAME);
+            env::set_var(CONFIG_PATH_ENV_VAR_NAME, dir);
+            ConfigDirGuard {
+                previous,
+                _lock: lock,
+            }
+        }
+    }
+
+    impl Drop for ConfigDirGuard {
+        fn drop(&mut self) {
+            match &self.previous {
+                Some(previous) => env::set_var(CONFIG_PATH_ENV_VAR_NAME, previous),
+                None => env::remove_var(CONFIG_PATH_ENV_VAR_NAME),
+            }
+        }
+    }
+
     #[tokio::test]
     async fn test_context_functions() -> Result<(), Box<dyn Error>> {
         let cx1 = Context {
@@ -790,9 +2208,18 @@ mod tests {
             overwritten_region: None,
             overwritten_table_name: None,
             overwritten_port: None,
+            overwritten_endpoint_url: None,
+            overwritten_profile: None,
+            overwritten_assume_role: None,
+            assumed_role_credentials: Arc::new(OnceCell::new()),
             output: None,
+            default_output: None,
+            color: ColorMode::Auto,
             should_strict_for_query: None,
             retry: None,
+            non_interactive: false,
+            recent_attribute_names: Arc::new(Mutex::new(VecDeque::new())),
+            metrics: None,
         };
         assert_eq!(
             &cx1.effective_region().await,
@@ -809,21 +2236,34 @@ mod tests {
                 using_table: Some(String::from("cfgtbl")),
                 using_port: Some(8000),
                 query: QueryConfig { strict_mode: false },
+                output: None,
+                cache_expiration_time: None,
                 retry: Some(RetrySettingGlobal::default()),
+                current_context: None,
+                contexts: BTreeMap::new(),
             }),
             cache: None,
             overwritten_region: None,
             overwritten_table_name: None,
             overwritten_port: None,
+            overwritten_endpoint_url: None,
+            overwritten_profile: None,
+            overwritten_assume_role: None,
+            assumed_role_credentials: Arc::new(OnceCell::new()),
             output: None,
+            default_output: None,
+            color: ColorMode::Auto,
             should_strict_for_query: None,
             retry: Some(RetrySettingGlobal::default().try_into()?),
+            non_interactive: false,
+            recent_attribute_names: Arc::new(Mutex::new(VecDeque::new())),
+            metrics: None,
         };
         assert_eq!(
             cx2.effective_region().await,
             Region::from_static("ap-northeast-1")
         );
-        assert_eq!(cx2.effective_table_name(), String::from("cfgtbl"));
+        assert_eq!(cx2.effective_table_name().unwrap(), String::from("cfgtbl"));
 
         let cx3 = Context {
             overwritten_region: Some(Region::from_static("us-east-1")), // --region us-east-1
@@ -834,7 +2274,7 @@ mod tests {
             cx3.effective_region().await,
             Region::from_static("us-east-1")
         );
-        assert_eq!(cx3.effective_table_name(), String::from("argtbl"));
+        assert_eq!(cx3.effective_table_name().unwrap(), String::from("argtbl"));
 
         let cx4 = Context {
             overwritten_region: Some(Region::from_static("us-east-1")), // --region us-east-1
@@ -844,7 +2284,7 @@ mod tests {
             cx4.effective_region().await,
             Region::from_static("us-east-1")
         );
-        assert_eq!(cx4.effective_table_name(), String::from("cfgtbl"));
+        assert_eq!(cx4.effective_table_name().unwrap(), String::from("cfgtbl"));
 
         let cx5 = Context {
             overwritten_table_name: Some(String::from("argtbl")), // --table argtbl
@@ -854,11 +2294,525 @@ mod tests {
             cx5.effective_region().await,
             Region::from_static("ap-northeast-1")
         );
-        assert_eq!(cx5.effective_table_name(), String::from("argtbl"));
+        assert_eq!(cx5.effective_table_name().unwrap(), String::from("argtbl"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_named_contexts() -> Result<(), Box<dyn Error>> {
+        let yaml = r#"
+using_region: ap-northeast-1
+using_table: toptbl
+using_port: null
+retry: null
+current_context: localstack
+contexts:
+  localstack:
+    region: us-east-1
+    endpoint_url: http://localhost:4566
+    table: lstbl
+  docker:
+    region: local
+    port: 8001
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml)?;
+        let cx = Context {
+            config: Some(config.clone()),
+            cache: None,
+            overwritten_region: None,
+            overwritten_table_name: None,
+            overwritten_port: None,
+            overwritten_endpoint_url: None,
+            overwritten_profile: None,
+            overwritten_assume_role: None,
+            assumed_role_credentials: Arc::new(OnceCell::new()),
+            output: None,
+            default_output: None,
+            color: ColorMode::Auto,
+            should_strict_for_query: None,
+            retry: None,
+            non_interactive: false,
+            recent_attribute_names: Arc::new(Mutex::new(VecDeque::new())),
+            metrics: None,
+        };
+        assert_eq!(
+            cx.effective_region().await,
+            Region::from_static("us-east-1")
+        );
+        assert_eq!(cx.effective_table_name().unwrap(), "lstbl");
+        assert_eq!(
+            cx.effective_endpoint_url().await.as_deref(),
+            Some("http://localhost:4566")
+        );
+        assert_eq!(
+            cx.effective_cache_key().await.unwrap(),
+            "http://localhost:4566/us-east-1/lstbl"
+        );
+
+        // --region takes precedence over the endpoint of the context.
+        let cx_region = cx.clone().with_region("us-west-2");
+        assert_eq!(cx_region.effective_endpoint_url().await, None);
+        assert_eq!(
+            cx_region.effective_cache_key().await.unwrap(),
+            "us-west-2/lstbl"
+        );
+
+        // A table of `dy diff` and `dy copy` can be on another endpoint or context, regardless of --region.
+        let location: TableLocation = "Movie@http://localhost:8000".parse()?;
+        let resolved = location.resolve(&cx_region).await;
+        assert_eq!(resolved.to_string(), "Movie@http://localhost:8000");
+        assert_eq!(
+            location
+                .context(&cx_region)
+                .effective_cache_key()
+                .await
+                .unwrap(),
+            "http://localhost:8000/us-west-2/Movie"
+        );
+        assert_ne!(
+            resolved,
+            "Movie".parse::<TableLocation>()?.resolve(&cx_region).await
+        );
+        let location: TableLocation = "Movie@docker".parse()?;
+        assert_eq!(
+            location.resolve(&cx_region).await.to_string(),
+            "Movie@local:8001"
+        );
+        assert_eq!(
+            location
+                .context(&cx_region)
+                .effective_endpoint_url()
+                .await
+                .as_deref(),
+            Some("http://localhost:8001")
+        );
+
+        config.use_context(Some("docker".to_owned()))?;
+        let cx_docker = Context {
+            config: Some(config.clone()),
+            ..cx.clone()
+        };
+        assert!(cx_docker.is_local().await);
+        assert_eq!(
+            cx_docker.effective_endpoint_url().await.as_deref(),
+            Some("http://localhost:8001")
+        );
+
+        // `dy use` updates the current context, and the top-level fields are kept.
+        config.set_using_target("local".to_owned(), "newtbl".to_owned(), 8001);
+        assert_eq!(config.contexts["docker"].table.as_deref(), Some("newtbl"));
+        assert_eq!(config.using_table.as_deref(), Some("toptbl"));
+
+        assert!(config.use_context(Some("unknown".to_owned())).is_err());
+        config.use_context(None)?;
+        assert_eq!(config.using_table(), Some("toptbl"));
+        assert_eq!(config.using_region(), Some("ap-northeast-1"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_credential_settings() -> Result<(), Box<dyn Error>> {
+        let yaml = r#"
+using_region: null
+using_table: null
+using_port: null
+retry: null
+current_context: prod
+contexts:
+  prod:
+    region: us-east-1
+    profile: prod-readonly
+    role_arn: arn:aws:iam::123456789012:role/dynein
+    external_id: ext
+"#;
+        let mut cx = Context {
+            config: Some(serde_yaml::from_str(yaml)?),
+            cache: None,
+            overwritten_region: None,
+            overwritten_table_name: None,
+            overwritten_port: None,
+            overwritten_endpoint_url: None,
+            overwritten_profile: None,
+            overwritten_assume_role: None,
+            assumed_role_credentials: Arc::new(OnceCell::new()),
+            output: None,
+            default_output: None,
+            color: ColorMode::Auto,
+            should_strict_for_query: None,
+            retry: None,
+            non_interactive: false,
+            recent_attribute_names: Arc::new(Mutex::new(VecDeque::new())),
+            metrics: None,
+        };
+        assert_eq!(cx.effective_profile().as_deref(), Some("prod-readonly"));
+        assert_eq!(
+            cx.effective_assume_role(),
+            Some(AssumeRoleSetting {
+                role_arn: "arn:aws:iam::123456789012:role/dynein".to_owned(),
+                external_id: Some("ext".to_owned()),
+                mfa_serial: None,
+            })
+        );
+
+        // Command line options take precedence over the context.
+        cx.overwritten_profile = Some("admin".to_owned());
+        cx.overwritten_assume_role = Some(AssumeRoleSetting {
+            role_arn: "arn:aws:iam::123456789012:role/other".to_owned(),
+            external_id: None,
+            mfa_serial: Some("arn:aws:iam::123456789012:mfa/user".to_owned()),
+        });
+        assert_eq!(cx.effective_profile().as_deref(), Some("admin"));
+        assert_eq!(
+            cx.effective_assume_role().map(|r| r.role_arn).as_deref(),
+            Some("arn:aws:iam::123456789012:role/other")
+        );
+
+        cx.config = None;
+        cx.overwritten_profile = None;
+        cx.overwritten_assume_role = None;
+        assert_eq!(cx.effective_profile(), None);
+        assert_eq!(cx.effective_assume_role(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_schema_freshness() -> Result<(), Box<dyn Error>> {
+        let yaml = r#"
+region: us-west-2
+name: tbl
+pk:
+  name: pk
+  kind: S
+sk: null
+indexes: null
+mode: OnDemand
+"#;
+        let mut schema: TableSchema = serde_yaml::from_str(yaml)?;
+        // Schemas cached by older versions don't have cached_at.
+        assert!(!schema.is_fresh(Duration::from_secs(300)));
+
+        schema.cached_at = Some((Utc::now() - chrono::Duration::seconds(60)).to_rfc3339());
+        assert!(schema.is_fresh(Duration::from_secs(300)));
+        assert!(!schema.is_fresh(Duration::from_secs(30)));
+
+        schema.cached_at = Some(String::from("invalid"));
+        assert!(!schema.is_fresh(Duration::from_secs(300)));
+
+        let config: Config = serde_yaml::from_str("cache_expiration_time: 10")?;
+        assert_eq!(config.cache_expiration(), Duration::from_secs(10));
+        assert_eq!(
+            Config::default().cache_expiration(),
+            Duration::from_secs(300)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_layers() -> Result<(), Box<dyn Error>> {
+        let user = r#"
+using_region: us-east-1
+using_table: usertbl
+using_port: null
+retry:
+  default:
+    max_attempts: 5
+"#;
+        let project = r#"
+using_region: local
+query:
+  strict_mode: true
+retry:
+  default:
+    max_backoff:
+      secs: 10
+      nanos: 0
+"#;
+        let layers = vec![
+            ConfigLayer {
+                name: "user",
+                path: "config.yml".to_owned(),
+                value: load_yaml_mapping(user)?,
+            },
+            ConfigLayer {
+                name: "project",
+                path: ".dynein.yml".to_owned(),
+                value: load_yaml_mapping(project)?,
+            },
+        ];
+        let config = merge_layers(&layers)?;
+        assert_eq!(config.using_region(), Some("local"));
+        assert_eq!(config.using_table(), Some("usertbl"));
+        assert!(config.query.strict_mode);
+        let retry = config.retry.expect("retry should be merged").default;
+        assert_eq!(retry.max_attempts, Some(5));
+        assert_eq!(retry.max_backoff, Some(Duration::from_secs(10)));
+
+        let mut origins = HashMap::new();
+        for (i, layer) in layers.iter().enumerate() {
+            collect_origins(&layer.value, "", i, &mut origins);
+        }
+        assert_eq!(origins.get("using_table"), Some(&0));
+        assert_eq!(origins.get("using_region"), Some(&1));
+        assert_eq!(origins.get("retry.default.max_attempts"), Some(&0));
+        assert_eq!(origins.get("retry.default.max_backoff.secs"), Some(&1));
+
+        let names: HashMap<String, &str> = origins
+            .into_iter()
+            .map(|(path, i)| (path, layers[i].name))
+            .collect();
+        let mut dumped = String::new();
+        write_with_origins(
+            &load_yaml_mapping(
+                "using_region: local\nquery:\n  strict_mode: true\nusing_port: null\n",
+            )?,
+            "",
+            0,
+            &names,
+            &mut dumped,
+        )?;
+        assert_eq!(
+            dumped,
+            "using_region: local  # project\nquery:\n  strict_mode: true  # project\nusing_port: null  # user\n"
+        );
+        Ok(())
+    }
 
+    #[test]
+    fn test_parallel_cache_updates() -> Result<(), Box<dyn Error>> {
+        let dir = tempfile::tempdir()?;
+        let _guard = ConfigDirGuard::new(dir.path());
+        fs::create_dir_all(dir.path().join(CONFIG_DIR))?;
+
+        let schema: TableSchema = serde_yaml::from_str(
+            "region: local\nname: tbl\npk:\n  name: pk\n  kind: S\nsk: null\nindexes: null\nmode: OnDemand\n",
+        )?;
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let schema = schema.clone();
+                std::thread::spawn(move || {
+                    update_cache_file(|cache| {
+                        cache
+                            .tables
+                            .get_or_insert_with(HashMap::new)
+                            .insert(format!("local/tbl{}", i), schema)
+                    })
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().expect("thread should not panic")?;
+        }
+        let tables = load_or_touch_cache_file(true)?.tables.unwrap_or_default();
+        assert_eq!(tables.len(), 8);
+
+        // A broken cache file is ignored instead of failing.
+        fs::write(
+            retrieve_dynein_file_path(DyneinFileType::CacheFile)?,
+            "tables: [",
+        )?;
+        assert!(load_or_touch_cache_file(true)?.tables.is_none());
         Ok(())
     }
 
+    #[test]
+    fn test_config_key_paths() -> Result<(), Box<dyn Error>> {
+        let mut root = Value::Mapping(Mapping::new());
+        insert_value(&mut root, "contexts.dev.port", Value::from(8001))?;
+        insert_value(&mut root, "retry.default.max_attempts", Value::from(0))?;
+        assert_eq!(
+            lookup_value(&root, "contexts.dev.port"),
+            Some(&Value::from(8001))
+        );
+        assert!(insert_value(&mut root, "contexts..port", Value::Null).is_err());
+        assert!(insert_value(&mut root, "contexts.dev.port.x", Value::Null).is_err());
+
+        let config: Config = serde_yaml::from_value(root.clone())?;
+        let problems: Vec<String> = validate_config(&config)
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(
+            problems,
+            vec!["invalid value for 'retry.default': max_attempts should be greater than zero"]
+        );
+
+        insert_value(&mut root, "contexts.dev.regoin", Value::from("us-west-2"))?;
+        let mut problems = vec![];
+        collect_unknown_keys(&root, &serde_yaml::to_value(&config)?, "", &mut problems);
+        assert_eq!(
+            problems.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+            vec!["unknown key 'contexts.dev.regoin'"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_confirm_without_prompt() -> Result<(), Box<dyn Error>> {
+        let cx = Context {
+            config: None,
+            cache: None,
+            overwritten_region: None,
+            overwritten_table_name: None,
+            overwritten_port: None,
+            overwritten_endpoint_url: None,
+            overwritten_profile: None,
+            overwritten_assume_role: None,
+            assumed_role_credentials: Arc::new(OnceCell::new()),
+            output: None,
+            default_output: None,
+            color: ColorMode::Auto,
+            should_strict_for_query: None,
+            retry: None,
+            non_interactive: true,
+            recent_attribute_names: Arc::new(Mutex::new(VecDeque::new())),
+            metrics: None,
+        };
+        // --yes answers any prompt without showing it.
+        assert!(cx.confirm("Proceed?", true, Unattended::Fail("Use --yes."))?);
+        // In non-interactive mode, the prompt takes its default if it has one.
+        assert!(cx.confirm("Proceed?", false, Unattended::Proceed)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_format() {
+        let mut cx = Context {
+            config: Some(Config {
+                output: Some(OutputFormat::Csv),
+                ..Default::default()
+            }),
+            cache: None,
+            overwritten_region: None,
+            overwritten_table_name: None,
+            overwritten_port: None,
+            overwritten_endpoint_url: None,
+            overwritten_profile: None,
+            overwritten_assume_role: None,
+            assumed_role_credentials: Arc::new(OnceCell::new()),
+            output: None,
+            default_output: None,
+            color: ColorMode::Auto,
+            should_strict_for_query: None,
+            retry: None,
+            non_interactive: true,
+            recent_attribute_names: Arc::new(Mutex::new(VecDeque::new())),
+            metrics: None,
+        };
+        let items = crate::output::ITEM_FORMATS;
+        let descriptions = crate::output::DESCRIPTION_FORMATS;
+        // config.yml is used if the command supports the format.
+        assert_eq!(
+            cx.output_format(items, OutputFormat::Table),
+            OutputFormat::Csv
+        );
+        assert_eq!(
+            cx.output_format(descriptions, OutputFormat::Yaml),
+            OutputFormat::Yaml
+        );
+        // The top-level --output takes precedence over config.yml.
+        cx.default_output = Some(OutputFormat::Jsonl);
+        assert_eq!(
+            cx.output_format(items, OutputFormat::Table),
+            OutputFormat::Jsonl
+        );
+        assert_eq!(
+            cx.output_format(descriptions, OutputFormat::Yaml),
+            OutputFormat::Jsonl
+        );
+        // --output of the command takes precedence over any default.
+        cx.output = Some(OutputFormat::Json);
+        assert_eq!(
+            cx.output_format(items, OutputFormat::Table),
+            OutputFormat::Json
+        );
+    }
+
+    #[test]
+    fn test_remember_attribute_names() {
+        let cx = Context {
+            config: None,
+            cache: None,
+            overwritten_region: None,
+            overwritten_table_name: None,
+            overwritten_port: None,
+            overwritten_endpoint_url: None,
+            overwritten_profile: None,
+            overwritten_assume_role: None,
+            assumed_role_credentials: Arc::new(OnceCell::new()),
+            output: None,
+            default_output: None,
+            color: ColorMode::Auto,
+            should_strict_for_query: None,
+            retry: None,
+            non_interactive: false,
+            recent_attribute_names: Arc::new(Mutex::new(VecDeque::new())),
+            metrics: None,
+        };
+        let item = |names: &[&str]| -> HashMap<String, AttributeValue> {
+            names
+                .iter()
+                .map(|n| (n.to_string(), AttributeValue::Null(true)))
+                .collect()
+        };
+        cx.remember_attribute_names(&[item(&["a"]), item(&["b"])]);
+        cx.remember_attribute_names(&[item(&["a"])]);
+        assert_eq!(
+            *cx.recent_attribute_names.lock().unwrap(),
+            VecDeque::from(vec!["a".to_owned(), "b".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_request_metrics() {
+        use aws_sdk_dynamodb::types::Capacity;
+        let mut metrics = RequestMetrics::default();
+        // Query on a GSI, broken down by ReturnConsumedCapacity=Indexes.
+        metrics.add_capacity(
+            RequestKind::Read,
+            &ConsumedCapacity::builder()
+                .table_name("Movie")
+                .capacity_units(1.5)
+                .table(Capacity::builder().capacity_units(0.0).build())
+                .global_secondary_indexes(
+                    "year-index",
+                    Capacity::builder()
+                        .read_capacity_units(1.5)
+                        .capacity_units(1.5)
+                        .build(),
+                )
+                .build(),
+        );
+        // PutItem without breakdown, e.g. on DynamoDB Local.
+        metrics.add_capacity(
+            RequestKind::Write,
+            &ConsumedCapacity::builder()
+                .table_name("Movie")
+                .capacity_units(1.0)
+                .build(),
+        );
+        metrics.requests = 2;
+        metrics.latency = Duration::from_micros(12345);
+        metrics.scanned_count = 10;
+        metrics.returned_count = 3;
+        metrics.counted = true;
+        assert_eq!(
+            metrics.to_string(),
+            "Requests: 2, latency: 12.345 ms\n\
+             Consumed capacity:\n  \
+               Movie: 0 RCU, 1 WCU\n  \
+               Movie (index: year-index): 1.5 RCU, 0 WCU\n\
+             Items: 10 scanned, 3 returned\n"
+        );
+        assert_eq!(
+            RequestMetrics::default().to_string(),
+            "Requests: 0, latency: 0.000 ms\nConsumed capacity: not reported\n"
+        );
+    }
+
     #[test]
     fn test_retry_setting_success() {
         let config1 = RetrySetting::default();
@@ -901,4 +2855,40 @@ mod tests {
             _ => unreachable!("unexpected error"),
         }
     }
+
+    #[test]
+    fn test_table_location() {
+        let location = |table: &str, region: Option<&str>, port: Option<u32>| TableLocation {
+            table: table.to_owned(),
+            region: region.map(str::to_owned),
+            port,
+            endpoint_url: None,
+        };
+        assert_eq!("Movie".parse(), Ok(location("Movie", None, None)));
+        assert_eq!(
+            "Movie@us-west-2".parse(),
+            Ok(location("Movie", Some("us-west-2"), None))
+        );
+        assert_eq!(
+            "Movie@local:8001".parse(),
+            Ok(location("Movie", Some("local"), Some(8001)))
+        );
+        assert_eq!(
+            location("Movie", Some("local"), Some(8001)).to_string(),
+            "Movie@local:8001"
+        );
+        let with_endpoint = TableLocation {
+            endpoint_url: Some(String::from("http://localhost:4566")),
+            ..location("Movie", None, None)
+        };
+        assert_eq!(
+            "Movie@http://localhost:4566".parse(),
+            Ok(with_endpoint.clone())
+        );
+        assert_eq!(with_endpoint.to_string(), "Movie@http://localhost:4566");
+        assert!("@us-west-2".parse::<TableLocation>().is_err());
+        assert!("Movie@".parse::<TableLocation>().is_err());
+        assert!("Movie@us-west-2:8001".parse::<TableLocation>().is_err());
+        assert!("Movie@local:port".parse::<TableLocation>().is_err());
+    }
 }
@@ -24,9 +24,9 @@ use base64::{engine::general_purpose, Engine as _};
 use bytes::Bytes;
 use log::{debug, error};
 use serde_json::Value as JsonValue;
-use std::{collections::HashMap, error, fmt, fs, io::Error as IOError};
+use std::{collections::HashMap, error, fmt, fs, io::Error as IOError, time::Instant};
 
-use super::app;
+use super::app::{self, RequestKind};
 use super::data;
 use super::ddb::key;
 
@@ -41,6 +41,8 @@ pub enum DyneinBatchError {
     BatchWriteError(aws_sdk_dynamodb::error::SdkError<BatchWriteItemError>),
     InvalidInput(String),
     ParseError(crate::parser::ParseError),
+    ParquetError(crate::columnar::DyneinParquetError),
+    Exit(app::DyneinExit),
 }
 impl fmt::Display for DyneinBatchError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -50,6 +52,8 @@ impl fmt::Display for DyneinBatchError {
             DyneinBatchError::BatchWriteError(ref e) => e.fmt(f),
             DyneinBatchError::InvalidInput(ref msg) => write!(f, "{}", msg),
             DyneinBatchError::ParseError(ref e) => e.fmt(f),
+            DyneinBatchError::ParquetError(ref e) => e.fmt(f),
+            DyneinBatchError::Exit(ref e) => e.fmt(f),
         }
     }
 }
@@ -61,6 +65,8 @@ impl error::Error for DyneinBatchError {
             DyneinBatchError::BatchWriteError(ref e) => Some(e),
             DyneinBatchError::InvalidInput(_) => None,
             DyneinBatchError::ParseError(_) => None,
+            DyneinBatchError::ParquetError(ref e) => Some(e),
+            DyneinBatchError::Exit(ref e) => Some(e),
         }
     }
 }
@@ -86,6 +92,18 @@ impl From<crate::parser::ParseError> for DyneinBatchError {
     }
 }
 
+impl From<crate::columnar::DyneinParquetError> for DyneinBatchError {
+    fn from(e: crate::columnar::DyneinParquetError) -> Self {
+        Self::ParquetError(e)
+    }
+}
+
+impl From<app::DyneinExit> for DyneinBatchError {
+    fn from(e: app::DyneinExit) -> Self {
+        Self::Exit(e)
+    }
+}
+
 impl From<dialoguer::Error> for DyneinBatchError {
     fn from(e: dialoguer::Error) -> Self {
         match e {
@@ -239,12 +257,21 @@ async fn batch_write_item_api(
         .await;
     let ddb = DynamoDbSdkClient::new(&config);
 
+    let started_at = Instant::now();
     match ddb
         .batch_write_item()
         .set_request_items(Some(request_items))
+        .set_return_consumed_capacity(cx.return_consumed_capacity())
         .send()
         .await
-    {
+        .inspect(|res| {
+            cx.add_request_metrics(
+                RequestKind::Write,
+                started_at,
+                res.consumed_capacity(),
+                None,
+            )
+        }) {
         Ok(res) => Ok(res.unprocessed_items),
         Err(e) => Err(e),
     }
@@ -296,7 +323,7 @@ pub async fn batch_write_item(
     if puts.is_some() || dels.is_some() {
         let mut write_requests = Vec::<WriteRequest>::new();
         let parser = DyneinParser::new();
-        let ts: app::TableSchema = app::table_schema(cx).await;
+        let ts: app::TableSchema = app::table_schema(cx).await?;
 
         if let Some(items) = puts {
             for item in items.iter() {
@@ -385,11 +412,60 @@ pub async fn convert_jsonvals_to_request_items(
     }
 
     // A single table name as a key, and insert all (up to 25) write_requests under the single table.
-    results.insert(cx.effective_table_name(), write_requests);
+    results.insert(cx.effective_table_name()?, write_requests);
+
+    Ok(results)
+}
+
+/// This function is similar to `convert_jsonvals_to_request_items`, but each JsonValue is an item in DynamoDB JSON format
+///   (e.g. {"pk": {"S": "abc"}, "count": {"N": "3"}}) instead of a standard JSON. Types in DynamoDB JSON are explicit, so no inference is applied.
+/// Note that this function assumes that target table is only one table.
+pub fn convert_ddbjson_items_to_request_items(
+    cx: &app::Context,
+    ddbjson_items: Vec<JsonValue>,
+) -> Result<HashMap<String, Vec<WriteRequest>>, app::DyneinExit> {
+    convert_items_to_request_items(
+        cx,
+        ddbjson_items
+            .iter()
+            .map(convert_ddbjson_to_item)
+            .collect::<Vec<HashMap<String, AttributeValue>>>(),
+    )
+}
+
+/// This function takes items which are already converted into AttributeValues, and returns a HashMap from table name to Vec<WriteRequest> of PutRequests.
+/// Note that this function assumes that target table is only one table.
+pub fn convert_items_to_request_items(
+    cx: &app::Context,
+    items: Vec<HashMap<String, AttributeValue>>,
+) -> Result<HashMap<String, Vec<WriteRequest>>, app::DyneinExit> {
+    let mut results = HashMap::<String, Vec<WriteRequest>>::new();
+    let write_requests = items
+        .into_iter()
+        .map(|item| {
+            WriteRequest::builder()
+                .put_request(PutRequest::builder().set_item(Some(item)).build().unwrap())
+                .build()
+        })
+        .collect::<Vec<WriteRequest>>();
+
+    // A single table name as a key, and insert all (up to 25) write_requests under the single table.
+    results.insert(cx.effective_table_name()?, write_requests);
 
     Ok(results)
 }
 
+/// Converts an item in DynamoDB JSON format (e.g. {"pk": {"S": "abc"}}) into AttributeValues.
+pub fn convert_ddbjson_to_item(ddbjson_item: &JsonValue) -> HashMap<String, AttributeValue> {
+    ddbjson_attributes_to_attrvals(ddbjson_item)
+}
+
+/// Converts a single attribute value in DynamoDB JSON format (e.g. {"N": "4"}) into an AttributeValue.
+/// Returns None if the value is not in DynamoDB JSON format.
+pub fn convert_ddbjson_to_attrval(ddbjson_val: &JsonValue) -> Option<AttributeValue> {
+    ddbjson_val_to_attrval(ddbjson_val)
+}
+
 /// "matrix" is a vector of vectors. These internal vectors has strs, each of them is an attribute for an item.
 ///
 /// e.g.
@@ -410,8 +486,10 @@ pub async fn csv_matrix_to_request_items(
         .iter()
         .sum::<usize>();
     if (headers.len() * matrix.len()) != total_elements_in_matrix {
-        error!("cells in the 'matrix' should have exact the same number of elements of 'headers'");
-        std::process::exit(1);
+        return Err(app::bye_with_error(
+            "cells in the 'matrix' should have exact the same number of elements of 'headers'",
+        )
+        .into());
     }
 
     let mut results = HashMap::<String, Vec<WriteRequest>>::new();
@@ -441,7 +519,7 @@ pub async fn csv_matrix_to_request_items(
     }
 
     // A single table name as a key, and insert all (up to 25) write_requests under the single table.
-    results.insert(cx.effective_table_name(), write_requests);
+    results.insert(cx.effective_table_name()?, write_requests);
 
     Ok(results)
 }
@@ -45,6 +45,7 @@ pub enum DyneinBootstrapError {
     LoadData(IOError),
     PraseJSON(serde_json::Error),
     BatchError(aws_sdk_dynamodb::error::SdkError<BatchWriteItemError>),
+    Exit(app::DyneinExit),
 }
 impl fmt::Display for DyneinBootstrapError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -52,6 +53,7 @@ impl fmt::Display for DyneinBootstrapError {
             DyneinBootstrapError::LoadData(ref e) => e.fmt(f),
             DyneinBootstrapError::PraseJSON(ref e) => e.fmt(f),
             DyneinBootstrapError::BatchError(ref e) => e.fmt(f),
+            DyneinBootstrapError::Exit(ref e) => e.fmt(f),
         }
     }
 }
@@ -61,6 +63,7 @@ impl error::Error for DyneinBootstrapError {
             DyneinBootstrapError::LoadData(ref e) => Some(e),
             DyneinBootstrapError::PraseJSON(ref e) => Some(e),
             DyneinBootstrapError::BatchError(ref e) => Some(e),
+            DyneinBootstrapError::Exit(ref e) => Some(e),
         }
     }
 }
@@ -79,6 +82,11 @@ impl From<aws_sdk_dynamodb::error::SdkError<BatchWriteItemError>> for DyneinBoot
         Self::BatchError(e)
     }
 }
+impl From<app::DyneinExit> for DyneinBootstrapError {
+    fn from(e: app::DyneinExit) -> Self {
+        Self::Exit(e)
+    }
+}
 
 /* =================================================
 Public functions
@@ -108,7 +116,7 @@ pub async fn launch_sample(
             } else {
                 println!("Unknown sample name. Available samples are:");
                 list_samples();
-                std::process::exit(1);
+                Err(app::DyneinExit(1).into())
             }
         }
     }
@@ -132,10 +140,10 @@ see https://github.com/awslabs/dynein#working-with-dynamodb-items for detail
     );
 
     // Step 1. create tables
-    prepare_table(cx, "Movie", vec!["year,N", "title,S"].as_ref()).await;
+    prepare_table(cx, "Movie", vec!["year,N", "title,S"].as_ref()).await?;
 
     // Step 2. wait tables to be created and in ACTIVE status
-    wait_table_creation(cx, vec!["Movie"]).await;
+    wait_table_creation(cx, vec!["Movie"]).await?;
 
     // Step 3. decompress data
     let compressed_data = include_bytes!("./resources/bootstrap/moviedata.json.br");
@@ -154,8 +162,7 @@ see https://github.com/awslabs/dynein#working-with-dynamodb-items for detail
     let deserialized_json: JsonValue = serde_json::from_str(&content).unwrap();
     debug!("converted JSON: {:#?}", &deserialized_json);
     if !deserialized_json.is_array() {
-        println!("target JSON should be an array.");
-        std::process::exit(1);
+        return Err(app::bye(1, "target JSON should be an array.").into());
     };
     let mut whole_items = deserialized_json.as_array().expect("is array").iter();
 
@@ -238,12 +245,12 @@ https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/AppendixSampleT
 
     // Step 1. Create tables
     for (table_name, keys) in &tables {
-        prepare_table(cx, table_name, keys).await
+        prepare_table(cx, table_name, keys).await?
     }
 
     // Step 2. wait tables to be created and in ACTIVE status
     let creating_table_names: Vec<&str> = tables.iter().map(|pair| pair.0).collect();
-    wait_table_creation(cx, creating_table_names).await;
+    wait_table_creation(cx, creating_table_names).await?;
 
     println!("Tables are ready and retrieved sample data locally. Now start writing data into samle tables...");
     for (table_name, _) in &tables {
@@ -287,7 +294,11 @@ https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/AppendixSampleT
     Ok(())
 }
 
-async fn prepare_table(cx: &app::Context, table_name: &str, keys: &[&str]) {
+async fn prepare_table(
+    cx: &app::Context,
+    table_name: &str,
+    keys: &[&str],
+) -> Result<(), app::DyneinExit> {
     match control::create_table_api(
         cx,
         table_name.to_string(),
@@ -311,13 +322,18 @@ async fn prepare_table(cx: &app::Context, table_name: &str, keys: &[&str]) {
             ),
             _ => {
                 debug!("CreateTable API call got an error -- {:#?}", e);
-                app::bye_with_sdk_error(1, e);
+                return Err(app::bye_with_sdk_error(1, e));
             }
         },
     }
+    Ok(())
 }
 
-async fn wait_table_creation(cx: &app::Context, mut processing_tables: Vec<&str>) {
+/// Waits until all of the tables become ACTIVE, e.g. after CreateTable API calls.
+pub async fn wait_table_creation(
+    cx: &app::Context,
+    mut processing_tables: Vec<&str>,
+) -> Result<(), app::DyneinExit> {
     debug!("tables in progress: {:?}", processing_tables);
     loop {
         let create_table_results = join_all(
@@ -327,9 +343,9 @@ async fn wait_table_creation(cx: &app::Context, mut processing_tables: Vec<&str>
         )
         .await;
         let statuses: Vec<String> = create_table_results
-            .iter()
-            .map(|desc| desc.table_status.to_owned().unwrap().to_string())
-            .collect();
+            .into_iter()
+            .map(|desc| Ok(desc?.table_status.unwrap().to_string()))
+            .collect::<Result<_, app::DyneinExit>>()?;
         debug!("Current table statues: {:?}", statuses);
         processing_tables = processing_tables
             .iter()
@@ -340,7 +356,7 @@ async fn wait_table_creation(cx: &app::Context, mut processing_tables: Vec<&str>
         println!("Still CREATING following tables: {:?}", processing_tables);
         if processing_tables.is_empty() {
             println!("All tables are in ACTIVE.");
-            break;
+            return Ok(());
         }
         println!("Waiting for tables to be ACTIVE status...");
         thread::sleep(time::Duration::from_millis(5000));
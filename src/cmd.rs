@@ -15,10 +15,17 @@
  */
 
 use ::serde::{Deserialize, Serialize};
-use clap::{CommandFactory, FromArgMatches, Parser};
+use clap::{
+    builder::{PossibleValuesParser, TypedValueParser},
+    CommandFactory, FromArgMatches, Parser,
+};
 use std::error::Error;
 use std::ffi::OsString;
 
+use crate::app::TableLocation;
+use crate::output::{ColorMode, OutputFormat};
+use crate::schema::SchemaFormat;
+
 /* =================================================
 struct / enum / const
 ================================================= */
@@ -49,9 +56,65 @@ pub struct Dynein {
     #[clap(short, long, global = true, verbatim_doc_comment)]
     pub table: Option<String>,
 
+    /// AWS profile to use. It takes precedence over `profile` of the current context.
+    #[clap(long, global = true, verbatim_doc_comment)]
+    pub profile: Option<String>,
+
+    /// ARN of an IAM role to assume with STS. Credentials of the profile (or the default credentials) are used to assume the role.
+    #[clap(long, global = true, verbatim_doc_comment)]
+    pub role_arn: Option<String>,
+
+    /// External ID to assume the role given by --role-arn.
+    #[clap(long, global = true, requires = "role_arn", verbatim_doc_comment)]
+    pub external_id: Option<String>,
+
+    /// Serial number (or ARN) of the MFA device to assume the role given by --role-arn. dynein asks the MFA code.
+    #[clap(long, global = true, requires = "role_arn", verbatim_doc_comment)]
+    pub mfa_serial: Option<String>,
+
     #[clap(long, verbatim_doc_comment)]
     pub shell: bool,
 
+    /// Run dy commands written in the file, e.g. `dy --script migration.dy`. The syntax is the same as --shell.
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with = "shell",
+        verbatim_doc_comment
+    )]
+    pub script: Option<String>,
+
+    /// Stop at the first failed command of --shell or --script. Without this option, the rest of commands are executed.
+    /// In both cases, dynein exits with a non-zero status if any command failed, unless commands are typed in a terminal.
+    #[clap(long, verbatim_doc_comment)]
+    pub stop_on_error: bool,
+
+    /// Define a variable referred as `${NAME}` in commands of --shell or --script. This option can be given multiple times.
+    #[clap(long = "var", value_name = "NAME=VALUE", value_parser = crate::shell::parse_variable, verbatim_doc_comment)]
+    pub vars: Vec<(String, String)>,
+
+    /// Never show interactive prompts. Prompts take their default answer, or dynein exits with an error which tells the option to use instead.
+    /// This mode is enabled automatically when stdin is not a terminal.
+    #[clap(long, global = true, verbatim_doc_comment)]
+    pub non_interactive: bool,
+
+    /// Show consumed RCU/WCU per table and index, scanned and returned item counts of Scan/Query, and request latency
+    /// after the command. They are written to stderr. In shell mode, they are shown after each command.
+    #[clap(long, global = true, verbatim_doc_comment)]
+    pub show_capacity: bool,
+
+    /// When to colorize json and yaml output. auto colorizes if stdout is a terminal and NO_COLOR environment variable is not set.
+    /// always colorizes even if stdout is not a terminal, e.g. piped to `less -R`.
+    #[clap(long, value_enum, default_value_t, global = true, verbatim_doc_comment)]
+    pub color: ColorMode,
+
+    /// Default output format of scan, query, get and desc, e.g. `dy --output jsonl scan`. `output` in config.yml sets it as well.
+    /// --output of each command takes precedence. Commands which don't support the format use their own default.
+    /// table aligns columns for humans, and jsonl writes one compact JSON per line. csv has a header line, and its values are written in JSON.
+    /// ddb-json (or raw) is DynamoDB JSON, which has the type of each value, e.g. {"id": {"S": "abc"}}.
+    #[clap(short, long, value_enum, verbatim_doc_comment)]
+    pub output: Option<OutputFormat>,
+
     /// This option displays detailed information about third-party libraries, frameworks, and other components incorporated into dynein,    
     /// as well as the full license texts under which they are distributed.
     #[clap(long)]
@@ -107,8 +170,8 @@ pub enum Sub {
         all_tables: bool,
 
         /// Switch output format.
-        #[clap(short, long, value_parser = ["yaml" /*, "raw" */ ], verbatim_doc_comment)]
-        output: Option<String>,
+        #[clap(short, long, value_parser = PossibleValuesParser::new(["yaml", "json", "jsonl"]).map(|s| s.parse::<OutputFormat>().unwrap()), verbatim_doc_comment)]
+        output: Option<OutputFormat>,
     },
 
     /* =================================================
@@ -135,13 +198,28 @@ pub enum Sub {
         #[clap(long, verbatim_doc_comment)]
         keys_only: bool,
 
+        /// Document paths to show as columns, separated by commas, which is mapped to ProjectionExpression
+        /// (e.g. --columns 'address.city,tags[0],price'). Primary key(s) are always included as well.
+        /// By default, the table output shows each attribute found in the items as a column.
+        #[clap(long, conflicts_with_all = ["attributes", "keys_only"], verbatim_doc_comment)]
+        columns: Option<String>,
+
+        /// Show cells of the table output without truncation. By default, long cells are truncated to fit the terminal width.
+        #[clap(long, verbatim_doc_comment)]
+        wide: bool,
+
+        /// Annotate each value with its DynamoDB type (e.g. S, N, SS, L or M) in json and yaml output, which is json by default.
+        /// Note that the annotations make the output invalid JSON.
+        #[clap(long, verbatim_doc_comment)]
+        show_types: bool,
+
         /// Read data from index instead of base table.
         #[clap(short, long, verbatim_doc_comment)]
         index: Option<String>,
 
-        /// Switch output format.
-        #[clap(short, long, value_parser = ["table", "json", "raw"], verbatim_doc_comment)]
-        output: Option<String>,
+        /// Switch output format. See --output of `dy help` for the formats.
+        #[clap(short, long, value_enum, verbatim_doc_comment)]
+        output: Option<OutputFormat>,
     },
 
     /// Retrieve an item by specifying primary key(s). [API: GetItem]
@@ -157,9 +235,14 @@ pub enum Sub {
         #[clap(long, verbatim_doc_comment)]
         consistent_read: bool,
 
-        /// Switch output format.
-        #[clap(short, long, value_parser = ["json", "yaml", "raw"], verbatim_doc_comment)]
-        output: Option<String>,
+        /// Annotate each value with its DynamoDB type (e.g. S, N, SS, L or M) in json and yaml output.
+        /// Note that the annotations make the output invalid JSON.
+        #[clap(long, verbatim_doc_comment)]
+        show_types: bool,
+
+        /// Switch output format. See --output of `dy help` for the formats.
+        #[clap(short, long, value_enum, verbatim_doc_comment)]
+        output: Option<OutputFormat>,
     },
 
     /// Retrieve items that match conditions. Partition key is required. [API: Query]
@@ -195,6 +278,21 @@ pub enum Sub {
         #[clap(long, verbatim_doc_comment)]
         keys_only: bool,
 
+        /// Document paths to show as columns, separated by commas, which is mapped to ProjectionExpression
+        /// (e.g. --columns 'address.city,tags[0],price'). Primary key(s) are always included as well.
+        /// By default, the table output shows each attribute found in the items as a column.
+        #[clap(long, conflicts_with_all = ["attributes", "keys_only"], verbatim_doc_comment)]
+        columns: Option<String>,
+
+        /// Show cells of the table output without truncation. By default, long cells are truncated to fit the terminal width.
+        #[clap(long, verbatim_doc_comment)]
+        wide: bool,
+
+        /// Annotate each value with its DynamoDB type (e.g. S, N, SS, L or M) in json and yaml output, which is json by default.
+        /// Note that the annotations make the output invalid JSON.
+        #[clap(long, verbatim_doc_comment)]
+        show_types: bool,
+
         /// Results of query are always sorted by the sort key value. By default, the sort order is ascending.
         /// Specify --descending to traverse descending order.
         #[clap(short, long, verbatim_doc_comment)]
@@ -216,9 +314,9 @@ pub enum Sub {
         #[clap(long, conflicts_with = "strict")]
         non_strict: bool,
 
-        /// Switch output format.
-        #[clap(short, long, value_parser = ["table", "json", "raw"], verbatim_doc_comment)]
-        output: Option<String>,
+        /// Switch output format. See --output of `dy help` for the formats.
+        #[clap(short, long, value_enum, verbatim_doc_comment)]
+        output: Option<OutputFormat>,
     },
 
     /// Create a new item, or replace an existing item. [API: PutItem]
@@ -276,6 +374,21 @@ pub enum Sub {
         atomic_counter: Option<String>,
     },
 
+    /// Edit an item in $EDITOR and apply the change. [API: GetItem, UpdateItem]
+    ///
+    /// The item is opened in the dynein format, e.g. sets are written as <<...>>.
+    /// Changed, added and removed attributes (including nested ones) are applied by SET/REMOVE actions,
+    /// on condition that the primary key and the changed top-level attributes are the same as when the item was read,
+    /// and the added ones still don't exist. Changes of other attributes made in the meantime are not detected, and are kept.
+    /// The editor is given by $VISUAL or $EDITOR.
+    #[clap(verbatim_doc_comment)]
+    Edit {
+        /// Partition Key of the target item.
+        pval: String,
+        /// Sort Key of the target item (if any).
+        sval: Option<String>,
+    },
+
     /// Put or Delete multiple items at one time, up to 25 requests. [API: BatchWriteItem]
     ///
     /// https://docs.aws.amazon.com/amazondynamodb/latest/APIReference/API_BatchWriteItem.html
@@ -346,8 +459,9 @@ pub enum Sub {
         ///   json = JSON format with newline/indent.{n}
         ///   jsonl = JSON Lines (http://jsonlines.org). i.e. one item per line.{n}
         ///   json-compact = JSON format, all items are packed in oneline.{n}
-        ///   csv = comma-separated values with header. Use it with --keys-only or --attributes. If neither of them are given dynein will ask you target attributes interactively.
-        #[clap(short, long, value_parser = ["csv", "json", "jsonl", "json-compact"], verbatim_doc_comment)]
+        ///   csv = comma-separated values with header. Use it with --keys-only or --attributes. If neither of them are given dynein will ask you target attributes interactively.{n}
+        ///   parquet = Apache Parquet. The schema is inferred from all exported items. Lists and maps are stored as strings in DynamoDB JSON.
+        #[clap(short, long, value_parser = ["csv", "json", "jsonl", "json-compact", "parquet"], verbatim_doc_comment)]
         format: Option<String>,
 
         /// [csv] Specify attributes to export, separated by commas (e.g. --attributes name,address,age). Effective only when --format is 'csv'.{n}
@@ -358,6 +472,37 @@ pub enum Sub {
         /// [csv] Export only Primary Key(s). Effective only when --format is 'csv'.
         #[clap(long, conflicts_with("attributes"), verbatim_doc_comment)]
         keys_only: bool,
+
+        /// Compress the output file. If not specified, compression is chosen by the extension of the output file (.gz, .zst, .br).
+        #[clap(long, value_parser = ["gzip", "zstd", "brotli"], verbatim_doc_comment)]
+        compress: Option<String>,
+
+        /// Export items from the index instead of the base table.
+        #[clap(short, long, verbatim_doc_comment)]
+        index: Option<String>,
+
+        /// Export only items in the partition, using Query API instead of Scan API.{n}
+        /// When --index is given, this is a partition key value of the index.
+        #[clap(long, verbatim_doc_comment)]
+        pval: Option<String>,
+
+        /// Additional Sort Key condition for --pval, which will be converted to KeyConditionExpression.{n}
+        /// Valid syntax: ['= 12', '> 12', '>= 12', '< 12', '<= 12', 'between 10 and 99', 'begins_with myVal"]
+        #[clap(short, long = "sort-key", requires("pval"), verbatim_doc_comment)]
+        sort_key_expression: Option<String>,
+
+        /// Export only items matching the FilterExpression (e.g. --filter '#price > :min AND #status = :s').{n}
+        /// Attribute name placeholders (#name) are resolved to the attribute of the same name.
+        #[clap(long, verbatim_doc_comment)]
+        filter: Option<String>,
+
+        /// Values of placeholders used in --filter, given in dynein format (e.g. --filter-values '{":min": 100, ":s": "active"}').
+        #[clap(long, requires("filter"), verbatim_doc_comment)]
+        filter_values: Option<String>,
+
+        /// Skip interactive confirmations, i.e. overwrite the existing output file and export attributes found in the first item for CSV.
+        #[clap(short, long, alias = "force", verbatim_doc_comment)]
+        yes: bool,
     },
 
     /// Import items into a DynamoDB table from CSV/JSON file.
@@ -367,22 +512,162 @@ pub enum Sub {
     #[clap(verbatim_doc_comment)]
     Import {
         /// Filename contains DynamoDB items data. Specify appropriate format with --format option.
-        #[clap(short, long, verbatim_doc_comment)]
-        input_file: String,
+        #[clap(
+            short,
+            long,
+            required_unless_present("from_export_dir"),
+            verbatim_doc_comment
+        )]
+        input_file: Option<String>,
 
         /// Data format for import items.{n}
         ///   json = JSON format with newline/indent.{n}
         ///   jsonl = JSON Lines (http://jsonlines.org). i.e. one item per line.{n}
         ///   json-compact = JSON format, all items are packed in oneline.{n}
-        ///   csv = comma-separated values with header. Header columns are considered to be DynamoDB attributes.
-        #[clap(short, long, value_parser = ["csv", "json", "jsonl", "json-compact"], verbatim_doc_comment)]
+        ///   csv = comma-separated values with header. Header columns are considered to be DynamoDB attributes.{n}
+        ///   parquet = Apache Parquet. Types are restored from the metadata written by `dy export --format parquet`.
+        #[clap(short, long, value_parser = ["csv", "json", "jsonl", "json-compact", "parquet"], verbatim_doc_comment)]
         format: Option<String>,
 
         /// Enable type inference for set types. This option is provided for backward compatibility.
         #[clap(long)]
         enable_set_inference: bool,
+
+        /// Directory downloaded from DynamoDB's native "Export to S3" (DynamoDB JSON format), which contains manifest-summary.json and data/*.json.gz.{n}
+        /// Data files are verified with item counts and checksums in the manifest before importing.
+        #[clap(long, conflicts_with_all(["input_file", "format", "enable_set_inference"]), verbatim_doc_comment)]
+        from_export_dir: Option<String>,
+
+        /// Decompress the input file. If not specified, compression is chosen by the extension of the input file (.gz, .zst, .br).
+        #[clap(long, value_parser = ["gzip", "zstd", "brotli"], conflicts_with("from_export_dir"), verbatim_doc_comment)]
+        compress: Option<String>,
+
+        /// Skip interactive confirmations, e.g. the warning about importing into a provisioned table.
+        #[clap(short, long, alias = "force", verbatim_doc_comment)]
+        yes: bool,
     },
 
+    /// Compare items of two tables, or a table and a file exported by `dy export`. [API: Scan]
+    ///
+    /// Items are matched by their primary keys, and added, removed and changed items are reported
+    /// with differences of each attribute (including nested ones).
+    /// A table is given as TABLE, TABLE@REGION, TABLE@local:PORT for DynamoDB Local, TABLE@ENDPOINT_URL or TABLE@CONTEXT,
+    /// so that tables in different regions, endpoints or contexts can be compared, e.g. `dy diff Movie@us-east-1 Movie@us-west-2`.
+    /// Items of the source are kept in memory while comparing (and items of the file with --file).
+    #[clap(verbatim_doc_comment)]
+    Diff {
+        /// The source table, e.g. Movie, Movie@us-west-2, Movie@local:8000, Movie@http://localhost:4566 or Movie@staging.
+        #[clap(value_name = "SOURCE", verbatim_doc_comment)]
+        source: TableLocation,
+
+        /// The target table compared with the source, in the same form as the source.
+        #[clap(
+            value_name = "TARGET",
+            required_unless_present("file"),
+            verbatim_doc_comment
+        )]
+        target: Option<TableLocation>,
+
+        /// Compare the source table with items in the file written by `dy export` instead of a target table.{n}
+        /// As types like sets are lost in the file, items of the table are compared as they're exported.
+        #[clap(long, conflicts_with("target"), verbatim_doc_comment)]
+        file: Option<String>,
+
+        /// Data format of the file. json = JSON format with newline/indent, json-compact = all items in one line,
+        /// jsonl = one item per line. [default: json]
+        #[clap(short, long, value_parser = ["json", "jsonl", "json-compact"], requires("file"), verbatim_doc_comment)]
+        format: Option<String>,
+
+        /// Decompress the file. If not specified, compression is chosen by the extension of the file (.gz, .zst, .br).
+        #[clap(long, value_parser = ["gzip", "zstd", "brotli"], requires("file"), verbatim_doc_comment)]
+        compress: Option<String>,
+
+        /// Exit with status 1 if there's any difference, like `diff` command.
+        #[clap(long, verbatim_doc_comment)]
+        exit_code: bool,
+
+        /// Switch output format. Differences are printed in a human-readable form by default.
+        #[clap(short, long, value_parser = PossibleValuesParser::new(["json", "jsonl"]).map(|s| s.parse::<OutputFormat>().unwrap()), verbatim_doc_comment)]
+        output: Option<OutputFormat>,
+    },
+
+    /// Copy items from a table to another table, e.g. in another region or DynamoDB Local. [API: Scan, BatchWriteItem]
+    ///
+    /// The source table is scanned in parallel, and items are written into the target table by BatchWriteItem.
+    /// A table is given as TABLE, TABLE@REGION, TABLE@local:PORT for DynamoDB Local, TABLE@ENDPOINT_URL or TABLE@CONTEXT,
+    /// e.g. `dy copy --from Movie@us-east-1 --to Movie@local --create-table`.
+    /// With --resume-file, the progress is recorded in the file, and running the same command again resumes an interrupted copy.
+    #[clap(verbatim_doc_comment)]
+    Copy {
+        /// The source table, e.g. Movie, Movie@us-west-2, Movie@local:8000, Movie@http://localhost:4566 or Movie@staging.
+        #[clap(long, value_name = "TABLE", verbatim_doc_comment)]
+        from: TableLocation,
+
+        /// The target table, in the same form as the source.
+        #[clap(long, value_name = "TABLE", verbatim_doc_comment)]
+        to: TableLocation,
+
+        /// Create the target table in on-demand mode if it doesn't exist. [API: DescribeTable, CreateTable]{n}
+        /// Its primary key and secondary indexes are the same as the source table (renamed by --rename).
+        /// Other settings such as streams and TTL are not copied.
+        #[clap(long, verbatim_doc_comment)]
+        create_table: bool,
+
+        /// Rename an attribute of items, e.g. `--rename pk=PK` when key names of the target table are different.{n}
+        /// Only top-level attributes can be renamed. This option can be repeated.
+        #[clap(long, value_name = "OLD=NEW", verbatim_doc_comment)]
+        rename: Vec<String>,
+
+        /// Number of segments to scan the source table in parallel.
+        #[clap(long, value_name = "N", default_value = "4", value_parser = clap::value_parser!(i32).range(1..=1_000_000), verbatim_doc_comment)]
+        segments: i32,
+
+        /// Record the progress in the file. If the file exists, the copy resumes from it (with the number of segments in the file).
+        /// It's removed when the copy completes.
+        #[clap(long, value_name = "FILE", verbatim_doc_comment)]
+        resume_file: Option<String>,
+    },
+
+    /// Show statistics of items in a table, e.g. item sizes and hot partition keys. [API: Scan]
+    ///
+    /// It shows the item count, item sizes (average/p50/p99/max), top partition keys by item count and bytes,
+    /// and the frequency and types of attributes.
+    /// Item sizes are computed by the rules of DynamoDB, which are used to calculate consumed capacity.
+    #[clap(verbatim_doc_comment)]
+    Stats {
+        /// Scan at most this number of items instead of the whole table.
+        #[clap(long, value_name = "N", value_parser = clap::value_parser!(i32).range(1..), verbatim_doc_comment)]
+        sample: Option<i32>,
+
+        /// Number of partition keys to show, ordered by item count and by bytes respectively.
+        #[clap(long, value_name = "N", default_value = "10", verbatim_doc_comment)]
+        top: usize,
+
+        /// Read data from index instead of base table. Partition keys are those of the index.
+        #[clap(short, long, verbatim_doc_comment)]
+        index: Option<String>,
+
+        /// Switch output format.
+        #[clap(short, long, value_parser = PossibleValuesParser::new(["yaml", "json", "jsonl"]).map(|s| s.parse::<OutputFormat>().unwrap()), verbatim_doc_comment)]
+        output: Option<OutputFormat>,
+    },
+
+    /// <sub> Infer a logical schema of items in a table
+    #[clap(verbatim_doc_comment)]
+    Schema {
+        #[clap(subcommand, verbatim_doc_comment)]
+        grandchild: SchemaSub,
+    },
+
+    /// Browse tables and items in a terminal UI. [API: ListTables, Scan, Query, GetItem, UpdateItem]
+    ///
+    /// Pick a table, page through items by Scan or Query, switch between the table and its indexes,
+    /// and open an item to show it in JSON. An item can be edited in $EDITOR, and only the changed attributes are
+    /// written by a conditional update, which fails if the item has been changed since it was read.
+    /// It starts with the table in use (by `dy use` or --table) if any.
+    #[clap(verbatim_doc_comment)]
+    Tui,
+
     /// Take backup of a DynamoDB table using on-demand backup
     ///
     /// For more details: https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/BackupRestore.html
@@ -433,8 +718,8 @@ pub enum AdminSub {
         all_tables: bool,
 
         /// Switch output format.
-        #[clap(short, long, value_parser = ["yaml" /*, "raw" */ ], verbatim_doc_comment)]
-        output: Option<String>,
+        #[clap(short, long, value_parser = PossibleValuesParser::new(["yaml", "json", "jsonl"]).map(|s| s.parse::<OutputFormat>().unwrap()), verbatim_doc_comment)]
+        output: Option<OutputFormat>,
     },
 
     /// Create new DynamoDB table or GSI. [API: CreateTable, UpdateTable]
@@ -558,6 +843,33 @@ pub enum DeleteSub {
     // }
 }
 
+#[derive(Parser, Debug, Serialize, Deserialize, PartialEq)]
+pub enum SchemaSub {
+    /// Scan a table and infer a schema of its items, printed as JSON Schema, TypeScript interfaces or Rust structs. [API: Scan]
+    ///
+    /// Every attribute path, including attributes in maps and elements of lists, is shown with the observed DynamoDB types,
+    /// the percentage of items (or parent maps) which have it, and example values.
+    /// Attributes found in all items are required, and the others are optional.
+    #[clap(verbatim_doc_comment)]
+    Infer {
+        /// Scan at most this number of items instead of the whole table.
+        #[clap(long, value_name = "N", value_parser = clap::value_parser!(i32).range(1..), verbatim_doc_comment)]
+        sample: Option<i32>,
+
+        /// Read data from index instead of base table.
+        #[clap(short, long, verbatim_doc_comment)]
+        index: Option<String>,
+
+        /// Format of the schema.
+        #[clap(short, long, value_enum, default_value_t, verbatim_doc_comment)]
+        format: SchemaFormat,
+
+        /// Name of the root type. The table name in PascalCase is used by default, e.g. `MovieTable` for `movie-table`.
+        #[clap(long, verbatim_doc_comment)]
+        name: Option<String>,
+    },
+}
+
 #[derive(Parser, Debug, Serialize, Deserialize, PartialEq)]
 pub enum ConfigSub {
     /// Show all configuration in config (config.yml) and cache (cache.yml) files.
@@ -568,11 +880,80 @@ pub enum ConfigSub {
     /// Reset all dynein configuration in the `~/.dynein/` directory. This command initializes dynein related files only and won't remove your data stored in DynamoDB tables.
     #[clap(verbatim_doc_comment)]
     Clear,
+
+    /// Switch to a named context defined in `contexts` of config.yml, e.g. `dy config use-context staging`.
+    ///
+    /// Each context has its own region, AWS profile, role to assume, endpoint URL, table and retry setting.
+    /// Without a name, the top-level settings (using_region, using_table and using_port) are used.
+    #[clap(verbatim_doc_comment)]
+    UseContext {
+        /// Name of the context.
+        name: Option<String>,
+    },
+
+    /// List contexts defined in config.yml. The current context is marked with `*`.
+    #[clap(verbatim_doc_comment)]
+    GetContexts,
+
+    /// Show the value of a key in config.yml, e.g. `dy config get retry.default.max_attempts`.
+    #[clap(verbatim_doc_comment)]
+    Get {
+        /// Dot-separated path of the key, e.g. `query.strict_mode` or `contexts.dev.region`.
+        key: String,
+    },
+
+    /// Set a value to a key in config.yml, e.g. `dy config set retry.default.max_attempts 5`.
+    ///
+    /// The value is parsed as YAML, and the config is saved only if it's valid after the change.
+    #[clap(verbatim_doc_comment)]
+    Set {
+        /// Dot-separated path of the key, e.g. `query.strict_mode` or `contexts.dev.region`.
+        key: String,
+        /// New value of the key, e.g. `5`, `true` or `us-west-2`.
+        value: String,
+    },
+
+    /// Remove a key from config.yml, so that the default is used.
+    #[clap(verbatim_doc_comment)]
+    Unset {
+        /// Dot-separated path of the key, e.g. `query.strict_mode` or `contexts.dev.region`.
+        key: String,
+    },
+
+    /// Check config.yml and `.dynein.yml`, e.g. unknown keys, invalid retry settings or an undefined current context.
+    #[clap(verbatim_doc_comment)]
+    Validate,
+
+    /// Manage cached table schemas in cache.yml, e.g. `dy config cache refresh` after adding an index.
+    ///
+    /// Cached schemas are refreshed automatically when `cache_expiration_time` (in second, default 300) in config.yml has passed.
+    #[clap(verbatim_doc_comment)]
+    Cache {
+        #[clap(subcommand, verbatim_doc_comment)]
+        action: CacheSub,
+    },
+}
+
+#[derive(Parser, Debug, Serialize, Deserialize, PartialEq)]
+pub enum CacheSub {
+    /// Retrieve the schema of the table by DescribeTable API, and save it into the cache.
+    #[clap(verbatim_doc_comment)]
+    Refresh {
+        /// Table name to refresh. The table currently used is refreshed if it's not given.
+        table: Option<String>,
+    },
+
+    /// Remove the cached schema of the table. All cached schemas are removed if no table is given.
+    #[clap(verbatim_doc_comment)]
+    Clear {
+        /// Table name to remove from the cache.
+        table: Option<String>,
+    },
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{parse_args, Sub};
+    use super::{parse_args, OutputFormat, Sub};
 
     #[test]
     fn test_parse_args() {
@@ -588,6 +969,9 @@ mod tests {
                 limit: None,
                 attributes: None,
                 keys_only: false,
+                columns: None,
+                wide: false,
+                show_types: false,
                 descending: false,
                 output: None,
                 strict: false,
@@ -595,4 +979,28 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_parse_output() {
+        let scan = parse_args(vec!["scan", "--output", "raw"]).unwrap();
+        assert!(matches!(
+            scan,
+            Sub::Scan {
+                output: Some(OutputFormat::DdbJson),
+                ..
+            }
+        ));
+        let desc = parse_args(vec!["desc", "-o", "jsonl"]).unwrap();
+        assert!(matches!(
+            desc,
+            Sub::Desc {
+                output: Some(OutputFormat::Jsonl),
+                ..
+            }
+        ));
+        // --columns selects document paths instead of attributes.
+        assert!(parse_args(vec!["scan", "--columns", "a.b", "--attributes", "a"]).is_err());
+        // desc doesn't support formats for items.
+        assert!(parse_args(vec!["desc", "-o", "csv"]).is_err());
+    }
 }
@@ -0,0 +1,812 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// This module converts DynamoDB items from/to Apache Arrow RecordBatches, which are written into/read from Apache Parquet files.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use arrow_array::{
+    builder::{
+        BinaryBuilder, BooleanBuilder, Float64Builder, Int64Builder, ListBuilder, StringBuilder,
+    },
+    cast::AsArray,
+    types::{Float32Type, Float64Type, Int32Type, Int64Type},
+    Array, ArrayRef, RecordBatch,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use aws_sdk_dynamodb::{primitives::Blob, types::AttributeValue};
+use log::debug;
+use parquet::errors::ParquetError;
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+
+use super::app;
+use super::batch;
+use super::data;
+use super::ddb::key;
+
+/* =================================================
+struct / enum / const
+================================================= */
+
+/// Field metadata key to keep the original DynamoDB type of a column, so that `import` can restore items.
+const DYNEIN_TYPE_METADATA_KEY: &str = "dynein.type";
+
+#[derive(Error, Debug)]
+pub enum DyneinParquetError {
+    #[error("parquet error: {0}")]
+    Parquet(#[from] ParquetError),
+    #[error("arrow error: {0}")]
+    Arrow(#[from] ArrowError),
+    #[error("serde error: {0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("column '{0}' has unsupported type {1}")]
+    UnsupportedColumn(String, DataType),
+    #[error("invalid DynamoDB JSON value: {0}")]
+    InvalidDynamoDbJson(String),
+}
+
+/// Column type inferred from DynamoDB attribute values.
+/// Lists and maps are written as strings in DynamoDB JSON, and attributes with inconsistent types among items fall back to them as well (Json).
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ColumnType {
+    String,
+    Number(NumberType),
+    Binary,
+    Boolean,
+    StringSet,
+    NumberSet(NumberType),
+    BinarySet,
+    List,
+    Map,
+    Json,
+}
+
+/// How numbers in a column are written. Numbers are written as Int64 when all observed values are integers,
+/// as Float64 when all of them are restored exactly from Float64, and otherwise as strings (Decimal),
+/// as DynamoDB numbers can have up to 38 digits of precision.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+enum NumberType {
+    Integer,
+    Float,
+    Decimal,
+}
+
+impl NumberType {
+    fn of(n: &str) -> NumberType {
+        if n.parse::<i64>().is_ok() {
+            NumberType::Integer
+        } else if n
+            .parse::<f64>()
+            .ok()
+            .filter(|f| f.is_finite())
+            .and_then(|f| normalize_number(&f.to_string()))
+            .map_or(false, |f| Some(f) == normalize_number(n))
+        {
+            NumberType::Float
+        } else {
+            NumberType::Decimal
+        }
+    }
+
+    fn merge(self, other: NumberType) -> NumberType {
+        if self > other {
+            self
+        } else {
+            other
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        match self {
+            NumberType::Integer => DataType::Int64,
+            NumberType::Float => DataType::Float64,
+            NumberType::Decimal => DataType::Utf8,
+        }
+    }
+}
+
+impl ColumnType {
+    /// Returns None for NULL, which doesn't determine a column type.
+    fn of(attrval: &AttributeValue) -> Option<ColumnType> {
+        let number_type = |ns: &[String]| {
+            ns.iter()
+                .map(|n| NumberType::of(n))
+                .fold(NumberType::Integer, NumberType::merge)
+        };
+        match attrval {
+            AttributeValue::S(_) => Some(ColumnType::String),
+            AttributeValue::N(v) => Some(ColumnType::Number(NumberType::of(v))),
+            AttributeValue::B(_) => Some(ColumnType::Binary),
+            AttributeValue::Bool(_) => Some(ColumnType::Boolean),
+            AttributeValue::Ss(_) => Some(ColumnType::StringSet),
+            AttributeValue::Ns(v) => Some(ColumnType::NumberSet(number_type(v))),
+            AttributeValue::Bs(_) => Some(ColumnType::BinarySet),
+            AttributeValue::L(_) => Some(ColumnType::List),
+            AttributeValue::M(_) => Some(ColumnType::Map),
+            _ => None,
+        }
+    }
+
+    fn merge(self, other: ColumnType) -> ColumnType {
+        match (self, other) {
+            (ColumnType::Number(a), ColumnType::Number(b)) => ColumnType::Number(a.merge(b)),
+            (ColumnType::NumberSet(a), ColumnType::NumberSet(b)) => {
+                ColumnType::NumberSet(a.merge(b))
+            }
+            (a, b) if a == b => a,
+            _ => ColumnType::Json,
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        let list_of = |t: DataType| DataType::List(Arc::new(Field::new("item", t, true)));
+        match self {
+            ColumnType::String | ColumnType::List | ColumnType::Map | ColumnType::Json => {
+                DataType::Utf8
+            }
+            ColumnType::Number(number_type) => number_type.data_type(),
+            ColumnType::Binary => DataType::Binary,
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::StringSet => list_of(DataType::Utf8),
+            ColumnType::NumberSet(number_type) => list_of(number_type.data_type()),
+            ColumnType::BinarySet => list_of(DataType::Binary),
+        }
+    }
+
+    /// DynamoDB data type name stored in the field metadata.
+    fn tag(&self) -> &'static str {
+        match self {
+            ColumnType::String => "S",
+            ColumnType::Number(_) => "N",
+            ColumnType::Binary => "B",
+            ColumnType::Boolean => "BOOL",
+            ColumnType::StringSet => "SS",
+            ColumnType::NumberSet(_) => "NS",
+            ColumnType::BinarySet => "BS",
+            ColumnType::List => "L",
+            ColumnType::Map => "M",
+            ColumnType::Json => "JSON",
+        }
+    }
+}
+
+/// Collects attribute types from scanned items to build an Arrow schema.
+/// Primary keys come first and are required. Other attributes are nullable and ordered by name.
+#[derive(Debug)]
+pub struct SchemaInference {
+    keys: Vec<String>,
+    columns: BTreeMap<String, Option<ColumnType>>,
+}
+
+impl SchemaInference {
+    pub fn new(ts: &app::TableSchema) -> SchemaInference {
+        let mut keys = vec![&ts.pk];
+        if let Some(sk) = &ts.sk {
+            keys.push(sk);
+        }
+        // Types of primary keys are known from the table schema, even if the table has no item.
+        let columns = keys
+            .iter()
+            .map(|k| {
+                let column_type = match k.kind {
+                    key::KeyType::S => ColumnType::String,
+                    key::KeyType::N => ColumnType::Number(NumberType::Integer),
+                    key::KeyType::B => ColumnType::Binary,
+                };
+                (k.name.clone(), Some(column_type))
+            })
+            .collect::<BTreeMap<String, Option<ColumnType>>>();
+        SchemaInference {
+            keys: keys.into_iter().map(|k| k.name.clone()).collect(),
+            columns,
+        }
+    }
+
+    pub fn observe(&mut self, item: &HashMap<String, AttributeValue>) {
+        for (name, attrval) in item {
+            let observed = ColumnType::of(attrval);
+            let column = self.columns.entry(name.to_owned()).or_insert(observed);
+            *column = match (*column, observed) {
+                (Some(current), Some(observed)) => Some(current.merge(observed)),
+                (current, observed) => current.or(observed),
+            };
+        }
+    }
+
+    pub fn schema(&self) -> Schema {
+        let key_fields = self.keys.iter().map(|name| (name, false));
+        let attribute_fields = self
+            .columns
+            .keys()
+            .filter(|name| !self.keys.contains(name))
+            .map(|name| (name, true));
+
+        let fields = key_fields
+            .chain(attribute_fields)
+            .map(|(name, nullable)| {
+                // An attribute which has only NULL values is written as a JSON string column.
+                let column_type = self
+                    .columns
+                    .get(name)
+                    .copied()
+                    .flatten()
+                    .unwrap_or(ColumnType::Json);
+                Field::new(name, column_type.data_type(), nullable).with_metadata(HashMap::from([
+                    (
+                        DYNEIN_TYPE_METADATA_KEY.to_owned(),
+                        column_type.tag().to_owned(),
+                    ),
+                ]))
+            })
+            .collect::<Vec<Field>>();
+        debug!("inferred arrow fields: {:?}", fields);
+        Schema::new(fields)
+    }
+}
+
+/* =================================================
+Public functions
+================================================= */
+
+/// Converts DynamoDB items into a RecordBatch with the given schema, which should be built by `SchemaInference`.
+pub fn items_to_record_batch(
+    schema: Arc<Schema>,
+    items: &[HashMap<String, AttributeValue>],
+) -> Result<RecordBatch, DyneinParquetError> {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| build_column(field, items))
+        .collect::<Result<Vec<ArrayRef>, DyneinParquetError>>()?;
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+/// Converts a RecordBatch read from a Parquet file into DynamoDB items.
+/// Types are restored from the field metadata written by dynein. For other Parquet files, types are derived from Arrow data types.
+/// Null values are treated as missing attributes.
+pub fn record_batch_to_items(
+    batch: &RecordBatch,
+) -> Result<Vec<HashMap<String, AttributeValue>>, DyneinParquetError> {
+    let mut items = vec![HashMap::<String, AttributeValue>::new(); batch.num_rows()];
+    for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+        let tag = field
+            .metadata()
+            .get(DYNEIN_TYPE_METADATA_KEY)
+            .map(String::as_str);
+        for (row, item) in items.iter_mut().enumerate() {
+            if column.is_null(row) {
+                continue;
+            }
+            let attrval = array_value_to_attrval(column, row, tag).ok_or_else(|| {
+                DyneinParquetError::UnsupportedColumn(
+                    field.name().to_owned(),
+                    field.data_type().clone(),
+                )
+            })??;
+            item.insert(field.name().to_owned(), attrval);
+        }
+    }
+    Ok(items)
+}
+
+/* =================================================
+Private functions
+================================================= */
+
+fn build_column(
+    field: &Field,
+    items: &[HashMap<String, AttributeValue>],
+) -> Result<ArrayRef, DyneinParquetError> {
+    let values = items.iter().map(|item| match item.get(field.name()) {
+        Some(AttributeValue::Null(_)) | None => None,
+        Some(attrval) => Some(attrval),
+    });
+    let tag = field
+        .metadata()
+        .get(DYNEIN_TYPE_METADATA_KEY)
+        .map(String::as_str);
+
+    let array: ArrayRef = match field.data_type() {
+        DataType::Utf8 => {
+            let mut builder = StringBuilder::new();
+            for v in values {
+                match v {
+                    Some(AttributeValue::S(s)) if tag == Some("S") => builder.append_value(s),
+                    // Numbers which cannot be written as Int64 or Float64 are kept as strings.
+                    Some(AttributeValue::N(n)) if tag == Some("N") => builder.append_value(n),
+                    Some(attrval) => builder.append_value(attrval_to_json_string(attrval)?),
+                    None => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Int64 => {
+            let mut builder = Int64Builder::new();
+            for v in values {
+                builder.append_option(v.and_then(|a| a.as_n().ok()).and_then(|n| n.parse().ok()));
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::new();
+            for v in values {
+                builder.append_option(v.and_then(|a| a.as_n().ok()).and_then(|n| n.parse().ok()));
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Binary => {
+            let mut builder = BinaryBuilder::new();
+            for v in values {
+                builder.append_option(v.and_then(|a| a.as_b().ok()).map(|b| b.as_ref()));
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Boolean => {
+            let mut builder = BooleanBuilder::new();
+            for v in values {
+                builder.append_option(v.and_then(|a| a.as_bool().ok()).copied());
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::List(element) => match element.data_type() {
+            DataType::Utf8 => {
+                let mut builder = ListBuilder::new(StringBuilder::new());
+                for v in values {
+                    match v {
+                        Some(AttributeValue::Ss(ss)) | Some(AttributeValue::Ns(ss)) => {
+                            for s in ss {
+                                builder.values().append_value(s);
+                            }
+                            builder.append(true);
+                        }
+                        _ => builder.append_null(),
+                    }
+                }
+                Arc::new(builder.finish())
+            }
+            DataType::Int64 => {
+                let mut builder = ListBuilder::new(Int64Builder::new());
+                for v in values {
+                    match v.and_then(|a| a.as_ns().ok()) {
+                        Some(ns) => {
+                            for n in ns {
+                                builder.values().append_option(n.parse().ok());
+                            }
+                            builder.append(true);
+                        }
+                        None => builder.append_null(),
+                    }
+                }
+                Arc::new(builder.finish())
+            }
+            DataType::Float64 => {
+                let mut builder = ListBuilder::new(Float64Builder::new());
+                for v in values {
+                    match v.and_then(|a| a.as_ns().ok()) {
+                        Some(ns) => {
+                            for n in ns {
+                                builder.values().append_option(n.parse().ok());
+                            }
+                            builder.append(true);
+                        }
+                        None => builder.append_null(),
+                    }
+                }
+                Arc::new(builder.finish())
+            }
+            DataType::Binary => {
+                let mut builder = ListBuilder::new(BinaryBuilder::new());
+                for v in values {
+                    match v.and_then(|a| a.as_bs().ok()) {
+                        Some(bs) => {
+                            for b in bs {
+                                builder.values().append_value(b.as_ref());
+                            }
+                            builder.append(true);
+                        }
+                        None => builder.append_null(),
+                    }
+                }
+                Arc::new(builder.finish())
+            }
+            t => {
+                return Err(DyneinParquetError::UnsupportedColumn(
+                    field.name().to_owned(),
+                    t.clone(),
+                ))
+            }
+        },
+        t => {
+            return Err(DyneinParquetError::UnsupportedColumn(
+                field.name().to_owned(),
+                t.clone(),
+            ))
+        }
+    };
+    Ok(array)
+}
+
+/// Lists, maps and values of mixed-type columns are serialized in DynamoDB JSON, e.g. {"L":[{"N":"1"}]},
+/// so that binaries, sets and numbers in them are restored exactly.
+fn attrval_to_json_string(attrval: &AttributeValue) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&data::attrval_to_ddbjson(attrval))
+}
+
+/// Returns None if the value cannot be converted into an AttributeValue.
+fn array_value_to_attrval(
+    column: &ArrayRef,
+    row: usize,
+    tag: Option<&str>,
+) -> Option<Result<AttributeValue, DyneinParquetError>> {
+    let attrval = match (column.data_type(), tag) {
+        (DataType::Utf8, Some("S") | None) => {
+            AttributeValue::S(column.as_string::<i32>().value(row).to_owned())
+        }
+        (DataType::Utf8, Some("N")) => {
+            AttributeValue::N(column.as_string::<i32>().value(row).to_owned())
+        }
+        (DataType::Utf8, Some(_)) => {
+            let s = column.as_string::<i32>().value(row);
+            let jsonval: JsonValue = match serde_json::from_str(s) {
+                Ok(v) => v,
+                Err(e) => return Some(Err(e.into())),
+            };
+            match batch::convert_ddbjson_to_attrval(&jsonval) {
+                Some(attrval) => attrval,
+                None => return Some(Err(DyneinParquetError::InvalidDynamoDbJson(s.to_owned()))),
+            }
+        }
+        (DataType::List(_), Some("L") | None) => {
+            let values = column.as_list::<i32>().value(row);
+            let elements = (0..values.len())
+                .filter(|i| !values.is_null(*i))
+                .map(|i| array_value_to_attrval(&values, i, None))
+                .collect::<Option<Result<Vec<AttributeValue>, DyneinParquetError>>>()?;
+            match elements {
+                Ok(elements) => AttributeValue::L(elements),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        (DataType::List(_), Some(set_tag)) => {
+            // Elements of a set column are typed by the set, e.g. strings in a NS column are numbers.
+            let element_tag = match set_tag {
+                "NS" => Some("N"),
+                "BS" => Some("B"),
+                _ => None,
+            };
+            let values = column.as_list::<i32>().value(row);
+            let elements = (0..values.len())
+                .filter(|i| !values.is_null(*i))
+                .map(|i| array_value_to_attrval(&values, i, element_tag))
+                .collect::<Option<Result<Vec<AttributeValue>, DyneinParquetError>>>()?;
+            match elements {
+                Ok(elements) => elements_to_set(elements)?,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        (DataType::Int32, _) => {
+            AttributeValue::N(column.as_primitive::<Int32Type>().value(row).to_string())
+        }
+        (DataType::Int64, _) => {
+            AttributeValue::N(column.as_primitive::<Int64Type>().value(row).to_string())
+        }
+        (DataType::Float32, _) => {
+            AttributeValue::N(column.as_primitive::<Float32Type>().value(row).to_string())
+        }
+        (DataType::Float64, _) => {
+            AttributeValue::N(column.as_primitive::<Float64Type>().value(row).to_string())
+        }
+        (DataType::Binary, _) => AttributeValue::B(Blob::new(column.as_binary::<i32>().value(row))),
+        (DataType::Boolean, _) => AttributeValue::Bool(column.as_boolean().value(row)),
+        _ => return None,
+    };
+    Some(Ok(attrval))
+}
+
+/// Builds SS, NS or BS from elements of a list column.
+/// An empty list is restored as an empty L, as DynamoDB doesn't allow empty sets.
+fn elements_to_set(elements: Vec<AttributeValue>) -> Option<AttributeValue> {
+    if elements.is_empty() {
+        return Some(AttributeValue::L(elements));
+    }
+    let mut ss: Vec<String> = vec![];
+    let mut ns: Vec<String> = vec![];
+    let mut bs: Vec<Blob> = vec![];
+    for element in elements {
+        match element {
+            AttributeValue::S(s) => ss.push(s),
+            AttributeValue::N(n) => ns.push(n),
+            AttributeValue::B(b) => bs.push(b),
+            _ => return None,
+        }
+    }
+    match (ss.is_empty(), ns.is_empty(), bs.is_empty()) {
+        (_, true, true) => Some(AttributeValue::Ss(ss)),
+        (true, _, true) => Some(AttributeValue::Ns(ns)),
+        (true, true, _) => Some(AttributeValue::Bs(bs)),
+        _ => None,
+    }
+}
+
+/// Normalizes a number in decimal notation (e.g. "-1.50e2") into its sign, significant digits without trailing zeros, and exponent,
+/// so that numbers can be compared regardless of notation. Returns None if it's not a number.
+fn normalize_number(n: &str) -> Option<(bool, String, i64)> {
+    let (negative, n) = match n.strip_prefix('-') {
+        Some(n) => (true, n),
+        None => (false, n.strip_prefix('+').unwrap_or(n)),
+    };
+    let (mantissa, exponent) = match n.find(['e', 'E']) {
+        Some(i) => (&n[..i], n[i + 1..].parse::<i64>().ok()?),
+        None => (n, 0),
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int_part.is_empty() && frac_part.is_empty()
+        || !int_part
+            .chars()
+            .chain(frac_part.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let digits = format!("{}{}", int_part, frac_part);
+    let exponent = exponent + int_part.len() as i64;
+    let leading_zeros = digits.len() - digits.trim_start_matches('0').len();
+    let digits = digits.trim_matches('0');
+    if digits.is_empty() {
+        return Some((false, String::from("0"), 0));
+    }
+    Some((negative, digits.to_owned(), exponent - leading_zeros as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddb::table;
+
+    fn table_schema() -> app::TableSchema {
+        app::TableSchema {
+            region: String::from("local"),
+            name: String::from("table"),
+            pk: key::Key {
+                name: String::from("pk"),
+                kind: key::KeyType::S,
+            },
+            sk: Some(key::Key {
+                name: String::from("sk"),
+                kind: key::KeyType::N,
+            }),
+            indexes: None,
+            mode: table::Mode::OnDemand,
+            cached_at: None,
+        }
+    }
+
+    fn items() -> Vec<HashMap<String, AttributeValue>> {
+        vec![
+            HashMap::from([
+                ("pk".to_owned(), AttributeValue::S("a".to_owned())),
+                ("sk".to_owned(), AttributeValue::N("1".to_owned())),
+                ("count".to_owned(), AttributeValue::N("3".to_owned())),
+                ("price".to_owned(), AttributeValue::N("10".to_owned())),
+                (
+                    "tags".to_owned(),
+                    AttributeValue::Ss(vec!["x".to_owned(), "y".to_owned()]),
+                ),
+                (
+                    "nested".to_owned(),
+                    AttributeValue::M(HashMap::from([(
+                        "flag".to_owned(),
+                        AttributeValue::Bool(true),
+                    )])),
+                ),
+                ("mixed".to_owned(), AttributeValue::S("text".to_owned())),
+                ("bin".to_owned(), AttributeValue::B(Blob::new(vec![0, 1]))),
+            ]),
+            HashMap::from([
+                ("pk".to_owned(), AttributeValue::S("b".to_owned())),
+                ("sk".to_owned(), AttributeValue::N("2".to_owned())),
+                ("price".to_owned(), AttributeValue::N("1.5".to_owned())),
+                ("mixed".to_owned(), AttributeValue::N("7".to_owned())),
+                (
+                    "list".to_owned(),
+                    AttributeValue::L(vec![
+                        AttributeValue::S("s".to_owned()),
+                        AttributeValue::N("2".to_owned()),
+                    ]),
+                ),
+                ("none".to_owned(), AttributeValue::Null(true)),
+            ]),
+        ]
+    }
+
+    #[test]
+    fn test_schema_inference() {
+        let mut inference = SchemaInference::new(&table_schema());
+        for item in items() {
+            inference.observe(&item);
+        }
+        let schema = inference.schema();
+
+        let summary = schema
+            .fields()
+            .iter()
+            .map(|f| {
+                (
+                    f.name().as_str(),
+                    f.data_type().clone(),
+                    f.is_nullable(),
+                    f.metadata()[DYNEIN_TYPE_METADATA_KEY].as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let list_of = |t: DataType| DataType::List(Arc::new(Field::new("item", t, true)));
+        assert_eq!(
+            summary,
+            vec![
+                ("pk", DataType::Utf8, false, "S"),
+                ("sk", DataType::Int64, false, "N"),
+                ("bin", DataType::Binary, true, "B"),
+                ("count", DataType::Int64, true, "N"),
+                ("list", DataType::Utf8, true, "L"),
+                ("mixed", DataType::Utf8, true, "JSON"),
+                ("nested", DataType::Utf8, true, "M"),
+                ("none", DataType::Utf8, true, "JSON"),
+                ("price", DataType::Float64, true, "N"),
+                ("tags", list_of(DataType::Utf8), true, "SS"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_record_batch_round_trip() {
+        let mut inference = SchemaInference::new(&table_schema());
+        for item in items() {
+            inference.observe(&item);
+        }
+        let batch = items_to_record_batch(Arc::new(inference.schema()), &items()).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(
+            batch
+                .column_by_name("nested")
+                .unwrap()
+                .as_string::<i32>()
+                .value(0),
+            r#"{"M":{"flag":{"BOOL":true}}}"#
+        );
+
+        let restored = record_batch_to_items(&batch).unwrap();
+        let mut expected = items();
+        // NULL is written as a null value, which means a missing attribute.
+        expected[1].remove("none");
+        // Floating point numbers are normalized through Float64.
+        expected[0].insert("price".to_owned(), AttributeValue::N("10".to_owned()));
+        assert_eq!(restored, expected);
+    }
+
+    #[test]
+    fn test_number_round_trip() {
+        let n = |v: &str| AttributeValue::N(v.to_owned());
+        let ns = |vs: &[&str]| AttributeValue::Ns(vs.iter().map(|v| v.to_string()).collect());
+        let items = vec![
+            HashMap::from([
+                ("pk".to_owned(), AttributeValue::S("a".to_owned())),
+                ("sk".to_owned(), n("1")),
+                ("float".to_owned(), n("0.25")),
+                ("big".to_owned(), n("12345678901234567890123")),
+                ("precise".to_owned(), n("0.1234567890123456789")),
+                ("nums".to_owned(), ns(&["1", "0.1234567890123456789"])),
+                ("empty".to_owned(), AttributeValue::L(vec![])),
+            ]),
+            HashMap::from([
+                ("pk".to_owned(), AttributeValue::S("b".to_owned())),
+                ("sk".to_owned(), n("2")),
+                ("float".to_owned(), n("-1.5E-10")),
+                ("big".to_owned(), n("1e400")),
+                ("precise".to_owned(), n("3")),
+                ("nums".to_owned(), ns(&["2"])),
+            ]),
+        ];
+        let mut inference = SchemaInference::new(&table_schema());
+        for item in &items {
+            inference.observe(item);
+        }
+        let schema = inference.schema();
+        let data_type = |name: &str| schema.field_with_name(name).unwrap().data_type().clone();
+        assert_eq!(data_type("float"), DataType::Float64);
+        assert_eq!(data_type("big"), DataType::Utf8);
+        assert_eq!(data_type("precise"), DataType::Utf8);
+        assert_eq!(
+            data_type("nums"),
+            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)))
+        );
+
+        let batch = items_to_record_batch(Arc::new(schema), &items).unwrap();
+        let mut expected = items.clone();
+        // Float64 keeps the value, not the notation.
+        expected[1].insert("float".to_owned(), n("-0.00000000015"));
+        assert_eq!(record_batch_to_items(&batch).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_nested_round_trip() {
+        let n = |v: &str| AttributeValue::N(v.to_owned());
+        let nested = AttributeValue::M(HashMap::from([
+            ("bin".to_owned(), AttributeValue::B(Blob::new(vec![0, 255]))),
+            (
+                "bins".to_owned(),
+                AttributeValue::Bs(vec![Blob::new(vec![1]), Blob::new(vec![2])]),
+            ),
+            (
+                "nums".to_owned(),
+                AttributeValue::Ns(vec!["1".to_owned(), "0.1234567890123456789".to_owned()]),
+            ),
+            (
+                "list".to_owned(),
+                AttributeValue::L(vec![
+                    n("12345678901234567890123"),
+                    AttributeValue::Ss(vec!["x".to_owned()]),
+                    AttributeValue::Null(true),
+                ]),
+            ),
+        ]));
+        let items = vec![
+            HashMap::from([
+                ("pk".to_owned(), AttributeValue::S("a".to_owned())),
+                ("sk".to_owned(), n("1")),
+                ("nested".to_owned(), nested.clone()),
+                ("mixed".to_owned(), n("1e400")),
+            ]),
+            HashMap::from([
+                ("pk".to_owned(), AttributeValue::S("b".to_owned())),
+                ("sk".to_owned(), n("2")),
+                (
+                    "nested".to_owned(),
+                    AttributeValue::M(HashMap::from([("big".to_owned(), n("1e400"))])),
+                ),
+                ("mixed".to_owned(), nested),
+            ]),
+        ];
+        let mut inference = SchemaInference::new(&table_schema());
+        for item in &items {
+            inference.observe(item);
+        }
+        let batch = items_to_record_batch(Arc::new(inference.schema()), &items).unwrap();
+        assert_eq!(record_batch_to_items(&batch).unwrap(), items);
+    }
+
+    #[test]
+    fn test_elements_to_set() {
+        assert_eq!(elements_to_set(vec![]), Some(AttributeValue::L(vec![])));
+        assert_eq!(
+            elements_to_set(vec![AttributeValue::N("1".to_owned())]),
+            Some(AttributeValue::Ns(vec!["1".to_owned()]))
+        );
+    }
+
+    #[test]
+    fn test_normalize_number() {
+        let normalized = |digits: &str, exponent: i64| Some((false, digits.to_owned(), exponent));
+        assert_eq!(normalize_number("1.50"), normalized("15", 1));
+        assert_eq!(normalize_number("150e-2"), normalized("15", 1));
+        assert_eq!(normalize_number("0.0015"), normalized("15", -2));
+        assert_eq!(normalize_number("-0.0"), normalized("0", 0));
+        assert_eq!(normalize_number("-2"), Some((true, "2".to_owned(), 1)));
+        assert_eq!(normalize_number("inf"), None);
+        assert_eq!(normalize_number("."), None);
+    }
+}
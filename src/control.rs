@@ -16,33 +16,37 @@
 
 // This module interact with DynamoDB Control Plane APIs
 use aws_sdk_dynamodb::{
+    operation::list_tables::ListTablesError,
     types::{
         BackupStatus, BackupSummary, BillingMode, CreateGlobalSecondaryIndexAction,
-        GlobalSecondaryIndexUpdate, Projection, ProjectionType, ProvisionedThroughput,
-        TableDescription,
+        GlobalSecondaryIndex, GlobalSecondaryIndexUpdate, LocalSecondaryIndex, Projection,
+        ProjectionType, ProvisionedThroughput, TableDescription,
     },
     Client as DynamoDbSdkClient,
 };
 use aws_sdk_ec2::Client as Ec2SdkClient;
+use aws_smithy_runtime_api::{client::result::SdkError, http::Response as HttpResponse};
 use futures::future::join_all;
-use log::{debug, error};
+use log::debug;
 use std::borrow::Cow::{Borrowed, Owned};
 use std::{
-    io::{self, Error as IOError, Write},
+    error::Error,
+    io::{self, Write},
     time,
 };
 
-use dialoguer::{theme::ColorfulTheme, Confirm, Select};
+use dialoguer::{theme::ColorfulTheme, Select};
 use tabwriter::TabWriter;
 
 use super::app;
 use super::ddb::table;
+use super::output::{self, OutputFormat};
 
 /* =================================================
 Public functions
 ================================================= */
 
-pub async fn list_tables_all_regions(cx: &app::Context) {
+pub async fn list_tables_all_regions(cx: &app::Context) -> Result<(), app::DyneinExit> {
     // get all regions from us-east-1 regardless specified region
     let config = cx
         .clone()
@@ -52,7 +56,7 @@ pub async fn list_tables_all_regions(cx: &app::Context) {
     let ec2 = Ec2SdkClient::new(&config);
     match ec2.describe_regions().send().await {
         Err(e) => {
-            app::bye_with_sdk_error(1, e);
+            return Err(app::bye_with_sdk_error(1, e));
         }
         Ok(res) => {
             join_all(
@@ -61,22 +65,29 @@ pub async fn list_tables_all_regions(cx: &app::Context) {
                     .iter()
                     .map(|r| list_tables(cx, Some(r.region_name.as_ref().unwrap()))),
             )
-            .await;
+            .await
+            .into_iter()
+            .collect::<Result<(), app::DyneinExit>>()?;
 
             if cx.is_local().await {
-                list_tables(cx, None).await;
+                list_tables(cx, None).await?;
             }
         }
     };
+    Ok(())
 }
 
-pub async fn list_tables(cx: &app::Context, override_region: Option<&str>) {
-    let table_names = list_tables_api(cx, override_region).await;
+pub async fn list_tables(
+    cx: &app::Context,
+    override_region: Option<&str>,
+) -> Result<(), app::DyneinExit> {
+    let table_names = list_tables_api(cx, override_region).await?;
     let region = cx.effective_region().await.to_string();
 
     println!("DynamoDB tables in region: {}", region);
     if table_names.is_empty() {
-        return println!("  No table in this region.");
+        println!("  No table in this region.");
+        return Ok(());
     }
 
     if let Some(table_in_config) = cx.cached_using_table_schema().await {
@@ -93,18 +104,25 @@ pub async fn list_tables(cx: &app::Context, override_region: Option<&str>) {
             println!("  {}", table_name)
         }
     }
+    Ok(())
 }
 
 /// Executed when you call `$ dy desc --all-tables`.
 /// Note that `describe_table` function calls are executed in parallel (async + join_all).
-pub async fn describe_all_tables(cx: &app::Context) {
-    let table_names = list_tables_api(cx, None).await;
-    join_all(table_names.into_iter().map(|t| describe_table(cx, Some(t)))).await;
+pub async fn describe_all_tables(cx: &app::Context) -> Result<(), app::DyneinExit> {
+    let table_names = list_tables_api(cx, None).await?;
+    join_all(table_names.into_iter().map(|t| describe_table(cx, Some(t))))
+        .await
+        .into_iter()
+        .collect()
 }
 
 /// Executed when you call `$ dy desc (table)`. Retrieve TableDescription via describe_table_api function,
 /// then print them in convenient way using table::print_table_description function (default/yaml).
-pub async fn describe_table(cx: &app::Context, target_table_to_desc: Option<String>) {
+pub async fn describe_table(
+    cx: &app::Context,
+    target_table_to_desc: Option<String>,
+) -> Result<(), app::DyneinExit> {
     debug!("context: {:#?}", &cx);
     debug!("positional arg table name: {:?}", &target_table_to_desc);
     let new_context = if let Some(t) = target_table_to_desc {
@@ -113,11 +131,12 @@ pub async fn describe_table(cx: &app::Context, target_table_to_desc: Option<Stri
         Borrowed(cx)
     };
 
+    let table_name = new_context.effective_table_name()?;
     let desc: TableDescription =
-        describe_table_api(new_context.as_ref(), new_context.effective_table_name()).await;
+        describe_table_api(new_context.as_ref(), table_name.clone()).await?;
     debug!(
         "Retrieved table to describe is: '{}' table in '{}' region.",
-        new_context.effective_table_name(),
+        table_name,
         new_context.effective_region().await.as_ref()
     );
 
@@ -131,21 +150,17 @@ pub async fn describe_table(cx: &app::Context, target_table_to_desc: Option<Stri
         ),
     };
 
-    match new_context.output.as_deref() {
-        None | Some("yaml") => {
-            table::print_table_description(new_context.effective_region().await.as_ref(), &desc)
-        }
-        // Some("raw") => println!("{:#?}", desc),
-        Some(_) => {
-            println!("ERROR: unsupported output type.");
-            std::process::exit(1);
-        }
-    }
+    let format = new_context.output_format(output::DESCRIPTION_FORMATS, OutputFormat::Yaml);
+    table::print_table_description_in(new_context.effective_region().await.as_ref(), &desc, format);
+    Ok(())
 }
 
 /// Originally intended to be called by describe_table function, which is called from `$ dy desc`,
 /// however it turned out that DescribeTable API result is useful in various logic, separated API into this standalone function.
-pub async fn describe_table_api(cx: &app::Context, table_name: String) -> TableDescription {
+pub async fn describe_table_api(
+    cx: &app::Context,
+    table_name: String,
+) -> Result<TableDescription, app::DyneinExit> {
     let region = cx.effective_region().await;
     let config = cx.effective_sdk_config_with_region(region.as_ref()).await;
     let ddb = DynamoDbSdkClient::new(&config);
@@ -153,31 +168,33 @@ pub async fn describe_table_api(cx: &app::Context, table_name: String) -> TableD
     match ddb.describe_table().table_name(table_name).send().await {
         Err(e) => {
             debug!("DescribeTable API call got an error -- {:#?}", e);
-            app::bye_with_sdk_error(1, e);
+            Err(app::bye_with_sdk_error(1, e))
         }
         Ok(res) => {
             let desc: TableDescription = res.table.expect("This message should not be shown.");
             debug!("Received DescribeTable Result: {:?}\n", desc);
-            desc
+            Ok(desc)
         }
     }
 }
 
 /// This function is designed to be called from dynein command, mapped in main.rs.
 /// Note that it simply ignores --table option if specified. Newly created table name should be given by the 1st argument "name".
-pub async fn create_table(cx: &app::Context, name: String, given_keys: Vec<String>) {
-    if given_keys.is_empty() || given_keys.len() >= 3 {
-        error!("You should pass one or two key definitions with --keys option");
-        std::process::exit(1);
-    };
+pub async fn create_table(
+    cx: &app::Context,
+    name: String,
+    given_keys: Vec<String>,
+) -> Result<(), app::DyneinExit> {
+    table::validate_key_definitions(&given_keys).map_err(app::bye_with_error)?;
 
     match create_table_api(cx, name, given_keys).await {
         Ok(desc) => table::print_table_description(cx.effective_region().await.as_ref(), &desc),
         Err(e) => {
             debug!("CreateTable API call got an error -- {:#?}", e);
-            app::bye_with_sdk_error(1, e);
+            return Err(app::bye_with_sdk_error(1, e));
         }
     }
+    Ok(())
 }
 
 pub async fn create_table_api(
@@ -212,16 +229,76 @@ pub async fn create_table_api(
         })
 }
 
-pub async fn create_index(cx: &app::Context, index_name: String, given_keys: Vec<String>) {
-    if given_keys.is_empty() || given_keys.len() >= 3 {
-        error!("You should pass one or two key definitions with --keys option");
-        std::process::exit(1);
-    };
+/// Creates a table with the primary key, attribute definitions and secondary indexes (with their projections)
+/// of the given table description in on-demand mode. Other settings such as streams and TTL are not copied.
+pub async fn create_table_like_api(
+    cx: &app::Context,
+    name: String,
+    desc: &TableDescription,
+) -> Result<
+    TableDescription,
+    aws_sdk_dynamodb::error::SdkError<aws_sdk_dynamodb::operation::create_table::CreateTableError>,
+> {
+    debug!(
+        "Trying to create a table '{}' like '{:?}'",
+        &name, &desc.table_name
+    );
+
+    let gsis: Option<Vec<GlobalSecondaryIndex>> =
+        desc.global_secondary_indexes.as_ref().map(|gsis| {
+            gsis.iter()
+                .map(|gsi| {
+                    GlobalSecondaryIndex::builder()
+                        .set_index_name(gsi.index_name.to_owned())
+                        .set_key_schema(gsi.key_schema.to_owned())
+                        .set_projection(gsi.projection.to_owned())
+                        .build()
+                        .expect("index name and key schema should be described")
+                })
+                .collect()
+        });
+    let lsis: Option<Vec<LocalSecondaryIndex>> =
+        desc.local_secondary_indexes.as_ref().map(|lsis| {
+            lsis.iter()
+                .map(|lsi| {
+                    LocalSecondaryIndex::builder()
+                        .set_index_name(lsi.index_name.to_owned())
+                        .set_key_schema(lsi.key_schema.to_owned())
+                        .set_projection(lsi.projection.to_owned())
+                        .build()
+                        .expect("index name and key schema should be described")
+                })
+                .collect()
+        });
+
+    let config = cx.effective_sdk_config().await;
+    let ddb = DynamoDbSdkClient::new(&config);
+
+    ddb.create_table()
+        .table_name(name)
+        .billing_mode(BillingMode::PayPerRequest)
+        .set_key_schema(desc.key_schema.to_owned())
+        .set_attribute_definitions(desc.attribute_definitions.to_owned())
+        .set_global_secondary_indexes(gsis)
+        .set_local_secondary_indexes(lsis)
+        .send()
+        .await
+        .map(|res| {
+            res.table_description
+                .expect("Table Description returned from API should be valid.")
+        })
+}
+
+pub async fn create_index(
+    cx: &app::Context,
+    index_name: String,
+    given_keys: Vec<String>,
+) -> Result<(), app::DyneinExit> {
+    table::validate_key_definitions(&given_keys).map_err(app::bye_with_error)?;
+    let table_name = cx.effective_table_name()?;
     debug!(
         "Trying to create an index '{}' with keys '{:?}', on table '{}' ",
-        &index_name,
-        &given_keys,
-        &cx.effective_table_name()
+        &index_name, &given_keys, &table_name
     );
 
     let (key_schema, attribute_definitions) =
@@ -248,7 +325,7 @@ pub async fn create_index(cx: &app::Context, index_name: String, given_keys: Vec
 
     match ddb
         .update_table()
-        .table_name(cx.effective_table_name())
+        .table_name(table_name)
         .set_attribute_definitions(Some(attribute_definitions))
         .global_secondary_index_updates(gsi_update)
         .send()
@@ -256,7 +333,7 @@ pub async fn create_index(cx: &app::Context, index_name: String, given_keys: Vec
     {
         Err(e) => {
             debug!("UpdateTable API call got an error -- {:#?}", e);
-            app::bye_with_sdk_error(1, e);
+            Err(app::bye_with_sdk_error(1, e))
         }
         Ok(res) => {
             debug!("Returned result: {:#?}", res);
@@ -264,6 +341,7 @@ pub async fn create_index(cx: &app::Context, index_name: String, given_keys: Vec
                 cx.effective_region().await.as_ref(),
                 &res.table_description.unwrap(),
             );
+            Ok(())
         }
     }
 }
@@ -274,9 +352,9 @@ pub async fn update_table(
     mode_string: Option<String>,
     wcu: Option<i64>,
     rcu: Option<i64>,
-) {
+) -> Result<(), app::DyneinExit> {
     // Retrieve TableDescription of the table to update, current (before update) status.
-    let desc: TableDescription = describe_table_api(cx, table_name_to_update.clone()).await;
+    let desc: TableDescription = describe_table_api(cx, table_name_to_update.clone()).await?;
 
     // Map given string into "Mode" enum. Note that in cmd.rs clap already limits acceptable values.
     let switching_to_mode: Option<table::Mode> = match mode_string {
@@ -356,10 +434,13 @@ pub async fn update_table(
     )
     .await
     {
-        Ok(desc) => table::print_table_description(cx.effective_region().await.as_ref(), &desc),
+        Ok(desc) => {
+            table::print_table_description(cx.effective_region().await.as_ref(), &desc);
+            Ok(())
+        }
         Err(e) => {
             debug!("UpdateTable API call got an error -- {:#?}", e);
-            app::bye_with_sdk_error(1, e);
+            Err(app::bye_with_sdk_error(1, e))
         }
     }
 }
@@ -401,13 +482,18 @@ async fn update_table_api(
         })
 }
 
-pub async fn delete_table(cx: &app::Context, name: String, skip_confirmation: bool) {
+pub async fn delete_table(
+    cx: &app::Context,
+    name: String,
+    skip_confirmation: bool,
+) -> Result<(), app::DyneinExit> {
     debug!("Trying to delete a table '{}'", &name);
 
     let msg = format!("You're trying to delete a table '{}'. Are you OK?", &name);
-    if !skip_confirmation && !Confirm::new().with_prompt(&msg).interact().unwrap() {
+    let unattended = app::Unattended::Fail("Use --yes to delete the table without confirmation.");
+    if !cx.confirm(&msg, skip_confirmation, unattended)? {
         println!("The table delete operation has been canceled.");
-        return;
+        return Ok(());
     }
 
     let config = cx.effective_sdk_config().await;
@@ -416,7 +502,7 @@ pub async fn delete_table(cx: &app::Context, name: String, skip_confirmation: bo
     match ddb.delete_table().table_name(name).send().await {
         Err(e) => {
             debug!("DeleteTable API call got an error -- {:#?}", e);
-            app::bye_with_sdk_error(1, e);
+            Err(app::bye_with_sdk_error(1, e))
         }
         Ok(res) => {
             debug!("Returned result: {:#?}", res);
@@ -424,6 +510,7 @@ pub async fn delete_table(cx: &app::Context, name: String, skip_confirmation: bo
                 "Delete operation for the table '{}' has been started.",
                 res.table_description.unwrap().table_name.unwrap()
             );
+            Ok(())
         }
     }
 }
@@ -432,13 +519,13 @@ pub async fn delete_table(cx: &app::Context, name: String, skip_confirmation: bo
 ///
 /// OnDemand backup is a type of backups that can be manually created. Another type is called PITR (Point-In-Time-Restore) but dynein doesn't support it for now.
 /// For more information about DynamoDB on-demand backup: https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/BackupRestore.html
-pub async fn backup(cx: &app::Context, all_tables: bool) {
+pub async fn backup(cx: &app::Context, all_tables: bool) -> Result<(), app::DyneinExit> {
     // this "backup" function is called only when --list is NOT given. So, --all-tables would be ignored.
     if all_tables {
         println!("NOTE: --all-tables option is ignored without --list option. Just trying to create a backup for the target table...")
     };
 
-    let table_name = cx.effective_table_name();
+    let table_name = cx.effective_table_name()?;
     debug!("Taking a backof of the table '{}'", table_name);
     let epoch: u64 = time::SystemTime::now()
         .duration_since(time::SystemTime::UNIX_EPOCH)
@@ -457,7 +544,7 @@ pub async fn backup(cx: &app::Context, all_tables: bool) {
     match req.send().await {
         Err(e) => {
             debug!("CreateBackup API call got an error -- {:#?}", e);
-            app::bye_with_sdk_error(1, e);
+            Err(app::bye_with_sdk_error(1, e))
         }
         Ok(res) => {
             debug!("Returned result: {:#?}", res);
@@ -472,13 +559,14 @@ pub async fn backup(cx: &app::Context, all_tables: bool) {
                 "  Backup Size: {} bytes",
                 details.backup_size_bytes.expect("should have table size")
             );
+            Ok(())
         }
     }
 }
 
 /// List backups for a specified table. With --all-tables option all backups for all tables in the region are shown.
-pub async fn list_backups(cx: &app::Context, all_tables: bool) -> Result<(), IOError> {
-    let backups = list_backups_api(cx, all_tables).await;
+pub async fn list_backups(cx: &app::Context, all_tables: bool) -> Result<(), Box<dyn Error>> {
+    let backups = list_backups_api(cx, all_tables).await?;
     let mut tw = TabWriter::new(io::stdout());
     // First defining header
     tw.write_all(
@@ -514,22 +602,32 @@ pub async fn list_backups(cx: &app::Context, all_tables: bool) -> Result<(), IOE
 /// This function restores DynamoDB table from specified backup data.
 /// If you don't specify backup data (name) explicitly, dynein will list backups and you can select out of them.
 /// Currently overwriting properties during rstore is not supported.
-pub async fn restore(cx: &app::Context, backup_name: Option<String>, restore_name: Option<String>) {
+pub async fn restore(
+    cx: &app::Context,
+    backup_name: Option<String>,
+    restore_name: Option<String>,
+) -> Result<(), app::DyneinExit> {
     // let backups = list_backups_api(&cx, false).await;
     let available_backups: Vec<BackupSummary> = list_backups_api(cx, false)
-        .await
+        .await?
         .into_iter()
         .filter(|b: &BackupSummary| b.to_owned().backup_status == Some(BackupStatus::Available))
         .collect();
     // let available_backups: Vec<BackupSummary> = backups.iter().filter(|b| b.backup_status.to_owned().unwrap() == "AVAILABLE").collect();
     if available_backups.is_empty() {
-        app::bye(0, "No AVAILABLE state backup found for the table.");
+        return Err(app::bye(
+            0,
+            "No AVAILABLE state backup found for the table.",
+        ));
     };
 
-    let source_table_name = cx.effective_table_name();
+    let source_table_name = cx.effective_table_name()?;
     let backup_arn = match backup_name {
         Some(bname) => fetch_arn_from_backup_name(bname, available_backups),
         None => {
+            if cx.non_interactive {
+                return Err(app::bye_with_error("Cannot select backup data to restore in non-interactive mode. Specify it with --backup-name option."));
+            }
             let selection_texts: Vec<String> = available_backups
                 .iter()
                 .map(|b| {
@@ -577,13 +675,14 @@ pub async fn restore(cx: &app::Context, backup_name: Option<String>, restore_nam
     {
         Err(e) => {
             debug!("RestoreTableFromBackup API call got an error -- {:#?}", e);
-            app::bye_with_sdk_error(1, e);
+            Err(app::bye_with_sdk_error(1, e))
         }
         Ok(res) => {
             debug!("Returned result: {:#?}", res);
             println!("Table restoration from: '{}' has been started", &backup_arn);
             let desc = res.table_description.unwrap();
             table::print_table_description(cx.effective_region().await.as_ref(), &desc);
+            Ok(())
         }
     }
 }
@@ -594,7 +693,22 @@ Private functions
 
 /// Basically called by list_tables function, which is called from `$ dy list`.
 /// To make ListTables API result reusable, separated API logic into this standalone function.
-async fn list_tables_api(cx: &app::Context, override_region: Option<&str>) -> Vec<String> {
+async fn list_tables_api(
+    cx: &app::Context,
+    override_region: Option<&str>,
+) -> Result<Vec<String>, app::DyneinExit> {
+    try_list_tables_api(cx, override_region).await.map_err(|e| {
+        debug!("ListTables API call got an error -- {:#?}", e);
+        app::bye_with_sdk_error(1, e)
+    })
+}
+
+/// Calls ListTables API and returns the error as is without printing it,
+/// for callers which can continue without table names, e.g. tab completion in the shell.
+pub async fn try_list_tables_api(
+    cx: &app::Context,
+    override_region: Option<&str>,
+) -> Result<Vec<String>, SdkError<ListTablesError, HttpResponse>> {
     let config = if let Some(override_region) = override_region {
         cx.effective_sdk_config_with_region(override_region).await
     } else {
@@ -602,34 +716,34 @@ async fn list_tables_api(cx: &app::Context, override_region: Option<&str>) -> Ve
     };
     let ddb = DynamoDbSdkClient::new(&config);
 
-    match ddb.list_tables().send().await {
-        Err(e) => {
-            debug!("ListTables API call got an error -- {:#?}", e);
-            app::bye_with_sdk_error(1, e);
-        }
-        // ListTables API returns blank array even if no table exists in a region.
-        Ok(res) => res.table_names.expect("This message should not be shown"),
-    }
+    // ListTables API returns blank array even if no table exists in a region.
+    ddb.list_tables()
+        .send()
+        .await
+        .map(|res| res.table_names.expect("This message should not be shown"))
 }
 
 /// This function is a private function that simply calls ListBackups API and return results
-async fn list_backups_api(cx: &app::Context, all_tables: bool) -> Vec<BackupSummary> {
+async fn list_backups_api(
+    cx: &app::Context,
+    all_tables: bool,
+) -> Result<Vec<BackupSummary>, app::DyneinExit> {
     let config = cx.effective_sdk_config().await;
     let ddb = DynamoDbSdkClient::new(&config);
 
     let mut req = ddb.list_backups();
     if !all_tables {
-        req = req.table_name(cx.effective_table_name());
+        req = req.table_name(cx.effective_table_name()?);
     }
 
     match req.send().await {
         Err(e) => {
             debug!("ListBackups API call got an error -- {:#?}", e);
-            app::bye_with_sdk_error(1, e);
+            Err(app::bye_with_sdk_error(1, e))
         }
-        Ok(res) => res
+        Ok(res) => Ok(res
             .backup_summaries
-            .expect("backup result should have something"),
+            .expect("backup result should have something")),
     }
 }
 
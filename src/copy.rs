@@ -0,0 +1,513 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// This module copies items from a table to another table, possibly in another region or DynamoDB Local, for `dy copy`.
+
+use std::{cell::RefCell, collections::HashMap, fs, path::Path};
+
+use aws_sdk_dynamodb::{
+    operation::create_table::CreateTableError,
+    types::{AttributeValue, KeySchemaElement, Projection, TableDescription},
+};
+use futures::future::try_join_all;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::app::{self, TableLocation};
+use super::batch::{self, DyneinBatchError};
+use super::bootstrap;
+use super::control;
+use super::data;
+use super::output;
+use super::parser::DyneinParser;
+use super::transfer::{ProgressState, MAX_NUMBER_OF_OBSERVES};
+
+/* =================================================
+struct / enum / const
+================================================= */
+
+type Item = HashMap<String, AttributeValue>;
+
+/// Progress of `dy copy`, which is written to the file given by --resume-file so that an interrupted copy can be resumed.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CopyState {
+    from: TableLocation,
+    to: TableLocation,
+    /// --rename options as given, i.e. `OLD=NEW`.
+    rename: Vec<String>,
+    segments: Vec<SegmentState>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+struct SegmentState {
+    /// Number of items copied from the segment.
+    copied: usize,
+    /// LastEvaluatedKey of the last page written into the target, in dynein format.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_evaluated_key: Option<String>,
+    done: bool,
+}
+
+impl CopyState {
+    fn new(
+        from: &TableLocation,
+        to: &TableLocation,
+        rename: &[String],
+        segments: i32,
+    ) -> CopyState {
+        CopyState {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            rename: rename.to_vec(),
+            segments: (0..segments).map(|_| SegmentState::default()).collect(),
+        }
+    }
+
+    fn copied(&self) -> usize {
+        self.segments.iter().map(|s| s.copied).sum()
+    }
+
+    /// Writes the state atomically, so that the file is not broken even if dynein is interrupted.
+    fn save(&self, path: &str) -> Result<(), DyneinBatchError> {
+        app::write_file_atomically(path, &serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/* =================================================
+Public functions
+================================================= */
+
+/// Copies all items of the `from` table into the `to` table. The source table is scanned in parallel by `segments` segments,
+/// and each page is written by BatchWriteItem API before the next page of the segment is scanned.
+/// With `resume_file`, the LastEvaluatedKey of each segment is recorded after its page is written,
+/// so running the same command again resumes the copy. Items of the page being written may be written twice, which is harmless.
+pub async fn copy(
+    cx: &app::Context,
+    from: TableLocation,
+    to: TableLocation,
+    create_table: bool,
+    rename: Vec<String>,
+    segments: i32,
+    resume_file: Option<String>,
+) -> Result<(), DyneinBatchError> {
+    let renames = parse_renames(&rename)?;
+    let from_cx = from.context(cx);
+    let to_cx = to.context(cx);
+    let (from_label, to_label) = (from.resolve(cx).await, to.resolve(cx).await);
+    if from_label == to_label {
+        return Err(DyneinBatchError::InvalidInput(format!(
+            "The source and the target are the same table '{}'.",
+            from_label
+        )));
+    }
+
+    let state = match &resume_file {
+        Some(path) if Path::new(path).exists() => {
+            let state: CopyState = serde_json::from_str(&fs::read_to_string(path)?)?;
+            if state.from != from || state.to != to || state.rename != rename {
+                return Err(DyneinBatchError::InvalidInput(format!(
+                    "'{}' records a copy from '{}' to '{}' with different options. Remove the file to start a new copy.",
+                    path, state.from, state.to
+                )));
+            }
+            println!(
+                "Resuming the copy from '{}'. {} items have been copied.",
+                path,
+                state.copied()
+            );
+            // The progress of segments can't be carried over into a different number of segments.
+            if state.segments.len() != segments as usize {
+                eprintln!(
+                    "--segments {} is ignored, as the copy is resumed with {} segments recorded in '{}'.",
+                    segments,
+                    state.segments.len(),
+                    path
+                );
+            }
+            state
+        }
+        _ => CopyState::new(&from, &to, &rename, segments),
+    };
+
+    if create_table {
+        create_target_table(&from_cx, &to_cx, &renames).await?;
+    }
+
+    let total_segments = state.segments.len() as i32;
+    let pending: Vec<i32> = (0..total_segments)
+        .filter(|&segment| !state.segments[segment as usize].done)
+        .collect();
+    let state = RefCell::new(state);
+    let progress = RefCell::new(ProgressState::new(MAX_NUMBER_OF_OBSERVES));
+    try_join_all(pending.into_iter().map(|segment| {
+        copy_segment(
+            &from_cx,
+            &to_cx,
+            segment,
+            total_segments,
+            &renames,
+            &state,
+            &progress,
+            resume_file.as_deref(),
+        )
+    }))
+    .await?;
+    println!();
+
+    if let Some(path) = &resume_file {
+        fs::remove_file(path)?;
+    }
+    println!(
+        "Copied {} items from '{}' to '{}'.",
+        state.borrow().copied(),
+        from_label,
+        to_label
+    );
+    Ok(())
+}
+
+/* =================================================
+Private functions
+================================================= */
+
+/// Parses --rename options given as `OLD=NEW` into a map from old attribute names to new ones.
+/// Renaming several attributes to the same name is rejected, as only one of them could be kept.
+fn parse_renames(rename: &[String]) -> Result<HashMap<String, String>, DyneinBatchError> {
+    let mut renames: HashMap<String, String> = HashMap::new();
+    for r in rename {
+        match r.split_once('=') {
+            Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+                if renames.values().any(|n| n == new) {
+                    return Err(DyneinBatchError::InvalidInput(format!(
+                        "More than one attribute is renamed to '{}'.",
+                        new
+                    )));
+                }
+                if renames.insert(old.to_owned(), new.to_owned()).is_some() {
+                    return Err(DyneinBatchError::InvalidInput(format!(
+                        "The attribute '{}' is renamed more than once.",
+                        old
+                    )));
+                }
+            }
+            _ => {
+                return Err(DyneinBatchError::InvalidInput(format!(
+                    "Invalid --rename option: '{}'. Valid format is '--rename OLD=NEW'",
+                    r
+                )))
+            }
+        }
+    }
+    Ok(renames)
+}
+
+/// Renames top-level attributes of the item. It fails if an attribute is renamed to the name of another attribute of the item,
+/// which is not renamed itself, so that neither of them is lost silently.
+fn rename_attributes(
+    item: Item,
+    renames: &HashMap<String, String>,
+) -> Result<Item, DyneinBatchError> {
+    for (old, new) in renames {
+        if item.contains_key(old) && item.contains_key(new) && !renames.contains_key(new) {
+            return Err(DyneinBatchError::InvalidInput(format!(
+                "The attribute '{}' can't be renamed to '{}', as an item already has '{}': {}",
+                old,
+                new,
+                new,
+                output::dynein_format(&item)
+            )));
+        }
+    }
+    Ok(item
+        .into_iter()
+        .map(|(name, attrval)| match renames.get(&name) {
+            Some(new_name) => (new_name.to_owned(), attrval),
+            None => (name, attrval),
+        })
+        .collect())
+}
+
+/// Creates the target table with the primary key and secondary indexes of the source table (renamed by --rename)
+/// in on-demand mode, and waits until it becomes ACTIVE. Other settings of the source table are not copied.
+async fn create_target_table(
+    from_cx: &app::Context,
+    to_cx: &app::Context,
+    renames: &HashMap<String, String>,
+) -> Result<(), app::DyneinExit> {
+    let desc = control::describe_table_api(from_cx, from_cx.effective_table_name()?).await?;
+    let desc = rename_table_description(desc, renames);
+
+    let table_name = to_cx.effective_table_name()?;
+    match control::create_table_like_api(to_cx, table_name.to_owned(), &desc).await {
+        Ok(_) => {
+            println!("Started to create table '{}'.", table_name);
+            bootstrap::wait_table_creation(to_cx, vec![&table_name]).await?;
+        }
+        Err(e) => match e.as_service_error() {
+            Some(CreateTableError::ResourceInUseException(_)) => println!(
+                "[skip] Table '{}' already exists, skipping to create new one.",
+                table_name
+            ),
+            _ => {
+                debug!("CreateTable API call got an error -- {:#?}", e);
+                return Err(app::bye_with_sdk_error(1, e));
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Renames attributes in the key schema, attribute definitions and indexes of the table description by --rename.
+fn rename_table_description(
+    mut desc: TableDescription,
+    renames: &HashMap<String, String>,
+) -> TableDescription {
+    let rename = |name: &mut String| {
+        if let Some(new_name) = renames.get(name) {
+            *name = new_name.to_owned();
+        }
+    };
+    let rename_key_schema = |ks: &mut Option<Vec<KeySchemaElement>>| {
+        ks.iter_mut()
+            .flatten()
+            .for_each(|k| rename(&mut k.attribute_name));
+    };
+    let rename_projection = |projection: &mut Option<Projection>| {
+        projection
+            .iter_mut()
+            .flat_map(|p| p.non_key_attributes.iter_mut().flatten())
+            .for_each(rename);
+    };
+
+    rename_key_schema(&mut desc.key_schema);
+    desc.attribute_definitions
+        .iter_mut()
+        .flatten()
+        .for_each(|attr| rename(&mut attr.attribute_name));
+    for gsi in desc.global_secondary_indexes.iter_mut().flatten() {
+        rename_key_schema(&mut gsi.key_schema);
+        rename_projection(&mut gsi.projection);
+    }
+    for lsi in desc.local_secondary_indexes.iter_mut().flatten() {
+        rename_key_schema(&mut lsi.key_schema);
+        rename_projection(&mut lsi.projection);
+    }
+    desc
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn copy_segment(
+    from_cx: &app::Context,
+    to_cx: &app::Context,
+    segment: i32,
+    total_segments: i32,
+    renames: &HashMap<String, String>,
+    state: &RefCell<CopyState>,
+    progress: &RefCell<ProgressState>,
+    resume_file: Option<&str>,
+) -> Result<(), DyneinBatchError> {
+    let mut esk: Option<Item> = state.borrow().segments[segment as usize]
+        .last_evaluated_key
+        .as_deref()
+        .map(|k| DyneinParser::new().parse_dynein_format(None, k))
+        .transpose()?;
+    loop {
+        let scan_output = data::scan_segment_page(from_cx, segment, total_segments, esk).await?;
+        let items: Vec<Item> = scan_output
+            .items
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| rename_attributes(item, renames))
+            .collect::<Result<_, _>>()?;
+        for chunk in items.chunks(25) {
+            // As BatchWriteItem request can have up to 25 items.
+            let request_items = batch::convert_items_to_request_items(to_cx, chunk.to_vec())?;
+            batch::batch_write_until_processed(to_cx, request_items).await?;
+            progress.borrow_mut().add_observation(chunk.len());
+            progress.borrow().show();
+        }
+
+        esk = scan_output.last_evaluated_key;
+        {
+            let mut state = state.borrow_mut();
+            let segment_state = &mut state.segments[segment as usize];
+            segment_state.copied += items.len();
+            segment_state.last_evaluated_key = esk.as_ref().map(output::dynein_format);
+            segment_state.done = esk.is_none();
+            if let Some(path) = resume_file {
+                state.save(path)?;
+            }
+        }
+        if esk.is_none() {
+            debug!("Segment {} of {} has been copied.", segment, total_segments);
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename_attributes() {
+        let renames = parse_renames(&[String::from("pk=PK"), String::from("sk=SK")]).unwrap();
+        let item: Item = DyneinParser::new()
+            .parse_dynein_format(None, r#"{"pk": "a", "sk": 1, "pkx": {"pk": "b"}}"#)
+            .unwrap();
+        // Only top-level attributes are renamed.
+        assert_eq!(
+            rename_attributes(item, &renames).unwrap(),
+            DyneinParser::new()
+                .parse_dynein_format(None, r#"{"PK": "a", "SK": 1, "pkx": {"pk": "b"}}"#)
+                .unwrap()
+        );
+
+        // Attributes can be swapped, but an attribute which is not renamed can't be overwritten.
+        let renames = parse_renames(&[String::from("a=b"), String::from("b=a")]).unwrap();
+        let item: Item = DyneinParser::new()
+            .parse_dynein_format(None, r#"{"a": 1, "b": 2}"#)
+            .unwrap();
+        assert_eq!(
+            rename_attributes(item.clone(), &renames).unwrap(),
+            DyneinParser::new()
+                .parse_dynein_format(None, r#"{"a": 2, "b": 1}"#)
+                .unwrap()
+        );
+        let renames = parse_renames(&[String::from("a=b")]).unwrap();
+        assert!(rename_attributes(item, &renames).is_err());
+
+        assert!(parse_renames(&[String::from("pk")]).is_err());
+        assert!(parse_renames(&[String::from("pk=")]).is_err());
+        assert!(parse_renames(&[String::from("pk=PK"), String::from("pk=Pk")]).is_err());
+        assert!(parse_renames(&[String::from("a=x"), String::from("b=x")]).is_err());
+    }
+
+    #[test]
+    fn test_rename_table_description() {
+        use aws_sdk_dynamodb::types::{
+            AttributeDefinition, GlobalSecondaryIndexDescription, KeyType,
+            LocalSecondaryIndexDescription, ProjectionType, ScalarAttributeType,
+        };
+        let key = |name: &str, key_type: KeyType| {
+            KeySchemaElement::builder()
+                .attribute_name(name)
+                .key_type(key_type)
+                .build()
+                .unwrap()
+        };
+        let attr = |name: &str| {
+            AttributeDefinition::builder()
+                .attribute_name(name)
+                .attribute_type(ScalarAttributeType::S)
+                .build()
+                .unwrap()
+        };
+        let desc = TableDescription::builder()
+            .key_schema(key("pk", KeyType::Hash))
+            .key_schema(key("sk", KeyType::Range))
+            .attribute_definitions(attr("pk"))
+            .attribute_definitions(attr("sk"))
+            .attribute_definitions(attr("email"))
+            .attribute_definitions(attr("date"))
+            .global_secondary_indexes(
+                GlobalSecondaryIndexDescription::builder()
+                    .index_name("by-email")
+                    .key_schema(key("email", KeyType::Hash))
+                    .projection(
+                        Projection::builder()
+                            .projection_type(ProjectionType::Include)
+                            .non_key_attributes("sk")
+                            .non_key_attributes("name")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .local_secondary_indexes(
+                LocalSecondaryIndexDescription::builder()
+                    .index_name("by-date")
+                    .key_schema(key("pk", KeyType::Hash))
+                    .key_schema(key("date", KeyType::Range))
+                    .projection(
+                        Projection::builder()
+                            .projection_type(ProjectionType::KeysOnly)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+
+        let renames = parse_renames(&[String::from("pk=PK"), String::from("sk=SK")]).unwrap();
+        let desc = rename_table_description(desc, &renames);
+        let names = |ks: &[KeySchemaElement]| -> Vec<String> {
+            ks.iter().map(|k| k.attribute_name.to_owned()).collect()
+        };
+        assert_eq!(names(desc.key_schema()), ["PK", "SK"]);
+        assert_eq!(
+            desc.attribute_definitions()
+                .iter()
+                .map(|a| a.attribute_name.as_str())
+                .collect::<Vec<&str>>(),
+            ["PK", "SK", "email", "date"]
+        );
+        let gsi = &desc.global_secondary_indexes()[0];
+        assert_eq!(names(gsi.key_schema()), ["email"]);
+        assert_eq!(
+            gsi.projection().unwrap().non_key_attributes(),
+            ["SK", "name"]
+        );
+        let lsi = &desc.local_secondary_indexes()[0];
+        assert_eq!(names(lsi.key_schema()), ["PK", "date"]);
+        assert_eq!(
+            lsi.projection().unwrap().projection_type(),
+            Some(&ProjectionType::KeysOnly)
+        );
+    }
+
+    #[test]
+    fn test_copy_state() {
+        let mut state = CopyState::new(
+            &"Movie@us-east-1".parse().unwrap(),
+            &"Movie@local:8001".parse().unwrap(),
+            &[],
+            2,
+        );
+        let esk: Item = DyneinParser::new()
+            .parse_dynein_format(None, r#"{"pk": "a", "sk": 1}"#)
+            .unwrap();
+        state.segments[0] = SegmentState {
+            copied: 10,
+            last_evaluated_key: Some(output::dynein_format(&esk)),
+            done: false,
+        };
+        state.segments[1].done = true;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("copy.json");
+        state.save(path.to_str().unwrap()).unwrap();
+        let saved: CopyState = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved, state);
+        assert_eq!(saved.copied(), 10);
+        // The key is restored to resume the scan.
+        assert_eq!(
+            DyneinParser::new()
+                .parse_dynein_format(None, saved.segments[0].last_evaluated_key.as_ref().unwrap())
+                .unwrap(),
+            esk
+        );
+    }
+}
@@ -20,28 +20,45 @@ use std::{
     error::Error,
     fmt,
     io::{self, Write},
+    time::Instant,
     vec::Vec,
 };
 
-use crate::parser::{AttributeDefinition, AttributeType, DyneinParser, ParseError};
+use crate::parser::{AttributeDefinition, AttributeType, DocumentPath, DyneinParser, ParseError};
 use aws_sdk_dynamodb::{
-    operation::scan::ScanOutput,
+    error::ProvideErrorMetadata,
+    operation::{
+        get_item::GetItemError,
+        query::{builders::QueryFluentBuilder, QueryError, QueryOutput},
+        scan::ScanOutput,
+        update_item::{UpdateItemError, UpdateItemOutput},
+    },
     types::{AttributeValue, ReturnValue},
     Client as DynamoDbSdkClient,
 };
-use log::{debug, error};
+use aws_smithy_types::error::display::DisplayErrorContext;
+use console::{measure_text_width, truncate_str, Term};
+use dialoguer::Editor;
+use log::debug;
+use regex::Regex;
 use serde::{ser::SerializeStruct, Serialize, Serializer};
 use serde_json::Value as JsonValue;
 use tabwriter::TabWriter;
 // use bytes::Bytes;
 
-use super::app;
+use super::app::{self, RequestKind};
 use super::ddb::key;
+use super::output::{self, OutputFormat};
 
 /* =================================================
 struct / enum / const
 ================================================= */
 
+/// Width of non-key cells in the table output when stdout is not a terminal, e.g. piped to other commands.
+const DEFAULT_CELL_WIDTH: usize = 50;
+/// Non-key cells are not truncated narrower than this to fit the terminal width.
+const MIN_CELL_WIDTH: usize = 10;
+
 #[derive(Debug)]
 struct GeneratedQueryParams {
     exp: Option<String>,
@@ -49,6 +66,24 @@ struct GeneratedQueryParams {
     vals: Option<HashMap<String, AttributeValue>>,
 }
 
+/// FilterExpression with its ExpressionAttributeNames and ExpressionAttributeValues,
+/// which is generated by [`generate_filter_expressions`] and applied on Scan/Query.
+#[derive(Debug, Clone)]
+pub struct GeneratedFilterParams {
+    exp: String,
+    names: Option<HashMap<String, String>>,
+    vals: Option<HashMap<String, AttributeValue>>,
+}
+
+/// ConditionExpression with its ExpressionAttributeNames and ExpressionAttributeValues,
+/// which is generated by [`generate_unchanged_condition`] and applied on writes.
+#[derive(Debug)]
+struct GeneratedConditionParams {
+    exp: String,
+    names: HashMap<String, String>,
+    vals: HashMap<String, AttributeValue>,
+}
+
 #[derive(Debug)]
 struct GeneratedScanParams {
     exp: Option<String>,
@@ -62,6 +97,46 @@ struct GeneratedUpdateParams {
     vals: Option<HashMap<String, AttributeValue>>,
 }
 
+/// An element of a document path, i.e. an attribute name or an index of a list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathElement {
+    Attribute(String),
+    Index(usize),
+}
+
+/// A difference of an attribute between two items, found by [`diff_items`].
+/// `before` (or `after`) is None if the attribute is added (or removed).
+#[derive(Debug, PartialEq)]
+pub struct AttributeDiff<'a> {
+    pub path: Vec<PathElement>,
+    pub before: Option<&'a AttributeValue>,
+    pub after: Option<&'a AttributeValue>,
+}
+
+impl AttributeDiff<'_> {
+    /// Returns the document path, e.g. `address.city` or `tags[0]`.
+    /// If `quote` is true, attribute names are quoted by backticks so that the parser accepts any name.
+    pub fn path_string(&self, quote: bool) -> String {
+        let mut path = String::new();
+        for element in &self.path {
+            match element {
+                PathElement::Attribute(name) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    if quote {
+                        path.push_str(&format!("`{}`", name.replace('`', "``")));
+                    } else {
+                        path.push_str(name);
+                    }
+                }
+                PathElement::Index(i) => path.push_str(&format!("[{}]", i)),
+            }
+        }
+        path
+    }
+}
+
 enum UpdateActionType {
     Set,
     Remove,
@@ -72,14 +147,67 @@ pub enum DyneinQueryParamsError {
     NoSuchIndex(String /* index name */, String /* table name */),
     NoSortKeyDefined,
     InvalidSortKeyOption(ParseError),
+    InvalidFilterValues(ParseError),
+    UndefinedFilterValue(String /* placeholder */),
+    InvalidColumns(ParseError),
+    Exit(app::DyneinExit),
+}
+
+/// Errors of editing an item in $EDITOR, which leave the item as is.
+#[derive(Debug)]
+pub enum DyneinEditError {
+    Editor(dialoguer::Error),
+    InvalidItem(ParseError),
+    KeyChanged,
+    Update(aws_sdk_dynamodb::error::SdkError<UpdateItemError>),
+}
+
+impl fmt::Display for DyneinEditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DyneinEditError::Editor(err) => write!(
+                f,
+                "Failed to open the editor: {}. Set $EDITOR (or $VISUAL) to the editor to use.",
+                err
+            ),
+            DyneinEditError::InvalidItem(err) => write!(
+                f,
+                "{}\nThe edited item is not in the dynein format. \
+                For more information, please visit https://github.com/awslabs/dynein/blob/main/docs/format.md.",
+                err
+            ),
+            DyneinEditError::KeyChanged => write!(
+                f,
+                "The primary key of an item cannot be changed. Please put a new item with `dy put` instead."
+            ),
+            DyneinEditError::Update(err) => write!(f, "{}", DisplayErrorContext(err)),
+        }
+    }
 }
 
+impl Error for DyneinEditError {}
+
 impl From<ParseError> for DyneinQueryParamsError {
     fn from(err: ParseError) -> Self {
         DyneinQueryParamsError::InvalidSortKeyOption(err)
     }
 }
 
+impl From<app::DyneinExit> for DyneinQueryParamsError {
+    fn from(e: app::DyneinExit) -> Self {
+        DyneinQueryParamsError::Exit(e)
+    }
+}
+
+impl From<DyneinQueryParamsError> for app::DyneinExit {
+    fn from(e: DyneinQueryParamsError) -> Self {
+        match e {
+            DyneinQueryParamsError::Exit(e) => e,
+            e => app::bye_with_error(e),
+        }
+    }
+}
+
 impl fmt::Display for DyneinQueryParamsError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self {
@@ -111,6 +239,34 @@ impl fmt::Display for DyneinQueryParamsError {
                     err
                 )
             }
+            DyneinQueryParamsError::InvalidFilterValues(err) => {
+                write!(
+                    f,
+                    "{}\n--filter-values syntax is invalid. \
+                    This option accepts a map of placeholders and values in dynein format, \
+                    e.g. '{{\":min\": 100, \":status\": \"active\"}}'.",
+                    err
+                )
+            }
+            DyneinQueryParamsError::UndefinedFilterValue(placeholder) => {
+                write!(
+                    f,
+                    "The value of '{}' used in --filter is not given. \
+                    Please pass it with --filter-values option.",
+                    placeholder
+                )
+            }
+            DyneinQueryParamsError::InvalidColumns(err) => {
+                write!(
+                    f,
+                    "{}\n--columns syntax is invalid. \
+                    This option accepts document paths separated by commas, \
+                    e.g. 'address.city,tags[0],price'. \
+                    Quote an attribute name with backticks if it has special characters, e.g. '`first name`'.",
+                    err
+                )
+            }
+            DyneinQueryParamsError::Exit(e) => e.fmt(f),
         }
     }
 }
@@ -122,45 +278,53 @@ Public functions
 
 /// This function calls Scan API and return mutiple items. By default it uses 'table' output format.
 /// Scan API retrieves all items in a given table, something like `SELECT * FROM mytable` in SQL world.
+#[allow(clippy::too_many_arguments)]
 pub async fn scan(
     cx: &app::Context,
     index: Option<String>,
     consistent_read: bool,
     attributes: &Option<String>,
+    columns: &Option<String>,
     keys_only: bool,
+    wide: bool,
+    show_types: bool,
     limit: i32,
-) {
-    let ts: app::TableSchema = app::table_schema(cx).await;
+) -> Result<(), app::DyneinExit> {
+    let ts: app::TableSchema = table_schema_for_index(cx, &index).await?;
 
-    let items = scan_api(
+    let (paths, scan_params) = match columns {
+        Some(columns) => {
+            let (paths, scan_params) = generate_column_expressions(&ts, columns)?;
+            (Some(paths), scan_params)
+        }
+        None => (None, generate_scan_expressions(&ts, attributes, keys_only)),
+    };
+    let items = scan_with_projection(
         cx,
+        &ts,
         index,
         consistent_read,
-        attributes,
-        keys_only,
+        scan_params,
         Some(limit),
         None,
+        None,
     )
-    .await
+    .await?
     .items
     .expect("items should be 'Some' even if there's no item in the table.");
-    match cx.output.as_deref() {
-        None | Some("table") => display_items_table(items, &ts, attributes, keys_only),
-        Some("json") => println!(
-            "{}",
-            serde_json::to_string_pretty(&convert_to_json_vec(&items)).unwrap()
-        ),
-        Some("raw") => println!(
-            "{}",
-            serde_json::to_string_pretty(&strip_items(&items)).unwrap()
-        ),
-        Some(o) => {
-            println!("ERROR: unsupported output type '{}'.", o);
-            std::process::exit(1);
-        }
-    }
+    cx.remember_attribute_names(&items);
+    let format = cx.output_format(output::ITEM_FORMATS, default_items_format(show_types));
+    let options = output::PrintOptions {
+        columns: output::Columns::new(attributes, paths, keys_only),
+        wide,
+        show_types,
+        color: cx.color.enabled(),
+    };
+    output::print_items(items, &ts, &options, format);
+    Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn scan_api(
     cx: &app::Context,
     index: Option<String>,
@@ -169,31 +333,156 @@ pub async fn scan_api(
     keys_only: bool,
     limit: Option<i32>,
     esk: Option<HashMap<String, AttributeValue>>,
-) -> ScanOutput {
+    filter: Option<GeneratedFilterParams>,
+) -> Result<ScanOutput, app::DyneinExit> {
     debug!("context: {:#?}", &cx);
-    let ts: app::TableSchema = app::table_schema(cx).await;
+    let ts: app::TableSchema = table_schema_for_index(cx, &index).await?;
 
     let scan_params: GeneratedScanParams = generate_scan_expressions(&ts, attributes, keys_only);
+    scan_with_projection(
+        cx,
+        &ts,
+        index,
+        consistent_read,
+        scan_params,
+        limit,
+        esk,
+        filter,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn scan_with_projection(
+    cx: &app::Context,
+    ts: &app::TableSchema,
+    index: Option<String>,
+    consistent_read: bool,
+    scan_params: GeneratedScanParams,
+    limit: Option<i32>,
+    esk: Option<HashMap<String, AttributeValue>>,
+    filter: Option<GeneratedFilterParams>,
+) -> Result<ScanOutput, app::DyneinExit> {
+    let (filter_exp, filter_names, filter_vals) = match filter {
+        Some(f) => (Some(f.exp), f.names, f.vals),
+        None => (None, None, None),
+    };
 
     let config = cx.effective_sdk_config().await;
     let ddb = DynamoDbSdkClient::new(&config);
 
+    let started_at = Instant::now();
     ddb.scan()
-        .table_name(ts.name)
+        .table_name(&ts.name)
         .set_index_name(index)
         .set_limit(limit)
         .set_projection_expression(scan_params.exp)
-        .set_expression_attribute_names(scan_params.names)
+        .set_filter_expression(filter_exp)
+        .set_expression_attribute_names(merge_maps(scan_params.names, filter_names))
+        .set_expression_attribute_values(filter_vals)
         .consistent_read(consistent_read)
         .set_exclusive_start_key(esk)
+        .set_return_consumed_capacity(cx.return_consumed_capacity())
+        .send()
+        .await
+        .inspect(|res| {
+            cx.add_request_metrics(
+                RequestKind::Read,
+                started_at,
+                res.consumed_capacity(),
+                Some((res.scanned_count, res.count)),
+            )
+        })
+        .map_err(|e| {
+            debug!("Scan API call got an error -- {:?}", e);
+            app::bye_with_sdk_error(1, e)
+        })
+}
+
+/// Calls Scan API for a page of items of the table (or the index) whose schema has been retrieved, e.g. for `dy tui`.
+pub async fn scan_page(
+    cx: &app::Context,
+    ts: &app::TableSchema,
+    index: Option<String>,
+    limit: i32,
+    esk: Option<HashMap<String, AttributeValue>>,
+) -> Result<ScanOutput, app::DyneinExit> {
+    let scan_params = generate_scan_expressions(ts, &None, false);
+    scan_with_projection(cx, ts, index, false, scan_params, Some(limit), esk, None).await
+}
+
+/// Calls Scan API for a page of the segment of a parallel scan over the table, e.g. for `dy copy`.
+/// ref: https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/Scan.html#Scan.ParallelScan
+pub async fn scan_segment_page(
+    cx: &app::Context,
+    segment: i32,
+    total_segments: i32,
+    esk: Option<HashMap<String, AttributeValue>>,
+) -> Result<ScanOutput, app::DyneinExit> {
+    let config = cx.effective_sdk_config().await;
+    let ddb = DynamoDbSdkClient::new(&config);
+
+    let started_at = Instant::now();
+    ddb.scan()
+        .table_name(cx.effective_table_name()?)
+        .segment(segment)
+        .total_segments(total_segments)
+        .set_exclusive_start_key(esk)
+        .set_return_consumed_capacity(cx.return_consumed_capacity())
         .send()
         .await
-        .unwrap_or_else(|e| {
+        .inspect(|res| {
+            cx.add_request_metrics(
+                RequestKind::Read,
+                started_at,
+                res.consumed_capacity(),
+                Some((res.scanned_count, res.count)),
+            )
+        })
+        .map_err(|e| {
             debug!("Scan API call got an error -- {:?}", e);
-            app::bye_with_sdk_error(1, e);
+            app::bye_with_sdk_error(1, e)
         })
 }
 
+/// Scans the whole table (or the index), or up to `sample` items if given, and passes each item to `observe`.
+/// Returns true if the scan stopped at `sample` items before the end of the table.
+pub async fn scan_sample(
+    cx: &app::Context,
+    index: Option<String>,
+    sample: Option<i32>,
+    mut observe: impl FnMut(&HashMap<String, AttributeValue>),
+) -> Result<bool, app::DyneinExit> {
+    let mut scanned: i32 = 0;
+    let mut last_evaluated_key: Option<HashMap<String, AttributeValue>> = None;
+    loop {
+        let scan_output = scan_api(
+            cx,
+            index.clone(),
+            false, /* consistent_read */
+            &None, /* attributes */
+            false, /* keys_only */
+            sample.map(|n| n - scanned),
+            last_evaluated_key,
+            None, /* filter */
+        )
+        .await?;
+        for item in scan_output.items.unwrap_or_default() {
+            observe(&item);
+            scanned += 1;
+        }
+        debug!("{} items scanned so far", scanned);
+
+        last_evaluated_key = scan_output.last_evaluated_key;
+        if last_evaluated_key.is_none() {
+            return Ok(false);
+        }
+        if sample.is_some_and(|n| scanned >= n) {
+            return Ok(true);
+        }
+    }
+}
+
 pub struct QueryParams {
     pub pval: String,
     pub sort_key_expression: Option<String>,
@@ -210,71 +499,170 @@ pub struct QueryParams {
 /// References:
 /// - https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/Query.html#Query.KeyConditionExpressions
 /// - https://aws.amazon.com/blogs/database/using-sort-keys-to-organize-data-in-amazon-dynamodb/
-pub async fn query(cx: &app::Context, params: QueryParams) {
+pub async fn query(
+    cx: &app::Context,
+    params: QueryParams,
+    columns: &Option<String>,
+    wide: bool,
+    show_types: bool,
+) -> Result<(), app::DyneinExit> {
     debug!("context: {:#?}", &cx);
-    let ts: app::TableSchema = app::table_schema(cx).await;
-
-    debug!("For table '{}' (index '{:?}'), generating KeyConditionExpression using sort_key_expression: '{:?}'", &ts.name, &params.index, &params.sort_key_expression);
-    let query_params: GeneratedQueryParams = match generate_query_expressions(
-        &ts,
-        &params.pval,
-        &params.sort_key_expression,
-        &params.index,
-        cx.should_strict_for_query(),
-    ) {
-        Ok(qp) => qp,
-        Err(e) => {
-            error!("{}", e.to_string());
-            std::process::exit(1);
-        }
-    };
-    debug!(
-        "Generated QueryParams for the table '{}' is: {:#?}",
-        &ts.name, &query_params
-    );
-
-    let config = cx.effective_sdk_config().await;
-    let ddb = DynamoDbSdkClient::new(&config);
-
-    let req = ddb
-        .query()
-        .table_name(ts.name.to_string())
-        .set_index_name(params.index)
-        .set_limit(params.limit)
-        .set_key_condition_expression(query_params.exp)
-        .set_expression_attribute_names(query_params.names)
-        .set_expression_attribute_values(query_params.vals)
-        .consistent_read(params.consistent_read)
-        .set_scan_index_forward(params.descending.then_some(false));
-    debug!("Request: {:#?}", req);
+    let mut ts: app::TableSchema = table_schema_for_index(cx, &params.index).await?;
+    let mut retried = false;
+    loop {
+        debug!("For table '{}' (index '{:?}'), generating KeyConditionExpression using sort_key_expression: '{:?}'", &ts.name, &params.index, &params.sort_key_expression);
+        let query_params: GeneratedQueryParams = generate_query_expressions(
+            &ts,
+            &params.pval,
+            &params.sort_key_expression,
+            &params.index,
+            cx.should_strict_for_query(),
+        )?;
+        let (paths, projection_params) = match columns {
+            Some(columns) => {
+                let (paths, projection_params) = generate_column_expressions(&ts, columns)?;
+                (Some(paths), projection_params)
+            }
+            None => (
+                None,
+                GeneratedScanParams {
+                    exp: None,
+                    names: None,
+                },
+            ),
+        };
+        debug!(
+            "Generated QueryParams for the table '{}' is: {:#?}",
+            &ts.name, &query_params
+        );
 
-    match req.send().await {
-        Ok(res) => {
-            match res.items {
-                None => panic!("This message should not be shown"), // as Query returns 'Some([])' if there's no item to return.
-                Some(items) => match cx.output.as_deref() {
-                    None | Some("table") => {
-                        display_items_table(items, &ts, &params.attributes, params.keys_only)
+        let config = cx.effective_sdk_config().await;
+        let ddb = DynamoDbSdkClient::new(&config);
+
+        let req = ddb
+            .query()
+            .table_name(ts.name.to_string())
+            .set_index_name(params.index.clone())
+            .set_limit(params.limit)
+            .set_key_condition_expression(query_params.exp)
+            .set_projection_expression(projection_params.exp)
+            .set_expression_attribute_names(merge_maps(query_params.names, projection_params.names))
+            .set_expression_attribute_values(query_params.vals)
+            .consistent_read(params.consistent_read)
+            .set_scan_index_forward(params.descending.then_some(false))
+            .set_return_consumed_capacity(cx.return_consumed_capacity());
+        debug!("Request: {:#?}", req);
+
+        let started_at = Instant::now();
+        match req.send().await {
+            Ok(res) => {
+                cx.add_request_metrics(
+                    RequestKind::Read,
+                    started_at,
+                    res.consumed_capacity(),
+                    Some((res.scanned_count, res.count)),
+                );
+                match res.items {
+                    None => panic!("This message should not be shown"), // as Query returns 'Some([])' if there's no item to return.
+                    Some(items) => {
+                        cx.remember_attribute_names(&items);
+                        let format = cx
+                            .output_format(output::ITEM_FORMATS, default_items_format(show_types));
+                        let options = output::PrintOptions {
+                            columns: output::Columns::new(
+                                &params.attributes,
+                                paths,
+                                params.keys_only,
+                            ),
+                            wide,
+                            show_types,
+                            color: cx.color.enabled(),
+                        };
+                        output::print_items(items, &ts, &options, format);
                     }
-                    Some("json") => println!(
-                        "{}",
-                        serde_json::to_string_pretty(&convert_to_json_vec(&items)).unwrap()
-                    ),
-                    Some("raw") => println!(
-                        "{}",
-                        serde_json::to_string_pretty(&strip_items(&items)).unwrap()
-                    ),
-                    Some(o) => {
-                        println!("ERROR: unsupported output type '{}'.", o);
-                        std::process::exit(1);
+                }
+                return Ok(());
+            }
+            Err(e) => {
+                debug!("Query API call got an error -- {:?}", e);
+                if !retried {
+                    if let Some(refreshed) = refresh_schema_on_validation_error(cx, &e).await? {
+                        ts = refreshed;
+                        retried = true;
+                        continue;
                     }
-                },
+                }
+                return Err(app::bye_with_sdk_error(1, e));
             }
         }
-        Err(e) => {
-            debug!("Query API call got an error -- {:?}", e);
-            app::bye_with_sdk_error(1, e);
-        }
+    }
+}
+
+/// This function calls Query API and returns a page of items, which is used by commands iterating all items in a partition (e.g. `dy export`).
+/// ProjectionExpression is built from `attributes` and `keys_only` of the given params in the same way as Scan.
+pub async fn query_api(
+    cx: &app::Context,
+    params: &QueryParams,
+    esk: Option<HashMap<String, AttributeValue>>,
+    filter: Option<GeneratedFilterParams>,
+) -> Result<QueryOutput, DyneinQueryParamsError> {
+    debug!("context: {:#?}", &cx);
+    let ts: app::TableSchema = table_schema_for_index(cx, &params.index).await?;
+    let req = build_query_page(cx, &ts, params, esk.clone(), filter.clone()).await?;
+    let req = match send_query_page(cx, req).await {
+        Ok(res) => return Ok(res),
+        Err(e) => match refresh_schema_on_validation_error(cx, &e).await? {
+            Some(ts) => build_query_page(cx, &ts, params, esk, filter).await?,
+            None => {
+                debug!("Query API call got an error -- {:?}", e);
+                return Err(app::bye_with_sdk_error(1, e).into());
+            }
+        },
+    };
+    send_query_page(cx, req).await.map_err(|e| {
+        debug!("Query API call got an error -- {:?}", e);
+        app::bye_with_sdk_error(1, e).into()
+    })
+}
+
+/// Same as [`query_api`], but for the table whose schema has been retrieved, e.g. for `dy tui`.
+pub async fn query_page(
+    cx: &app::Context,
+    ts: &app::TableSchema,
+    params: &QueryParams,
+    esk: Option<HashMap<String, AttributeValue>>,
+    filter: Option<GeneratedFilterParams>,
+) -> Result<QueryOutput, DyneinQueryParamsError> {
+    let req = build_query_page(cx, ts, params, esk, filter).await?;
+    send_query_page(cx, req).await.map_err(|e| {
+        debug!("Query API call got an error -- {:?}", e);
+        app::bye_with_sdk_error(1, e).into()
+    })
+}
+
+/// Returns the schema of the target table, checking that the table has the index if given.
+/// The index may have been created after the schema was cached, so the cached schema is refreshed once if the index is not found in it.
+/// Commands which take --index retrieve the schema by this function.
+pub async fn table_schema_for_index(
+    cx: &app::Context,
+    index: &Option<String>,
+) -> Result<app::TableSchema, DyneinQueryParamsError> {
+    let has_index = |ts: &app::TableSchema| match index {
+        None => true,
+        Some(index) => ts.indexes.iter().flatten().any(|idx| &idx.name == index),
+    };
+    let mut ts: app::TableSchema = app::table_schema(cx).await?;
+    // With --table, the schema has just been retrieved by DescribeTable API.
+    if !has_index(&ts) && cx.overwritten_table_name.is_none() {
+        debug!("index is not found in the cached schema, refreshing it");
+        ts = app::refresh_table_schema(cx).await?;
+    }
+    match index {
+        Some(index) if !has_index(&ts) => Err(DyneinQueryParamsError::NoSuchIndex(
+            index.to_owned(),
+            ts.name,
+        )),
+        _ => Ok(ts),
     }
 }
 
@@ -284,11 +672,12 @@ pub async fn get_item(
     pval: String,
     sval: Option<String>,
     consistent_read: bool,
-) {
+    show_types: bool,
+) -> Result<(), app::DyneinExit> {
     debug!("context: {:#?}", &cx);
     // Use table if explicitly specified by `--table/-t` option. Otherwise, load table name from config file.
-    let ts: app::TableSchema = app::table_schema(cx).await;
-    let primary_keys = identify_target(&ts, &pval, sval.as_deref());
+    let ts: app::TableSchema = app::table_schema(cx).await?;
+    let primary_keys = identify_target(&ts, &pval, sval.as_deref())?;
 
     debug!(
         "Calling GetItem API for the table '{}' with key(s): {:?}",
@@ -298,48 +687,76 @@ pub async fn get_item(
     let config = cx.effective_sdk_config().await;
     let ddb = DynamoDbSdkClient::new(&config);
 
+    let started_at = Instant::now();
     match ddb
         .get_item()
-        .table_name(ts.name)
+        .table_name(&ts.name)
         .set_key(Some(primary_keys))
         .consistent_read(consistent_read)
+        .set_return_consumed_capacity(cx.return_consumed_capacity())
         .send()
         .await
-    {
+        .inspect(|res| {
+            cx.add_request_metrics(RequestKind::Read, started_at, res.consumed_capacity(), None)
+        }) {
         Ok(res) => match res.item {
             None => println!("No item found."),
-            Some(item) => match cx.output.as_deref() {
-                None | Some("json") => println!(
-                    "{}",
-                    serde_json::to_string_pretty(&convert_to_json(&item)).unwrap()
-                ),
-                Some("yaml") => println!(
-                    "{}",
-                    serde_yaml::to_string(&convert_to_json(&item)).unwrap()
-                ),
-                Some("raw") => println!(
-                    "{}",
-                    serde_json::to_string_pretty(&strip_item(&item)).unwrap()
-                ),
-                Some(o) => {
-                    println!("ERROR: unsupported output type '{}'.", o);
-                    std::process::exit(1);
-                }
-            },
+            Some(item) => {
+                cx.remember_attribute_names(std::slice::from_ref(&item));
+                let format = cx.output_format(output::ITEM_FORMATS, OutputFormat::Json);
+                let options = output::PrintOptions {
+                    columns: output::Columns::Auto,
+                    wide: false,
+                    show_types,
+                    color: cx.color.enabled(),
+                };
+                output::print_item(item, &ts, &options, format);
+            }
         },
         Err(e) => {
             debug!("GetItem API call got an error -- {:?}", e);
-            app::bye_with_sdk_error(1, e);
+            return Err(app::bye_with_sdk_error(1, e));
         }
     }
+    Ok(())
+}
+
+/// Calls GetItem API for an item identified by the given primary key(s), and returns the item if it exists.
+/// Unlike [`get_item`], errors are returned to the caller, e.g. `dy tui` shows them instead of exiting.
+pub async fn get_item_api(
+    cx: &app::Context,
+    ts: &app::TableSchema,
+    key: HashMap<String, AttributeValue>,
+    consistent_read: bool,
+) -> Result<Option<HashMap<String, AttributeValue>>, aws_sdk_dynamodb::error::SdkError<GetItemError>>
+{
+    let config = cx.effective_sdk_config().await;
+    let ddb = DynamoDbSdkClient::new(&config);
+
+    let started_at = Instant::now();
+    let res = ddb
+        .get_item()
+        .table_name(&ts.name)
+        .set_key(Some(key))
+        .consistent_read(consistent_read)
+        .set_return_consumed_capacity(cx.return_consumed_capacity())
+        .send()
+        .await?;
+    cx.add_request_metrics(RequestKind::Read, started_at, res.consumed_capacity(), None);
+    Ok(res.item)
 }
 
 // put_item function saves an item with given primary key(s). You can pass other attributes with --item/-i option in JSON format.
 // As per DynamoDB PutItem API behavior, if the item already exists it'd be replaced.
-pub async fn put_item(cx: &app::Context, pval: String, sval: Option<String>, item: Option<String>) {
+pub async fn put_item(
+    cx: &app::Context,
+    pval: String,
+    sval: Option<String>,
+    item: Option<String>,
+) -> Result<(), app::DyneinExit> {
     debug!("context: {:#?}", &cx);
-    let ts: app::TableSchema = app::table_schema(cx).await;
-    let mut full_item_image = identify_target(&ts, &pval, sval.as_deref()); // Firstly, ideitify primary key(s) to ideitnfy an item to put.
+    let ts: app::TableSchema = app::table_schema(cx).await?;
+    let mut full_item_image = identify_target(&ts, &pval, sval.as_deref())?; // Firstly, ideitify primary key(s) to ideitnfy an item to put.
 
     debug!(
         "Inserting (or replacing) an item identified by the primary key(s): {:?}",
@@ -357,8 +774,10 @@ pub async fn put_item(cx: &app::Context, pval: String, sval: Option<String>, ite
                     full_item_image = attrs;
                 }
                 Err(e) => {
-                    error!("ERROR: failed to load item. {:?}", e);
-                    std::process::exit(1);
+                    return Err(app::bye_with_error(format!(
+                        "ERROR: failed to load item. {:?}",
+                        e
+                    )));
                 }
             };
         }
@@ -369,28 +788,42 @@ pub async fn put_item(cx: &app::Context, pval: String, sval: Option<String>, ite
     let config = cx.effective_sdk_config().await;
     let ddb = DynamoDbSdkClient::new(&config);
 
+    let started_at = Instant::now();
     match ddb
         .put_item()
         .table_name(ts.name.to_string())
         .set_item(Some(full_item_image))
+        .set_return_consumed_capacity(cx.return_consumed_capacity())
         .send()
         .await
-    {
+        .inspect(|res| {
+            cx.add_request_metrics(
+                RequestKind::Write,
+                started_at,
+                res.consumed_capacity(),
+                None,
+            )
+        }) {
         Ok(_) => {
             println!("Successfully put an item to the table '{}'.", &ts.name);
         }
         Err(e) => {
             debug!("PutItem API call got an error -- {:?}", e);
-            app::bye_with_sdk_error(1, e);
+            return Err(app::bye_with_sdk_error(1, e));
         }
     }
+    Ok(())
 }
 
 // delete_item functions calls DeleteItem API - delete an item with given primary key(s).
-pub async fn delete_item(cx: &app::Context, pval: String, sval: Option<String>) {
+pub async fn delete_item(
+    cx: &app::Context,
+    pval: String,
+    sval: Option<String>,
+) -> Result<(), app::DyneinExit> {
     debug!("context: {:#?}", &cx);
-    let ts: app::TableSchema = app::table_schema(cx).await;
-    let primary_keys = identify_target(&ts, &pval, sval.as_deref());
+    let ts: app::TableSchema = app::table_schema(cx).await?;
+    let primary_keys = identify_target(&ts, &pval, sval.as_deref())?;
 
     debug!(
         "Calling DeleteItem API for the table '{}' with key(s): {:?}",
@@ -400,13 +833,22 @@ pub async fn delete_item(cx: &app::Context, pval: String, sval: Option<String>)
     let config = cx.effective_sdk_config().await;
     let ddb = DynamoDbSdkClient::new(&config);
 
+    let started_at = Instant::now();
     match ddb
         .delete_item()
         .table_name(ts.name.to_string())
         .set_key(Some(primary_keys))
+        .set_return_consumed_capacity(cx.return_consumed_capacity())
         .send()
         .await
-    {
+        .inspect(|res| {
+            cx.add_request_metrics(
+                RequestKind::Write,
+                started_at,
+                res.consumed_capacity(),
+                None,
+            )
+        }) {
         // NOTE: DynamoDB DeleteItem API is idempotent and returns "OK" even if an item trying to delete doesn't exist.
         Ok(_) => {
             println!(
@@ -416,9 +858,10 @@ pub async fn delete_item(cx: &app::Context, pval: String, sval: Option<String>)
         }
         Err(e) => {
             debug!("Deletetem API call got an error -- {:?}", e);
-            app::bye_with_sdk_error(1, e);
+            return Err(app::bye_with_sdk_error(1, e));
         }
     }
+    Ok(())
 }
 
 // UpdateItem API https://docs.aws.amazon.com/amazondynamodb/latest/APIReference/API_UpdateItem.html
@@ -428,16 +871,17 @@ pub async fn update_item(
     sval: Option<String>,
     set_expression: Option<String>,
     remove_expression: Option<String>,
-) {
+) -> Result<(), app::DyneinExit> {
     debug!("context: {:#?}", &cx);
     if set_expression.is_none() && remove_expression.is_none() {
         // setting both --set and --remove is prohibited by conflicts_with of clap
-        error!("One of --set or --remove option is required. Passing both options is invalid.");
-        std::process::exit(1);
+        return Err(app::bye_with_error(
+            "One of --set or --remove option is required. Passing both options is invalid.",
+        ));
     };
 
-    let ts: app::TableSchema = app::table_schema(cx).await;
-    let primary_keys = identify_target(&ts, &pval, sval.as_deref());
+    let ts: app::TableSchema = app::table_schema(cx).await?;
+    let primary_keys = identify_target(&ts, &pval, sval.as_deref())?;
 
     debug!(
         "Calling UpdateItem API for the table '{}' with key(s): {:?}",
@@ -456,6 +900,7 @@ pub async fn update_item(
     let config = cx.effective_sdk_config().await;
     let ddb = DynamoDbSdkClient::new(&config);
 
+    let started_at = Instant::now();
     match ddb
         .update_item()
         .table_name(ts.name.to_string())
@@ -464,9 +909,17 @@ pub async fn update_item(
         .set_expression_attribute_names(update_params.names)
         .set_expression_attribute_values(update_params.vals)
         .return_values(ReturnValue::AllNew) // ask DynamoDB to return updated item.
+        .set_return_consumed_capacity(cx.return_consumed_capacity())
         .send()
         .await
-    {
+        .inspect(|res| {
+            cx.add_request_metrics(
+                RequestKind::Write,
+                started_at,
+                res.consumed_capacity(),
+                None,
+            )
+        }) {
         Ok(res) => {
             println!("Successfully updated an item in the table '{}'.", &ts.name);
             println!(
@@ -476,9 +929,136 @@ pub async fn update_item(
         }
         Err(e) => {
             debug!("UpdateItem API call got an error -- {:?}", e);
-            app::bye_with_sdk_error(1, e);
+            return Err(app::bye_with_sdk_error(1, e));
+        }
+    }
+    Ok(())
+}
+
+/// This function edits an item in $EDITOR. The item is read by GetItem API, and the difference made in the editor is applied by UpdateItem API
+/// on condition that the item has not been changed since it was read. Nested attributes are updated by their document paths.
+pub async fn edit_item(
+    cx: &app::Context,
+    pval: String,
+    sval: Option<String>,
+) -> Result<(), app::DyneinExit> {
+    debug!("context: {:#?}", &cx);
+    let ts: app::TableSchema = app::table_schema(cx).await?;
+    let primary_keys = identify_target(&ts, &pval, sval.as_deref())?;
+
+    let original = match get_item_api(cx, &ts, primary_keys, true).await {
+        Ok(Some(item)) => item,
+        Ok(None) => return Err(app::bye(1, "No item found.")),
+        Err(e) => {
+            debug!("GetItem API call got an error -- {:?}", e);
+            return Err(app::bye_with_sdk_error(1, e));
+        }
+    };
+    let edited = match edit_in_editor(&ts, &original) {
+        Ok(Some(item)) => item,
+        Ok(None) => {
+            println!("The item was not saved in the editor. Nothing is updated.");
+            return Ok(());
+        }
+        Err(e) => return Err(app::bye_with_error(e)),
+    };
+    match update_item_if_unchanged(cx, &ts, &original, &edited).await {
+        Ok(None) => {
+            println!("No change to the item. Nothing is updated.");
+        }
+        Ok(Some(res)) => {
+            println!("Successfully updated an item in the table '{}'.", &ts.name);
+            println!(
+                "Updated item: {}",
+                serde_json::to_string(&convert_to_json(&res.attributes.unwrap())).unwrap()
+            );
+        }
+        Err(DyneinEditError::Update(e))
+            if e.as_service_error()
+                .map_or(false, |e| e.is_conditional_check_failed_exception()) =>
+        {
+            return Err(app::bye_with_error(
+                "The item has been changed since it was read. Nothing is updated. Please edit it again.",
+            ));
+        }
+        Err(DyneinEditError::Update(e)) => {
+            debug!("UpdateItem API call got an error -- {:?}", e);
+            return Err(app::bye_with_sdk_error(1, e));
         }
+        Err(e) => return Err(app::bye_with_error(e)),
+    }
+    Ok(())
+}
+
+/// Opens the item in $EDITOR (or $VISUAL) in the dynein format, and returns the edited item.
+/// Returns None if the editor is closed without saving the item.
+pub fn edit_in_editor(
+    ts: &app::TableSchema,
+    original: &HashMap<String, AttributeValue>,
+) -> Result<Option<HashMap<String, AttributeValue>>, DyneinEditError> {
+    let text = match Editor::new()
+        .extension(".json")
+        .edit(&output::dynein_format(original))
+        .map_err(DyneinEditError::Editor)?
+    {
+        Some(text) => text,
+        None => return Ok(None),
+    };
+    let edited = DyneinParser::new()
+        .parse_dynein_format(None, &text)
+        .map_err(DyneinEditError::InvalidItem)?;
+    if item_key(ts, &edited) != item_key(ts, original) {
+        return Err(DyneinEditError::KeyChanged);
+    }
+    Ok(Some(edited))
+}
+
+/// Calls UpdateItem API with SET and REMOVE actions which turn `original` into `edited`,
+/// on condition that the attributes to update have not been changed since `original` was read (see [`generate_unchanged_condition`]).
+/// Returns None without calling the API if there is no difference.
+pub async fn update_item_if_unchanged(
+    cx: &app::Context,
+    ts: &app::TableSchema,
+    original: &HashMap<String, AttributeValue>,
+    edited: &HashMap<String, AttributeValue>,
+) -> Result<Option<UpdateItemOutput>, DyneinEditError> {
+    let (set_actions, remove_actions) = generate_edit_actions(original, edited);
+    if set_actions.is_empty() && remove_actions.is_empty() {
+        return Ok(None);
     }
+    debug!(
+        "SET actions: {:?}, REMOVE actions: {:?}",
+        set_actions, remove_actions
+    );
+    // Actions are generated from edited items, so this doesn't fail unless an attribute name can't be quoted, e.g. an empty one.
+    let update_params = generate_edit_expressions(&set_actions, &remove_actions)
+        .map_err(DyneinEditError::InvalidItem)?;
+    let condition = generate_unchanged_condition(ts, original, edited);
+
+    let config = cx.effective_sdk_config().await;
+    let ddb = DynamoDbSdkClient::new(&config);
+
+    let started_at = Instant::now();
+    let res = ddb
+        .update_item()
+        .table_name(&ts.name)
+        .set_key(Some(item_key(ts, original)))
+        .set_update_expression(update_params.exp)
+        .condition_expression(condition.exp)
+        .set_expression_attribute_names(merge_maps(update_params.names, Some(condition.names)))
+        .set_expression_attribute_values(merge_maps(update_params.vals, Some(condition.vals)))
+        .return_values(ReturnValue::AllNew)
+        .set_return_consumed_capacity(cx.return_consumed_capacity())
+        .send()
+        .await
+        .map_err(DyneinEditError::Update)?;
+    cx.add_request_metrics(
+        RequestKind::Write,
+        started_at,
+        res.consumed_capacity(),
+        None,
+    );
+    Ok(Some(res))
 }
 
 // https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/WorkingWithItems.html#WorkingWithItems.AtomicCounters
@@ -489,20 +1069,96 @@ pub async fn atomic_counter(
     set_expression: Option<String>,
     remove_expression: Option<String>,
     target_attr: String,
-) {
+) -> Result<(), app::DyneinExit> {
     debug!("context: {:#?}", &cx);
     if set_expression.is_some() || remove_expression.is_some() {
-        error!("--atomic-counter option cannot be used with --set or --remove.");
-        std::process::exit(1);
+        return Err(app::bye_with_error(
+            "--atomic-counter option cannot be used with --set or --remove.",
+        ));
     };
     let atomic_counter_expression = format!("{} = {} + 1", target_attr, target_attr);
-    update_item(cx, pval, sval, Some(atomic_counter_expression), None).await;
+    update_item(cx, pval, sval, Some(atomic_counter_expression), None).await
 }
 
 /* =================================================
 Private functions
 ================================================= */
 
+/// Refreshes the cached schema if a request built from it was rejected by ValidationException,
+/// e.g. as the key schema of the index has been changed since the schema was cached.
+/// Returns the refreshed schema to retry the request once with, or None if the schema was not cached.
+async fn refresh_schema_on_validation_error(
+    cx: &app::Context,
+    e: &impl ProvideErrorMetadata,
+) -> Result<Option<app::TableSchema>, app::DyneinExit> {
+    if cx.overwritten_table_name.is_none() && e.code() == Some("ValidationException") {
+        debug!("the request may be built from a stale schema, refreshing it");
+        Ok(Some(app::refresh_table_schema(cx).await?))
+    } else {
+        Ok(None)
+    }
+}
+
+async fn send_query_page(
+    cx: &app::Context,
+    req: QueryFluentBuilder,
+) -> Result<QueryOutput, aws_sdk_dynamodb::error::SdkError<QueryError>> {
+    let started_at = Instant::now();
+    let res = req.send().await?;
+    cx.add_request_metrics(
+        RequestKind::Read,
+        started_at,
+        res.consumed_capacity(),
+        Some((res.scanned_count, res.count)),
+    );
+    Ok(res)
+}
+
+async fn build_query_page(
+    cx: &app::Context,
+    ts: &app::TableSchema,
+    params: &QueryParams,
+    esk: Option<HashMap<String, AttributeValue>>,
+    filter: Option<GeneratedFilterParams>,
+) -> Result<QueryFluentBuilder, DyneinQueryParamsError> {
+    let query_params: GeneratedQueryParams = generate_query_expressions(
+        ts,
+        &params.pval,
+        &params.sort_key_expression,
+        &params.index,
+        cx.should_strict_for_query(),
+    )?;
+    let projection_params: GeneratedScanParams =
+        generate_scan_expressions(ts, &params.attributes, params.keys_only);
+    let (filter_exp, filter_names, filter_vals) = match filter {
+        Some(f) => (Some(f.exp), f.names, f.vals),
+        None => (None, None, None),
+    };
+
+    let config = cx.effective_sdk_config().await;
+    let ddb = DynamoDbSdkClient::new(&config);
+
+    let req = ddb
+        .query()
+        .table_name(ts.name.to_string())
+        .set_index_name(params.index.clone())
+        .set_limit(params.limit)
+        .set_key_condition_expression(query_params.exp)
+        .set_projection_expression(projection_params.exp)
+        .set_filter_expression(filter_exp)
+        .set_expression_attribute_names(merge_maps(
+            merge_maps(query_params.names, projection_params.names),
+            filter_names,
+        ))
+        .set_expression_attribute_values(merge_maps(query_params.vals, filter_vals))
+        .consistent_read(params.consistent_read)
+        .set_scan_index_forward(params.descending.then_some(false))
+        .set_exclusive_start_key(esk)
+        .set_return_consumed_capacity(cx.return_consumed_capacity());
+    debug!("Request: {:#?}", req);
+    Ok(req)
+}
+
 /*
 Basically what this function does is to replace attribute names and values into DynamoDB style placeholders, i.e. "#ATTRNAME" and ":VALUE".
 And return UpdateExpression [1] string and supplementary names/values that are saved as HashMaps.
@@ -580,13 +1236,186 @@ fn generate_update_expressions(
     }
 }
 
+/// Generates UpdateExpression from SET and REMOVE actions. Both share ExpressionAttributeNames and ExpressionAttributeValues.
+fn generate_edit_expressions(
+    set_actions: &[String],
+    remove_actions: &[String],
+) -> Result<GeneratedUpdateParams, ParseError> {
+    let mut parser = DyneinParser::new();
+    let mut clauses: Vec<String> = vec![];
+    let mut result = None;
+    if !set_actions.is_empty() {
+        let set = parser.parse_set_action(&set_actions.join(", "))?;
+        clauses.push(format!("SET {}", set.get_expression()));
+        result = Some(set);
+    }
+    if !remove_actions.is_empty() {
+        let remove = parser.parse_remove_action(&remove_actions.join(", "))?;
+        clauses.push(format!("REMOVE {}", remove.get_expression()));
+        result = Some(remove);
+    }
+    // The parser accumulates names and values, so the last result has all of them.
+    let names = result.as_ref().map(|r| r.get_names()).unwrap_or_default();
+    let vals = result.as_ref().map(|r| r.get_values()).unwrap_or_default();
+    debug!("generated UpdateExpression: {:?}", clauses);
+    Ok(GeneratedUpdateParams {
+        exp: Some(clauses.join(" ")),
+        names: if names.is_empty() { None } else { Some(names) },
+        vals: if vals.is_empty() { None } else { Some(vals) },
+    })
+}
+
+/// Returns SET and REMOVE actions (in the syntax of `dy upd --set/--remove`) which turn `original` into `edited`.
+/// Only changed document paths are updated, as found by [`diff_items`].
+fn generate_edit_actions(
+    original: &HashMap<String, AttributeValue>,
+    edited: &HashMap<String, AttributeValue>,
+) -> (Vec<String>, Vec<String>) {
+    let mut set_actions: Vec<String> = vec![];
+    let mut remove_actions: Vec<String> = vec![];
+    for diff in diff_items(original, edited) {
+        // Attribute names are always quoted, as they may have special characters.
+        let path = diff.path_string(true);
+        match diff.after {
+            Some(value) => set_actions.push(format!("{} = {}", path, output::dynein_value(value))),
+            None => remove_actions.push(path),
+        }
+    }
+    (set_actions, remove_actions)
+}
+
+/// Compares two items and returns differences of attributes, ordered by their paths.
+/// Maps, and lists of the same length, are compared recursively so that differences are found at nested paths.
+pub fn diff_items<'a>(
+    before: &'a HashMap<String, AttributeValue>,
+    after: &'a HashMap<String, AttributeValue>,
+) -> Vec<AttributeDiff<'a>> {
+    let mut diffs: Vec<AttributeDiff> = vec![];
+    diff_maps(&[], before, after, &mut diffs);
+    diffs
+}
+
+fn diff_maps<'a>(
+    prefix: &[PathElement],
+    before: &'a HashMap<String, AttributeValue>,
+    after: &'a HashMap<String, AttributeValue>,
+    diffs: &mut Vec<AttributeDiff<'a>>,
+) {
+    let mut names: Vec<&String> = before.keys().chain(after.keys()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let mut path = prefix.to_vec();
+        path.push(PathElement::Attribute(name.to_owned()));
+        match (before.get(name), after.get(name)) {
+            (Some(b), Some(a)) => diff_attrvals(path, b, a, diffs),
+            (b, a) => diffs.push(AttributeDiff {
+                path,
+                before: b,
+                after: a,
+            }),
+        }
+    }
+}
+
+fn diff_attrvals<'a>(
+    path: Vec<PathElement>,
+    before: &'a AttributeValue,
+    after: &'a AttributeValue,
+    diffs: &mut Vec<AttributeDiff<'a>>,
+) {
+    match (before, after) {
+        (AttributeValue::M(b), AttributeValue::M(a)) => diff_maps(&path, b, a, diffs),
+        // Elements of lists of different lengths are shifted, so such lists differ as a whole.
+        (AttributeValue::L(b), AttributeValue::L(a)) if b.len() == a.len() => {
+            for (i, (b, a)) in b.iter().zip(a).enumerate() {
+                let mut path = path.clone();
+                path.push(PathElement::Index(i));
+                diff_attrvals(path, b, a, diffs);
+            }
+        }
+        _ if !attrval_eq(before, after) => diffs.push(AttributeDiff {
+            path,
+            before: Some(before),
+            after: Some(after),
+        }),
+        _ => (),
+    }
+}
+
+/// Unlike `==`, elements of sets are compared regardless of their order, which DynamoDB doesn't keep.
+fn attrval_eq(a: &AttributeValue, b: &AttributeValue) -> bool {
+    fn same_elements<T: PartialEq>(a: &[T], b: &[T]) -> bool {
+        a.len() == b.len() && a.iter().all(|x| b.contains(x))
+    }
+    match (a, b) {
+        (AttributeValue::Ss(a), AttributeValue::Ss(b)) => same_elements(a, b),
+        (AttributeValue::Ns(a), AttributeValue::Ns(b)) => same_elements(a, b),
+        (AttributeValue::Bs(a), AttributeValue::Bs(b)) => same_elements(a, b),
+        _ => a == b,
+    }
+}
+
+/// Generates a condition that the attributes touched by the change from `original` to `edited` have not been changed since `original` was read.
+/// The primary key and the top-level attributes which are changed or removed must be equal to `original`,
+/// and the attributes which are added must not exist, so that a concurrent change of them (or a deletion of the item) fails the update.
+/// Other attributes are not compared, which keeps the expression small for wide items. Attributes are compared in the order of names.
+fn generate_unchanged_condition(
+    ts: &app::TableSchema,
+    original: &HashMap<String, AttributeValue>,
+    edited: &HashMap<String, AttributeValue>,
+) -> GeneratedConditionParams {
+    let mut touched: Vec<String> = diff_items(original, edited)
+        .into_iter()
+        .filter_map(|diff| match diff.path.into_iter().next() {
+            Some(PathElement::Attribute(name)) => Some(name),
+            _ => None,
+        })
+        .chain(item_key(ts, original).into_keys())
+        .collect();
+    touched.sort();
+    touched.dedup();
+
+    let mut conditions: Vec<String> = Vec::with_capacity(touched.len());
+    let mut names = HashMap::<String, String>::new();
+    let mut vals = HashMap::<String, AttributeValue>::new();
+    for (i, name) in touched.into_iter().enumerate() {
+        match original.get(&name) {
+            Some(attrval) => {
+                conditions.push(format!("#DYNEIN_COND{i} = :DYNEIN_COND{i}"));
+                vals.insert(format!(":DYNEIN_COND{}", i), attrval.to_owned());
+            }
+            None => conditions.push(format!("attribute_not_exists(#DYNEIN_COND{})", i)),
+        }
+        names.insert(format!("#DYNEIN_COND{}", i), name);
+    }
+    GeneratedConditionParams {
+        exp: conditions.join(" AND "),
+        names,
+        vals,
+    }
+}
+
+/// Returns the primary key(s) of the item, which identify the item in the table.
+pub fn item_key(
+    ts: &app::TableSchema,
+    item: &HashMap<String, AttributeValue>,
+) -> HashMap<String, AttributeValue> {
+    item.iter()
+        .filter(|(name, _)| {
+            **name == ts.pk.name || ts.sk.as_ref().map_or(false, |sk| **name == sk.name)
+        })
+        .map(|(name, attrval)| (name.to_owned(), attrval.to_owned()))
+        .collect()
+}
+
 // Without `--table/-t` option, `identify_target` utilizes table info stored in config file which is saved via `dy use` command.
 // With `--table/-t` option, `identify_target` retrieves primary key(s) info by calling DescribeTable API each time which would consumre additional time.
 fn identify_target(
     ts: &app::TableSchema,
     pval: &str,
     optional_sval: Option<&str>,
-) -> HashMap<String, AttributeValue> {
+) -> Result<HashMap<String, AttributeValue>, app::DyneinExit> {
     let mut target = HashMap::<String, AttributeValue>::new();
     target.insert(
         ts.pk.name.to_string(),
@@ -601,8 +1430,7 @@ fn identify_target(
                 build_attrval_scalar(&sk.kind.to_string(), sval),
             ),
             None => {
-                error!("Partition and Sort keys are given to identify an item, but table '{t}' uses Partition key only. Check `dy desc {t}`", t = &ts.name);
-                std::process::exit(1);
+                return Err(app::bye_with_error(format!("Partition and Sort keys are given to identify an item, but table '{t}' uses Partition key only. Check `dy desc {t}`", t = &ts.name)));
             }
         };
     }
@@ -610,7 +1438,7 @@ fn identify_target(
         "Generated primary key(s) to identify an item: {:?}",
         &target
     );
-    target
+    Ok(target)
 }
 
 // top 3 scalar types that can be used for primary keys.
@@ -683,6 +1511,40 @@ fn build_attrval_map(
     AttributeValue::M(mapval)
 }
 
+/// Builds a FilterExpression from the given expression and values.
+/// Attribute name placeholders (e.g. `#status`) are resolved to the attribute of the same name,
+/// and values of placeholders (e.g. `:min`) are given as a map in dynein format, e.g. `{":min": 100}`.
+pub fn generate_filter_expressions(
+    expression: &str,
+    values: &Option<String>,
+) -> Result<GeneratedFilterParams, DyneinQueryParamsError> {
+    let names: HashMap<String, String> = Regex::new(r"#([0-9A-Za-z_]+)")
+        .unwrap()
+        .captures_iter(expression)
+        .map(|c| (c[0].to_owned(), c[1].to_owned()))
+        .collect();
+
+    let vals: HashMap<String, AttributeValue> = match values {
+        Some(v) => DyneinParser::new()
+            .parse_dynein_format(None, v)
+            .map_err(DyneinQueryParamsError::InvalidFilterValues)?,
+        None => HashMap::new(),
+    };
+    for placeholder in Regex::new(r":[0-9A-Za-z_]+").unwrap().find_iter(expression) {
+        if !vals.contains_key(placeholder.as_str()) {
+            return Err(DyneinQueryParamsError::UndefinedFilterValue(
+                placeholder.as_str().to_owned(),
+            ));
+        }
+    }
+
+    Ok(GeneratedFilterParams {
+        exp: expression.to_owned(),
+        names: if names.is_empty() { None } else { Some(names) },
+        vals: if vals.is_empty() { None } else { Some(vals) },
+    })
+}
+
 /// Convert from serde_json::Value (standard JSON values) into DynamoDB style AttributeValue
 pub fn dispatch_jsonvalue_to_attrval(jv: &JsonValue, enable_set_inference: bool) -> AttributeValue {
     match jv {
@@ -761,7 +1623,7 @@ impl Serialize for AttributeValueWrapper {
 }
 
 /// `strip_items` calls `strip_item` for each item.
-fn strip_items(
+pub fn strip_items(
     items: &[HashMap<String, AttributeValue>],
 ) -> Vec<HashMap<String, serde_json::Value>> {
     items.iter().map(strip_item).collect()
@@ -785,7 +1647,7 @@ fn strip_items(
 /// to something like this:
 ///
 ///     { "pkA": { "S": "e0a170d9-5ce3-443b-bbce-d0d49c71d151" }
-fn strip_item(item: &HashMap<String, AttributeValue>) -> HashMap<String, serde_json::Value> {
+pub fn strip_item(item: &HashMap<String, AttributeValue>) -> HashMap<String, serde_json::Value> {
     item.iter()
         .map(|attr| {
             (
@@ -796,12 +1658,32 @@ fn strip_item(item: &HashMap<String, AttributeValue>) -> HashMap<String, serde_j
         .collect()
 }
 
+/// Converts an AttributeValue into DynamoDB JSON, e.g. {"N": "1"}. Unlike `attrval_to_jsonval`, types of values are kept.
+pub fn attrval_to_ddbjson(attrval: &AttributeValue) -> serde_json::Value {
+    serde_json::to_value(AttributeValueWrapper(attrval.to_owned())).unwrap()
+}
+
 impl From<key::Key> for AttributeDefinition {
     fn from(value: key::Key) -> Self {
         AttributeDefinition::new(value.name, value.kind)
     }
 }
 
+/// Merges two optional maps of expression attribute names/values, as Scan/Query accept only one map for all expressions.
+fn merge_maps<V>(
+    a: Option<HashMap<String, V>>,
+    b: Option<HashMap<String, V>>,
+) -> Option<HashMap<String, V>> {
+    match (a, b) {
+        (Some(mut a), Some(b)) => {
+            a.extend(b);
+            Some(a)
+        }
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
 fn generate_query_expressions(
     ts: &app::TableSchema,
     pval: &str,
@@ -960,11 +1842,11 @@ fn append_sort_key_expression(
 ///   thash       1582050565
 ///   tayoyo      1582000111
 ///   osaka       1583020931
-fn display_items_table(
+pub fn display_items_table(
     items: Vec<HashMap<String, AttributeValue>>,
     ts: &app::TableSchema,
-    selected_attributes: &Option<String>,
-    keys_only: bool,
+    columns: &[String],
+    wide: bool,
 ) {
     // Print no item message and return if items length is 0.
     if items.is_empty() {
@@ -972,64 +1854,134 @@ fn display_items_table(
         return;
     };
 
+    let (mut rows, key_count) = table_rows(items, ts, columns);
+    // Truncate non-key cells so that the table fits in the terminal, unless --wide is given.
+    if !wide {
+        let width = match Term::stdout().size_checked() {
+            Some((_, terminal_width)) => fitting_width(&rows, key_count, terminal_width as usize),
+            None => DEFAULT_CELL_WIDTH,
+        };
+        truncate_cells(&mut rows, key_count, width);
+    }
+    write_table(rows, key_count, io::stdout()).unwrap();
+}
+
+/// Lays out items in the table format which fits in the terminal width, e.g. for `dy tui`. The first line is the header.
+pub fn items_table_lines(
+    items: Vec<HashMap<String, AttributeValue>>,
+    ts: &app::TableSchema,
+    columns: &[String],
+    terminal_width: usize,
+) -> Vec<String> {
+    let (mut rows, key_count) = table_rows(items, ts, columns);
+    let width = fitting_width(&rows, key_count, terminal_width);
+    truncate_cells(&mut rows, key_count, width);
+    let mut table: Vec<u8> = vec![];
+    write_table(rows, key_count, &mut table).unwrap();
+    String::from_utf8_lossy(&table)
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Returns cells of the table format including the header, and the number of key columns which come first.
+fn table_rows(
+    items: Vec<HashMap<String, AttributeValue>>,
+    ts: &app::TableSchema,
+    columns: &[String],
+) -> (Vec<Vec<String>>, usize) {
     // build header - first, primary key(s). Even index, key(s) are always projected.
     // ref: https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/GSI.html#GSI.Projections
-    let mut header: Vec<&str> = vec![ts.pk.name.as_str()];
+    let mut header: Vec<String> = vec![ts.pk.name.clone()];
     if let Some(sk) = &ts.sk {
-        header.push(sk.name.as_str())
-    };
-
-    // build header - next, attribute names or aggregated "attributes" header, unless --keys-only flag is set.
-    if !keys_only {
-        if let Some(attrs) = selected_attributes {
-            header.extend(attrs.split(',').collect::<Vec<&str>>());
-        } else {
-            header.push("attributes")
-        };
+        header.push(sk.name.clone())
     };
+    let key_count = header.len();
+    // build header - next, non-key attributes (or document paths given by --columns).
+    header.extend(columns.iter().cloned());
     debug!("built header elements: {:?}", header);
 
-    let mut tw = TabWriter::new(io::stdout());
-    tw.write_all((header.join("\t") + "\n").as_bytes()).unwrap();
-
-    // `cells` is sth like: ["item1-pk\titem1-attr1\titem1-attr2", "item2-pk\titem2-attr1\titem2-attr2"]
-    let mut cells: Vec<String> = vec![]; // may be able to use with_capacity to initialize the vec.
+    // `rows` is sth like: [["item1-pk", "item1-attr1", "item1-attr2"], ["item2-pk", "item2-attr1", "item2-attr2"]]
+    let mut rows: Vec<Vec<String>> = Vec::with_capacity(items.len() + 1);
+    rows.push(header);
     for mut item in items {
-        let mut item_attributes = vec![];
-        // First, take primary key(s) of each item.
-        let x: Option<AttributeValue> = item.remove(&ts.pk.name);
+        let mut row = vec![attrval_to_cell_print(item.remove(&ts.pk.name))];
         if let Some(sk) = &ts.sk {
-            let y: Option<AttributeValue> = item.remove(&sk.name);
-            item_attributes.extend(vec![attrval_to_cell_print(x), attrval_to_cell_print(y)]);
-        } else {
-            item_attributes.extend(vec![attrval_to_cell_print(x)]);
+            row.push(attrval_to_cell_print(item.remove(&sk.name)));
         };
+        row.extend(
+            columns
+                .iter()
+                .map(|column| attrval_to_cell_print(item.remove(column))),
+        );
+        rows.push(row);
+    }
+    (rows, key_count)
+}
 
-        if !item.is_empty() {
-            if let Some(_attributes) = selected_attributes {
-                let attrs: Vec<&str> = _attributes.split(',').map(|x| x.trim()).collect();
-                for attr in attrs {
-                    let attrval: Option<AttributeValue> = item.get(attr).cloned();
-                    item_attributes.push(attrval_to_cell_print(attrval));
-                }
-            } else if !keys_only {
-                // print rest aggreated "attributes" column in JSON format.
-                let full = serde_json::to_string(&convert_to_json(&item)).unwrap();
-                let threshold: usize = 50;
-                if full.chars().count() > threshold {
-                    // NOTE: counting bytes slice doesn't work for multi-bytes strings
-                    let st: &String = &full.chars().take(threshold).collect();
-                    item_attributes.push(String::from(st) + "...");
-                } else {
-                    item_attributes.push(full);
-                }
+/// Returns the width of non-key cells with which the table fits in the terminal width.
+fn fitting_width(rows: &[Vec<String>], key_count: usize, terminal_width: usize) -> usize {
+    let widths = column_widths(rows);
+    let separators = 2 * (widths.len() - 1);
+    let key_widths: usize = widths[..key_count].iter().sum();
+    let budget = terminal_width.saturating_sub(key_widths + separators);
+    fitting_cell_width(&widths[key_count..], budget)
+}
+
+fn truncate_cells(rows: &mut [Vec<String>], key_count: usize, width: usize) {
+    for cell in rows
+        .iter_mut()
+        .flat_map(|row| row.iter_mut().skip(key_count))
+    {
+        if measure_text_width(cell) > width {
+            *cell = truncate_str(cell, width, "...").into_owned();
+        }
+    }
+}
+
+fn write_table(rows: Vec<Vec<String>>, key_count: usize, out: impl Write) -> io::Result<()> {
+    let mut tw = TabWriter::new(out);
+    for mut row in rows {
+        // Trailing empty cells are omitted, so that tabwriter doesn't pad them.
+        while row.len() > key_count && row.last().map_or(false, |c| c.is_empty()) {
+            row.pop();
+        }
+        tw.write_all((row.join("\t") + "\n").as_bytes())?;
+    }
+    tw.flush()
+}
+
+/// Returns the display width of each column, i.e. the width of the widest cell in the column.
+fn column_widths(rows: &[Vec<String>]) -> Vec<usize> {
+    let mut widths: Vec<usize> = vec![];
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            let width = measure_text_width(cell);
+            match widths.get_mut(i) {
+                Some(w) => *w = (*w).max(width),
+                None => widths.push(width),
             }
         }
-        cells.push(item_attributes.join("\t"));
     }
+    widths
+}
 
-    tw.write_all((cells.join("\n") + "\n").as_bytes()).unwrap();
-    tw.flush().unwrap();
+/// Returns the largest cell width with which the columns fit in the budget, i.e. the rest of the terminal width.
+/// Narrow columns keep their width and wide columns share the rest. The width is never smaller than MIN_CELL_WIDTH.
+fn fitting_cell_width(widths: &[usize], budget: usize) -> usize {
+    let mut sorted = widths.to_vec();
+    sorted.sort_unstable();
+    let mut budget = budget;
+    let mut rest = sorted.len();
+    for width in sorted {
+        if width * rest <= budget {
+            budget -= width;
+            rest -= 1;
+        } else {
+            return (budget / rest).max(MIN_CELL_WIDTH);
+        }
+    }
+    usize::MAX
 }
 
 /// This function takes Option<AttributeValue> and return string,
@@ -1050,7 +2002,11 @@ fn attrval_to_cell_print(optional_attrval: Option<AttributeValue>) -> String {
                 )
                 .unwrap(),
                 AttributeValue::Null(_) => String::from("null"),
-                _ => String::from("(snip)"), // B, BS, L, and M are not shown.
+                // Maps and lists are shown in JSON, which is truncated to fit the table.
+                AttributeValue::L(_) | AttributeValue::M(_) => {
+                    attrval_to_jsonval(&attrval).to_string()
+                }
+                _ => String::from("(snip)"), // B and BS are not shown.
             }
         }
     }
@@ -1073,6 +2029,24 @@ pub fn attrval_to_type(attrval: &AttributeValue) -> Option<String> {
     }
 }
 
+/// Returns the data type descriptor of DynamoDB JSON, e.g. "S" or "SS", which is shown by --show-types.
+/// https://docs.aws.amazon.com/amazondynamodb/latest/APIReference/API_AttributeValue.html
+pub fn attrval_to_type_descriptor(attrval: &AttributeValue) -> Option<&'static str> {
+    match attrval {
+        AttributeValue::S(_) => Some("S"),
+        AttributeValue::N(_) => Some("N"),
+        AttributeValue::B(_) => Some("B"),
+        AttributeValue::Bool(_) => Some("BOOL"),
+        AttributeValue::Null(_) => Some("NULL"),
+        AttributeValue::Ss(_) => Some("SS"),
+        AttributeValue::Ns(_) => Some("NS"),
+        AttributeValue::Bs(_) => Some("BS"),
+        AttributeValue::M(_) => Some("M"),
+        AttributeValue::L(_) => Some("L"),
+        _ => None,
+    }
+}
+
 /// This function takes items and returns values in multiple lines - one line for one item.
 pub fn convert_items_to_csv_lines(
     items: &[HashMap<String, AttributeValue>],
@@ -1121,10 +2095,12 @@ fn convert_item_to_csv_line(
     if keys_only {
     } else if let Some(attrs) = attributes_to_append {
         for attr /* String */ in attrs {
-            let attrval: &AttributeValue = item.iter().find(|x| x.0 == attr).expect("Specified attribute not found in the item.").1;
             line.push(',');
-            // NOTE: If special handling for complex data type is needed: `if let Some(_) = attrval.m {...`
-            line.push_str(&attrval_to_jsonval(attrval).to_string());
+            // An attribute missing in the item is an empty cell, as items may have different attributes.
+            if let Some(attrval) = item.get(attr) {
+                // NOTE: If special handling for complex data type is needed: `if let Some(_) = attrval.m {...`
+                line.push_str(&attrval_to_jsonval(attrval).to_string());
+            }
         }
     }
 
@@ -1158,7 +2134,7 @@ fn str_to_json_num(s: &str) -> JsonValue {
     }
 }
 
-fn attrval_to_jsonval(attrval: &AttributeValue) -> JsonValue {
+pub fn attrval_to_jsonval(attrval: &AttributeValue) -> JsonValue {
     let unsupported: &str = "<<<JSON output doesn't support this type attributes>>>";
     //  following list of if-else statements would be return value of this function.
     match attrval {
@@ -1188,6 +2164,60 @@ fn attrval_to_json_map(attrval_map: &HashMap<String, AttributeValue>) -> JsonVal
     serde_json::to_value(result).unwrap()
 }
 
+/// Scan and Query show items in a table by default, but in JSON with --show-types as types are annotated only in JSON and YAML.
+fn default_items_format(show_types: bool) -> OutputFormat {
+    if show_types {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Table
+    }
+}
+
+/// Generate `ProjectionExpression` from document paths given by --columns, e.g. `address.city,tags[0]`.
+/// Primary key(s) are added to the expression unless they're given, as dynein always shows them.
+/// Placeholders are named `#DYNEIN_COLUMN*` so as not to collide with ones of KeyConditionExpression of Query.
+fn generate_column_expressions(
+    ts: &app::TableSchema,
+    columns: &str,
+) -> Result<(Vec<DocumentPath>, GeneratedScanParams), DyneinQueryParamsError> {
+    let (paths, result) = DyneinParser::new()
+        .parse_projection(columns)
+        .map_err(DyneinQueryParamsError::InvalidColumns)?;
+    let placeholder = Regex::new(r"#DYNEIN_ATTRNAME(\d+)").unwrap();
+    let mut names: HashMap<String, String> = result
+        .get_names()
+        .into_iter()
+        .map(|(k, v)| (placeholder.replace(&k, "#DYNEIN_COLUMN$1").into_owned(), v))
+        .collect();
+    let mut returning_attributes: Vec<String> = vec![];
+    if !paths.iter().any(|p| p.is_attribute(&ts.pk.name)) {
+        returning_attributes.push(String::from("#DYNEIN_COLUMN_PKNAME"));
+        names.insert(String::from("#DYNEIN_COLUMN_PKNAME"), ts.pk.name.clone());
+    }
+    if let Some(sk) = &ts.sk {
+        if !paths.iter().any(|p| p.is_attribute(&sk.name)) {
+            returning_attributes.push(String::from("#DYNEIN_COLUMN_SKNAME"));
+            names.insert(String::from("#DYNEIN_COLUMN_SKNAME"), sk.name.clone());
+        }
+    }
+    returning_attributes.push(
+        placeholder
+            .replace_all(&result.get_expression(), "#DYNEIN_COLUMN$1")
+            .into_owned(),
+    );
+
+    let expression: String = returning_attributes.join(",");
+    debug!("generated ProjectionExpression: {}", &expression);
+    debug!("generated ExpressionAttributeNames: {:?}", &names);
+    Ok((
+        paths,
+        GeneratedScanParams {
+            exp: Some(expression),
+            names: Some(names),
+        },
+    ))
+}
+
 /// Generate `ProjectionExpression` expression string and supplementary ExpressionAttributeNames.
 /// If attributes = None and keys_only is false, returns GeneratedScanParams with Nones and Scan behaves as default.
 /// If you set keys_only to true, the expression contains only primary key(s).
@@ -1702,4 +2732,196 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_generate_filter_expressions() {
+        let actual = generate_filter_expressions(
+            "#price > :min AND #status = :s",
+            &Some(r#"{":min": 100, ":s": "active"}"#.to_owned()),
+        )
+        .unwrap();
+        assert_eq!(actual.exp, "#price > :min AND #status = :s");
+        assert_eq!(
+            actual.names,
+            Some(HashMap::from([
+                ("#price".to_owned(), "price".to_owned()),
+                ("#status".to_owned(), "status".to_owned()),
+            ]))
+        );
+        assert_eq!(
+            actual.vals,
+            Some(HashMap::from([
+                (":min".to_owned(), AttributeValue::N("100".to_owned())),
+                (":s".to_owned(), AttributeValue::S("active".to_owned())),
+            ]))
+        );
+
+        let actual = generate_filter_expressions("attribute_exists(deleted)", &None).unwrap();
+        assert_eq!(actual.names, None);
+        assert_eq!(actual.vals, None);
+
+        assert!(matches!(
+            generate_filter_expressions("price > :min", &None),
+            Err(DyneinQueryParamsError::UndefinedFilterValue(p)) if p == ":min"
+        ));
+        assert!(matches!(
+            generate_filter_expressions("price > :min", &Some("100".to_owned())),
+            Err(DyneinQueryParamsError::InvalidFilterValues(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate_column_expressions() {
+        let ts = app::TableSchema {
+            region: String::from("local"),
+            name: String::from("table"),
+            pk: key::Key {
+                name: String::from("pk"),
+                kind: key::KeyType::S,
+            },
+            sk: Some(key::Key {
+                name: String::from("sk"),
+                kind: key::KeyType::N,
+            }),
+            indexes: None,
+            mode: crate::ddb::table::Mode::OnDemand,
+            cached_at: None,
+        };
+        let (paths, actual) = generate_column_expressions(&ts, "address.city,tags[0],sk").unwrap();
+        assert_eq!(paths.len(), 3);
+        assert_eq!(
+            actual.exp,
+            Some(
+                "#DYNEIN_COLUMN_PKNAME,#DYNEIN_COLUMN0.#DYNEIN_COLUMN1,#DYNEIN_COLUMN2[0],#DYNEIN_COLUMN3"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            actual.names,
+            Some(HashMap::from([
+                ("#DYNEIN_COLUMN_PKNAME".to_owned(), "pk".to_owned()),
+                ("#DYNEIN_COLUMN0".to_owned(), "address".to_owned()),
+                ("#DYNEIN_COLUMN1".to_owned(), "city".to_owned()),
+                ("#DYNEIN_COLUMN2".to_owned(), "tags".to_owned()),
+                ("#DYNEIN_COLUMN3".to_owned(), "sk".to_owned()),
+            ]))
+        );
+
+        assert!(matches!(
+            generate_column_expressions(&ts, "tags[]"),
+            Err(DyneinQueryParamsError::InvalidColumns(_))
+        ));
+    }
+
+    #[test]
+    fn test_fitting_cell_width() {
+        // All columns fit.
+        assert_eq!(fitting_cell_width(&[5, 10], 20), usize::MAX);
+        // The narrow column keeps its width and the wide ones share the rest.
+        assert_eq!(fitting_cell_width(&[5, 100, 100], 45), 20);
+        // Columns are not narrower than MIN_CELL_WIDTH even if they don't fit.
+        assert_eq!(fitting_cell_width(&[100, 100], 4), MIN_CELL_WIDTH);
+        assert_eq!(fitting_cell_width(&[], 0), usize::MAX);
+    }
+
+    #[test]
+    fn test_generate_unchanged_condition() {
+        let ts = app::TableSchema {
+            region: String::from("local"),
+            name: String::from("table"),
+            pk: key::Key {
+                name: String::from("pk"),
+                kind: key::KeyType::S,
+            },
+            sk: None,
+            indexes: None,
+            mode: crate::ddb::table::Mode::OnDemand,
+            cached_at: None,
+        };
+        let parser = DyneinParser::new();
+        let original = parser
+            .parse_dynein_format(
+                None,
+                r#"{"pk": "a", "count": 1, "m": {"k": 1}, "gone": true, "same": "x"}"#,
+            )
+            .unwrap();
+        let edited = parser
+            .parse_dynein_format(
+                None,
+                r#"{"pk": "a", "count": 1, "m": {"k": 2}, "added": "y", "same": "x"}"#,
+            )
+            .unwrap();
+        let actual = generate_unchanged_condition(&ts, &original, &edited);
+        // Untouched attributes (count and same) are not compared.
+        assert_eq!(
+            actual.exp,
+            "attribute_not_exists(#DYNEIN_COND0) AND #DYNEIN_COND1 = :DYNEIN_COND1 AND #DYNEIN_COND2 = :DYNEIN_COND2 AND #DYNEIN_COND3 = :DYNEIN_COND3"
+        );
+        assert_eq!(
+            actual.names,
+            HashMap::from([
+                ("#DYNEIN_COND0".to_owned(), "added".to_owned()),
+                ("#DYNEIN_COND1".to_owned(), "gone".to_owned()),
+                ("#DYNEIN_COND2".to_owned(), "m".to_owned()),
+                ("#DYNEIN_COND3".to_owned(), "pk".to_owned()),
+            ])
+        );
+        assert_eq!(
+            actual.vals,
+            HashMap::from([
+                (":DYNEIN_COND1".to_owned(), AttributeValue::Bool(true)),
+                (":DYNEIN_COND2".to_owned(), original["m"].clone()),
+                (
+                    ":DYNEIN_COND3".to_owned(),
+                    AttributeValue::S("a".to_owned())
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_generate_edit_actions() {
+        let parser = DyneinParser::new();
+        let original = parser
+            .parse_dynein_format(
+                None,
+                r#"{"pk": "a", "gone": 1, "same": "x", "m": {"k": 1, "old": true}, "l": [1, {"n": 2}], "grow": [1]}"#,
+            )
+            .unwrap();
+        let edited = parser
+            .parse_dynein_format(
+                None,
+                r#"{"pk": "a", "same": "x", "m": {"k": 2, "new": <<"s">>}, "l": [1, {"n": 3}], "grow": [1, 2], "a b": null}"#,
+            )
+            .unwrap();
+        let (set_actions, remove_actions) = generate_edit_actions(&original, &edited);
+        assert_eq!(
+            set_actions,
+            vec![
+                "`a b` = null",
+                "`grow` = [\n  1,\n  2\n]",
+                "`l`[1].`n` = 3",
+                "`m`.`k` = 2",
+                "`m`.`new` = <<\"s\">>",
+            ]
+        );
+        assert_eq!(remove_actions, vec!["`gone`", "`m`.`old`"]);
+        assert_eq!(
+            generate_edit_actions(&original, &original),
+            (vec![], vec![])
+        );
+
+        // The actions are accepted by the parser, and share placeholders.
+        let actual = generate_edit_expressions(&set_actions, &remove_actions).unwrap();
+        assert_eq!(
+            actual.exp.unwrap(),
+            "SET #DYNEIN_ATTRNAME0=:DYNEIN_ATTRVAL0,#DYNEIN_ATTRNAME1=:DYNEIN_ATTRVAL1,\
+            #DYNEIN_ATTRNAME2[1].#DYNEIN_ATTRNAME3=:DYNEIN_ATTRVAL2,\
+            #DYNEIN_ATTRNAME4.#DYNEIN_ATTRNAME5=:DYNEIN_ATTRVAL3,\
+            #DYNEIN_ATTRNAME4.#DYNEIN_ATTRNAME6=:DYNEIN_ATTRVAL4 \
+            REMOVE #DYNEIN_ATTRNAME7,#DYNEIN_ATTRNAME4.#DYNEIN_ATTRNAME8"
+        );
+        assert_eq!(actual.names.unwrap().len(), 9);
+        assert_eq!(actual.vals.unwrap().len(), 5);
+    }
 }
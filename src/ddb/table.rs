@@ -21,9 +21,9 @@ use aws_sdk_dynamodb::types::{
     ScalarAttributeType, StreamSpecification, TableDescription,
 };
 use chrono::DateTime;
-use log::error;
 
 use crate::ddb::key;
+use crate::output::OutputFormat;
 
 /* =================================================
 struct / enum / const
@@ -87,6 +87,11 @@ struct PrintSecondaryIndex {
 /// Receives region (just to show in one line for reference) and TableDescription,
 /// print them in readable YAML format. NOTE: '~' representes 'null' or 'no value' in YAML syntax.
 pub fn print_table_description(region: &str, desc: &TableDescription) {
+    print_table_description_in(region, desc, OutputFormat::Yaml)
+}
+
+/// Prints the table description in the format, i.e. YAML, JSON or JSON Lines (JSON in a line).
+pub fn print_table_description_in(region: &str, desc: &TableDescription, format: OutputFormat) {
     let attr_defs = desc.attribute_definitions.as_ref().unwrap();
     let mode = extract_mode(&desc.billing_mode_summary);
 
@@ -112,10 +117,30 @@ pub fn print_table_description(region: &str, desc: &TableDescription) {
         count: desc.item_count.unwrap(),
         created_at: epoch_to_rfc3339(desc.creation_date_time.unwrap().as_secs_f64()),
     };
-    println!("{}", serde_yaml::to_string(&print_table).unwrap());
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&print_table).unwrap()),
+        OutputFormat::Jsonl => println!("{}", serde_json::to_string(&print_table).unwrap()),
+        _ => println!("{}", serde_yaml::to_string(&print_table).unwrap()),
+    }
+}
+
+/// Validates key definitions passed via --keys option, e.g. `myPk,S mySk,N`, and returns the error message if invalid.
+pub fn validate_key_definitions(given_keys: &[String]) -> Result<(), String> {
+    if given_keys.is_empty() || given_keys.len() >= 3 {
+        return Err(String::from(
+            "You should pass one or two key definitions with --keys option",
+        ));
+    };
+    match given_keys.iter().find(|k| k.split(',').count() >= 3) {
+        Some(key_str) => Err(format!(
+            "Invalid format for --keys option: '{}'. Valid format is '--keys myPk,S mySk,N'",
+            key_str
+        )),
+        None => Ok(()),
+    }
 }
 
-/// Using Vec of String which is passed via command line,
+/// Using Vec of String which is passed via command line (and validated by `validate_key_definitions`),
 /// generate KeySchemaElement(s) & AttributeDefinition(s), that are essential information to create DynamoDB tables or GSIs.
 pub fn generate_essential_key_definitions(
     given_keys: &[String],
@@ -124,13 +149,6 @@ pub fn generate_essential_key_definitions(
     let mut attribute_definitions: Vec<AttributeDefinition> = vec![];
     for (key_id, key_str) in given_keys.iter().enumerate() {
         let key_and_type = key_str.split(',').collect::<Vec<&str>>();
-        if key_and_type.len() >= 3 {
-            error!(
-                "Invalid format for --keys option: '{}'. Valid format is '--keys myPk,S mySk,N'",
-                &key_str
-            );
-            std::process::exit(1);
-        }
 
         // assumes first given key is Partition key, and second given key is Sort key (if any).
         key_schema.push(
@@ -0,0 +1,478 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// This module compares items of two tables, or a table and an export file, for `dy diff`.
+
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use super::app::{self, TableLocation};
+use super::data;
+use super::output::OutputFormat;
+use super::transfer;
+
+/* =================================================
+struct / enum / const
+================================================= */
+
+type Item = HashMap<String, AttributeValue>;
+
+/// Differences between items of the source and the target, printed by `dy diff`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DiffReport {
+    source: String,
+    target: String,
+    summary: DiffSummary,
+    /// Items only in the target.
+    added: Vec<ItemEntry>,
+    /// Items only in the source.
+    removed: Vec<ItemEntry>,
+    changed: Vec<ChangedItem>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+struct DiffSummary {
+    same: usize,
+    added: usize,
+    removed: usize,
+    changed: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct ItemEntry {
+    key: JsonValue,
+    item: JsonValue,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct ChangedItem {
+    key: JsonValue,
+    attributes: Vec<ChangedAttribute>,
+}
+
+/// A changed attribute. `source` (or `target`) is omitted if the attribute doesn't exist in it.
+#[derive(Serialize, Debug, PartialEq)]
+struct ChangedAttribute {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<JsonValue>,
+}
+
+/// Matches items of the target with items of the source by their primary keys.
+/// Items of the source are kept in memory until the target is compared.
+struct ItemMatcher {
+    pk: String,
+    sk: Option<String>,
+    source: HashMap<String /* key in DynamoDB JSON */, (JsonValue, Item)>,
+    summary: DiffSummary,
+    added: Vec<(String, ItemEntry)>,
+    changed: Vec<(String, ChangedItem)>,
+}
+
+impl ItemMatcher {
+    fn new(ts: &app::TableSchema) -> ItemMatcher {
+        ItemMatcher {
+            pk: ts.pk.name.to_owned(),
+            sk: ts.sk.as_ref().map(|sk| sk.name.to_owned()),
+            source: HashMap::new(),
+            summary: DiffSummary::default(),
+            added: vec![],
+            changed: vec![],
+        }
+    }
+
+    /// Returns the primary key of the item to match items with, and the key in JSON to show, or None if the item lacks it.
+    /// Items are matched by raw values in DynamoDB JSON, as different numbers (or binaries) can be the same in JSON, e.g. large numbers.
+    fn key(&self, item: &Item) -> Option<(String, JsonValue)> {
+        let mut raw = vec![];
+        let mut key = serde_json::Map::new();
+        for name in std::iter::once(&self.pk).chain(&self.sk) {
+            let attrval = item.get(name)?;
+            raw.push(data::attrval_to_ddbjson(attrval));
+            key.insert(name.to_owned(), attrval_to_json(attrval));
+        }
+        Some((JsonValue::Array(raw).to_string(), JsonValue::Object(key)))
+    }
+
+    fn add_source(&mut self, item: Item) -> Option<()> {
+        let (key_str, key) = self.key(&item)?;
+        self.source.insert(key_str, (key, item));
+        Some(())
+    }
+
+    fn compare_target(&mut self, item: Item) -> Option<()> {
+        let (key_str, key) = self.key(&item)?;
+        match self.source.remove(&key_str) {
+            None => {
+                self.summary.added += 1;
+                let entry = ItemEntry {
+                    key,
+                    item: item_to_json(&item),
+                };
+                self.added.push((key_str, entry));
+            }
+            Some((_, source)) => {
+                let attributes: Vec<ChangedAttribute> = data::diff_items(&source, &item)
+                    .into_iter()
+                    .map(|diff| ChangedAttribute {
+                        path: diff.path_string(false),
+                        source: diff.before.map(attrval_to_json),
+                        target: diff.after.map(attrval_to_json),
+                    })
+                    .collect();
+                if attributes.is_empty() {
+                    self.summary.same += 1;
+                } else {
+                    self.summary.changed += 1;
+                    self.changed
+                        .push((key_str, ChangedItem { key, attributes }));
+                }
+            }
+        }
+        Some(())
+    }
+
+    fn finish(mut self, source: String, target: String) -> DiffReport {
+        // Items are ordered by their keys in JSON (and then by raw values) so that the output is stable.
+        let mut removed: Vec<(String, ItemEntry)> = self
+            .source
+            .into_iter()
+            .map(|(key_str, (key, item))| {
+                let entry = ItemEntry {
+                    key,
+                    item: item_to_json(&item),
+                };
+                (key_str, entry)
+            })
+            .collect();
+        removed.sort_by_cached_key(|(key_str, e)| (e.key.to_string(), key_str.to_owned()));
+        self.added
+            .sort_by_cached_key(|(key_str, e)| (e.key.to_string(), key_str.to_owned()));
+        self.changed
+            .sort_by_cached_key(|(key_str, c)| (c.key.to_string(), key_str.to_owned()));
+        self.summary.removed = removed.len();
+
+        DiffReport {
+            source,
+            target,
+            summary: self.summary,
+            added: self.added.into_iter().map(|(_, e)| e).collect(),
+            removed: removed.into_iter().map(|(_, e)| e).collect(),
+            changed: self.changed.into_iter().map(|(_, c)| c).collect(),
+        }
+    }
+}
+
+impl DiffReport {
+    fn has_differences(&self) -> bool {
+        self.summary.added + self.summary.removed + self.summary.changed > 0
+    }
+
+    /// Renders the report like `diff -u`: `-` for removed items, `+` for added ones, and `~` for changed ones.
+    fn to_human_readable(&self) -> String {
+        let mut lines: Vec<String> = vec![
+            format!("--- {} (source)", self.source),
+            format!("+++ {} (target)", self.target),
+        ];
+        for entry in &self.removed {
+            lines.push(format!("- {}", entry.key));
+        }
+        for entry in &self.added {
+            lines.push(format!("+ {}", entry.key));
+        }
+        let value =
+            |v: &Option<JsonValue>| v.as_ref().map_or(String::from("(none)"), |v| v.to_string());
+        for item in &self.changed {
+            lines.push(format!("~ {}", item.key));
+            for attribute in &item.attributes {
+                lines.push(format!(
+                    "    {}: {} -> {}",
+                    attribute.path,
+                    value(&attribute.source),
+                    value(&attribute.target)
+                ));
+            }
+        }
+        lines.push(format!(
+            "{} added, {} removed, {} changed, {} same",
+            self.summary.added, self.summary.removed, self.summary.changed, self.summary.same
+        ));
+        lines.join("\n")
+    }
+}
+
+/* =================================================
+Public functions
+================================================= */
+
+/// Compares items of the source table with items of the target table (or the export file), and prints differences.
+/// With `exit_code`, it fails with 1 if there's any difference.
+#[allow(clippy::too_many_arguments)]
+pub async fn diff(
+    cx: &app::Context,
+    source: TableLocation,
+    target: Option<TableLocation>,
+    file: Option<String>,
+    format: Option<String>,
+    compress: Option<String>,
+    exit_code: bool,
+) -> Result<(), app::DyneinExit> {
+    let source_cx = source.context(cx);
+    let ts = app::cached_table_schema(&source_cx).await?;
+    let source_label = source.resolve(cx).await.to_string();
+
+    let mut matcher = ItemMatcher::new(&ts);
+    let report = match (target, file) {
+        (Some(target), _) => {
+            let target_cx = target.context(cx);
+            let target_ts = app::cached_table_schema(&target_cx).await?;
+            if target_ts.pk.name != ts.pk.name
+                || target_ts.sk.as_ref().map(|sk| &sk.name) != ts.sk.as_ref().map(|sk| &sk.name)
+            {
+                return Err(app::bye_with_error(format!(
+                    "Items cannot be matched as primary keys of '{}' and '{}' are different.",
+                    source, target
+                )));
+            }
+            let target_label = target.resolve(cx).await.to_string();
+
+            scan_all(&source_cx, |item| matcher.add_source(item.clone())).await?;
+            scan_all(&target_cx, |item| matcher.compare_target(item.clone())).await?;
+            matcher.finish(source_label, target_label)
+        }
+        (None, Some(file)) => {
+            let items = transfer::read_export_file(&file, format.as_deref(), compress.as_deref())
+                .map_err(app::bye_with_error)?;
+            // Items of the table are compared as they're exported, as types like sets are lost in the file.
+            scan_all(&source_cx, |item| matcher.add_source(as_exported(item))).await?;
+            for item in items {
+                match data::dispatch_jsonvalue_to_attrval(&item, false) {
+                    AttributeValue::M(item) => matcher.compare_target(item),
+                    _ => None,
+                }
+                .ok_or_else(|| {
+                    app::bye_with_error(format!(
+                        "An item in '{}' doesn't have the primary key: {}",
+                        file, item
+                    ))
+                })?;
+            }
+            matcher.finish(source_label, file)
+        }
+        (None, None) => {
+            return Err(app::bye_with_error(
+                "Either a target table or --file is required.",
+            ))
+        }
+    };
+
+    match cx.output_format(
+        &[OutputFormat::Table, OutputFormat::Json, OutputFormat::Jsonl],
+        OutputFormat::Table,
+    ) {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+        OutputFormat::Jsonl => println!("{}", serde_json::to_string(&report).unwrap()),
+        _ => println!("{}", report.to_human_readable()),
+    }
+    if exit_code && report.has_differences() {
+        return Err(app::DyneinExit(1));
+    }
+    Ok(())
+}
+
+/* =================================================
+Private functions
+================================================= */
+
+/// Scans all items of the table. Items without the primary key of the table (which doesn't happen) are skipped.
+async fn scan_all(
+    cx: &app::Context,
+    mut observe: impl FnMut(&Item) -> Option<()>,
+) -> Result<(), app::DyneinExit> {
+    data::scan_sample(cx, None, None, |item| {
+        observe(item);
+    })
+    .await?;
+    Ok(())
+}
+
+/// Converts an item in the same way as `dy export` writes it and `dy import` reads it.
+fn as_exported(item: &Item) -> Item {
+    let exported = data::attrval_to_jsonval(&AttributeValue::M(item.to_owned()));
+    match data::dispatch_jsonvalue_to_attrval(&exported, false) {
+        AttributeValue::M(item) => item,
+        _ => unreachable!("an item should be converted into a map"),
+    }
+}
+
+fn item_to_json(item: &Item) -> JsonValue {
+    attrval_to_json(&AttributeValue::M(item.to_owned()))
+}
+
+/// Converts a value into JSON for the report. Unlike JSON output of items, binaries are shown in base64 so that they can be compared.
+fn attrval_to_json(attrval: &AttributeValue) -> JsonValue {
+    let base64 = |b: &aws_sdk_dynamodb::primitives::Blob| {
+        JsonValue::from(general_purpose::STANDARD.encode(b.as_ref()))
+    };
+    match attrval {
+        AttributeValue::B(b) => base64(b),
+        AttributeValue::Bs(bs) => bs.iter().map(base64).collect(),
+        AttributeValue::L(l) => l.iter().map(attrval_to_json).collect(),
+        AttributeValue::M(m) => {
+            // serde_json::Map sorts keys, so that the output is stable.
+            JsonValue::Object(
+                m.iter()
+                    .map(|(k, v)| (k.to_owned(), attrval_to_json(v)))
+                    .collect(),
+            )
+        }
+        _ => data::attrval_to_jsonval(attrval),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddb::{key, table};
+    use crate::parser::DyneinParser;
+
+    fn item(s: &str) -> Item {
+        DyneinParser::new().parse_dynein_format(None, s).unwrap()
+    }
+
+    #[test]
+    fn test_diff_items() {
+        let ts = app::TableSchema {
+            region: String::from("local"),
+            name: String::from("table"),
+            pk: key::Key {
+                name: String::from("pk"),
+                kind: key::KeyType::S,
+            },
+            sk: Some(key::Key {
+                name: String::from("sk"),
+                kind: key::KeyType::N,
+            }),
+            indexes: None,
+            mode: table::Mode::OnDemand,
+            cached_at: None,
+        };
+        let mut matcher = ItemMatcher::new(&ts);
+        for s in [
+            r#"{"pk": "a", "sk": 1, "tags": <<"x", "y">>}"#,
+            r#"{"pk": "b", "sk": 1, "address": {"city": "Tokyo"}, "name": "x"}"#,
+            r#"{"pk": "c", "sk": 1}"#,
+        ] {
+            matcher.add_source(item(s)).unwrap();
+        }
+        for s in [
+            // Sets are equal regardless of the order of elements.
+            r#"{"pk": "a", "sk": 1, "tags": <<"y", "x">>}"#,
+            r#"{"pk": "b", "sk": 1, "address": {"city": "Osaka"}, "bin": b"\x01"}"#,
+            r#"{"pk": "d", "sk": 2}"#,
+        ] {
+            matcher.compare_target(item(s)).unwrap();
+        }
+        assert_eq!(matcher.compare_target(item(r#"{"pk": "e"}"#)), None);
+
+        let report = matcher.finish(String::from("s@local"), String::from("t@local"));
+        assert_eq!(
+            report.summary,
+            DiffSummary {
+                same: 1,
+                added: 1,
+                removed: 1,
+                changed: 1,
+            }
+        );
+        assert_eq!(
+            report.to_human_readable(),
+            r#"--- s@local (source)
++++ t@local (target)
+- {"pk":"c","sk":1}
++ {"pk":"d","sk":2}
+~ {"pk":"b","sk":1}
+    address.city: "Tokyo" -> "Osaka"
+    bin: (none) -> "AQ=="
+    name: "x" -> (none)
+1 added, 1 removed, 1 changed, 1 same"#
+        );
+        assert_eq!(
+            serde_json::to_value(&report.changed).unwrap(),
+            serde_json::json!([{
+                "key": {"pk": "b", "sk": 1},
+                "attributes": [
+                    {"path": "address.city", "source": "Tokyo", "target": "Osaka"},
+                    {"path": "bin", "target": "AQ=="},
+                    {"path": "name", "source": "x"},
+                ],
+            }])
+        );
+        assert!(report.has_differences());
+    }
+
+    #[test]
+    fn test_diff_large_number_keys() {
+        let ts = app::TableSchema {
+            region: String::from("local"),
+            name: String::from("table"),
+            pk: key::Key {
+                name: String::from("pk"),
+                kind: key::KeyType::N,
+            },
+            sk: None,
+            indexes: None,
+            mode: table::Mode::OnDemand,
+            cached_at: None,
+        };
+        // The numbers are the same in JSON (as f64), but they are different items.
+        let mut matcher = ItemMatcher::new(&ts);
+        matcher
+            .add_source(item(r#"{"pk": 12345678901234567890123}"#))
+            .unwrap();
+        matcher
+            .add_source(item(r#"{"pk": 12345678901234567890124}"#))
+            .unwrap();
+        matcher
+            .compare_target(item(r#"{"pk": 12345678901234567890124}"#))
+            .unwrap();
+        let report = matcher.finish(String::from("s@local"), String::from("t@local"));
+        assert_eq!(
+            report.summary,
+            DiffSummary {
+                same: 1,
+                added: 0,
+                removed: 1,
+                changed: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_as_exported() {
+        // Sets become lists in an export file, so items of the table are compared as lists.
+        assert_eq!(
+            as_exported(&item(r#"{"pk": "a", "tags": <<"x">>, "n": 1.50}"#)),
+            item(r#"{"pk": "a", "tags": ["x"], "n": 1.5}"#)
+        );
+    }
+}
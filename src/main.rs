@@ -16,7 +16,9 @@
 
 use crate::data::QueryParams;
 use brotli::Decompressor;
-use std::io::{stdout, Cursor};
+use std::io::{stdin, stdout, Cursor, IsTerminal};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use log::debug;
 use std::error::Error;
@@ -30,12 +32,19 @@ mod app;
 mod batch;
 mod bootstrap;
 mod cmd;
+mod columnar;
 mod control;
+mod copy;
 mod data;
 mod ddb;
+mod diff;
+mod output;
 mod parser;
+mod schema;
 mod shell;
+mod stats;
 mod transfer;
+mod tui;
 
 /* =================================================
    helper functions
@@ -46,9 +55,9 @@ async fn dispatch(context: &mut app::Context, subcommand: cmd::Sub) -> Result<()
         cmd::Sub::Admin { grandchild } => match grandchild {
             cmd::AdminSub::List { all_regions } => {
                 if all_regions {
-                    control::list_tables_all_regions(context).await
+                    control::list_tables_all_regions(context).await?
                 } else {
-                    control::list_tables(context, None).await
+                    control::list_tables(context, None).await?
                 }
             }
             cmd::AdminSub::Desc {
@@ -58,18 +67,18 @@ async fn dispatch(context: &mut app::Context, subcommand: cmd::Sub) -> Result<()
             } => {
                 context.output = output;
                 if all_tables {
-                    control::describe_all_tables(context).await
+                    control::describe_all_tables(context).await?
                 } else {
-                    control::describe_table(context, target_table_to_desc).await
+                    control::describe_table(context, target_table_to_desc).await?
                 }
             }
             cmd::AdminSub::Create { target_type } => match target_type {
                 cmd::CreateSub::Table {
                     new_table_name,
                     keys,
-                } => control::create_table(context, new_table_name, keys).await,
+                } => control::create_table(context, new_table_name, keys).await?,
                 cmd::CreateSub::Index { index_name, keys } => {
-                    control::create_index(context, index_name, keys).await
+                    control::create_index(context, index_name, keys).await?
                 }
             },
             cmd::AdminSub::Update { target_type } => match target_type {
@@ -78,13 +87,13 @@ async fn dispatch(context: &mut app::Context, subcommand: cmd::Sub) -> Result<()
                     mode,
                     wcu,
                     rcu,
-                } => control::update_table(context, table_name_to_update, mode, wcu, rcu).await,
+                } => control::update_table(context, table_name_to_update, mode, wcu, rcu).await?,
             },
             cmd::AdminSub::Delete { target_type } => match target_type {
                 cmd::DeleteSub::Table {
                     table_name_to_delete,
                     yes,
-                } => control::delete_table(context, table_name_to_delete, yes).await,
+                } => control::delete_table(context, table_name_to_delete, yes).await?,
             },
             cmd::AdminSub::Apply { dev } => {
                 if dev {
@@ -100,6 +109,9 @@ async fn dispatch(context: &mut app::Context, subcommand: cmd::Sub) -> Result<()
             consistent_read,
             attributes,
             keys_only,
+            columns,
+            wide,
+            show_types,
             limit,
             output,
         } => {
@@ -109,10 +121,13 @@ async fn dispatch(context: &mut app::Context, subcommand: cmd::Sub) -> Result<()
                 index,
                 consistent_read,
                 &attributes,
+                &columns,
                 keys_only,
+                wide,
+                show_types,
                 limit,
             )
-            .await
+            .await?
         }
         cmd::Sub::Query {
             pval,
@@ -122,6 +137,9 @@ async fn dispatch(context: &mut app::Context, subcommand: cmd::Sub) -> Result<()
             attributes,
             consistent_read,
             keys_only,
+            columns,
+            wide,
+            show_types,
             descending,
             strict,
             non_strict,
@@ -143,20 +161,24 @@ async fn dispatch(context: &mut app::Context, subcommand: cmd::Sub) -> Result<()
                     attributes,
                     keys_only,
                 },
+                &columns,
+                wide,
+                show_types,
             )
-            .await
+            .await?
         }
         cmd::Sub::Get {
             pval,
             sval,
             consistent_read,
+            show_types,
             output,
         } => {
             context.output = output;
-            data::get_item(context, pval, sval, consistent_read).await
+            data::get_item(context, pval, sval, consistent_read, show_types).await?
         }
-        cmd::Sub::Put { pval, sval, item } => data::put_item(context, pval, sval, item).await,
-        cmd::Sub::Del { pval, sval } => data::delete_item(context, pval, sval).await,
+        cmd::Sub::Put { pval, sval, item } => data::put_item(context, pval, sval, item).await?,
+        cmd::Sub::Del { pval, sval } => data::delete_item(context, pval, sval).await?,
         cmd::Sub::Upd {
             pval,
             sval,
@@ -165,19 +187,20 @@ async fn dispatch(context: &mut app::Context, subcommand: cmd::Sub) -> Result<()
             atomic_counter,
         } => {
             if let Some(target) = atomic_counter {
-                data::atomic_counter(context, pval, sval, set, remove, target).await;
+                data::atomic_counter(context, pval, sval, set, remove, target).await?;
             } else {
-                data::update_item(context, pval, sval, set, remove).await;
+                data::update_item(context, pval, sval, set, remove).await?;
             }
         }
+        cmd::Sub::Edit { pval, sval } => data::edit_item(context, pval, sval).await?,
         cmd::Sub::Bwrite { puts, dels, input } => {
             batch::batch_write_item(context, puts, dels, input).await?
         }
         cmd::Sub::List { all_regions } => {
             if all_regions {
-                control::list_tables_all_regions(context).await
+                control::list_tables_all_regions(context).await?
             } else {
-                control::list_tables(context, None).await
+                control::list_tables(context, None).await?
             }
         }
         cmd::Sub::Desc {
@@ -187,9 +210,9 @@ async fn dispatch(context: &mut app::Context, subcommand: cmd::Sub) -> Result<()
         } => {
             context.output = output;
             if all_tables {
-                control::describe_all_tables(context).await
+                control::describe_all_tables(context).await?
             } else {
-                control::describe_table(context, target_table_to_desc).await
+                control::describe_table(context, target_table_to_desc).await?
             }
         }
         cmd::Sub::Use {
@@ -201,12 +224,42 @@ async fn dispatch(context: &mut app::Context, subcommand: cmd::Sub) -> Result<()
                     "{}",
                     serde_yaml::to_string(&app::load_or_touch_cache_file(true)?)?
                 );
-                println!(
-                    "{}",
-                    serde_yaml::to_string(&app::load_or_touch_config_file(true)?)?
-                );
+                println!("{}", app::dump_config()?);
             }
             cmd::ConfigSub::Clear => app::remove_dynein_files()?,
+            cmd::ConfigSub::UseContext { name } => app::use_context(context, name)?,
+            cmd::ConfigSub::GetContexts => app::list_contexts(context),
+            cmd::ConfigSub::Get { key } => {
+                let value = app::get_config_value(context, &key).map_err(app::bye_with_error)?;
+                print!("{}", serde_yaml::to_string(&value)?);
+            }
+            cmd::ConfigSub::Cache { action } => match action {
+                cmd::CacheSub::Refresh { table } => {
+                    let cx = match table {
+                        Some(table) => context.clone().with_table(&table),
+                        None => context.clone(),
+                    };
+                    let schema = app::refresh_table_schema(&cx).await?;
+                    println!(
+                        "Refreshed the cached schema of the table '{}' ({}).",
+                        schema.name, schema.region
+                    );
+                    context.cache = Some(app::load_or_touch_cache_file(true)?);
+                }
+                cmd::CacheSub::Clear { table } => {
+                    match app::remove_from_table_cache(context, table.as_deref()).await? {
+                        true => println!("Removed the cached schema."),
+                        false => println!("No cached schema is found."),
+                    }
+                }
+            },
+            cmd::ConfigSub::Set { .. }
+            | cmd::ConfigSub::Unset { .. }
+            | cmd::ConfigSub::Validate => {
+                edit_config(&grandchild)?;
+                // Reload the config so that the rest of a shell session uses the new config.
+                context.config = Some(app::load_layered_config()?);
+            }
         },
 
         cmd::Sub::Bootstrap { list, sample } => {
@@ -222,12 +275,105 @@ async fn dispatch(context: &mut app::Context, subcommand: cmd::Sub) -> Result<()
             keys_only,
             output_file,
             format,
-        } => transfer::export(context, attributes, keys_only, output_file, format).await?,
+            compress,
+            index,
+            pval,
+            sort_key_expression,
+            filter,
+            filter_values,
+            yes,
+        } => {
+            transfer::export(
+                context,
+                attributes,
+                keys_only,
+                output_file,
+                format,
+                compress,
+                transfer::ExportSource {
+                    index,
+                    pval,
+                    sort_key_expression,
+                    filter,
+                    filter_values,
+                },
+                yes,
+            )
+            .await?
+        }
         cmd::Sub::Import {
             input_file,
             format,
             enable_set_inference,
-        } => transfer::import(context, input_file, format, enable_set_inference).await?,
+            from_export_dir,
+            compress,
+            yes,
+        } => {
+            if let Some(export_dir) = from_export_dir {
+                transfer::import_from_export_dir(context, export_dir, yes).await?
+            } else {
+                let input_file =
+                    input_file.expect("--input-file is required without --from-export-dir");
+                transfer::import(
+                    context,
+                    input_file,
+                    format,
+                    enable_set_inference,
+                    compress,
+                    yes,
+                )
+                .await?
+            }
+        }
+        cmd::Sub::Diff {
+            source,
+            target,
+            file,
+            format,
+            compress,
+            exit_code,
+            output,
+        } => {
+            context.output = output;
+            diff::diff(context, source, target, file, format, compress, exit_code).await?
+        }
+        cmd::Sub::Copy {
+            from,
+            to,
+            create_table,
+            rename,
+            segments,
+            resume_file,
+        } => {
+            copy::copy(
+                context,
+                from,
+                to,
+                create_table,
+                rename,
+                segments,
+                resume_file,
+            )
+            .await?
+        }
+        cmd::Sub::Stats {
+            sample,
+            top,
+            index,
+            output,
+        } => {
+            context.output = output;
+            stats::stats(context, index, sample, top).await?
+        }
+        cmd::Sub::Schema { grandchild } => match grandchild {
+            cmd::SchemaSub::Infer {
+                sample,
+                index,
+                format,
+                name,
+            } => schema::infer(context, index, sample, format, name).await?,
+        },
+        cmd::Sub::Tui => tui::tui(context).await?,
         cmd::Sub::Backup { list, all_tables } => {
             if list {
                 control::list_backups(context, all_tables).await?
@@ -235,17 +381,161 @@ async fn dispatch(context: &mut app::Context, subcommand: cmd::Sub) -> Result<()
                 control::backup(
                     context, all_tables, /* all_tables is simply ignored for "backup" */
                 )
-                .await
+                .await?
             }
         }
         cmd::Sub::Restore {
             backup_name,
             restore_name,
-        } => control::restore(context, backup_name, restore_name).await,
+        } => control::restore(context, backup_name, restore_name).await?,
     }
     Ok(())
 }
 
+/// Runs config commands which edit or check config.yml. Returns false for other config commands.
+/// These commands don't use Context, so that they work even if config.yml is invalid and Context cannot be created.
+fn edit_config(sub: &cmd::ConfigSub) -> Result<bool, app::DyneinExit> {
+    match sub {
+        cmd::ConfigSub::Set { key, value } => {
+            app::set_config_value(key, value).map_err(app::bye_with_error)?
+        }
+        cmd::ConfigSub::Unset { key } => {
+            app::unset_config_value(key).map_err(app::bye_with_error)?
+        }
+        cmd::ConfigSub::Validate => {
+            let problems = app::validate_config_file().map_err(app::bye_with_error)?;
+            if !problems.is_empty() {
+                for (path, problem) in &problems {
+                    eprintln!("{}: {}", path, problem);
+                }
+                return Err(app::DyneinExit(1));
+            }
+            println!("The config file is valid.");
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Formats the elapsed time of a command and metrics of its requests, for --show-capacity and `\timing`.
+fn timing_report(started_at: Instant, metrics: &Mutex<app::RequestMetrics>) -> String {
+    format!(
+        "Time: {:.3} ms\n{}",
+        started_at.elapsed().as_secs_f64() * 1000.0,
+        metrics.lock().expect("metrics should not be poisoned")
+    )
+}
+
+/// Runs commands of the shell or a script until `exit` or the end of input, and returns the number of failed commands.
+/// With `stop_on_error`, it returns at the first failure.
+async fn run_shell(
+    context: &mut app::Context,
+    reader: &mut shell::ShellReader<'_>,
+    stop_on_error: bool,
+    timing: bool,
+) -> Result<usize, Box<dyn Error>> {
+    use shell::BuiltinCommands;
+    use shell::ShellInput::*;
+
+    reader.refresh_candidates(context, true).await;
+    let mut timing = timing;
+    let initial_output = context.default_output;
+    let mut failures = 0;
+    loop {
+        let prompt = shell::prompt(context).await;
+        let succeeded = match reader.read_line(&prompt)? {
+            Builtin(BuiltinCommands::Exit) => break,
+            Eof => break,
+            Builtin(BuiltinCommands::Use(table)) => {
+                // The table given to `use` takes precedence over --table given when the shell started.
+                context.overwritten_table_name = None;
+                let result = match app::use_cached_table(context, &table).await {
+                    Ok(true) => Ok(()),
+                    Ok(false) => {
+                        let child = cmd::Sub::Use {
+                            target_table_to_use: Some(table),
+                        };
+                        dispatch(context, child).await
+                    }
+                    Err(e) => Err(e.into()),
+                };
+                reader.refresh_candidates(context, false).await;
+                succeeded(result)
+            }
+            Builtin(BuiltinCommands::Region(region)) => {
+                context.overwritten_region = app::region_from_str(Some(region));
+                reader.refresh_candidates(context, true).await;
+                true
+            }
+            Builtin(BuiltinCommands::Timing(on)) => {
+                timing = on.unwrap_or(!timing);
+                println!("Timing is {}.", if timing { "on" } else { "off" });
+                true
+            }
+            Builtin(BuiltinCommands::SetOutput(output)) => {
+                context.default_output = output.or(initial_output);
+                true
+            }
+            Command(child) => {
+                // Tables can be changed only by these commands, so ListTables is called again only after them.
+                let tables_changed = matches!(
+                    *child,
+                    cmd::Sub::Admin { .. } | cmd::Sub::Bootstrap { .. } | cmd::Sub::Restore { .. }
+                );
+                if timing {
+                    context.metrics = Some(Arc::new(Mutex::new(app::RequestMetrics::default())));
+                }
+                let started_at = Instant::now();
+
+                debug!("context before execution of shell command: {:#?}", context);
+                let result = dispatch(context, *child).await;
+                debug!("context after execution of shell command: {:#?}", context);
+                // The command may have refreshed cached table schemas, e.g. expired ones.
+                if let Ok(cache) = app::load_or_touch_cache_file(true) {
+                    context.cache = Some(cache);
+                }
+
+                if let Some(metrics) = context.metrics.take() {
+                    print!("{}", timing_report(started_at, &metrics));
+                }
+                reader.refresh_candidates(context, tables_changed).await;
+                succeeded(result)
+            }
+            ParseError(_) => {
+                // read_line already printed the error
+                false
+            }
+        };
+
+        if !succeeded {
+            failures += 1;
+            // A line number helps to find the failed command in a script, but not in a terminal.
+            if !reader.is_interactive() {
+                eprintln!("Command at line {} failed.", reader.command_line_number());
+            }
+            if stop_on_error {
+                break;
+            }
+        }
+    }
+    Ok(failures)
+}
+
+/// Prints the error of a shell command unless the command has already printed it, and returns whether the command succeeded.
+/// A command which quits with status 0, e.g. when a confirmation is declined, is not a failure.
+fn succeeded(result: Result<(), Box<dyn Error>>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(e) => match app::exit_code(&*e) {
+            Some(code) => code == 0,
+            None => {
+                eprintln!("{}", e);
+                false
+            }
+        },
+    }
+}
+
 /* =================================================
    main() function
    =================================================
@@ -257,38 +547,69 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let c = cmd::initialize_from_args();
     debug!("Command details: {:?}", c);
 
+    if let Some(cmd::Sub::Config { grandchild }) = &c.child {
+        match edit_config(grandchild) {
+            Ok(true) => return Ok(()),
+            Ok(false) => (),
+            Err(e) => std::process::exit(e.0),
+        }
+    }
+
     // when --region <region-name e.g. ap-northeast-1>, use the region. when --region local, use DynamoDB local.
     // --region/--table option can be passed as a top-level or subcommand-level (i.e. global).
-    let mut context = app::Context::new(c.region, c.port, c.table)?;
+    let mut context = app::Context::new(c.region, c.port, c.table)
+        .unwrap_or_else(|e| std::process::exit(app::bye_with_error(e).0));
+    // Prompts would block (or consume piped input) when stdin is not a terminal, e.g. in CI or `dy --shell < commands.txt`.
+    context.non_interactive = c.non_interactive || !stdin().is_terminal();
+    context.default_output = c.output;
+    context.color = c.color;
+    context.overwritten_profile = c.profile;
+    if let Some(role_arn) = c.role_arn {
+        context.overwritten_assume_role = Some(app::AssumeRoleSetting {
+            role_arn,
+            external_id: c.external_id,
+            mfa_serial: c.mfa_serial,
+        });
+    }
     debug!("Initial command context: {:?}", &context);
 
     if let Some(child) = c.child {
         // subcommand
-        dispatch(&mut context, child).await?
-    } else if c.shell {
-        // shell mode
-        use shell::BuiltinCommands;
-        use shell::ShellInput::*;
-        use std::io::stdin;
-
-        let input = stdin();
-        let mut reader = shell::ShellReader::new(&input);
-        loop {
-            let child = reader.read_line()?;
-            match child {
-                Builtin(BuiltinCommands::Exit) => break,
-                Eof => break,
-                Command(child) => {
-                    debug!("context before execution of shell command: {:#?}", context);
-                    if let Err(e) = dispatch(&mut context, child).await {
-                        eprintln!("{}", e)
-                    }
-                    debug!("context after execution of shell command: {:#?}", context)
-                }
-                ParseError(_) => {
-                    // do nothing because read_line already handles the error
-                }
+        if c.show_capacity {
+            context.metrics = Some(Arc::new(Mutex::new(app::RequestMetrics::default())));
+        }
+        let started_at = Instant::now();
+        let result = dispatch(&mut context, child).await;
+        // Printed to stderr not to mix with the output of the command, e.g. `dy scan -o json --show-capacity | jq`.
+        if let Some(metrics) = context.metrics.take() {
+            eprint!("{}", timing_report(started_at, &metrics));
+        }
+        if let Err(e) = result {
+            // The command has already printed why it failed.
+            if let Some(code) = app::exit_code(&*e) {
+                std::process::exit(code);
             }
+            return Err(e);
+        }
+    } else if c.shell || c.script.is_some() {
+        // shell mode, or script mode which reads commands from the file instead of stdin
+        let input = stdin();
+        let mut reader = match &c.script {
+            Some(path) => shell::ShellReader::from_script(path).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }),
+            None => shell::ShellReader::new(&input)?,
+        };
+        for (name, value) in c.vars {
+            reader.set_variable(name, value);
+        }
+        let failures =
+            run_shell(&mut context, &mut reader, c.stop_on_error, c.show_capacity).await?;
+        // Mistyped commands in a terminal session don't make the exit status non-zero, unlike those in a script or piped stdin.
+        if failures > 0 && !reader.is_interactive() {
+            eprintln!("{} command(s) failed.", failures);
+            std::process::exit(1);
         }
     } else if c.third_party_attribution {
         // Load 3rd party attribution file
@@ -299,9 +620,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
         let mut stdout = stdout().lock();
         std::io::copy(&mut decompressor, &mut stdout)?;
     } else {
-        // Neiter subcommand nor --shell specified
+        // Neiter subcommand, --shell nor --script specified
         use clap::CommandFactory;
-        eprintln!("Invalid argument: please specify a subcommand or '--shell'");
+        eprintln!("Invalid argument: please specify a subcommand, '--shell' or '--script'");
         cmd::Dynein::command().print_help()?;
         std::process::exit(1);
     }
@@ -0,0 +1,855 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// This module renders results of read commands (scan, query, get and desc) in the output format shared by them.
+use std::{
+    collections::HashMap,
+    env, fmt,
+    io::{self, IsTerminal, Write},
+    str::FromStr,
+};
+
+use aws_sdk_dynamodb::{primitives::Blob, types::AttributeValue};
+use base64::{engine::general_purpose, Engine as _};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use termcolor::{Buffer, Color, ColorSpec, WriteColor};
+
+use super::{app, data, parser::DocumentPath, transfer};
+
+/* =================================================
+struct / enum / const
+================================================= */
+
+/// Output format of read commands, given by --output or `output` in config.yml.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Jsonl,
+    Yaml,
+    Csv,
+    #[value(alias = "raw")]
+    #[serde(alias = "raw")]
+    DdbJson,
+}
+
+/// Formats which can be used to show items, i.e. all formats.
+pub const ITEM_FORMATS: &[OutputFormat] = &[
+    OutputFormat::Table,
+    OutputFormat::Json,
+    OutputFormat::Jsonl,
+    OutputFormat::Yaml,
+    OutputFormat::Csv,
+    OutputFormat::DdbJson,
+];
+
+/// Formats which can be used to show table descriptions.
+pub const DESCRIPTION_FORMATS: &[OutputFormat] =
+    &[OutputFormat::Yaml, OutputFormat::Json, OutputFormat::Jsonl];
+
+/// Columns of the table and CSV formats other than primary key(s).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Columns {
+    /// Each attribute found in the items.
+    Auto,
+    /// Attributes given by --attributes, or document paths given by --columns.
+    Selected(Vec<DocumentPath>),
+    /// No column other than primary key(s), i.e. --keys-only.
+    KeysOnly,
+}
+
+/// When to colorize the output, given by --color.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// Returns true if the output should be colorized.
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            // https://no-color.org/
+            ColorMode::Auto => {
+                io::stdout().is_terminal() && env::var_os("NO_COLOR").map_or(true, |v| v.is_empty())
+            }
+        }
+    }
+}
+
+/// Options of read commands on how to print items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintOptions {
+    /// Columns of the table and CSV formats.
+    pub columns: Columns,
+    /// Show cells of the table format without truncation.
+    pub wide: bool,
+    /// Annotate values in the JSON and YAML formats with their DynamoDB types.
+    pub show_types: bool,
+    /// Highlight the JSON and YAML formats.
+    pub color: bool,
+}
+
+impl Columns {
+    pub fn new(
+        attributes: &Option<String>,
+        paths: Option<Vec<DocumentPath>>,
+        keys_only: bool,
+    ) -> Columns {
+        if keys_only {
+            Columns::KeysOnly
+        } else if let Some(paths) = paths {
+            Columns::Selected(paths)
+        } else if let Some(attributes) = attributes {
+            Columns::Selected(
+                attributes
+                    .split(',')
+                    .map(|a| DocumentPath::attribute(a.trim()))
+                    .collect(),
+            )
+        } else {
+            Columns::Auto
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self
+            .to_possible_value()
+            .expect("no output format is skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, false)
+    }
+}
+
+/* =================================================
+Public functions
+================================================= */
+
+/// Prints items in the format.
+pub fn print_items(
+    items: Vec<HashMap<String, AttributeValue>>,
+    ts: &app::TableSchema,
+    options: &PrintOptions,
+    format: OutputFormat,
+) {
+    match format {
+        OutputFormat::Table => {
+            let (names, rows) = tabulate(items, ts, &options.columns);
+            data::display_items_table(rows, ts, &names, options.wide)
+        }
+        OutputFormat::Json => print_highlighted(options, |out| {
+            let value = JsonValue::Array(items.iter().map(item_to_json).collect());
+            write_json(
+                out,
+                &value,
+                Source::Items(&items),
+                0,
+                false,
+                options.show_types,
+            )
+        }),
+        OutputFormat::Jsonl => {
+            for item in &items {
+                println!("{}", serde_json::to_string(&item_to_json(item)).unwrap());
+            }
+        }
+        OutputFormat::Yaml => print_highlighted(options, |out| {
+            let value = JsonValue::Array(items.iter().map(item_to_json).collect());
+            write_yaml(
+                out,
+                &value,
+                Source::Items(&items),
+                0,
+                true,
+                options.show_types,
+            )
+        }),
+        OutputFormat::Csv => {
+            let (names, rows) = tabulate(items, ts, &options.columns);
+            let names = Some(names).filter(|n| !n.is_empty());
+            print!("{}", transfer::build_csv_header(ts, names.clone(), false));
+            if !rows.is_empty() {
+                println!(
+                    "{}",
+                    data::convert_items_to_csv_lines(&rows, ts, &names, false)
+                );
+            }
+        }
+        OutputFormat::DdbJson => print_highlighted(options, |out| {
+            let value = serde_json::to_value(data::strip_items(&items)).unwrap();
+            write_json(out, &value, Source::None, 0, false, false)
+        }),
+    }
+}
+
+/// Prints an item in the format. A single JSON (or YAML) object is printed rather than an array.
+pub fn print_item(
+    item: HashMap<String, AttributeValue>,
+    ts: &app::TableSchema,
+    options: &PrintOptions,
+    format: OutputFormat,
+) {
+    match format {
+        OutputFormat::Json => print_highlighted(options, |out| {
+            let value = item_to_json(&item);
+            write_json(
+                out,
+                &value,
+                Source::Item(&item),
+                0,
+                false,
+                options.show_types,
+            )
+        }),
+        OutputFormat::Yaml => print_highlighted(options, |out| {
+            let value = item_to_json(&item);
+            write_yaml(
+                out,
+                &value,
+                Source::Item(&item),
+                0,
+                true,
+                options.show_types,
+            )
+        }),
+        OutputFormat::DdbJson => print_highlighted(options, |out| {
+            let value = serde_json::to_value(data::strip_item(&item)).unwrap();
+            write_json(out, &value, Source::None, 0, false, false)
+        }),
+        OutputFormat::Table | OutputFormat::Jsonl | OutputFormat::Csv => {
+            print_items(vec![item], ts, options, format)
+        }
+    }
+}
+
+/// Lays out items in the table format which fits in the terminal width, e.g. for `dy tui`. The first line is the header.
+pub fn table_lines(
+    items: Vec<HashMap<String, AttributeValue>>,
+    ts: &app::TableSchema,
+    terminal_width: usize,
+) -> Vec<String> {
+    let (names, rows) = tabulate(items, ts, &Columns::Auto);
+    data::items_table_lines(rows, ts, &names, terminal_width)
+}
+
+/// Returns an item as a pretty-printed JSON object, whose attributes are sorted by name.
+pub fn pretty_json(item: &HashMap<String, AttributeValue>) -> String {
+    serde_json::to_string_pretty(&item_to_json(item)).unwrap()
+}
+
+/// Returns an item in the dynein format (see docs/format.md), which is accepted by `dy put --item`.
+/// Unlike JSON, sets, binaries and numbers are written as is, so that the item can be edited and written back without loss.
+pub fn dynein_format(item: &HashMap<String, AttributeValue>) -> String {
+    let mut out = String::new();
+    write_dynein_map(&mut out, item, 0);
+    out
+}
+
+/// Returns an attribute value in the dynein format, e.g. for the value of a SET action.
+pub fn dynein_value(attrval: &AttributeValue) -> String {
+    let mut out = String::new();
+    write_dynein_value(&mut out, attrval, 0);
+    out
+}
+
+/* =================================================
+Private functions
+================================================= */
+
+fn write_dynein_map(out: &mut String, map: &HashMap<String, AttributeValue>, indent: usize) {
+    if map.is_empty() {
+        out.push_str("{}");
+        return;
+    }
+    let mut names: Vec<&String> = map.keys().collect();
+    names.sort();
+    out.push_str("{\n");
+    for (i, name) in names.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(",\n");
+        }
+        out.push_str(&"  ".repeat(indent + 1));
+        out.push_str(&serde_json::to_string(name).unwrap());
+        out.push_str(": ");
+        write_dynein_value(out, &map[name], indent + 1);
+    }
+    out.push('\n');
+    out.push_str(&"  ".repeat(indent));
+    out.push('}');
+}
+
+fn write_dynein_value(out: &mut String, attrval: &AttributeValue, indent: usize) {
+    let binary = |b: &Blob| format!("b64\"{}\"", general_purpose::STANDARD.encode(b.as_ref()));
+    let string = |s: &String| serde_json::to_string(s).unwrap();
+    match attrval {
+        AttributeValue::S(s) => out.push_str(&string(s)),
+        AttributeValue::N(n) => out.push_str(n),
+        AttributeValue::B(b) => out.push_str(&binary(b)),
+        AttributeValue::Bool(b) => out.push_str(&b.to_string()),
+        AttributeValue::Null(_) => out.push_str("null"),
+        AttributeValue::Ss(ss) => out.push_str(&dynein_set(ss.iter().map(string))),
+        AttributeValue::Ns(ns) => out.push_str(&dynein_set(ns.iter().cloned())),
+        AttributeValue::Bs(bs) => out.push_str(&dynein_set(bs.iter().map(binary))),
+        AttributeValue::L(l) if l.is_empty() => out.push_str("[]"),
+        AttributeValue::L(l) => {
+            out.push_str("[\n");
+            for (i, element) in l.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(",\n");
+                }
+                out.push_str(&"  ".repeat(indent + 1));
+                write_dynein_value(out, element, indent + 1);
+            }
+            out.push('\n');
+            out.push_str(&"  ".repeat(indent));
+            out.push(']');
+        }
+        AttributeValue::M(m) => write_dynein_map(out, m, indent),
+        _ => panic!("DynamoDB returned an unknown type of value: {:?}", attrval),
+    }
+}
+
+fn dynein_set(elements: impl Iterator<Item = String>) -> String {
+    format!("<<{}>>", elements.collect::<Vec<String>>().join(", "))
+}
+
+/// Returns names of non-key columns, and items whose attributes are the columns (and primary keys).
+/// As all lines of the table and CSV have the same columns, every attribute found in any item is a column by default.
+fn tabulate(
+    items: Vec<HashMap<String, AttributeValue>>,
+    ts: &app::TableSchema,
+    columns: &Columns,
+) -> (Vec<String>, Vec<HashMap<String, AttributeValue>>) {
+    let is_key =
+        |name: &str| name == ts.pk.name || ts.sk.as_ref().map_or(false, |sk| name == sk.name);
+    match columns {
+        Columns::KeysOnly => (vec![], items),
+        Columns::Auto => {
+            let mut names: Vec<String> = items
+                .iter()
+                .flat_map(|item| item.keys())
+                .filter(|name| !is_key(name))
+                .cloned()
+                .collect();
+            names.sort();
+            names.dedup();
+            (names, items)
+        }
+        Columns::Selected(paths) => {
+            let paths: Vec<&DocumentPath> = paths.iter().filter(|p| !is_key(p.label())).collect();
+            let rows = items
+                .iter()
+                .map(|item| {
+                    let mut row: HashMap<String, AttributeValue> = item
+                        .iter()
+                        .filter(|(name, _)| is_key(name))
+                        .map(|(name, value)| (name.clone(), value.clone()))
+                        .collect();
+                    for path in &paths {
+                        if let Some(value) = path.get(item) {
+                            row.insert(path.label().to_owned(), value.clone());
+                        }
+                    }
+                    row
+                })
+                .collect();
+            (paths.iter().map(|p| p.label().to_owned()).collect(), rows)
+        }
+    }
+}
+
+/// Converts an item into a JSON object, whose attributes are sorted by name unlike HashMap.
+fn item_to_json(item: &HashMap<String, AttributeValue>) -> JsonValue {
+    serde_json::to_value(data::convert_to_json(item)).unwrap()
+}
+
+/// Original attribute values of a JSON value, which give the DynamoDB type of each value for --show-types.
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    None,
+    Items(&'a [HashMap<String, AttributeValue>]),
+    Item(&'a HashMap<String, AttributeValue>),
+    Value(&'a AttributeValue),
+}
+
+impl<'a> Source<'a> {
+    fn field(self, name: &str) -> Source<'a> {
+        let value = match self {
+            Source::Item(item) => item.get(name),
+            Source::Value(AttributeValue::M(map)) => map.get(name),
+            _ => None,
+        };
+        value.map_or(Source::None, Source::Value)
+    }
+
+    fn element(self, index: usize) -> Source<'a> {
+        match self {
+            Source::Items(items) => items.get(index).map_or(Source::None, Source::Item),
+            Source::Value(AttributeValue::L(list)) => {
+                list.get(index).map_or(Source::None, Source::Value)
+            }
+            // Elements of sets have the type of the set.
+            _ => Source::None,
+        }
+    }
+
+    fn type_descriptor(self) -> Option<&'static str> {
+        match self {
+            Source::Value(value) => data::attrval_to_type_descriptor(value),
+            _ => None,
+        }
+    }
+}
+
+/// Writes the output into a buffer, which is colorized if enabled, and prints it.
+fn print_highlighted(options: &PrintOptions, write: impl FnOnce(&mut Buffer) -> io::Result<()>) {
+    let mut buffer = if options.color {
+        Buffer::ansi()
+    } else {
+        Buffer::no_color()
+    };
+    write(&mut buffer).expect("writing to a buffer should not fail");
+    io::stdout()
+        .write_all(buffer.as_slice())
+        .expect("failed to write to stdout");
+}
+
+fn write_colored(out: &mut impl WriteColor, text: &str, spec: &ColorSpec) -> io::Result<()> {
+    out.set_color(spec)?;
+    out.write_all(text.as_bytes())?;
+    out.reset()
+}
+
+fn key_color() -> ColorSpec {
+    let mut spec = ColorSpec::new();
+    spec.set_fg(Some(Color::Blue)).set_bold(true);
+    spec
+}
+
+fn scalar_color(value: &JsonValue) -> ColorSpec {
+    let mut spec = ColorSpec::new();
+    match value {
+        JsonValue::String(_) => spec.set_fg(Some(Color::Green)),
+        JsonValue::Number(_) => spec.set_fg(Some(Color::Cyan)),
+        _ => spec.set_fg(Some(Color::Yellow)),
+    };
+    spec
+}
+
+/// Writes the DynamoDB type of the value as a comment, e.g. `  # SS`, if --show-types is given.
+fn write_type(
+    out: &mut impl WriteColor,
+    source: Source,
+    comment: &str,
+    show_types: bool,
+) -> io::Result<()> {
+    match source.type_descriptor() {
+        Some(descriptor) if show_types => {
+            let mut spec = ColorSpec::new();
+            spec.set_dimmed(true);
+            write_colored(out, &format!("  {} {}", comment, descriptor), &spec)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Writes a value in the same layout as `serde_json::to_string_pretty`, followed by a newline.
+/// With `show_types`, each value is annotated with its type like `"age": 3,  // N`, which makes the output invalid JSON.
+fn write_json(
+    out: &mut impl WriteColor,
+    value: &JsonValue,
+    source: Source,
+    indent: usize,
+    comma: bool,
+    show_types: bool,
+) -> io::Result<()> {
+    let separator = if comma { "," } else { "" };
+    match value {
+        JsonValue::Array(list) if !list.is_empty() => {
+            write!(out, "[")?;
+            write_type(out, source, "//", show_types)?;
+            writeln!(out)?;
+            for (i, element) in list.iter().enumerate() {
+                write!(out, "{}", "  ".repeat(indent + 1))?;
+                let comma = i + 1 < list.len();
+                write_json(
+                    out,
+                    element,
+                    source.element(i),
+                    indent + 1,
+                    comma,
+                    show_types,
+                )?;
+            }
+            writeln!(out, "{}]{}", "  ".repeat(indent), separator)
+        }
+        JsonValue::Object(map) if !map.is_empty() => {
+            write!(out, "{{")?;
+            write_type(out, source, "//", show_types)?;
+            writeln!(out)?;
+            for (i, (name, field)) in map.iter().enumerate() {
+                write!(out, "{}", "  ".repeat(indent + 1))?;
+                write_colored(
+                    out,
+                    &JsonValue::String(name.clone()).to_string(),
+                    &key_color(),
+                )?;
+                write!(out, ": ")?;
+                let comma = i + 1 < map.len();
+                write_json(
+                    out,
+                    field,
+                    source.field(name),
+                    indent + 1,
+                    comma,
+                    show_types,
+                )?;
+            }
+            writeln!(out, "{}}}{}", "  ".repeat(indent), separator)
+        }
+        _ => {
+            write_colored(out, &value.to_string(), &scalar_color(value))?;
+            write!(out, "{}", separator)?;
+            write_type(out, source, "//", show_types)?;
+            writeln!(out)
+        }
+    }
+}
+
+/// Writes a value in the same layout as `serde_yaml::to_string`, followed by a newline.
+/// `inline` means that the value follows `- ` of a sequence (or it's the document), so the first line is not indented.
+/// With `show_types`, each value is annotated with its type as a comment like `age: 3  # N`.
+fn write_yaml(
+    out: &mut impl WriteColor,
+    value: &JsonValue,
+    source: Source,
+    indent: usize,
+    inline: bool,
+    show_types: bool,
+) -> io::Result<()> {
+    match value {
+        JsonValue::Array(list) if !list.is_empty() => {
+            for (i, element) in list.iter().enumerate() {
+                if i > 0 || !inline {
+                    write!(out, "{}", "  ".repeat(indent))?;
+                }
+                write!(out, "-")?;
+                write_yaml_entry(out, element, source.element(i), indent, true, show_types)?;
+            }
+            Ok(())
+        }
+        JsonValue::Object(map) if !map.is_empty() => {
+            for (i, (name, field)) in map.iter().enumerate() {
+                if i > 0 || !inline {
+                    write!(out, "{}", "  ".repeat(indent))?;
+                }
+                write_colored(
+                    out,
+                    &yaml_scalar(&JsonValue::String(name.clone())),
+                    &key_color(),
+                )?;
+                write!(out, ":")?;
+                write_yaml_entry(out, field, source.field(name), indent, false, show_types)?;
+            }
+            Ok(())
+        }
+        _ => {
+            write_colored(out, &yaml_scalar(value), &scalar_color(value))?;
+            write_type(out, source, "#", show_types)?;
+            writeln!(out)
+        }
+    }
+}
+
+/// Writes a value after `key:` of a mapping, or `-` of a sequence.
+fn write_yaml_entry(
+    out: &mut impl WriteColor,
+    value: &JsonValue,
+    source: Source,
+    indent: usize,
+    in_sequence: bool,
+    show_types: bool,
+) -> io::Result<()> {
+    let is_block = match value {
+        JsonValue::Array(list) => !list.is_empty(),
+        JsonValue::Object(map) => !map.is_empty(),
+        _ => false,
+    };
+    if !is_block {
+        write!(out, " ")?;
+        return write_yaml(out, value, source, indent, true, show_types);
+    }
+    // A sequence in a mapping is not indented, as serde_yaml does.
+    let block_indent = if in_sequence || value.is_object() {
+        indent + 1
+    } else {
+        indent
+    };
+    if show_types && source.type_descriptor().is_some() {
+        // The type comment is at the end of the line, so the block starts from the next line.
+        write_type(out, source, "#", show_types)?;
+        writeln!(out)?;
+        write_yaml(out, value, source, block_indent, false, show_types)
+    } else if in_sequence {
+        write!(out, " ")?;
+        write_yaml(out, value, source, block_indent, true, show_types)
+    } else {
+        writeln!(out)?;
+        write_yaml(out, value, source, block_indent, false, show_types)
+    }
+}
+
+/// A scalar in YAML. Strings with line breaks are written in double quotes, so that they don't need indentation.
+fn yaml_scalar(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) if s.contains('\n') => value.to_string(),
+        _ => serde_yaml::to_string(value)
+            .unwrap()
+            .trim_end_matches('\n')
+            .to_owned(),
+    }
+}
+
+/* =================================================
+Unit Tests
+================================================= */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddb::{key, table};
+
+    #[test]
+    fn test_output_format_names() {
+        assert_eq!("ddb-json".parse(), Ok(OutputFormat::DdbJson));
+        assert_eq!("raw".parse(), Ok(OutputFormat::DdbJson));
+        assert_eq!("jsonl".parse(), Ok(OutputFormat::Jsonl));
+        assert!("xml".parse::<OutputFormat>().is_err());
+        assert_eq!(OutputFormat::DdbJson.to_string(), "ddb-json");
+        assert_eq!(
+            serde_yaml::from_str::<OutputFormat>("raw").unwrap(),
+            OutputFormat::DdbJson
+        );
+    }
+
+    #[test]
+    fn test_tabulate() {
+        let ts = app::TableSchema {
+            region: String::from("local"),
+            name: String::from("table"),
+            pk: key::Key {
+                name: String::from("pk"),
+                kind: key::KeyType::S,
+            },
+            sk: None,
+            indexes: None,
+            mode: table::Mode::OnDemand,
+            cached_at: None,
+        };
+        let items = vec![
+            HashMap::from([
+                ("pk".to_owned(), AttributeValue::S("a".to_owned())),
+                ("name".to_owned(), AttributeValue::S("x".to_owned())),
+            ]),
+            HashMap::from([
+                ("pk".to_owned(), AttributeValue::S("b".to_owned())),
+                ("age".to_owned(), AttributeValue::N("3".to_owned())),
+                (
+                    "tags".to_owned(),
+                    AttributeValue::L(vec![AttributeValue::S("t".to_owned())]),
+                ),
+            ]),
+        ];
+
+        let (names, rows) = tabulate(items.clone(), &ts, &Columns::Auto);
+        assert_eq!(names, vec!["age", "name", "tags"]);
+        assert_eq!(rows, items);
+        assert_eq!(
+            data::convert_items_to_csv_lines(&rows, &ts, &Some(names), false),
+            "\"a\",,\"x\",\n\"b\",3,,[\"t\"]"
+        );
+
+        let attributes = Columns::new(&Some("name, pk".to_owned()), None, false);
+        let (names, _) = tabulate(items.clone(), &ts, &attributes);
+        assert_eq!(names, vec!["name"]);
+
+        let (paths, _) = crate::parser::DyneinParser::new()
+            .parse_projection("tags[0],age")
+            .unwrap();
+        let (names, rows) = tabulate(items.clone(), &ts, &Columns::new(&None, Some(paths), false));
+        assert_eq!(names, vec!["tags[0]", "age"]);
+        assert_eq!(
+            rows[1],
+            HashMap::from([
+                ("pk".to_owned(), AttributeValue::S("b".to_owned())),
+                ("age".to_owned(), AttributeValue::N("3".to_owned())),
+                ("tags[0]".to_owned(), AttributeValue::S("t".to_owned())),
+            ])
+        );
+
+        let (names, rows) = tabulate(items.clone(), &ts, &Columns::new(&None, None, true));
+        assert!(names.is_empty());
+        assert_eq!(rows, items);
+    }
+
+    fn sample_item() -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            ("pk".to_owned(), AttributeValue::S("yes".to_owned())),
+            ("n".to_owned(), AttributeValue::N("1.5".to_owned())),
+            ("flag".to_owned(), AttributeValue::Bool(true)),
+            ("none".to_owned(), AttributeValue::Null(true)),
+            (
+                "tags".to_owned(),
+                AttributeValue::Ss(vec!["a".to_owned(), "b".to_owned()]),
+            ),
+            ("empty".to_owned(), AttributeValue::L(vec![])),
+            (
+                "nested".to_owned(),
+                AttributeValue::M(HashMap::from([
+                    (
+                        "list".to_owned(),
+                        AttributeValue::L(vec![
+                            AttributeValue::L(vec![AttributeValue::N("1".to_owned())]),
+                            AttributeValue::M(HashMap::from([(
+                                "x".to_owned(),
+                                AttributeValue::S("123".to_owned()),
+                            )])),
+                        ]),
+                    ),
+                    ("m".to_owned(), AttributeValue::M(HashMap::new())),
+                ])),
+            ),
+        ])
+    }
+
+    fn render(write: impl FnOnce(&mut Buffer) -> io::Result<()>) -> String {
+        let mut buffer = Buffer::no_color();
+        write(&mut buffer).unwrap();
+        String::from_utf8(buffer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_highlighted_layout() {
+        // Without --show-types, the output is the same as serde_json and serde_yaml.
+        let items = vec![sample_item(), sample_item()];
+        let value = JsonValue::Array(items.iter().map(item_to_json).collect());
+        assert_eq!(
+            render(|out| write_json(out, &value, Source::Items(&items), 0, false, false)),
+            serde_json::to_string_pretty(&value).unwrap() + "\n"
+        );
+        assert_eq!(
+            render(|out| write_yaml(out, &value, Source::Items(&items), 0, true, false)),
+            serde_yaml::to_string(&value).unwrap()
+        );
+        let value = &value[0];
+        assert_eq!(
+            render(|out| write_yaml(out, value, Source::Item(&items[0]), 0, true, false)),
+            serde_yaml::to_string(value).unwrap()
+        );
+        // Line breaks are escaped in a double-quoted string.
+        assert_eq!(
+            render(|out| write_yaml(out, &JsonValue::from("a\nb"), Source::None, 0, true, false)),
+            "\"a\\nb\"\n"
+        );
+    }
+
+    #[test]
+    fn test_show_types() {
+        let item = HashMap::from([
+            ("tags".to_owned(), AttributeValue::Ss(vec!["a".to_owned()])),
+            (
+                "list".to_owned(),
+                AttributeValue::L(vec![AttributeValue::N("1".to_owned())]),
+            ),
+        ]);
+        let value = item_to_json(&item);
+        assert_eq!(
+            render(|out| write_json(out, &value, Source::Item(&item), 0, false, true)),
+            "{\n  \"list\": [  // L\n    1  // N\n  ],\n  \"tags\": [  // SS\n    \"a\"\n  ]\n}\n"
+        );
+        assert_eq!(
+            render(|out| write_yaml(out, &value, Source::Item(&item), 0, true, true)),
+            "list:  # L\n- 1  # N\ntags:  # SS\n- a\n"
+        );
+        // The annotated YAML is still valid.
+        let parsed: JsonValue = serde_yaml::from_str(&render(|out| {
+            write_yaml(out, &value, Source::Item(&item), 0, true, true)
+        }))
+        .unwrap();
+        assert_eq!(parsed, value);
+        let item = sample_item();
+        let value = item_to_json(&item);
+        let annotated = render(|out| write_yaml(out, &value, Source::Item(&item), 0, true, true));
+        assert_eq!(
+            serde_yaml::from_str::<JsonValue>(&annotated).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn test_dynein_format_round_trip() {
+        let item = HashMap::from([
+            (
+                "pk".to_owned(),
+                AttributeValue::S("a \"quoted\"\nline".to_owned()),
+            ),
+            ("n".to_owned(), AttributeValue::N("1.50".to_owned())),
+            ("b".to_owned(), AttributeValue::B(Blob::new(vec![0, 255]))),
+            ("null".to_owned(), AttributeValue::Null(true)),
+            (
+                "ss".to_owned(),
+                AttributeValue::Ss(vec!["x".to_owned(), "y".to_owned()]),
+            ),
+            ("ns".to_owned(), AttributeValue::Ns(vec!["-1".to_owned()])),
+            ("bs".to_owned(), AttributeValue::Bs(vec![Blob::new("hi")])),
+            ("empty".to_owned(), AttributeValue::L(vec![])),
+            (
+                "m".to_owned(),
+                AttributeValue::M(HashMap::from([(
+                    "l".to_owned(),
+                    AttributeValue::L(vec![
+                        AttributeValue::Bool(true),
+                        AttributeValue::M(HashMap::new()),
+                    ]),
+                )])),
+            ),
+        ]);
+        let text = dynein_format(&item);
+        assert!(text.starts_with("{\n  \"b\": b64\"AP8=\",\n  \"bs\": <<b64\"aGk=\">>,\n"));
+        assert_eq!(
+            crate::parser::DyneinParser::new()
+                .parse_dynein_format(None, &text)
+                .unwrap(),
+            item
+        );
+    }
+}
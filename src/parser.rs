@@ -187,6 +187,54 @@ impl Path {
     }
 }
 
+/// A document path to an attribute, e.g. `address.city` or `tags[0]`, which selects a column of items.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DocumentPath {
+    path: Path,
+    label: String,
+}
+
+impl DocumentPath {
+    /// Create a path to a top-level attribute. The name is used as is, without parsing.
+    pub fn attribute(name: &str) -> DocumentPath {
+        let mut path = Path::new();
+        path.add_attr(name.to_owned());
+        DocumentPath {
+            path,
+            label: name.to_owned(),
+        }
+    }
+
+    /// The path as it was written, which is used as a column name.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns true if the path is the top-level attribute of the name.
+    pub fn is_attribute(&self, name: &str) -> bool {
+        matches!(self.path.elements.as_slice(), [PathElement::Attribute(attr)] if attr == name)
+    }
+
+    /// Returns the value at the path in the item. None is returned if any element of the path is missing.
+    pub fn get<'a>(&self, item: &'a HashMap<String, AttributeValue>) -> Option<&'a AttributeValue> {
+        let mut elements = self.path.elements.iter();
+        let mut value = match elements.next()? {
+            PathElement::Attribute(name) => item.get(name)?,
+            PathElement::Index(_) => return None,
+        };
+        for element in elements {
+            value = match (element, value) {
+                (PathElement::Attribute(name), AttributeValue::M(map)) => map.get(name)?,
+                (PathElement::Index(idx), AttributeValue::L(list)) => {
+                    list.get(idx.parse::<usize>().ok()?)?
+                }
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 enum Value {
     PlusExpression(Operand, Operand),
@@ -1492,6 +1540,39 @@ impl DyneinParser {
         }
     }
 
+    /// Parse document paths separated by commas, e.g. `address.city, tags[0]`.
+    ///
+    /// The `exp` of returned [`ExpressionResult`] is `ProjectionExpression` to get the paths.
+    pub fn parse_projection(
+        &mut self,
+        exp: &str,
+    ) -> Result<(Vec<DocumentPath>, ExpressionResult), ParseError> {
+        let mut pair = GeneratedParser::parse(Rule::projection, exp)
+            .map_err(|err| ParseError::ParsingError(Box::new(err)))?;
+        let paths: Vec<DocumentPath> = pair
+            .next()
+            .unwrap()
+            .into_inner()
+            .map(|pair| DocumentPath {
+                label: pair.as_str().trim().to_owned(),
+                path: parse_path(pair),
+            })
+            .collect();
+        let expression = paths
+            .iter()
+            .map(|p| self.process_path(p.path.clone()))
+            .collect::<Vec<String>>()
+            .join(",");
+        Ok((
+            paths,
+            ExpressionResult {
+                exp: expression,
+                names: self.names.clone(),
+                values: self.values.clone(),
+            },
+        ))
+    }
+
     fn try_sort_key_parse(
         &self,
         exp: &str,
@@ -2808,6 +2889,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_projection() {
+        let mut parser = DyneinParser::new();
+        let (paths, result) = parser
+            .parse_projection("address.city, tags[0], `a b`")
+            .unwrap();
+        assert_eq!(
+            result.get_expression(),
+            format!(
+                "{}.{},{}[0],{}",
+                attr_name_ref(0),
+                attr_name_ref(1),
+                attr_name_ref(2),
+                attr_name_ref(3)
+            )
+        );
+        assert_eq!(
+            paths.iter().map(|p| p.label()).collect::<Vec<_>>(),
+            vec!["address.city", "tags[0]", "`a b`"]
+        );
+        assert!(paths[2].is_attribute("a b"));
+        assert!(!paths[0].is_attribute("address"));
+
+        let item = HashMap::from([
+            (
+                "address".to_owned(),
+                AttributeValue::M(HashMap::from([(
+                    "city".to_owned(),
+                    AttributeValue::S("Tokyo".to_owned()),
+                )])),
+            ),
+            (
+                "tags".to_owned(),
+                AttributeValue::L(vec![AttributeValue::S("a".to_owned())]),
+            ),
+        ]);
+        assert_eq!(
+            paths[0].get(&item),
+            Some(&AttributeValue::S("Tokyo".to_owned()))
+        );
+        assert_eq!(
+            paths[1].get(&item),
+            Some(&AttributeValue::S("a".to_owned()))
+        );
+        assert_eq!(paths[2].get(&item), None);
+        assert_eq!(DocumentPath::attribute("tags").get(&item), item.get("tags"));
+
+        assert!(parser.parse_projection("address.").is_err());
+    }
+
     #[test]
     fn test_set_and_remove_action() {
         let mut parser = DyneinParser::new();
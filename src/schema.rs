@@ -0,0 +1,667 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// This module infers a logical schema from items in a table, and prints it as JSON Schema or type definitions.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use clap::ValueEnum;
+use console::truncate_str;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+
+use super::app;
+use super::data;
+
+/* =================================================
+struct / enum / const
+================================================= */
+
+/// Number of distinct example values kept for each attribute path.
+const MAX_EXAMPLES: usize = 3;
+/// Example strings longer than this are truncated.
+const MAX_EXAMPLE_WIDTH: usize = 40;
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while",
+];
+
+/// Format of the schema printed by `dy schema infer`.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum SchemaFormat {
+    /// JSON Schema (draft 2020-12). Observed types and presence are given in `description`.
+    #[default]
+    JsonSchema,
+    /// TypeScript interfaces.
+    Typescript,
+    /// Rust structs with serde's Serialize and Deserialize.
+    Rust,
+}
+
+/// Types and values observed at an attribute path. Attributes of items are observed as fields of a map.
+#[derive(Debug, Default)]
+struct Shape {
+    /// Number of values observed at the path.
+    count: usize,
+    /// Number of values of each DynamoDB type, e.g. {S: 10, N: 2}.
+    types: BTreeMap<&'static str, usize>,
+    /// True if any number (or number in a set) has a fraction or an exponent.
+    fractional: bool,
+    /// Distinct scalar values, up to MAX_EXAMPLES.
+    examples: Vec<JsonValue>,
+    /// Attributes of maps.
+    fields: BTreeMap<String, Shape>,
+    /// Elements of lists. None if no list has an element.
+    elements: Option<Box<Shape>>,
+}
+
+impl Shape {
+    fn observe_item(&mut self, item: &HashMap<String, AttributeValue>) {
+        self.count += 1;
+        *self.types.entry("M").or_default() += 1;
+        self.observe_fields(item);
+    }
+
+    fn observe_fields(&mut self, map: &HashMap<String, AttributeValue>) {
+        for (name, attrval) in map {
+            self.fields
+                .entry(name.to_owned())
+                .or_default()
+                .observe(attrval);
+        }
+    }
+
+    fn observe(&mut self, attrval: &AttributeValue) {
+        self.count += 1;
+        if let Some(t) = data::attrval_to_type_descriptor(attrval) {
+            *self.types.entry(t).or_default() += 1;
+        }
+        match attrval {
+            AttributeValue::S(s) => {
+                self.add_example(JsonValue::from(truncate_str(s, MAX_EXAMPLE_WIDTH, "...")))
+            }
+            AttributeValue::N(n) => {
+                self.fractional |= n.contains(['.', 'e', 'E']);
+                self.add_example(data::attrval_to_jsonval(attrval));
+            }
+            AttributeValue::Bool(b) => self.add_example(JsonValue::from(*b)),
+            AttributeValue::Ns(ns) => {
+                self.fractional |= ns.iter().any(|n| n.contains(['.', 'e', 'E']))
+            }
+            AttributeValue::M(m) => self.observe_fields(m),
+            AttributeValue::L(l) => {
+                for element in l {
+                    self.elements
+                        .get_or_insert_with(Box::default)
+                        .observe(element);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn add_example(&mut self, value: JsonValue) {
+        if self.examples.len() < MAX_EXAMPLES && !self.examples.contains(&value) {
+            self.examples.push(value);
+        }
+    }
+
+    /// Number of maps observed at the path, which is the denominator of the presence of fields.
+    fn maps(&self) -> usize {
+        self.types.get("M").copied().unwrap_or_default()
+    }
+
+    /// Returns true if the field is found in all maps of its parent.
+    fn required(&self, parent: &Shape) -> bool {
+        self.count == parent.maps()
+    }
+
+    /// Describes the presence and types of the field, e.g. `66.7% present, types: S 2, N 1`.
+    fn describe(&self, parent: &Shape, with_examples: bool) -> String {
+        let types = self
+            .types
+            .iter()
+            .map(|(t, n)| format!("{} {}", t, n))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let mut description = format!(
+            "{:.1}% present, types: {}",
+            self.count as f64 * 100.0 / parent.maps() as f64,
+            types
+        );
+        if with_examples && !self.examples.is_empty() {
+            let examples = self
+                .examples
+                .iter()
+                .map(JsonValue::to_string)
+                .collect::<Vec<String>>()
+                .join(", ");
+            write!(description, ", e.g. {}", examples).unwrap();
+        }
+        description
+    }
+}
+
+/* =================================================
+Public functions
+================================================= */
+
+/// Scans the table (or the index), up to `sample` items if given, and prints a schema inferred from the items.
+/// The root type is named after the table unless `name` is given.
+pub async fn infer(
+    cx: &app::Context,
+    index: Option<String>,
+    sample: Option<i32>,
+    format: SchemaFormat,
+    name: Option<String>,
+) -> Result<(), app::DyneinExit> {
+    let ts: app::TableSchema = data::table_schema_for_index(cx, &index).await?;
+    let mut root = Shape::default();
+    let sampled = data::scan_sample(cx, index, sample, |item| root.observe_item(item)).await?;
+
+    let source = format!(
+        "Inferred from {} {} items of the table '{}'.",
+        if sampled { "the first" } else { "all" },
+        root.count,
+        ts.name
+    );
+    let name = name.unwrap_or_else(|| pascal_case(&ts.name));
+    let output = match format {
+        SchemaFormat::JsonSchema => json_schema(&root, &name, &source),
+        SchemaFormat::Typescript => typescript(&root, &name, &source),
+        SchemaFormat::Rust => rust(&root, &name, &source),
+    };
+    print!("{}", output);
+    Ok(())
+}
+
+/* =================================================
+Private functions
+================================================= */
+
+fn json_schema(root: &Shape, name: &str, source: &str) -> String {
+    let mut schema = match json_schema_of(root) {
+        JsonValue::Object(m) => m,
+        _ => unreachable!("the root is a map"),
+    };
+    schema.insert(
+        "$schema".to_owned(),
+        JsonValue::from("https://json-schema.org/draft/2020-12/schema"),
+    );
+    schema.insert("title".to_owned(), JsonValue::from(name));
+    schema.insert("description".to_owned(), JsonValue::from(source));
+    let mut s = serde_json::to_string_pretty(&schema).unwrap();
+    s.push('\n');
+    s
+}
+
+/// Returns the JSON Schema of values observed at a path. Multiple types are given as `anyOf`.
+fn json_schema_of(shape: &Shape) -> JsonValue {
+    let number = if shape.fractional {
+        "number"
+    } else {
+        "integer"
+    };
+    let mut schemas: Vec<JsonValue> = shape
+        .types
+        .keys()
+        .map(|t| match *t {
+            "S" => serde_json::json!({"type": "string"}),
+            "N" => serde_json::json!({"type": number}),
+            "B" => serde_json::json!({"type": "string", "contentEncoding": "base64"}),
+            "BOOL" => serde_json::json!({"type": "boolean"}),
+            "NULL" => serde_json::json!({"type": "null"}),
+            "SS" => serde_json::json!({"type": "array", "uniqueItems": true, "items": {"type": "string"}}),
+            "NS" => serde_json::json!({"type": "array", "uniqueItems": true, "items": {"type": number}}),
+            "BS" => serde_json::json!({
+                "type": "array",
+                "uniqueItems": true,
+                "items": {"type": "string", "contentEncoding": "base64"}
+            }),
+            "M" => {
+                let mut properties = Map::new();
+                for (name, field) in &shape.fields {
+                    let mut property = json_schema_of(field);
+                    property["description"] = JsonValue::from(field.describe(shape, false));
+                    if !field.examples.is_empty() {
+                        property["examples"] = JsonValue::from(field.examples.clone());
+                    }
+                    properties.insert(name.to_owned(), property);
+                }
+                let required: Vec<&String> = shape
+                    .fields
+                    .iter()
+                    .filter(|(_, field)| field.required(shape))
+                    .map(|(name, _)| name)
+                    .collect();
+                serde_json::json!({"type": "object", "properties": properties, "required": required})
+            }
+            "L" => match &shape.elements {
+                Some(elements) => serde_json::json!({"type": "array", "items": json_schema_of(elements)}),
+                None => serde_json::json!({"type": "array"}),
+            },
+            _ => serde_json::json!({}),
+        })
+        .collect();
+    match schemas.len() {
+        0 => serde_json::json!({}),
+        1 => schemas.remove(0),
+        _ => serde_json::json!({ "anyOf": schemas }),
+    }
+}
+
+fn typescript(root: &Shape, name: &str, source: &str) -> String {
+    let mut s = format!("// {}\n", source);
+    let definitions = definitions(root, name);
+    for (name, shape) in &definitions {
+        write!(s, "\nexport interface {} {{\n", name).unwrap();
+        for (field_name, field) in &shape.fields {
+            let field_type = typescript_type(field, &definitions);
+            let is_identifier = field_name
+                .starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '$')
+                && field_name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+            let key = if is_identifier {
+                field_name.to_owned()
+            } else {
+                JsonValue::from(field_name.as_str()).to_string()
+            };
+            write!(
+                s,
+                "  /** {} */\n  {}{}: {};\n",
+                field.describe(shape, true),
+                key,
+                if field.required(shape) { "" } else { "?" },
+                field_type
+            )
+            .unwrap();
+        }
+        s.push_str("}\n");
+    }
+    s
+}
+
+/// Returns the TypeScript type of values observed at a path. Maps are named as in `definitions`.
+fn typescript_type(shape: &Shape, definitions: &[(String, &Shape)]) -> String {
+    let mut types: Vec<String> = shape
+        .types
+        .keys()
+        .map(|t| match *t {
+            "S" | "B" => "string".to_owned(),
+            "N" => "number".to_owned(),
+            "BOOL" => "boolean".to_owned(),
+            "NULL" => "null".to_owned(),
+            "SS" | "BS" => "string[]".to_owned(),
+            "NS" => "number[]".to_owned(),
+            "M" => type_name(shape, definitions),
+            "L" => match &shape.elements {
+                Some(elements) => {
+                    let element = typescript_type(elements, definitions);
+                    if element.contains(' ') {
+                        format!("({})[]", element)
+                    } else {
+                        format!("{}[]", element)
+                    }
+                }
+                None => "unknown[]".to_owned(),
+            },
+            _ => "unknown".to_owned(),
+        })
+        .collect();
+    types.sort();
+    types.dedup();
+    if types.is_empty() {
+        "unknown".to_owned()
+    } else {
+        types.join(" | ")
+    }
+}
+
+fn rust(root: &Shape, name: &str, source: &str) -> String {
+    let mut s = format!("// {}\n\nuse serde::{{Deserialize, Serialize}};\n", source);
+    let definitions = definitions(root, name);
+    for (name, shape) in &definitions {
+        write!(
+            s,
+            "\n#[derive(Debug, Clone, Serialize, Deserialize)]\npub struct {} {{\n",
+            name
+        )
+        .unwrap();
+        // Different attributes can have the same identifier, e.g. `firstName` and `first_name`.
+        let mut idents = HashSet::new();
+        for (field_name, field) in &shape.fields {
+            let mut field_type = rust_type(field, &definitions);
+            if !field.required(shape) || field.types.contains_key("NULL") {
+                field_type = format!("Option<{}>", field_type);
+            }
+            let ident = disambiguate(snake_case(field_name), "_", &mut idents);
+            writeln!(s, "    /// {}", field.describe(shape, true)).unwrap();
+            if ident != *field_name {
+                writeln!(
+                    s,
+                    "    #[serde(rename = {})]",
+                    JsonValue::from(field_name.as_str())
+                )
+                .unwrap();
+            }
+            let ident = if RUST_KEYWORDS.contains(&ident.as_str()) {
+                format!("r#{}", ident)
+            } else {
+                ident
+            };
+            writeln!(s, "    pub {}: {},", ident, field_type).unwrap();
+        }
+        s.push_str("}\n");
+    }
+    s
+}
+
+/// Returns the Rust type of values observed at a path, without Option for NULL. Maps are named as in `definitions`.
+fn rust_type(shape: &Shape, definitions: &[(String, &Shape)]) -> String {
+    let number = if shape.fractional { "f64" } else { "i64" };
+    let types: Vec<String> = shape
+        .types
+        .keys()
+        .filter(|t| **t != "NULL")
+        .map(|t| match *t {
+            "S" => "String".to_owned(),
+            "N" => number.to_owned(),
+            "B" => "Vec<u8>".to_owned(),
+            "BOOL" => "bool".to_owned(),
+            "SS" => "Vec<String>".to_owned(),
+            "NS" => format!("Vec<{}>", number),
+            "BS" => "Vec<Vec<u8>>".to_owned(),
+            "M" => type_name(shape, definitions),
+            "L" => match &shape.elements {
+                Some(elements) => format!("Vec<{}>", rust_type(elements, definitions)),
+                None => "Vec<serde_json::Value>".to_owned(),
+            },
+            _ => "serde_json::Value".to_owned(),
+        })
+        .collect();
+    match types.as_slice() {
+        [t] => t.to_owned(),
+        // Values of multiple types (or only NULL) can be kept as they are.
+        _ => "serde_json::Value".to_owned(),
+    }
+}
+
+/// Returns maps to define as types, i.e. the root and nested maps, with their names.
+/// A nested map is named after its parent and the path, e.g. `MovieInfo` for `info` of `Movie`,
+/// and `MovieActorsItem` for maps in the list `actors`.
+/// A name which is already taken, e.g. by `info_x` and `infoX`, gets a numeric suffix like `MovieInfoX2`.
+fn definitions<'a>(root: &'a Shape, name: &str) -> Vec<(String, &'a Shape)> {
+    fn visit<'a>(
+        shape: &'a Shape,
+        name: String,
+        found: &mut Vec<(String, &'a Shape)>,
+        names: &mut HashSet<String>,
+    ) {
+        if let Some(elements) = &shape.elements {
+            visit(elements, format!("{}Item", name), found, names);
+        }
+        // The root is defined even if the table has no item.
+        if shape.maps() > 0 || found.is_empty() {
+            let name = disambiguate(name, "", names);
+            found.push((name.clone(), shape));
+            for (field_name, field) in &shape.fields {
+                visit(
+                    field,
+                    format!("{}{}", name, pascal_case(field_name)),
+                    found,
+                    names,
+                );
+            }
+        }
+    }
+    let mut found = vec![];
+    visit(root, name.to_owned(), &mut found, &mut HashSet::new());
+    found
+}
+
+/// Returns the name given to the map at a path by `definitions`.
+fn type_name(shape: &Shape, definitions: &[(String, &Shape)]) -> String {
+    definitions
+        .iter()
+        .find(|(_, s)| std::ptr::eq(*s, shape))
+        .map(|(name, _)| name.to_owned())
+        .expect("maps are defined")
+}
+
+/// Returns the name, or the name with the smallest numeric suffix (from 2) which is not taken yet, and takes it.
+fn disambiguate(name: String, separator: &str, taken: &mut HashSet<String>) -> String {
+    let mut unique = name.clone();
+    let mut suffix = 2;
+    while taken.contains(&unique) {
+        unique = format!("{}{}{}", name, separator, suffix);
+        suffix += 1;
+    }
+    taken.insert(unique.clone());
+    unique
+}
+
+/// Splits a name into words at non-alphanumeric characters and lower-to-upper case boundaries, e.g. `firstName`.
+fn words(name: &str) -> Vec<String> {
+    let mut words: Vec<String> = vec![];
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            prev_lower = false;
+            words.push(String::new());
+            continue;
+        }
+        if words.is_empty() || (prev_lower && c.is_uppercase()) {
+            words.push(String::new());
+        }
+        words.last_mut().unwrap().push(c);
+        prev_lower = c.is_lowercase() || c.is_numeric();
+    }
+    words.retain(|w| !w.is_empty());
+    words
+}
+
+fn pascal_case(name: &str) -> String {
+    let pascal: String = words(name)
+        .iter()
+        .map(|w| {
+            let mut chars = w.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            first.into_iter().chain(chars).collect::<String>()
+        })
+        .collect();
+    if pascal.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        pascal
+    } else {
+        format!("T{}", pascal)
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let snake = words(name)
+        .iter()
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<String>>()
+        .join("_");
+    if snake.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        snake
+    } else {
+        format!("_{}", snake)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(v: &str) -> AttributeValue {
+        AttributeValue::S(v.to_owned())
+    }
+
+    fn n(v: &str) -> AttributeValue {
+        AttributeValue::N(v.to_owned())
+    }
+
+    fn observed() -> Shape {
+        let mut root = Shape::default();
+        root.observe_item(&HashMap::from([
+            ("pk".to_owned(), s("a")),
+            ("firstName".to_owned(), s("Alice")),
+            (
+                "info".to_owned(),
+                AttributeValue::M(HashMap::from([("rank".to_owned(), n("1"))])),
+            ),
+        ]));
+        root.observe_item(&HashMap::from([
+            ("pk".to_owned(), s("b")),
+            (
+                "info".to_owned(),
+                AttributeValue::M(HashMap::from([("rank".to_owned(), n("2.5"))])),
+            ),
+            ("tags".to_owned(), AttributeValue::L(vec![s("x"), n("1")])),
+        ]));
+        root
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(pascal_case("movie-table"), "MovieTable");
+        assert_eq!(pascal_case("firstName"), "FirstName");
+        assert_eq!(pascal_case("2024_events"), "T2024Events");
+        assert_eq!(snake_case("firstName"), "first_name");
+        assert_eq!(snake_case("HTTPStatus"), "httpstatus");
+        assert_eq!(snake_case("user id"), "user_id");
+        assert_eq!(snake_case("1st"), "_1st");
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let root = observed();
+        let schema: JsonValue =
+            serde_json::from_str(&json_schema(&root, "Users", "Inferred.")).unwrap();
+        assert_eq!(schema["title"], "Users");
+        assert_eq!(schema["required"], serde_json::json!(["info", "pk"]));
+        assert_eq!(
+            schema["properties"]["firstName"],
+            serde_json::json!({
+                "type": "string",
+                "description": "50.0% present, types: S 1",
+                "examples": ["Alice"]
+            })
+        );
+        assert_eq!(
+            schema["properties"]["info"]["properties"]["rank"]["type"],
+            "number"
+        );
+        assert_eq!(
+            schema["properties"]["tags"]["items"],
+            serde_json::json!({"anyOf": [{"type": "integer"}, {"type": "string"}]})
+        );
+    }
+
+    #[test]
+    fn test_typescript() {
+        assert_eq!(
+            typescript(&observed(), "Users", "Inferred."),
+            r#"// Inferred.
+
+export interface Users {
+  /** 50.0% present, types: S 1, e.g. "Alice" */
+  firstName?: string;
+  /** 100.0% present, types: M 2 */
+  info: UsersInfo;
+  /** 100.0% present, types: S 2, e.g. "a", "b" */
+  pk: string;
+  /** 50.0% present, types: L 1 */
+  tags?: (number | string)[];
+}
+
+export interface UsersInfo {
+  /** 100.0% present, types: N 2, e.g. 1, 2.5 */
+  rank: number;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_rust() {
+        assert_eq!(
+            rust(&observed(), "Users", "Inferred."),
+            r#"// Inferred.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Users {
+    /// 50.0% present, types: S 1, e.g. "Alice"
+    #[serde(rename = "firstName")]
+    pub first_name: Option<String>,
+    /// 100.0% present, types: M 2
+    pub info: UsersInfo,
+    /// 100.0% present, types: S 2, e.g. "a", "b"
+    pub pk: String,
+    /// 50.0% present, types: L 1
+    pub tags: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsersInfo {
+    /// 100.0% present, types: N 2, e.g. 1, 2.5
+    pub rank: f64,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_colliding_names() {
+        let map = |field: &str| AttributeValue::M(HashMap::from([(field.to_owned(), s("x"))]));
+        let mut root = Shape::default();
+        root.observe_item(&HashMap::from([
+            ("firstName".to_owned(), s("Alice")),
+            ("first_name".to_owned(), s("Bob")),
+            ("info_x".to_owned(), map("a")),
+            ("infoX".to_owned(), map("b")),
+        ]));
+        let names: Vec<String> = definitions(&root, "Users")
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["Users", "UsersInfoX", "UsersInfoX2"]);
+
+        let rust = rust(&root, "Users", "Inferred.");
+        assert!(
+            rust.contains("    #[serde(rename = \"firstName\")]\n    pub first_name: String,\n")
+        );
+        assert!(
+            rust.contains("    #[serde(rename = \"first_name\")]\n    pub first_name_2: String,\n")
+        );
+        assert!(rust.contains("    pub info_x: UsersInfoX,\n"));
+        assert!(rust.contains("    pub info_x_2: UsersInfoX2,\n"));
+        assert!(rust.contains("pub struct UsersInfoX2 {\n    /// 100.0% present, types: S 1, e.g. \"x\"\n    pub a: String,\n"));
+
+        let typescript = typescript(&root, "Users", "Inferred.");
+        assert!(typescript.contains("  infoX: UsersInfoX;\n"));
+        assert!(typescript.contains("  info_x: UsersInfoX2;\n"));
+    }
+}
@@ -14,10 +14,26 @@
  * limitations under the License.
  */
 
+use crate::app;
 use crate::cmd;
+use crate::control;
+use crate::output::{self, OutputFormat};
+use clap::{Command, CommandFactory};
 use log::debug;
-use std::io::{stdout, BufRead, IsTerminal, Stdin, StdinLock, Write};
-use std::{error::Error, io};
+use rustyline::{
+    completion::Completer,
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::FileHistory,
+    validate::{ValidationContext, ValidationResult, Validator},
+    CompletionType, Config, Editor, Helper,
+};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, IsTerminal, Stdin};
+use std::iter::Peekable;
+use std::str::Chars;
+use std::{env, error::Error, fs, io};
 
 /* =================================================
 struct / enum / const
@@ -25,18 +41,75 @@ struct / enum / const
 
 pub enum ShellInput {
     Builtin(BuiltinCommands),
-    Command(cmd::Sub),
+    Command(Box<cmd::Sub>),
     Eof,
     ParseError(Box<dyn Error>),
 }
 
 pub enum BuiltinCommands {
     Exit,
+    /// `use <table>` switches the table, without calling DescribeTable API if its schema is cached.
+    Use(String),
+    /// `region <name>` switches the region for the rest of the session.
+    Region(String),
+    /// `\timing [on|off]` shows elapsed time and consumed capacity after each command. Toggled without an argument.
+    Timing(Option<bool>),
+    /// `set output <format>` sets default --output of commands in the session. `set output default` resets it.
+    SetOutput(Option<OutputFormat>),
+}
+
+// `use` is not listed here as it's also a dy command.
+const BUILTIN_COMMANDS: &[&str] = &["exit", "region", "\\timing", "set", "history", "source"];
+const MAX_HISTORY_SIZE: usize = 1000;
+// Shown while reading the rest of a command, e.g. after a trailing backslash or in an open quote.
+const CONTINUATION_PROMPT: &str = "... ";
+// Maximum nesting of `source`, which stops a file sourcing itself directly or indirectly.
+const MAX_SOURCE_DEPTH: usize = 16;
+
+// Options and positional arguments whose values are completed with names other than subcommands/options.
+const TABLE_OPTIONS: &[&str] = &["--table", "-t"];
+const INDEX_OPTIONS: &[&str] = &["--index", "-i"];
+const ATTRIBUTE_OPTIONS: &[&str] = &["--attributes", "-a", "--set", "--remove", "--filter"];
+const TABLE_POSITIONALS: &[&str] = &[
+    "target_table_to_desc",
+    "target_table_to_use",
+    "table_name_to_update",
+    "table_name_to_delete",
+];
+
+/// Names for tab completion which cannot be known from the command definition.
+/// Completion runs synchronously while a line is being edited, so these names are refreshed by the shell loop between commands.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CompletionCandidates {
+    pub table_names: Vec<String>,
+    pub index_names: Vec<String>,
+    pub attribute_names: Vec<String>,
+}
+
+struct ShellHelper {
+    candidates: CompletionCandidates,
+}
+
+enum LineSource<'a> {
+    /// stdin is a terminal. Lines are edited with rustyline, and saved into the history file.
+    Editor {
+        editor: Box<Editor<ShellHelper, FileHistory>>,
+        history_path: Option<String>,
+    },
+    /// stdin is a pipe or a file, or a script is given by --script. Lines are read as is.
+    Reader(Box<dyn BufRead + 'a>),
 }
 
 pub struct ShellReader<'a> {
-    line: String,
-    input: StdinLock<'a>,
+    source: LineSource<'a>,
+    /// Lines of files given by `source`, with the nesting depth of `source` that read them.
+    pending_lines: VecDeque<(String, usize)>,
+    /// Variables defined by `set NAME=value` or --var, which are referred as `${NAME}` in commands.
+    variables: HashMap<String, String>,
+    /// Number of lines read from the source, excluding lines of files given by `source`.
+    line_number: usize,
+    /// Line number where the last command started, which is used in error messages of scripts.
+    command_line_number: usize,
 }
 
 /* =================================================
@@ -44,126 +117,884 @@ inherent methods
 ================================================= */
 
 impl<'a> ShellReader<'a> {
-    pub fn new(input: &'a Stdin) -> Self {
+    pub fn new(input: &'a Stdin) -> Result<Self, Box<dyn Error>> {
+        let source = if io::stdin().is_terminal() {
+            let config = Config::builder()
+                .completion_type(CompletionType::List)
+                .max_history_size(MAX_HISTORY_SIZE)?
+                .auto_add_history(false)
+                .build();
+            let mut editor: Editor<ShellHelper, FileHistory> = Editor::with_config(config)?;
+            editor.set_helper(Some(ShellHelper {
+                candidates: CompletionCandidates::default(),
+            }));
+
+            // History is not essential for the shell. Continue without it if the file is not available.
+            let history_path = app::retrieve_history_file_path()
+                .map_err(|e| debug!("history file is not available: {}", e))
+                .ok();
+            if let Some(path) = &history_path {
+                if let Err(e) = editor.load_history(path) {
+                    debug!("couldn't load the history file '{}': {}", path, e);
+                }
+            }
+            LineSource::Editor {
+                editor: Box::new(editor),
+                history_path,
+            }
+        } else {
+            LineSource::Reader(Box::new(input.lock()))
+        };
+        Ok(Self::with_source(source))
+    }
+
+    /// Creates a reader of a script file given by --script.
+    pub fn from_script(path: &str) -> Result<Self, Box<dyn Error>> {
+        let file = fs::File::open(path)
+            .map_err(|e| format!("Failed to open the script '{}': {}", path, e))?;
+        Ok(Self::with_source(LineSource::Reader(Box::new(
+            BufReader::new(file),
+        ))))
+    }
+
+    fn with_source(source: LineSource<'a>) -> Self {
         Self {
-            line: String::new(),
-            input: input.lock(),
+            source,
+            pending_lines: VecDeque::new(),
+            variables: HashMap::new(),
+            line_number: 0,
+            command_line_number: 0,
         }
     }
 
-    pub fn read_line(&mut self) -> Result<ShellInput, Box<dyn Error>> {
-        if io::stdin().is_terminal() {
-            print!("> ");
-            stdout().flush().expect("failed to flush output");
-        }
-        self.line.clear();
-        match self.input.read_line(&mut self.line) {
-            Ok(0) => {
-                return Ok(ShellInput::Eof);
-            }
-            Ok(_) => (),
-            Err(e) => return Err(Box::new(e)),
-        }
+    pub fn set_variable(&mut self, name: String, value: String) {
+        self.variables.insert(name, value);
+    }
 
-        let line = self.line.trim_end();
+    /// Returns true if commands are typed in a terminal, where the line number of a command is meaningless.
+    pub fn is_interactive(&self) -> bool {
+        matches!(self.source, LineSource::Editor { .. })
+    }
 
-        debug!("Line read: {:?}", line);
+    pub fn command_line_number(&self) -> usize {
+        self.command_line_number
+    }
 
-        match line {
-            // build-in shell command(s)
-            "exit" => Ok(ShellInput::Builtin(BuiltinCommands::Exit)),
-            // dy commands
-            line => {
-                // TODO: better handling of whitespaces
-                let args = match parse(line) {
-                    Ok(args) => args,
+    /// Refreshes names used for tab completion. Table names are fetched only when `with_tables` is true,
+    /// as it requires ListTables API call. Nothing is done when stdin is not a terminal.
+    pub async fn refresh_candidates(&mut self, cx: &app::Context, with_tables: bool) {
+        let helper = match &mut self.source {
+            LineSource::Editor { editor, .. } => match editor.helper_mut() {
+                Some(helper) => helper,
+                None => return,
+            },
+            LineSource::Reader(_) => return,
+        };
+
+        if with_tables {
+            match control::try_list_tables_api(cx, None).await {
+                Ok(table_names) => helper.candidates.table_names = table_names,
+                Err(e) => debug!("couldn't list tables for completion: {:?}", e),
+            }
+        }
+        helper.candidates.index_names = cx
+            .cached_using_table_schema()
+            .await
+            .and_then(|ts| ts.indexes)
+            .map(|indexes| indexes.into_iter().map(|idx| idx.name).collect())
+            .unwrap_or_default();
+        helper.candidates.attribute_names = cx
+            .recent_attribute_names
+            .lock()
+            .expect("recent_attribute_names should not be poisoned")
+            .iter()
+            .cloned()
+            .collect();
+    }
+
+    /// Reads the next command. Lines given by `source <file>` are read before the next line from stdin.
+    /// A command continues to the following lines while a quote or a bracket is open, or a line ends with a backslash.
+    /// `history` and `source` are handled here, and other built-ins are returned to the caller.
+    pub fn read_line(&mut self, prompt: &str) -> Result<ShellInput, Box<dyn Error>> {
+        loop {
+            let (mut command, depth) = match self.pending_lines.pop_front() {
+                Some(line) => line,
+                None => match self.read_raw_line(prompt)? {
+                    Some(line) => (line, 0),
+                    None => return Ok(ShellInput::Eof),
+                },
+            };
+            let from_file = depth > 0;
+            if !from_file {
+                self.command_line_number = self.line_number;
+            }
+            let args = loop {
+                match parse(command.trim_end_matches(['\r', '\n']), &self.variables) {
+                    Ok(args) => break args,
+                    Err(e) if is_incomplete(e.as_ref()) => {
+                        // The rest of a command in a sourced file is read from the same file.
+                        let next_line = if from_file {
+                            self.pending_lines.pop_front().map(|(line, _)| line)
+                        } else {
+                            self.read_raw_line(CONTINUATION_PROMPT)?
+                        };
+                        match next_line {
+                            Some(line) => {
+                                command.truncate(command.trim_end_matches(['\r', '\n']).len());
+                                command.push('\n');
+                                command.push_str(&line);
+                            }
+                            None => {
+                                eprintln!("Error while parsing input: {}", e);
+                                return Ok(ShellInput::ParseError(e));
+                            }
+                        }
+                    }
                     Err(e) => {
                         eprintln!("Error while parsing input: {}", e);
                         return Ok(ShellInput::ParseError(e));
                     }
-                };
-                debug!("Args: {:?}", args);
-                let child = match cmd::parse_args(args) {
-                    Ok(child) => child,
-                    Err(e) => {
-                        eprintln!("Invalid argument: {}", e);
-                        return Ok(ShellInput::ParseError(e));
+                }
+            };
+            debug!("Args: {:?}", args);
+            // Blank lines and comments
+            if args.is_empty() {
+                continue;
+            }
+
+            let str_args: Vec<&str> = args.iter().map(String::as_str).collect();
+            match str_args.as_slice() {
+                ["history"] => {
+                    self.print_history();
+                    continue;
+                }
+                ["set", definition] if definition.contains('=') => {
+                    match parse_variable(definition) {
+                        Ok((name, value)) => {
+                            self.variables.insert(name, value);
+                            continue;
+                        }
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            return Ok(ShellInput::ParseError(e.into()));
+                        }
+                    }
+                }
+                ["source", file] => {
+                    if depth >= MAX_SOURCE_DEPTH {
+                        let e = format!(
+                            "Failed to read '{}': source is nested more than {} levels. Does the file source itself?",
+                            file, MAX_SOURCE_DEPTH
+                        );
+                        eprintln!("{}", e);
+                        return Ok(ShellInput::ParseError(e.into()));
+                    }
+                    match fs::read_to_string(file) {
+                        // Lines of the file are executed before the rest of the current input.
+                        Ok(content) => content
+                            .lines()
+                            .rev()
+                            .for_each(|l| self.pending_lines.push_front((l.to_owned(), depth + 1))),
+                        Err(e) => {
+                            let e = format!("Failed to read '{}': {}", file, e);
+                            eprintln!("{}", e);
+                            return Ok(ShellInput::ParseError(e.into()));
+                        }
+                    }
+                    continue;
+                }
+                _ => (),
+            }
+            if let Some(builtin) = parse_builtin(&str_args) {
+                return match builtin {
+                    Ok(builtin) => Ok(ShellInput::Builtin(builtin)),
+                    Err(usage) => {
+                        eprintln!("{}", usage);
+                        Ok(ShellInput::ParseError(usage.into()))
                     }
                 };
-                Ok(ShellInput::Command(child))
             }
+
+            // dy commands
+            let child = match cmd::parse_args(args) {
+                Ok(child) => child,
+                Err(e) => {
+                    eprintln!("Invalid argument: {}", e);
+                    return Ok(ShellInput::ParseError(e));
+                }
+            };
+            return Ok(ShellInput::Command(Box::new(child)));
+        }
+    }
+
+    /// Reads a line from the terminal or stdin. Returns None at the end of input.
+    fn read_raw_line(&mut self, prompt: &str) -> Result<Option<String>, Box<dyn Error>> {
+        match &mut self.source {
+            LineSource::Editor {
+                editor,
+                history_path,
+            } => loop {
+                match editor.readline(prompt) {
+                    Ok(line) if line.trim().is_empty() => continue,
+                    Ok(line) => {
+                        editor.add_history_entry(line.as_str())?;
+                        if let Some(path) = history_path {
+                            if let Err(e) = editor.append_history(path) {
+                                debug!("couldn't save the history file '{}': {}", path, e);
+                            }
+                        }
+                        return Ok(Some(line));
+                    }
+                    // Ctrl-C discards the current line as other shells do.
+                    Err(ReadlineError::Interrupted) => continue,
+                    Err(ReadlineError::Eof) => return Ok(None),
+                    Err(e) => return Err(Box::new(e)),
+                }
+            },
+            LineSource::Reader(input) => {
+                let mut line = String::new();
+                match input.read_line(&mut line)? {
+                    0 => Ok(None),
+                    _ => {
+                        self.line_number += 1;
+                        Ok(Some(line))
+                    }
+                }
+            }
+        }
+    }
+
+    fn print_history(&self) {
+        if let LineSource::Editor { editor, .. } = &self.source {
+            for (i, entry) in editor.history().iter().enumerate() {
+                println!("{:>5}  {}", i + 1, entry);
+            }
+        }
+    }
+}
+
+/// Builds the prompt which shows the current region and table, e.g. `[local:8000/Movie]> `.
+/// The name of the current context is shown before them, e.g. `(staging) [us-west-2/Movie]> `.
+pub async fn prompt(cx: &app::Context) -> String {
+    let mut target = if cx.is_local().await {
+        format!("local:{}", cx.effective_port())
+    } else {
+        cx.effective_region().await.as_ref().to_owned()
+    };
+    let table = cx.overwritten_table_name.clone().or_else(|| {
+        cx.config
+            .as_ref()
+            .and_then(|c| c.using_table())
+            .map(str::to_owned)
+    });
+    if let Some(table) = table {
+        target.push('/');
+        target.push_str(&table);
+    }
+    let context_name = cx
+        .config
+        .as_ref()
+        .filter(|c| c.current_context().is_some())
+        .and_then(|c| c.current_context.as_deref());
+    match context_name {
+        Some(name) => format!("({}) [{}]> ", name, target),
+        None => format!("[{}]> ", target),
+    }
+}
+
+impl CompletionCandidates {
+    /// Returns the position where the completed word starts, and candidates of the word, for the line before the cursor.
+    pub fn complete(&self, line: &str) -> (usize, Vec<String>) {
+        let word_start = line.rfind(' ').map_or(0, |i| i + 1);
+        let word = &line[word_start..];
+        let tokens: Vec<&str> = line[..word_start].split_whitespace().collect();
+
+        // Walk down subcommands (e.g. `admin create table`) to find the command being typed.
+        let mut command = cmd::Sub::command();
+        let mut args = tokens.as_slice();
+        while let Some((first, rest)) = args.split_first() {
+            match command.find_subcommand(first) {
+                Some(sub) => {
+                    command = sub.clone();
+                    args = rest;
+                }
+                None => break,
+            }
+        }
+
+        let candidates: Vec<String> = match args.last() {
+            Some(prev) if TABLE_OPTIONS.contains(prev) => self.table_names.clone(),
+            Some(prev) if INDEX_OPTIONS.contains(prev) => self.index_names.clone(),
+            Some(prev) if ATTRIBUTE_OPTIONS.contains(prev) => {
+                // Values of these options are lists or expressions, e.g. `--attributes name,age` or `--set 'age = 1'`.
+                let name_start = word
+                    .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+                    .map_or(0, |i| i + 1);
+                return (
+                    word_start + name_start,
+                    filter_candidates(&self.attribute_names, &word[name_start..]),
+                );
+            }
+            _ if word.starts_with('-') => command
+                .get_arguments()
+                .filter_map(|arg| arg.get_long())
+                .map(|long| format!("--{}", long))
+                .collect(),
+            _ if command.has_subcommands() => {
+                let mut names: Vec<String> = command
+                    .get_subcommands()
+                    .map(|sub| sub.get_name().to_owned())
+                    .collect();
+                if tokens.is_empty() {
+                    names.extend(BUILTIN_COMMANDS.iter().map(|b| b.to_string()));
+                }
+                names
+            }
+            _ if expects_table_name(&command, args) => self.table_names.clone(),
+            _ => vec![],
+        };
+        (word_start, filter_candidates(&candidates, word))
+    }
+}
+
+/* =================================================
+trait implementations
+================================================= */
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates.complete(&line[..pos]))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {
+    /// Lets Enter insert a newline while the command is incomplete, so that a multi-line command is edited and saved in the history as a whole.
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        // Other errors such as undefined variables are reported after Enter is pressed.
+        match parse(ctx.input(), &HashMap::new()) {
+            Err(e) if is_incomplete(e.as_ref()) => Ok(ValidationResult::Incomplete),
+            _ => Ok(ValidationResult::Valid(None)),
+        }
+    }
+}
+
+impl Helper for ShellHelper {}
+
+/* =================================================
+Private functions
+================================================= */
+
+/// Parses built-in commands other than `history` and `source`. Returns None if the line is a dy command.
+fn parse_builtin(args: &[&str]) -> Option<Result<BuiltinCommands, String>> {
+    let builtin = match args {
+        ["exit"] => Ok(BuiltinCommands::Exit),
+        // `use` with options (e.g. `use --table mytable`) is executed as the dy command.
+        ["use", table] if !table.starts_with('-') => Ok(BuiltinCommands::Use(table.to_string())),
+        ["region", region] => Ok(BuiltinCommands::Region(region.to_string())),
+        ["region", ..] => Err("Usage: region <name>".to_owned()),
+        ["\\timing"] => Ok(BuiltinCommands::Timing(None)),
+        ["\\timing", "on"] => Ok(BuiltinCommands::Timing(Some(true))),
+        ["\\timing", "off"] => Ok(BuiltinCommands::Timing(Some(false))),
+        ["\\timing", ..] => Err("Usage: \\timing [on|off]".to_owned()),
+        ["set", "output", "default"] => Ok(BuiltinCommands::SetOutput(None)),
+        ["set", "output", format] if format.parse::<OutputFormat>().is_ok() => Ok(
+            BuiltinCommands::SetOutput(format.parse::<OutputFormat>().ok()),
+        ),
+        ["set", ..] => Err(format!(
+            "Usage: set output <{}|default>, or set NAME=value",
+            output::ITEM_FORMATS
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<_>>()
+                .join("|")
+        )),
+        ["history", ..] => Err("Usage: history".to_owned()),
+        ["source", ..] => Err("Usage: source <file>".to_owned()),
+        _ => return None,
+    };
+    Some(builtin)
+}
+
+fn filter_candidates(candidates: &[String], prefix: &str) -> Vec<String> {
+    let mut filtered: Vec<String> = candidates
+        .iter()
+        .filter(|c| c.starts_with(prefix))
+        .cloned()
+        .collect();
+    filtered.sort();
+    filtered.dedup();
+    filtered
+}
+
+/// Returns true if the next word is the first positional argument of the command and it is a table name, e.g. `use <table>`.
+fn expects_table_name(command: &Command, args: &[&str]) -> bool {
+    match command.get_positionals().next() {
+        Some(arg) if TABLE_POSITIONALS.contains(&arg.get_id().as_str()) => (),
+        _ => return false,
+    }
+
+    // Skip options and their values. Any other word means the positional argument is already given.
+    let mut skip_value = false;
+    for arg in args {
+        if skip_value {
+            skip_value = false;
+        } else if arg.starts_with('-') {
+            skip_value = command.get_arguments().any(|a| {
+                let matches_long = a.get_long().map(|l| format!("--{}", l)).as_deref() == Some(arg);
+                let matches_short =
+                    a.get_short().map(|s| format!("-{}", s)).as_deref() == Some(arg);
+                (matches_long || matches_short) && a.get_action().takes_values()
+            });
+        } else {
+            return false;
         }
     }
+    true
+}
+
+/// Splits a command into words as POSIX shells do, with a few differences:
+/// - Backslash escapes any character in single quotes too, e.g. `'it\'s'`.
+/// - Backslash at the beginning of a command is kept for built-ins such as `\timing`.
+/// - Unquoted brackets (`{}` and `[]`) are kept as is including quotes and whitespaces in them,
+///   so that JSON such as `--item {"title": "Lost"}` can be written without quoting, even across lines.
+///
+/// A backslash at the end of a line continues the command to the next line, and `#` at the beginning of a word starts a comment.
+/// `${NAME}` outside of single quotes is replaced with the variable, or the environment variable if the variable is not defined.
+/// Returns an error of `io::ErrorKind::UnexpectedEof` if the command is incomplete, e.g. a quote isn't closed.
+fn parse(line: &str, variables: &HashMap<String, String>) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut tokenizer = Tokenizer {
+        chars: line.chars().peekable(),
+        variables,
+        undefined_variable: None,
+    };
+    let words = tokenizer.words()?;
+    // Undefined variables are reported after the whole command is read, so that the rest of the command is not left unread.
+    match tokenizer.undefined_variable {
+        Some(name) => Err(Box::new(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("variable '{}' is not defined", name),
+        ))),
+        None => Ok(words),
+    }
+}
+
+struct Tokenizer<'a> {
+    chars: Peekable<Chars<'a>>,
+    variables: &'a HashMap<String, String>,
+    undefined_variable: Option<String>,
 }
 
-fn parse(line: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    let mut ret = vec![];
-    let mut input = line.trim_start();
-    while !input.is_empty() {
-        if input.starts_with('\'') {
-            let mut tok = String::new();
-            let mut iter = input.chars();
-            // discard first "'"
-            iter.next();
-            loop {
-                match iter.next() {
-                    Some('\'') => break,
-                    Some('\\') => match iter.next() {
-                        Some(c) => tok.push(c),
-                        None => {
-                            return Err(Box::new(io::Error::new(
-                                io::ErrorKind::InvalidData,
-                                "escape('\\') is incomplete",
-                            )));
+impl Tokenizer<'_> {
+    fn words(&mut self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut ret = vec![];
+        loop {
+            while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+            match self.chars.peek() {
+                None => break,
+                Some('#') => {
+                    while self.chars.next_if(|c| *c != '\n').is_some() {}
+                    continue;
+                }
+                Some(_) => (),
+            }
+
+            let mut word = String::new();
+            // Distinguishes an empty word given by quotes (e.g. `''`) from a line continuation.
+            let mut quoted = false;
+            while let Some(c) = self.chars.next_if(|c| !c.is_whitespace()) {
+                match c {
+                    '\'' => {
+                        quoted = true;
+                        loop {
+                            match self.chars.next() {
+                                Some('\'') => break,
+                                Some('\\') => word.push(self.next_escaped()?),
+                                Some(c) => word.push(c),
+                                None => return Err(incomplete("quote isn't closed")),
+                            }
                         }
+                    }
+                    '"' => {
+                        quoted = true;
+                        loop {
+                            match self.chars.next() {
+                                Some('"') => break,
+                                // Only these characters are escaped in double quotes as in POSIX shells.
+                                Some('\\') => match self.next_escaped()? {
+                                    '\n' => (),
+                                    c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                                    c => {
+                                        word.push('\\');
+                                        word.push(c);
+                                    }
+                                },
+                                Some('$') => self.push_dollar(&mut word)?,
+                                Some(c) => word.push(c),
+                                None => return Err(incomplete("quote isn't closed")),
+                            }
+                        }
+                    }
+                    // Keeps the backslash of built-ins such as `\timing`.
+                    '\\' if ret.is_empty()
+                        && word.is_empty()
+                        && self.chars.peek().map_or(false, |c| c.is_alphabetic()) =>
+                    {
+                        word.push(c)
+                    }
+                    '\\' => match self.next_escaped()? {
+                        '\n' => (),
+                        c => word.push(c),
                     },
-                    Some(c) => tok.push(c),
-                    None => {
-                        return Err(Box::new(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            "quote isn't closed",
-                        )));
+                    '{' | '[' => {
+                        word.push(c);
+                        self.push_bracketed(&mut word)?;
                     }
+                    '$' => self.push_dollar(&mut word)?,
+                    c => word.push(c),
                 }
             }
-            input = iter.as_str().trim_start();
-            ret.push(tok);
-        } else {
-            let pos = input.find(' ').unwrap_or(input.len());
-            let (tok, rest) = input.split_at(pos);
-            ret.push(tok.into());
-            input = rest.trim_start();
+            if quoted || !word.is_empty() {
+                ret.push(word);
+            }
+        }
+        Ok(ret)
+    }
+
+    fn next_escaped(&mut self) -> Result<char, Box<dyn Error>> {
+        self.chars
+            .next()
+            .ok_or_else(|| incomplete("escape('\\') is incomplete"))
+    }
+
+    /// Pushes characters into the word until the bracket already pushed is closed.
+    /// Brackets in JSON strings are ignored.
+    fn push_bracketed(&mut self, word: &mut String) -> Result<(), Box<dyn Error>> {
+        let mut depth = 1;
+        let mut in_string = false;
+        while depth > 0 {
+            let c = self
+                .chars
+                .next()
+                .ok_or_else(|| incomplete("bracket isn't closed"))?;
+            match (in_string, c) {
+                (true, '\\') => {
+                    word.push(c);
+                    word.push(self.next_escaped()?);
+                    continue;
+                }
+                (_, '$') => {
+                    self.push_dollar(word)?;
+                    continue;
+                }
+                (_, '"') => in_string = !in_string,
+                (false, '{' | '[') => depth += 1,
+                (false, '}' | ']') => depth -= 1,
+                _ => (),
+            }
+            word.push(c);
+        }
+        Ok(())
+    }
+
+    /// Pushes the value of `${NAME}` after `$`. `$` not followed by `{` is pushed as is.
+    fn push_dollar(&mut self, word: &mut String) -> Result<(), Box<dyn Error>> {
+        if self.chars.next_if_eq(&'{').is_none() {
+            word.push('$');
+            return Ok(());
+        }
+        let mut name = String::new();
+        loop {
+            match self.chars.next() {
+                Some('}') => break,
+                Some(c) if c == '_' || c.is_ascii_alphanumeric() => name.push(c),
+                _ => {
+                    return Err(Box::new(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid variable reference '${{{}'", name),
+                    )))
+                }
+            }
+        }
+        match self
+            .variables
+            .get(&name)
+            .cloned()
+            .or_else(|| env::var(&name).ok())
+        {
+            Some(value) => word.push_str(&value),
+            None => {
+                self.undefined_variable.get_or_insert(name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses `NAME=value` given to `set` or --var.
+pub fn parse_variable(definition: &str) -> Result<(String, String), String> {
+    match definition.split_once('=') {
+        Some((name, value))
+            if !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c == '_' || c.is_ascii_alphanumeric()) =>
+        {
+            Ok((name.to_owned(), value.to_owned()))
         }
+        _ => Err(format!(
+            "invalid variable definition '{}'. Use NAME=value, where NAME consists of alphanumerics and '_'.",
+            definition
+        )),
     }
-    Ok(ret)
+}
+
+fn is_incomplete(e: &(dyn Error + 'static)) -> bool {
+    matches!(e.downcast_ref::<io::Error>(), Some(e) if e.kind() == io::ErrorKind::UnexpectedEof)
+}
+
+fn incomplete(msg: &str) -> Box<dyn Error> {
+    Box::new(io::Error::new(io::ErrorKind::UnexpectedEof, msg))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn candidates() -> CompletionCandidates {
+        CompletionCandidates {
+            table_names: vec!["Movie".to_owned(), "Music".to_owned(), "Reply".to_owned()],
+            index_names: vec!["year-index".to_owned()],
+            attribute_names: vec!["title".to_owned(), "tags".to_owned(), "year".to_owned()],
+        }
+    }
+
+    #[test]
+    fn test_complete_subcommands() {
+        let c = candidates();
+        assert_eq!(c.complete("q"), (0, vec!["query".to_owned()]));
+        assert_eq!(
+            c.complete("ex"),
+            (0, vec!["exit".to_owned(), "export".to_owned()])
+        );
+        assert_eq!(c.complete("admin create t"), (13, vec!["table".to_owned()]));
+        assert_eq!(
+            c.complete("scan --con"),
+            (5, vec!["--consistent-read".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_complete_names() {
+        let c = candidates();
+        assert_eq!(
+            c.complete("use M"),
+            (4, vec!["Movie".to_owned(), "Music".to_owned()])
+        );
+        assert_eq!(c.complete("scan --table R"), (13, vec!["Reply".to_owned()]));
+        assert_eq!(
+            c.complete("query abc -i "),
+            (13, vec!["year-index".to_owned()])
+        );
+        assert_eq!(
+            c.complete("scan --attributes title,t"),
+            (24, vec!["tags".to_owned(), "title".to_owned()])
+        );
+        assert_eq!(c.complete("upd 1 --set 'ye"), (13, vec!["year".to_owned()]));
+        // Table names are not suggested after the positional argument is given.
+        assert_eq!(c.complete("use Movie M"), (10, vec![]));
+        // New table name is not completed with existing tables.
+        assert_eq!(c.complete("admin create table M"), (19, vec![]));
+    }
+
     #[test]
     fn test_parse_ok() {
         let input = r#"query --sort-key '= 12' 'pk\\is\'escaped'"#;
-        let result = parse(input);
+        let result = parse(input, &HashMap::new());
         assert_eq!(
             result.unwrap(),
             vec!["query", "--sort-key", "= 12", r#"pk\is'escaped"#]
         )
     }
 
+    #[test]
+    fn test_parse_quotes() {
+        assert_eq!(
+            parse(
+                r##"scan --filter "#n = :v"  --filter-values '{":v": 1}'"##,
+                &HashMap::new()
+            )
+            .unwrap(),
+            vec![
+                "scan",
+                "--filter",
+                "#n = :v",
+                "--filter-values",
+                r#"{":v": 1}"#
+            ]
+        );
+        assert_eq!(
+            parse(r#"get "a\"b\c" '' x"y"'z'"#, &HashMap::new()).unwrap(),
+            vec!["get", r#"a"b\c"#, "", "xyz"]
+        );
+        assert_eq!(
+            parse("\\timing on", &HashMap::new()).unwrap(),
+            vec!["\\timing", "on"]
+        );
+        assert_eq!(parse("get \\a", &HashMap::new()).unwrap(), vec!["get", "a"]);
+        assert_eq!(
+            parse("  \t ", &HashMap::new()).unwrap(),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_parse_multiline() {
+        // Line continuation and comments
+        assert_eq!(
+            parse(
+                "query 1 \\\n  --sort-key '> 3' # comment\n# comment only",
+                &HashMap::new()
+            )
+            .unwrap(),
+            vec!["query", "1", "--sort-key", "> 3"]
+        );
+        // JSON without quotes across lines
+        let input = r#"put 1 --item {
+  "title": "Lost in [Translation}",
+  "tags": ["a", "b"]
+}"#;
+        assert_eq!(
+            parse(input, &HashMap::new()).unwrap(),
+            vec![
+                "put",
+                "1",
+                "--item",
+                r#"{
+  "title": "Lost in [Translation}",
+  "tags": ["a", "b"]
+}"#
+            ]
+        );
+        // Quoted JSON keeps newlines too.
+        assert_eq!(
+            parse("put 1 --item '{\n\"a\": 1\n}'", &HashMap::new()).unwrap(),
+            vec!["put", "1", "--item", "{\n\"a\": 1\n}"]
+        );
+        assert_eq!(
+            parse("upd 1 --remove tags[0]", &HashMap::new()).unwrap(),
+            vec!["upd", "1", "--remove", "tags[0]"]
+        );
+    }
+
+    #[test]
+    fn test_parse_variables() {
+        let variables = HashMap::from([("PK".to_owned(), "abc".to_owned())]);
+        env::set_var("DYNEIN_TEST_SHELL_VARIABLE", "from env");
+        assert_eq!(
+            parse(
+                r#"get ${PK} "${DYNEIN_TEST_SHELL_VARIABLE}" '${PK}' \${PK} $PK"#,
+                &variables
+            )
+            .unwrap(),
+            vec!["get", "abc", "from env", "${PK}", "${PK}", "$PK"]
+        );
+        assert_eq!(
+            parse(r#"put ${PK} --item {"sk": "${PK}-1"}"#, &variables).unwrap(),
+            vec!["put", "abc", "--item", r#"{"sk": "abc-1"}"#]
+        );
+
+        let e = parse("get ${UNDEFINED} '", &variables).unwrap_err();
+        assert!(is_incomplete(e.as_ref()));
+        let e = parse("get ${UNDEFINED}", &variables).unwrap_err();
+        assert!(!is_incomplete(e.as_ref()));
+        assert!(parse("get ${PK", &variables).is_err());
+    }
+
+    #[test]
+    fn test_parse_variable() {
+        assert_eq!(
+            parse_variable("PK_1=a=b"),
+            Ok(("PK_1".to_owned(), "a=b".to_owned()))
+        );
+        assert_eq!(
+            parse_variable("EMPTY="),
+            Ok(("EMPTY".to_owned(), "".to_owned()))
+        );
+        assert!(parse_variable("PK").is_err());
+        assert!(parse_variable("=abc").is_err());
+        assert!(parse_variable("1PK=abc").is_err());
+        assert!(parse_variable("P-K=abc").is_err());
+    }
+
     #[test]
     fn test_parse_ng() {
         let input = r#"quote is 'broken"#;
-        let result = parse(input);
+        let result = parse(input, &HashMap::new());
         assert!(result.is_err());
 
         let input = r#"escape is 'broken\"#;
-        let result = parse(input);
+        let result = parse(input, &HashMap::new());
         assert!(result.is_err());
 
         let input = r#"quote is 'broken by escape\'"#;
-        let result = parse(input);
+        let result = parse(input, &HashMap::new());
         assert!(result.is_err());
+
+        assert!(parse("put 1 --item {\"a\": [1, 2}", &HashMap::new()).is_err());
+        assert!(parse("query 1 \\", &HashMap::new()).is_err());
+        assert!(parse(r#"get "abc"#, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_parse_builtin() {
+        assert!(matches!(
+            parse_builtin(&["use", "Movie"]),
+            Some(Ok(BuiltinCommands::Use(t))) if t == "Movie"
+        ));
+        assert!(matches!(
+            parse_builtin(&["region", "local"]),
+            Some(Ok(BuiltinCommands::Region(r))) if r == "local"
+        ));
+        assert!(matches!(
+            parse_builtin(&["\\timing"]),
+            Some(Ok(BuiltinCommands::Timing(None)))
+        ));
+        assert!(matches!(
+            parse_builtin(&["\\timing", "off"]),
+            Some(Ok(BuiltinCommands::Timing(Some(false))))
+        ));
+        assert!(matches!(
+            parse_builtin(&["set", "output", "json"]),
+            Some(Ok(BuiltinCommands::SetOutput(Some(OutputFormat::Json))))
+        ));
+        assert!(matches!(
+            parse_builtin(&["set", "output", "jsonl"]),
+            Some(Ok(BuiltinCommands::SetOutput(Some(OutputFormat::Jsonl))))
+        ));
+        assert!(matches!(
+            parse_builtin(&["set", "output", "default"]),
+            Some(Ok(BuiltinCommands::SetOutput(None)))
+        ));
+        assert!(matches!(
+            parse_builtin(&["set", "output", "xml"]),
+            Some(Err(_))
+        ));
+        assert!(matches!(parse_builtin(&["region"]), Some(Err(_))));
+
+        // dy commands are not built-ins.
+        assert!(parse_builtin(&["use", "--table", "Movie"]).is_none());
+        assert!(parse_builtin(&["scan"]).is_none());
     }
 }
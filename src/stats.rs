@@ -0,0 +1,395 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// This module scans a table and summarizes its items, i.e. item sizes, partition keys and attributes, for `dy stats`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use super::app;
+use super::data;
+use super::output::{self, OutputFormat};
+
+/* =================================================
+struct / enum / const
+================================================= */
+
+/// Statistics of scanned items, printed by `dy stats`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TableStats {
+    table: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<String>,
+    /// True if the scan stopped at --sample items before the end of the table.
+    sampled: bool,
+    items: usize,
+    /// Sizes of items in bytes, computed by the rules of DynamoDB. None if there's no item.
+    item_size: Option<SizeStats>,
+    top_partition_keys_by_items: Vec<PartitionKeyStats>,
+    top_partition_keys_by_bytes: Vec<PartitionKeyStats>,
+    attributes: Vec<AttributeStats>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct SizeStats {
+    average: f64,
+    p50: usize,
+    p99: usize,
+    max: usize,
+    total: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+struct PartitionKeyStats {
+    key: JsonValue,
+    items: usize,
+    bytes: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct AttributeStats {
+    name: String,
+    items: usize,
+    /// Percentage of items which have the attribute.
+    percent: f64,
+    /// Number of values of each DynamoDB type, e.g. {S: 10, N: 2}.
+    types: BTreeMap<&'static str, usize>,
+}
+
+/// Collects statistics from scanned items, page by page.
+#[derive(Debug)]
+struct StatsCollector {
+    pk: String,
+    sizes: Vec<usize>,
+    partitions: HashMap<String, (JsonValue, usize, usize)>, // keyed by DynamoDB JSON of the partition key value
+    attributes: HashMap<String, BTreeMap<&'static str, usize>>,
+}
+
+impl StatsCollector {
+    fn new(pk: String) -> StatsCollector {
+        StatsCollector {
+            pk,
+            sizes: vec![],
+            partitions: HashMap::new(),
+            attributes: HashMap::new(),
+        }
+    }
+
+    fn observe(&mut self, item: &HashMap<String, AttributeValue>) {
+        let size = item_size(item);
+        self.sizes.push(size);
+
+        // Items of a sparse index may lack the partition key of the table, but never that of the index.
+        if let Some(pval) = item.get(&self.pk) {
+            let key = match pval {
+                // JSON output doesn't support binary values, so binary keys are shown in base64.
+                AttributeValue::B(b) => JsonValue::from(general_purpose::STANDARD.encode(b)),
+                _ => data::attrval_to_jsonval(pval),
+            };
+            // Distinct numbers may be the same in JSON, e.g. large ones rounded into f64, so keys are grouped by their raw values.
+            let partition = self
+                .partitions
+                .entry(data::attrval_to_ddbjson(pval).to_string())
+                .or_insert((key, 0, 0));
+            partition.1 += 1;
+            partition.2 += size;
+        }
+
+        for (name, attrval) in item {
+            let types = self.attributes.entry(name.to_owned()).or_default();
+            if let Some(t) = data::attrval_to_type_descriptor(attrval) {
+                *types.entry(t).or_default() += 1;
+            }
+        }
+    }
+
+    fn finish(
+        mut self,
+        table: String,
+        index: Option<String>,
+        sampled: bool,
+        top: usize,
+    ) -> TableStats {
+        let items = self.sizes.len();
+        self.sizes.sort_unstable();
+        let item_size = self.sizes.last().map(|&max| {
+            let total: usize = self.sizes.iter().sum();
+            SizeStats {
+                average: round(total as f64 / items as f64),
+                p50: percentile(&self.sizes, 50),
+                p99: percentile(&self.sizes, 99),
+                max,
+                total,
+            }
+        });
+
+        let mut partitions: Vec<PartitionKeyStats> = self
+            .partitions
+            .into_values()
+            .map(|(key, items, bytes)| PartitionKeyStats { key, items, bytes })
+            .collect();
+        // Ties are ordered by the key so that the output is stable.
+        partitions.sort_by(|a, b| {
+            (b.items, b.bytes)
+                .cmp(&(a.items, a.bytes))
+                .then_with(|| a.key.to_string().cmp(&b.key.to_string()))
+        });
+        let by_items: Vec<PartitionKeyStats> = partitions.iter().take(top).cloned().collect();
+        partitions.sort_by(|a, b| {
+            (b.bytes, b.items)
+                .cmp(&(a.bytes, a.items))
+                .then_with(|| a.key.to_string().cmp(&b.key.to_string()))
+        });
+        partitions.truncate(top);
+
+        let mut attributes: Vec<AttributeStats> = self
+            .attributes
+            .into_iter()
+            .map(|(name, types)| {
+                let count = types.values().sum();
+                AttributeStats {
+                    name,
+                    items: count,
+                    percent: round(count as f64 * 100.0 / items as f64),
+                    types,
+                }
+            })
+            .collect();
+        attributes.sort_by(|a, b| b.items.cmp(&a.items).then_with(|| a.name.cmp(&b.name)));
+
+        TableStats {
+            table,
+            index,
+            sampled,
+            items,
+            item_size,
+            top_partition_keys_by_items: by_items,
+            top_partition_keys_by_bytes: partitions,
+            attributes,
+        }
+    }
+}
+
+/* =================================================
+Public functions
+================================================= */
+
+/// Scans the table (or the index), up to `sample` items if given, and prints statistics of the items.
+pub async fn stats(
+    cx: &app::Context,
+    index: Option<String>,
+    sample: Option<i32>,
+    top: usize,
+) -> Result<(), app::DyneinExit> {
+    let ts: app::TableSchema = data::table_schema_for_index(cx, &index).await?;
+    let pk = match &index {
+        None => ts.pk.name.to_owned(),
+        Some(name) => ts
+            .indexes
+            .iter()
+            .flatten()
+            .find(|idx| &idx.name == name)
+            .expect("the index should exist")
+            .pk
+            .name
+            .to_owned(),
+    };
+
+    let mut collector = StatsCollector::new(pk);
+    let sampled =
+        data::scan_sample(cx, index.clone(), sample, |item| collector.observe(item)).await?;
+
+    let stats = collector.finish(ts.name, index, sampled, top);
+    match cx.output_format(output::DESCRIPTION_FORMATS, OutputFormat::Yaml) {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&stats).unwrap()),
+        OutputFormat::Jsonl => println!("{}", serde_json::to_string(&stats).unwrap()),
+        _ => print!("{}", serde_yaml::to_string(&stats).unwrap()),
+    }
+    Ok(())
+}
+
+/// Returns the size of an item in bytes by the rules of DynamoDB, i.e. the sum of lengths of attribute names and values.
+/// https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/CapacityUnitCalculations.html
+pub fn item_size(item: &HashMap<String, AttributeValue>) -> usize {
+    item.iter()
+        .map(|(name, attrval)| name.len() + attrval_size(attrval))
+        .sum()
+}
+
+/* =================================================
+Private functions
+================================================= */
+
+fn attrval_size(attrval: &AttributeValue) -> usize {
+    match attrval {
+        AttributeValue::S(s) => s.len(),
+        AttributeValue::N(n) => number_size(n),
+        AttributeValue::B(b) => b.as_ref().len(),
+        AttributeValue::Bool(_) | AttributeValue::Null(_) => 1,
+        AttributeValue::Ss(ss) => ss.iter().map(String::len).sum(),
+        AttributeValue::Ns(ns) => ns.iter().map(|n| number_size(n)).sum(),
+        AttributeValue::Bs(bs) => bs.iter().map(|b| b.as_ref().len()).sum(),
+        // Lists and maps need 3 bytes, plus 1 byte per element.
+        AttributeValue::L(l) => 3 + l.iter().map(|v| 1 + attrval_size(v)).sum::<usize>(),
+        AttributeValue::M(m) => {
+            3 + m
+                .iter()
+                .map(|(k, v)| 1 + k.len() + attrval_size(v))
+                .sum::<usize>()
+        }
+        _ => 0,
+    }
+}
+
+/// A number takes 1 byte per 2 significant digits, plus 1 byte.
+fn number_size(n: &str) -> usize {
+    let mantissa = n.split(['e', 'E']).next().unwrap_or_default();
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    let significant = digits.trim_start_matches('0').trim_end_matches('0');
+    (significant.len() + 1) / 2 + 1
+}
+
+/// Returns the nearest-rank percentile of sorted values.
+fn percentile(sorted: &[usize], p: usize) -> usize {
+    let rank = (sorted.len() * p + 99) / 100;
+    sorted[rank.max(1) - 1]
+}
+
+/// Rounds a number to 1 decimal place for readability.
+fn round(x: f64) -> f64 {
+    (x * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::primitives::Blob;
+
+    fn s(v: &str) -> AttributeValue {
+        AttributeValue::S(v.to_owned())
+    }
+
+    fn n(v: &str) -> AttributeValue {
+        AttributeValue::N(v.to_owned())
+    }
+
+    #[test]
+    fn test_item_size() {
+        assert_eq!(number_size("0"), 1);
+        assert_eq!(number_size("7"), 2);
+        assert_eq!(number_size("-12.50"), 3);
+        assert_eq!(number_size("1000"), 2);
+        assert_eq!(number_size("123.456e10"), 4);
+
+        let item = HashMap::from([
+            ("pk".to_owned(), s("abc")),                                // 2 + 3
+            ("n".to_owned(), n("123")),                                 // 1 + 3
+            ("b".to_owned(), AttributeValue::B(Blob::new(vec![0, 1]))), // 1 + 2
+            ("f".to_owned(), AttributeValue::Bool(true)),               // 1 + 1
+            (
+                "ss".to_owned(),
+                AttributeValue::Ss(vec!["a".to_owned(), "bc".to_owned()]),
+            ), // 2 + 3
+            ("l".to_owned(), AttributeValue::L(vec![s("x"), n("1")])),  // 1 + 3 + 2 + 3
+            (
+                "m".to_owned(),
+                AttributeValue::M(HashMap::from([("k".to_owned(), s("v"))])),
+            ), // 1 + 3 + 3
+        ]);
+        assert_eq!(item_size(&item), 5 + 4 + 3 + 2 + 5 + 9 + 7);
+    }
+
+    #[test]
+    fn test_collect_stats() {
+        let mut collector = StatsCollector::new("pk".to_owned());
+        for (pk, extra) in [
+            ("a", None),
+            ("a", Some(n("1"))),
+            ("b", Some(s("long value"))),
+        ] {
+            let mut item = HashMap::from([("pk".to_owned(), s(pk))]);
+            if let Some(v) = extra {
+                item.insert("v".to_owned(), v);
+            }
+            collector.observe(&item);
+        }
+        let stats = collector.finish("t".to_owned(), None, false, 1);
+
+        assert_eq!(stats.items, 3);
+        assert_eq!(
+            stats.item_size,
+            Some(SizeStats {
+                average: 7.7,
+                p50: 6,
+                p99: 14,
+                max: 14,
+                total: 23,
+            })
+        );
+        assert_eq!(
+            stats.top_partition_keys_by_items,
+            vec![PartitionKeyStats {
+                key: JsonValue::from("a"),
+                items: 2,
+                bytes: 9,
+            }]
+        );
+        assert_eq!(
+            stats.top_partition_keys_by_bytes,
+            vec![PartitionKeyStats {
+                key: JsonValue::from("b"),
+                items: 1,
+                bytes: 14,
+            }]
+        );
+        assert_eq!(
+            stats.attributes,
+            vec![
+                AttributeStats {
+                    name: "pk".to_owned(),
+                    items: 3,
+                    percent: 100.0,
+                    types: BTreeMap::from([("S", 3)]),
+                },
+                AttributeStats {
+                    name: "v".to_owned(),
+                    items: 2,
+                    percent: 66.7,
+                    types: BTreeMap::from([("N", 1), ("S", 1)]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_stats_large_numbers() {
+        // These numbers are the same in f64, but they're different partition keys.
+        let mut collector = StatsCollector::new("pk".to_owned());
+        for pk in ["12345678901234567890123", "12345678901234567890124"] {
+            collector.observe(&HashMap::from([("pk".to_owned(), n(pk))]));
+        }
+        let stats = collector.finish("t".to_owned(), None, false, 10);
+        assert_eq!(stats.top_partition_keys_by_items.len(), 2);
+        assert!(stats
+            .top_partition_keys_by_items
+            .iter()
+            .all(|p| p.items == 1));
+    }
+}
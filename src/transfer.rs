@@ -16,26 +16,37 @@
 
 use console::Term;
 use std::collections::VecDeque;
+use std::sync::Arc;
 use std::time::Instant;
 use std::{
     collections::HashMap,
     fs,
-    io::{Error as IOError, Write},
-    path::Path,
+    io::{BufRead, BufReader, Error as IOError, Read, Write},
+    path::{Path, PathBuf},
 };
 
-use dialoguer::Confirm;
+use base64::{engine::general_purpose, Engine as _};
+use brotli::{CompressorWriter, Decompressor};
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
 use log::{debug, error};
+use md5::{Digest, Md5};
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+    basic::Compression as ParquetCompression,
+    file::properties::WriterProperties,
+};
+use serde::Deserialize;
 use serde_json::{de::StrRead, Deserializer, StreamDeserializer, Value as JsonValue};
 
 use aws_sdk_dynamodb::{
-    operation::scan::ScanOutput,
+    operation::{query::QueryOutput, scan::ScanOutput},
     types::{AttributeValue, WriteRequest},
 };
 use thiserror::Error;
 
 use super::app;
 use super::batch;
+use super::columnar;
 use super::data;
 use super::ddb::table;
 
@@ -45,6 +56,10 @@ pub enum DyneinExportError {
     IO(#[from] std::io::Error),
     #[error("serde error")]
     SerdeError(#[from] serde_json::Error),
+    #[error("parquet error")]
+    ParquetError(#[from] columnar::DyneinParquetError),
+    #[error("{0}")]
+    Exit(#[from] app::DyneinExit),
 }
 
 impl From<dialoguer::Error> for DyneinExportError {
@@ -55,21 +70,36 @@ impl From<dialoguer::Error> for DyneinExportError {
     }
 }
 
+/// Specifies which items `dy export` reads. By default, all items in the table are scanned.
+#[derive(Debug, Default)]
+pub struct ExportSource {
+    /// Read items from the secondary index instead of the base table.
+    pub index: Option<String>,
+    /// Partition key value. If given, items are read with Query API instead of Scan API.
+    pub pval: Option<String>,
+    /// Sort key condition, which is effective only with `pval`.
+    pub sort_key_expression: Option<String>,
+    /// FilterExpression and its values in dynein format.
+    pub filter: Option<String>,
+    pub filter_values: Option<String>,
+}
+
 #[derive(Debug)]
 struct SuggestedAttribute {
     name: String,
     type_str: String,
 }
 
+/// Number of processed items and the recent throughput, shown while items are imported or copied.
 #[derive(Clone, Debug, Hash, PartialOrd, PartialEq)]
-struct ProgressState {
+pub struct ProgressState {
     processed_items: usize,
     recent_processed_items: VecDeque<(Instant, usize)>,
     max_recordable_observations: usize,
 }
 
 impl ProgressState {
-    fn new(max_recordable_observations: usize) -> ProgressState {
+    pub fn new(max_recordable_observations: usize) -> ProgressState {
         ProgressState {
             processed_items: 0,
             recent_processed_items: VecDeque::with_capacity(max_recordable_observations),
@@ -77,7 +107,7 @@ impl ProgressState {
         }
     }
 
-    fn add_observation(&mut self, processed_items: usize) {
+    pub fn add_observation(&mut self, processed_items: usize) {
         self.add_observation_with_time(processed_items, Instant::now())
     }
 
@@ -115,7 +145,7 @@ impl ProgressState {
         }
     }
 
-    fn show(&self) {
+    pub fn show(&self) {
         let items = self.processed_items();
         let items_per_sec = self.recent_average_processed_items_per_second();
         let mut term = Term::stdout();
@@ -130,31 +160,200 @@ impl ProgressState {
     }
 }
 
-const MAX_NUMBER_OF_OBSERVES: usize = 10;
+pub const MAX_NUMBER_OF_OBSERVES: usize = 10;
+
+/// Number of items written in a row group of Parquet files.
+const PARQUET_ROW_GROUP_SIZE: usize = 10_000;
+
+const BROTLI_BUFFER_SIZE: usize = 4096;
+const BROTLI_QUALITY: u32 = 9;
+const BROTLI_LG_WINDOW_SIZE: u32 = 22;
+
+/// Compression algorithm applied to files on import/export.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Compression {
+    Gzip,
+    Zstd,
+    Brotli,
+}
+
+impl Compression {
+    /// Chooses a compression algorithm from --compress option if given, otherwise from the extension of the file.
+    fn resolve(file_name: &str, compress: Option<&str>) -> Option<Compression> {
+        match compress {
+            Some("gzip") => Some(Compression::Gzip),
+            Some("zstd") => Some(Compression::Zstd),
+            Some("brotli") => Some(Compression::Brotli),
+            Some(o) => panic!("Invalid compression is given: {}", o),
+            None => match Path::new(file_name)
+                .extension()
+                .and_then(|ext| ext.to_str())
+            {
+                Some("gz") | Some("gzip") => Some(Compression::Gzip),
+                Some("zst") | Some("zstd") => Some(Compression::Zstd),
+                Some("br") => Some(Compression::Brotli),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Writer which compresses contents on the fly. `finish` must be called to write trailing data of the compressed stream.
+enum CompressedWriter {
+    Plain(fs::File),
+    Gzip(GzEncoder<fs::File>),
+    Zstd(zstd::Encoder<'static, fs::File>),
+    Brotli(Box<CompressorWriter<fs::File>>),
+}
+
+impl CompressedWriter {
+    fn new(f: fs::File, compression: Option<Compression>) -> Result<CompressedWriter, IOError> {
+        Ok(match compression {
+            None => CompressedWriter::Plain(f),
+            Some(Compression::Gzip) => {
+                CompressedWriter::Gzip(GzEncoder::new(f, flate2::Compression::default()))
+            }
+            Some(Compression::Zstd) => CompressedWriter::Zstd(zstd::Encoder::new(f, 0)?),
+            Some(Compression::Brotli) => CompressedWriter::Brotli(Box::new(CompressorWriter::new(
+                f,
+                BROTLI_BUFFER_SIZE,
+                BROTLI_QUALITY,
+                BROTLI_LG_WINDOW_SIZE,
+            ))),
+        })
+    }
+
+    fn finish(self) -> Result<(), IOError> {
+        match self {
+            CompressedWriter::Plain(mut f) => f.flush(),
+            CompressedWriter::Gzip(e) => e.finish().map(|_| ()),
+            CompressedWriter::Zstd(e) => e.finish().map(|_| ()),
+            // `into_inner` finishes the stream but ignores errors, so pending data is flushed first to report them.
+            CompressedWriter::Brotli(mut e) => {
+                e.flush()?;
+                e.into_inner().flush()
+            }
+        }
+    }
+}
+
+impl Write for CompressedWriter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IOError> {
+        match self {
+            CompressedWriter::Plain(f) => f.write(buf),
+            CompressedWriter::Gzip(e) => e.write(buf),
+            CompressedWriter::Zstd(e) => e.write(buf),
+            CompressedWriter::Brotli(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), IOError> {
+        match self {
+            CompressedWriter::Plain(f) => f.flush(),
+            CompressedWriter::Gzip(e) => e.flush(),
+            CompressedWriter::Zstd(e) => e.flush(),
+            CompressedWriter::Brotli(e) => e.flush(),
+        }
+    }
+}
+
+/// Parses items in json (or json-compact) format, i.e. an array of objects, or jsonl format.
+fn parse_json_items(
+    input: &str,
+    format: Option<&str>,
+) -> Result<Vec<JsonValue>, serde_json::Error> {
+    match format {
+        Some("jsonl") => {
+            // JSON Lines can be deserialized with into_iter() as below.
+            let array_of_json_obj: StreamDeserializer<'_, StrRead<'_>, JsonValue> =
+                Deserializer::from_str(input).into_iter::<JsonValue>();
+            // list_of_jsons contains deserialize results. Filter them and get only valid items.
+            Ok(array_of_json_obj.filter_map(Result::ok).collect())
+        }
+        _ => serde_json::from_str(input),
+    }
+}
+
+/// Reads a whole file as a string, decompressing it if needed.
+fn read_to_string_decompressed(
+    path: &Path,
+    compression: Option<Compression>,
+) -> Result<String, IOError> {
+    let f = fs::File::open(path)?;
+    let mut reader: Box<dyn Read> = match compression {
+        None => Box::new(f),
+        Some(Compression::Gzip) => Box::new(MultiGzDecoder::new(f)),
+        Some(Compression::Zstd) => Box::new(zstd::Decoder::new(f)?),
+        Some(Compression::Brotli) => Box::new(Decompressor::new(f, BROTLI_BUFFER_SIZE)),
+    };
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+const EXPORT_MANIFEST_SUMMARY_FILE_NAME: &str = "manifest-summary.json";
+const EXPORT_MANIFEST_FILES_FILE_NAME: &str = "manifest-files.json";
+const EXPORT_DATA_DIR_NAME: &str = "data";
+const EXPORT_OUTPUT_FORMAT_DYNAMODB_JSON: &str = "DYNAMODB_JSON";
+
+/// manifest-summary.json written by DynamoDB's "Export to S3" feature.
+/// Only fields that dynein uses for validation are deserialized.
+/// https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/S3DataExport.Output.html
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ExportManifestSummary {
+    item_count: Option<usize>,
+    output_format: Option<String>,
+    manifest_files_s3_key: Option<String>,
+}
+
+/// Each line of manifest-files.json describes one data file in the export.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ExportManifestFile {
+    item_count: usize,
+    md5_checksum: String,
+    data_file_s3_key: String,
+}
 
 /* =================================================
 Public functions
 ================================================= */
 
-/// Export items in a DynamoDB table into specified format (JSON, JSONL, JSON compact, or CSV. default is JSON).
+/// Export items in a DynamoDB table into specified format (JSON, JSONL, JSON compact, CSV, or Parquet. default is JSON).
 /// As CSV is a kind of "structured" format, you cannot export DynamoDB's NoSQL-ish "unstructured" data into CSV without any instruction from users.
 /// Thus as an "instruction" this function takes --attributes or --keys-only options. If neither of them are given, dynein "guesses" attributes to export from the first item.
+/// The output file is compressed with --compress option, or based on its extension (.gz, .zst, .br).
+#[allow(clippy::too_many_arguments)]
 pub async fn export(
     cx: &app::Context,
     given_attributes: Option<String>,
     keys_only: bool,
     output_file: String,
     format: Option<String>,
+    compress: Option<String>,
+    source: ExportSource,
+    yes: bool,
 ) -> Result<(), DyneinExportError> {
     // TODO: Parallel scan to make it faster https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/Scan.html#Scan.ParallelScan
     // TODO: Show rough progress bar (sum(scan_output.scanned_item)/item_size_of_the_table(6hr)) to track progress.
-    let ts: app::TableSchema = app::table_schema(cx).await;
+    let ts: app::TableSchema = data::table_schema_for_index(cx, &source.index)
+        .await
+        .map_err(app::DyneinExit::from)?;
     let format_str: Option<&str> = format.as_deref();
+    let compression = Compression::resolve(&output_file, compress.as_deref());
+    if format_str == Some("parquet") && compression.is_some() {
+        return Err(app::bye(
+            1,
+            "Parquet files are compressed internally. You cannot use --compress with parquet format.",
+        )
+        .into());
+    }
 
     if ts.mode == table::Mode::Provisioned {
         let msg = "WARN: For the best performance on import/export, dynein recommends OnDemand mode. However the target table is Provisioned mode now. Proceed anyway?";
-        if !Confirm::new().with_prompt(msg).interact()? {
-            app::bye(0, "Operation has been cancelled.");
+        if !cx.confirm(msg, yes, app::Unattended::Proceed)? {
+            return Err(app::bye(0, "Operation has been cancelled.").into());
         }
     }
 
@@ -162,41 +361,60 @@ pub async fn export(
     let attributes: Option<String> = match format_str {
         Some("csv") => {
             if !keys_only && given_attributes.is_none() {
-                overwrite_attributes_or_exit(cx, &ts)
-                    .await
-                    .expect("failed to overwrite attributes based on a scanned item")
+                overwrite_attributes_or_exit(cx, &ts, yes).await?
             } else {
                 given_attributes
             }
         }
         None | Some(_) => {
             if keys_only || given_attributes.is_some() {
-                app::bye(
+                return Err(app::bye(
                     1,
                     "You can use --keys-only and --attributes only with CSV format.",
                 )
+                .into());
             }
             given_attributes
         }
     };
 
-    // Create output file. If target file already exists, ask users if it's ok to delete contents of the file.
-    // Though final output file is created here, it would be blank until scan all items. You can see progress in temporary output file.
-    let f: fs::File = if Path::new(&output_file).exists() {
+    // Validate the filter before touching the output file.
+    let ExportSource {
+        index,
+        pval,
+        sort_key_expression,
+        filter,
+        filter_values,
+    } = source;
+    let filter: Option<data::GeneratedFilterParams> = filter
+        .map(|f| data::generate_filter_expressions(&f, &filter_values))
+        .transpose()
+        .map_err(bye_with_query_error)?;
+    let query_params: Option<data::QueryParams> = pval.map(|pval| data::QueryParams {
+        pval,
+        sort_key_expression,
+        index: index.clone(),
+        limit: None,
+        consistent_read: false,
+        descending: false,
+        attributes: attributes.clone(),
+        keys_only,
+    });
+
+    // If target file already exists, ask users if it's ok to delete contents of the file.
+    // The output file is created (or truncated) only after all items are scanned, so that a failing Scan/Query leaves it untouched.
+    // You can see progress in temporary output file.
+    let output_file_exists = Path::new(&output_file).exists();
+    if output_file_exists {
         let msg = "Specified output file already exists. Is it OK to truncate contents?";
-        if !Confirm::new().with_prompt(msg).interact()? {
-            app::bye(0, "Operation has been cancelled.");
+        if !cx.confirm(
+            msg,
+            yes,
+            app::Unattended::Fail("Use --yes to overwrite the output file."),
+        )? {
+            return Err(app::bye(0, "Operation has been cancelled.").into());
         }
-        debug!("truncating existing output file.");
-        let _f = fs::OpenOptions::new().append(true).open(&output_file)?;
-        _f.set_len(0)?;
-        _f
-    } else {
-        fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&output_file)?
-    };
+    }
 
     // These temporary file is used to store data "body" and finally merged into output file.
     let tmp_output_filename: &str = &format!("{}_tmp", output_file);
@@ -206,25 +424,39 @@ pub async fn export(
         .open(tmp_output_filename)?;
     tmp_output_file.set_len(0)?;
 
+    // For Parquet, the schema of the file is inferred from all scanned items before writing the output file.
+    let mut schema_inference = columnar::SchemaInference::new(&ts);
+
     let mut last_evaluated_key: Option<HashMap<String, AttributeValue>> = None;
     let mut progress_status = ProgressState::new(MAX_NUMBER_OF_OBSERVES);
     loop {
-        // Invoke Scan API here. At the 1st iteration exclusive_start_key would be "None" as defined above, outside of the loop.
+        // Invoke Scan (or Query) API here. At the 1st iteration exclusive_start_key would be "None" as defined above, outside of the loop.
         // On 2nd iteration and later, passing last_evaluated_key from the previous loop as an exclusive_start_key.
-        let scan_output: ScanOutput = data::scan_api(
-            cx,
-            None,  /* index */
-            false, /* consistent_read */
-            &attributes,
-            keys_only,
-            None,               /* limit */
-            last_evaluated_key, /* exclusive_start_key */
-        )
-        .await;
-
-        let items = scan_output
-            .items
-            .expect("Scan result items should be 'Some' even if no item returned.");
+        let (items, next_key) = match &query_params {
+            Some(params) => {
+                let query_output: QueryOutput =
+                    data::query_api(cx, params, last_evaluated_key, filter.clone())
+                        .await
+                        .map_err(bye_with_query_error)?;
+                (query_output.items, query_output.last_evaluated_key)
+            }
+            None => {
+                let scan_output: ScanOutput = data::scan_api(
+                    cx,
+                    index.clone(),
+                    false, /* consistent_read */
+                    &attributes,
+                    keys_only,
+                    None,               /* limit */
+                    last_evaluated_key, /* exclusive_start_key */
+                    filter.clone(),
+                )
+                .await?;
+                (scan_output.items, scan_output.last_evaluated_key)
+            }
+        };
+        let items =
+            items.expect("Scan/Query result items should be 'Some' even if no item returned.");
 
         progress_status.add_observation(items.len());
         match format_str {
@@ -253,83 +485,119 @@ pub async fn export(
                 );
                 tmp_output_file.write_all(s.as_bytes())?;
             }
+            Some("parquet") => {
+                // Items are kept in DynamoDB JSON in the temporary file so that types are not lost.
+                let mut s: String = String::new();
+                for item in &items {
+                    schema_inference.observe(item);
+                    s.push_str(&serde_json::to_string(&data::strip_item(item))?);
+                    s.push('\n');
+                }
+                tmp_output_file.write_all(s.as_bytes())?;
+            }
             Some(o) => panic!("Invalid output format is given: {}", o),
         }
         progress_status.show();
 
         // update last_evaluated_key for the next iteration.
         // If there's no more item in the table, last_evaluated_key would be "None" and it means it's ok to break the loop.
-        debug!(
-            "scan_output.last_evaluated_key is: {:?}",
-            &scan_output.last_evaluated_key
-        );
-        match scan_output.last_evaluated_key {
+        debug!("last_evaluated_key is: {:?}", &next_key);
+        match next_key {
             None => break,
             Some(lek) => last_evaluated_key = Some(lek),
         }
     }
 
-    match format_str {
-        None | Some("json") => json_finish(f, tmp_output_filename)?.write_all(b"\n]")?,
-        Some("json-compact") => json_finish(f, tmp_output_filename)?.write_all(b"]")?,
-        Some("jsonl") => jsonl_finish(f, tmp_output_filename)?,
-        Some("csv") => csv_finish(
-            f,
-            tmp_output_filename,
-            &ts,
-            attrs_to_append(&ts, &attributes),
-            keys_only,
-        )?
-        .write_all(b"\n")?,
-        Some(o) => panic!("Invalid output format is given: {}", o),
+    let f: fs::File = if output_file_exists {
+        debug!("truncating existing output file.");
+        let _f = fs::OpenOptions::new().append(true).open(&output_file)?;
+        _f.set_len(0)?;
+        _f
+    } else {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&output_file)?
     };
 
+    if format_str == Some("parquet") {
+        parquet_finish(f, tmp_output_filename, &schema_inference)?;
+    } else {
+        let mut writer = CompressedWriter::new(f, compression)?;
+        match format_str {
+            None | Some("json") => {
+                json_finish(&mut writer, tmp_output_filename)?.write_all(b"\n]")?
+            }
+            Some("json-compact") => {
+                json_finish(&mut writer, tmp_output_filename)?.write_all(b"]")?
+            }
+            Some("jsonl") => jsonl_finish(&mut writer, tmp_output_filename)?,
+            Some("csv") => csv_finish(
+                &mut writer,
+                tmp_output_filename,
+                &ts,
+                attrs_to_append(&ts, &attributes),
+                keys_only,
+            )?
+            .write_all(b"\n")?,
+            Some(o) => panic!("Invalid output format is given: {}", o),
+        };
+        writer.finish()?;
+    }
+
     // As mentioned earlier, deleting temporary file here in all formats.
     fs::remove_file(tmp_output_filename)?;
 
     Ok(())
 }
 
+/// Import items from a file in specified format (JSON, JSONL, JSON compact, CSV, or Parquet. default is JSON).
+/// The input file is decompressed with --compress option, or based on its extension (.gz, .zst, .br).
 pub async fn import(
     cx: &app::Context,
     input_file: String,
     format: Option<String>,
     enable_set_inference: bool,
+    compress: Option<String>,
+    yes: bool,
 ) -> Result<(), batch::DyneinBatchError> {
     let format_str: Option<&str> = format.as_deref();
+    let compression = Compression::resolve(&input_file, compress.as_deref());
 
-    let ts: app::TableSchema = app::table_schema(cx).await;
+    let ts: app::TableSchema = app::table_schema(cx).await?;
     if ts.mode == table::Mode::Provisioned {
         let msg = "WARN: For the best performance on import/export, dynein recommends OnDemand mode. However the target table is Provisioned mode now. Proceed anyway?";
-        if !Confirm::new().with_prompt(msg).interact()? {
+        if !cx.confirm(msg, yes, app::Unattended::Proceed)? {
             println!("Operation has been cancelled.");
             return Ok(());
         }
     }
 
-    let input_string: String = if Path::new(&input_file).exists() {
-        fs::read_to_string(&input_file)?
-    } else {
-        error!("Couldn't find the input file '{}'.", &input_file);
-        std::process::exit(1);
-    };
+    if !Path::new(&input_file).exists() {
+        return Err(app::bye_with_error(format!(
+            "Couldn't find the input file '{}'.",
+            &input_file
+        ))
+        .into());
+    }
+
+    if format_str == Some("parquet") {
+        if compression.is_some() {
+            return Err(batch::DyneinBatchError::InvalidInput(String::from(
+                "Parquet files are compressed internally. You cannot use --compress with parquet format.",
+            )));
+        }
+        return import_parquet(cx, Path::new(&input_file)).await;
+    }
+
+    let input_string: String = read_to_string_decompressed(Path::new(&input_file), compression)?;
 
     match format_str {
-        None | Some("json") | Some("json-compact") => {
-            let array_of_json_obj: Vec<JsonValue> = serde_json::from_str(&input_string)?;
+        None | Some("json") | Some("json-compact") | Some("jsonl") => {
+            let array_of_json_obj = parse_json_items(&input_string, format_str)?;
             write_array_of_jsons_with_chunked_25(cx, array_of_json_obj, enable_set_inference)
                 .await?;
         }
-        Some("jsonl") => {
-            // JSON Lines can be deserialized with into_iter() as below.
-            let array_of_json_obj: StreamDeserializer<'_, StrRead<'_>, JsonValue> =
-                Deserializer::from_str(&input_string).into_iter::<JsonValue>();
-            // list_of_jsons contains deserialize results. Filter them and get only valid items.
-            let array_of_valid_json_obj: Vec<JsonValue> =
-                array_of_json_obj.filter_map(Result::ok).collect();
-            write_array_of_jsons_with_chunked_25(cx, array_of_valid_json_obj, enable_set_inference)
-                .await?;
-        }
         Some("csv") => {
             let lines: Vec<&str> = input_string
                 .split('\n')
@@ -364,16 +632,195 @@ pub async fn import(
     Ok(())
 }
 
+/// Reads items from a file written by `dy export` in json, json-compact or jsonl format, e.g. for `dy diff --file`.
+/// The file is decompressed if --compress is given or its extension tells the compression, as `dy import` does.
+pub fn read_export_file(
+    input_file: &str,
+    format: Option<&str>,
+    compress: Option<&str>,
+) -> Result<Vec<JsonValue>, batch::DyneinBatchError> {
+    let compression = Compression::resolve(input_file, compress);
+    let input_string: String = read_to_string_decompressed(Path::new(input_file), compression)?;
+    Ok(parse_json_items(&input_string, format)?)
+}
+
+/// Import items from a directory which is downloaded from DynamoDB's native "Export to S3" feature.
+/// The directory should keep the layout on S3, i.e. manifest-summary.json, manifest-files.json and data/*.json.gz.
+/// All data files are verified against the manifest (MD5 checksum and item count) before any item is written,
+/// so that a broken or partially downloaded export doesn't leave the table half-loaded.
+pub async fn import_from_export_dir(
+    cx: &app::Context,
+    export_dir: String,
+    yes: bool,
+) -> Result<(), batch::DyneinBatchError> {
+    let ts: app::TableSchema = app::table_schema(cx).await?;
+    if ts.mode == table::Mode::Provisioned {
+        let msg = "WARN: For the best performance on import/export, dynein recommends OnDemand mode. However the target table is Provisioned mode now. Proceed anyway?";
+        if !cx.confirm(msg, yes, app::Unattended::Proceed)? {
+            println!("Operation has been cancelled.");
+            return Ok(());
+        }
+    }
+
+    let export_dir = Path::new(&export_dir);
+    let manifest_files = load_export_manifest(export_dir)?;
+
+    for manifest_file in &manifest_files {
+        verify_export_data_file(
+            &export_data_file_path(export_dir, &manifest_file.data_file_s3_key),
+            manifest_file,
+        )?;
+    }
+    debug!(
+        "verified {} data files in the export directory",
+        manifest_files.len()
+    );
+
+    let mut progress_status = ProgressState::new(MAX_NUMBER_OF_OBSERVES);
+    for manifest_file in &manifest_files {
+        let items = read_export_data_file(&export_data_file_path(
+            export_dir,
+            &manifest_file.data_file_s3_key,
+        ))?;
+        for chunk in items.chunks(25) {
+            let request_items: HashMap<String, Vec<WriteRequest>> =
+                batch::convert_ddbjson_items_to_request_items(cx, chunk.to_vec())?;
+            batch::batch_write_until_processed(cx, request_items).await?;
+            progress_status.add_observation(chunk.len());
+            progress_status.show();
+        }
+    }
+    Ok(())
+}
+
 /* =================================================
 Private functions
 ================================================= */
 
+/// Loads manifest-summary.json and manifest-files.json in the export directory,
+/// then checks that the total item count of data files matches the summary.
+fn load_export_manifest(
+    export_dir: &Path,
+) -> Result<Vec<ExportManifestFile>, batch::DyneinBatchError> {
+    let summary_path = export_dir.join(EXPORT_MANIFEST_SUMMARY_FILE_NAME);
+    if !summary_path.exists() {
+        return Err(batch::DyneinBatchError::InvalidInput(format!(
+            "couldn't find {} in the export directory '{}'",
+            EXPORT_MANIFEST_SUMMARY_FILE_NAME,
+            export_dir.display()
+        )));
+    }
+    let summary: ExportManifestSummary = serde_json::from_str(&fs::read_to_string(&summary_path)?)?;
+    debug!("manifest summary of the export: {:?}", summary);
+
+    if let Some(output_format) = &summary.output_format {
+        if output_format != EXPORT_OUTPUT_FORMAT_DYNAMODB_JSON {
+            return Err(batch::DyneinBatchError::InvalidInput(format!(
+                "unsupported export output format '{}'. Only {} is supported",
+                output_format, EXPORT_OUTPUT_FORMAT_DYNAMODB_JSON
+            )));
+        }
+    }
+
+    // manifestFilesS3Key is a key on S3. Locally the file is placed next to manifest-summary.json.
+    let manifest_files_name = summary
+        .manifest_files_s3_key
+        .as_deref()
+        .and_then(|key| key.rsplit('/').next())
+        .unwrap_or(EXPORT_MANIFEST_FILES_FILE_NAME);
+    let manifest_files_content = fs::read_to_string(export_dir.join(manifest_files_name))?;
+    let manifest_files = Deserializer::from_str(&manifest_files_content)
+        .into_iter::<ExportManifestFile>()
+        .collect::<Result<Vec<ExportManifestFile>, serde_json::Error>>()?;
+
+    let total_item_count: usize = manifest_files.iter().map(|f| f.item_count).sum();
+    if let Some(expected_item_count) = summary.item_count {
+        if total_item_count != expected_item_count {
+            return Err(batch::DyneinBatchError::InvalidInput(format!(
+                "item count mismatch: {} says {} items but {} lists {} items",
+                EXPORT_MANIFEST_SUMMARY_FILE_NAME,
+                expected_item_count,
+                manifest_files_name,
+                total_item_count
+            )));
+        }
+    }
+
+    Ok(manifest_files)
+}
+
+/// dataFileS3Key in the manifest is a full S3 key (e.g. "AWSDynamoDB/<export id>/data/<hash>.json.gz").
+/// Data files of a downloaded export are expected to be found under the data directory.
+fn export_data_file_path(export_dir: &Path, data_file_s3_key: &str) -> PathBuf {
+    let file_name = data_file_s3_key
+        .rsplit('/')
+        .next()
+        .unwrap_or(data_file_s3_key);
+    export_dir.join(EXPORT_DATA_DIR_NAME).join(file_name)
+}
+
+/// Verifies a data file with the MD5 checksum (base64 encoded) and the number of items written in the manifest.
+fn verify_export_data_file(
+    path: &Path,
+    manifest_file: &ExportManifestFile,
+) -> Result<(), batch::DyneinBatchError> {
+    let compressed = fs::read(path)?;
+    let checksum = general_purpose::STANDARD.encode(Md5::digest(&compressed));
+    if checksum != manifest_file.md5_checksum {
+        return Err(batch::DyneinBatchError::InvalidInput(format!(
+            "checksum mismatch for '{}': expected {}, but got {}",
+            path.display(),
+            manifest_file.md5_checksum,
+            checksum
+        )));
+    }
+
+    let mut item_count = 0;
+    for line in BufReader::new(MultiGzDecoder::new(compressed.as_slice())).lines() {
+        if !line?.trim().is_empty() {
+            item_count += 1;
+        }
+    }
+    if item_count != manifest_file.item_count {
+        return Err(batch::DyneinBatchError::InvalidInput(format!(
+            "item count mismatch for '{}': expected {}, but got {}",
+            path.display(),
+            manifest_file.item_count,
+            item_count
+        )));
+    }
+
+    Ok(())
+}
+
+/// Reads a gzip compressed data file of the export. Each line has a form of {"Item": {<attributes in DynamoDB JSON>}}.
+fn read_export_data_file(path: &Path) -> Result<Vec<JsonValue>, batch::DyneinBatchError> {
+    let mut content = String::new();
+    MultiGzDecoder::new(fs::File::open(path)?).read_to_string(&mut content)?;
+
+    let mut items: Vec<JsonValue> = vec![];
+    for line in Deserializer::from_str(&content).into_iter::<JsonValue>() {
+        let mut line = line?;
+        match line.get_mut("Item").map(JsonValue::take) {
+            Some(item) if item.is_object() => items.push(item),
+            _ => {
+                return Err(batch::DyneinBatchError::InvalidInput(format!(
+                    "each line in '{}' should have an 'Item' object",
+                    path.display()
+                )))
+            }
+        }
+    }
+    Ok(items)
+}
+
 async fn overwrite_attributes_or_exit(
     cx: &app::Context,
     ts: &app::TableSchema,
-) -> Result<Option<String>, dialoguer::Error> {
+    yes: bool,
+) -> Result<Option<String>, app::DyneinExit> {
     println!("As neither --keys-only nor --attributes options are given, fetching an item to understand attributes to export...");
-    let suggested_attributes: Vec<SuggestedAttribute> = suggest_attributes(cx, ts).await;
+    let suggested_attributes: Vec<SuggestedAttribute> = suggest_attributes(cx, ts).await?;
 
     // if at least one attribute found
     println!("Found following attributes in the first item in the table:");
@@ -384,8 +831,12 @@ async fn overwrite_attributes_or_exit(
         );
     }
     let msg = "Are you OK to export items in CSV with columns(attributes) above?";
-    if !Confirm::new().with_prompt(msg).interact()? {
-        app::bye(0, "Operation has been cancelled. You can use --keys-only or --attributes option to specify columns explicitly.");
+    if !cx.confirm(
+        msg,
+        yes,
+        app::Unattended::Fail("Use --keys-only or --attributes option to specify columns explicitly, or --yes to export the attributes above."),
+    )? {
+        return Err(app::bye(0, "Operation has been cancelled. You can use --keys-only or --attributes option to specify columns explicitly."));
     }
 
     // Overwrite given attributes with suggested attributes beased on a sampled item
@@ -399,7 +850,10 @@ async fn overwrite_attributes_or_exit(
 }
 
 /// This function scan the fisrt item from the target table and use it as a source of attributes.
-async fn suggest_attributes(cx: &app::Context, ts: &app::TableSchema) -> Vec<SuggestedAttribute> {
+async fn suggest_attributes(
+    cx: &app::Context,
+    ts: &app::TableSchema,
+) -> Result<Vec<SuggestedAttribute>, app::DyneinExit> {
     let mut attributes_suggestion = vec![];
 
     // items: Vec<HashMap<String, AttributeValue>>
@@ -411,13 +865,17 @@ async fn suggest_attributes(cx: &app::Context, ts: &app::TableSchema) -> Vec<Sug
         false,   /* keys_only */
         Some(1), /* limit */
         None,    /* esk */
+        None,    /* filter */
     )
-    .await
+    .await?
     .items
     .expect("items should be 'Some' even if there's no item in the table.");
 
     if items.is_empty() {
-        app::bye(0, "No item to export in this table. Quit the operation.");
+        return Err(app::bye(
+            0,
+            "No item to export in this table. Quit the operation.",
+        ));
     }
 
     // Filter out primary keys. i.e. select attributes that aren't required by the table's keyschema.
@@ -444,7 +902,15 @@ async fn suggest_attributes(cx: &app::Context, ts: &app::TableSchema) -> Vec<Sug
     }
 
     debug!("Suggested attributes to use: {:?}", attributes_suggestion);
-    attributes_suggestion
+    Ok(attributes_suggestion)
+}
+
+/// Prints an invalid --pval, --sort-key or --filter to stdout as `bye` does, unless the error has already been printed.
+fn bye_with_query_error(e: data::DyneinQueryParamsError) -> app::DyneinExit {
+    match e {
+        data::DyneinQueryParamsError::Exit(e) => e,
+        e => app::bye(1, &e.to_string()),
+    }
 }
 
 fn attrs_to_append(ts: &app::TableSchema, attributes: &Option<String>) -> Option<Vec<String>> {
@@ -486,9 +952,70 @@ fn connectable_json(mut s: String, compact: bool) -> String {
     s
 }
 
+/// This function takes final output file and temporary filename which has items in DynamoDB JSON lines, and writes them into a Parquet file.
+/// Items are written in row groups of PARQUET_ROW_GROUP_SIZE items, so that whole items are not loaded on memory at once.
+fn parquet_finish(
+    f: fs::File,
+    tmp_output_filename: &str,
+    schema_inference: &columnar::SchemaInference,
+) -> Result<(), DyneinExportError> {
+    let schema = Arc::new(schema_inference.schema());
+    let props = WriterProperties::builder()
+        .set_compression(ParquetCompression::SNAPPY)
+        .set_max_row_group_size(PARQUET_ROW_GROUP_SIZE)
+        .build();
+    let mut writer = ArrowWriter::try_new(f, schema.clone(), Some(props))
+        .map_err(columnar::DyneinParquetError::from)?;
+
+    let mut items: Vec<HashMap<String, AttributeValue>> =
+        Vec::with_capacity(PARQUET_ROW_GROUP_SIZE);
+    for line in BufReader::new(fs::File::open(tmp_output_filename)?).lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        items.push(batch::convert_ddbjson_to_item(&serde_json::from_str(
+            &line,
+        )?));
+        if items.len() == PARQUET_ROW_GROUP_SIZE {
+            writer
+                .write(&columnar::items_to_record_batch(schema.clone(), &items)?)
+                .map_err(columnar::DyneinParquetError::from)?;
+            items.clear();
+        }
+    }
+    if !items.is_empty() {
+        writer
+            .write(&columnar::items_to_record_batch(schema.clone(), &items)?)
+            .map_err(columnar::DyneinParquetError::from)?;
+    }
+    writer.close().map_err(columnar::DyneinParquetError::from)?;
+    Ok(())
+}
+
+/// Reads a Parquet file in batches of 25 items (the limit of BatchWriteItem), and writes them into the table.
+async fn import_parquet(cx: &app::Context, path: &Path) -> Result<(), batch::DyneinBatchError> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(fs::File::open(path)?)
+        .and_then(|builder| builder.with_batch_size(25).build())
+        .map_err(columnar::DyneinParquetError::from)?;
+
+    let mut progress_status = ProgressState::new(MAX_NUMBER_OF_OBSERVES);
+    for record_batch in reader {
+        let record_batch = record_batch.map_err(columnar::DyneinParquetError::from)?;
+        let items = columnar::record_batch_to_items(&record_batch)?;
+        let count = items.len();
+        let request_items: HashMap<String, Vec<WriteRequest>> =
+            batch::convert_items_to_request_items(cx, items)?;
+        batch::batch_write_until_processed(cx, request_items).await?;
+        progress_status.add_observation(count);
+        progress_status.show();
+    }
+    Ok(())
+}
+
 /// This function takes final output file and temporary filename which has incomplete JSON body, and write final output JSON file.
 /// last "]" is not added in this function, as it depends on json or json-compact.
-fn json_finish(mut f: fs::File, tmp_output_filename: &str) -> Result<fs::File, IOError> {
+fn json_finish<W: Write>(mut f: W, tmp_output_filename: &str) -> Result<W, IOError> {
     f.write_all(b"[")?; // write initial "[" as the first letter of JSON array.
     let mut contents = fs::read_to_string(tmp_output_filename)?;
     let len = contents.len();
@@ -498,20 +1025,20 @@ fn json_finish(mut f: fs::File, tmp_output_filename: &str) -> Result<fs::File, I
 }
 
 /// This function takes final output file and temporary filename. For JSON"L", copying whole content is enough.
-fn jsonl_finish(mut f: fs::File, tmp_output_filename: &str) -> Result<(), IOError> {
+fn jsonl_finish<W: Write>(mut f: W, tmp_output_filename: &str) -> Result<(), IOError> {
     let contents = fs::read_to_string(tmp_output_filename)?;
     f.write_all(contents.as_bytes())?;
     Ok(())
 }
 
 /// This function takes final output file and temporary filename, writing CSV header and then copying contents to the output file.
-fn csv_finish(
-    mut f: fs::File,
+fn csv_finish<W: Write>(
+    mut f: W,
     tmp_output_filename: &str,
     ts: &app::TableSchema,
     attributes_to_append: Option<Vec<String>>,
     keys_only: bool,
-) -> Result<fs::File, IOError> {
+) -> Result<W, IOError> {
     f.write_all(build_csv_header(ts, attributes_to_append, keys_only).as_bytes())?;
     let contents = fs::read_to_string(tmp_output_filename)?;
     f.write_all(contents.as_bytes())?;
@@ -519,7 +1046,7 @@ fn csv_finish(
 }
 
 /// This function generate CSV headers for the output file to export.
-fn build_csv_header(
+pub fn build_csv_header(
     ts: &app::TableSchema,
     attributes_to_append: Option<Vec<String>>,
     keys_only: bool,
@@ -583,6 +1110,23 @@ mod tests {
     use super::*;
     use std::ops::Add;
     use std::time::Duration;
+    use tempfile::tempdir;
+
+    fn write_export_data_file(export_dir: &Path, file_name: &str, lines: &[&str]) -> String {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        for line in lines {
+            encoder.write_all(line.as_bytes()).unwrap();
+            encoder.write_all(b"\n").unwrap();
+        }
+        let compressed = encoder.finish().unwrap();
+        fs::create_dir_all(export_dir.join(EXPORT_DATA_DIR_NAME)).unwrap();
+        fs::write(
+            export_dir.join(EXPORT_DATA_DIR_NAME).join(file_name),
+            &compressed,
+        )
+        .unwrap();
+        general_purpose::STANDARD.encode(Md5::digest(&compressed))
+    }
 
     #[test]
     fn test_progress_status() {
@@ -614,4 +1158,148 @@ mod tests {
             (10.0 + 12.0) / 0.5
         );
     }
+
+    #[test]
+    fn test_export_data_file_path() {
+        let export_dir = Path::new("/tmp/export");
+        assert_eq!(
+            export_data_file_path(export_dir, "AWSDynamoDB/0123-abcd/data/xyz.json.gz"),
+            PathBuf::from("/tmp/export/data/xyz.json.gz")
+        );
+        assert_eq!(
+            export_data_file_path(export_dir, "xyz.json.gz"),
+            PathBuf::from("/tmp/export/data/xyz.json.gz")
+        );
+    }
+
+    #[test]
+    fn test_load_and_verify_export() {
+        let dir = tempdir().unwrap();
+        let lines = [
+            r#"{"Item":{"pk":{"S":"a"},"n":{"N":"1"}}}"#,
+            r#"{"Item":{"pk":{"S":"b"},"l":{"L":[{"BOOL":true}]}}}"#,
+        ];
+        let checksum = write_export_data_file(dir.path(), "xyz.json.gz", &lines);
+        fs::write(
+            dir.path().join(EXPORT_MANIFEST_SUMMARY_FILE_NAME),
+            r#"{"version":"2020-06-30","itemCount":2,"outputFormat":"DYNAMODB_JSON","manifestFilesS3Key":"AWSDynamoDB/0123-abcd/manifest-files.json"}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join(EXPORT_MANIFEST_FILES_FILE_NAME),
+            format!(
+                r#"{{"itemCount":2,"md5Checksum":"{}","etag":"x","dataFileS3Key":"AWSDynamoDB/0123-abcd/data/xyz.json.gz"}}"#,
+                checksum
+            ),
+        )
+        .unwrap();
+
+        let manifest_files = load_export_manifest(dir.path()).unwrap();
+        assert_eq!(
+            manifest_files,
+            vec![ExportManifestFile {
+                item_count: 2,
+                md5_checksum: checksum.clone(),
+                data_file_s3_key: "AWSDynamoDB/0123-abcd/data/xyz.json.gz".to_owned(),
+            }]
+        );
+
+        let path = export_data_file_path(dir.path(), &manifest_files[0].data_file_s3_key);
+        assert!(verify_export_data_file(&path, &manifest_files[0]).is_ok());
+        assert_eq!(
+            read_export_data_file(&path).unwrap(),
+            vec![
+                serde_json::json!({"pk":{"S":"a"},"n":{"N":"1"}}),
+                serde_json::json!({"pk":{"S":"b"},"l":{"L":[{"BOOL":true}]}}),
+            ]
+        );
+
+        let broken_checksum = ExportManifestFile {
+            md5_checksum: "AAAAAAAAAAAAAAAAAAAAAA==".to_owned(),
+            ..manifest_files[0].clone()
+        };
+        assert!(verify_export_data_file(&path, &broken_checksum).is_err());
+
+        let wrong_count = ExportManifestFile {
+            item_count: 3,
+            ..manifest_files[0].clone()
+        };
+        assert!(verify_export_data_file(&path, &wrong_count).is_err());
+    }
+
+    #[test]
+    fn test_load_export_manifest_item_count_mismatch() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(EXPORT_MANIFEST_SUMMARY_FILE_NAME),
+            r#"{"itemCount":5,"outputFormat":"DYNAMODB_JSON"}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join(EXPORT_MANIFEST_FILES_FILE_NAME),
+            r#"{"itemCount":2,"md5Checksum":"x","dataFileS3Key":"data/a.json.gz"}
+{"itemCount":2,"md5Checksum":"y","dataFileS3Key":"data/b.json.gz"}"#,
+        )
+        .unwrap();
+        assert!(load_export_manifest(dir.path()).is_err());
+
+        fs::write(
+            dir.path().join(EXPORT_MANIFEST_SUMMARY_FILE_NAME),
+            r#"{"itemCount":4,"outputFormat":"ION"}"#,
+        )
+        .unwrap();
+        assert!(load_export_manifest(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_compression_resolve() {
+        assert_eq!(Compression::resolve("out.json", None), None);
+        assert_eq!(
+            Compression::resolve("out.json.gz", None),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::resolve("out.jsonl.zst", None),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(
+            Compression::resolve("out.csv.br", None),
+            Some(Compression::Brotli)
+        );
+        // --compress option takes precedence over the extension
+        assert_eq!(
+            Compression::resolve("out.json.gz", Some("zstd")),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(
+            Compression::resolve("out.json", Some("brotli")),
+            Some(Compression::Brotli)
+        );
+    }
+
+    #[test]
+    fn test_compressed_writer_round_trip() {
+        let dir = tempdir().unwrap();
+        let contents = "{\"pk\":\"a\"}\n".repeat(100);
+        for compression in [
+            None,
+            Some(Compression::Gzip),
+            Some(Compression::Zstd),
+            Some(Compression::Brotli),
+        ] {
+            let path = dir.path().join(format!("{:?}", compression));
+            let mut writer =
+                CompressedWriter::new(fs::File::create(&path).unwrap(), compression).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+            writer.finish().unwrap();
+
+            if compression.is_some() {
+                assert!(fs::metadata(&path).unwrap().len() < contents.len() as u64);
+            }
+            assert_eq!(
+                read_to_string_decompressed(&path, compression).unwrap(),
+                contents
+            );
+        }
+    }
 }
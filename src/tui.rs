@@ -0,0 +1,595 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// This module implements `dy tui`, a terminal UI to browse tables and items on top of API calls of the data module.
+
+use std::{
+    collections::HashMap,
+    io::{self, IsTerminal},
+};
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_smithy_types::error::display::DisplayErrorContext;
+use console::{style, truncate_str, Key, Term};
+
+use super::app;
+use super::control;
+use super::data;
+use super::output;
+
+/* =================================================
+struct / enum / const
+================================================= */
+
+type Item = HashMap<String, AttributeValue>;
+
+/// Lines other than the body of a screen, i.e. the title, the status message and the key help.
+const CHROME_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Screen {
+    Tables,
+    Items,
+    Detail,
+}
+
+/// What to do after a key is pressed, which needs API calls or the terminal.
+#[derive(Debug, PartialEq)]
+enum Action {
+    None,
+    Quit,
+    OpenTable,
+    LoadPage,
+    Query,
+    OpenItem,
+    EditItem,
+}
+
+/// State of the terminal UI. Key handling and rendering don't call APIs, so that they can be tested.
+struct Browser {
+    screen: Screen,
+    tables: Vec<String>,
+    table_cursor: usize,
+    ts: Option<app::TableSchema>,
+    /// None for the base table.
+    index: Option<String>,
+    /// Partition key value of Query, or None for Scan.
+    query: Option<String>,
+    items: Vec<Item>,
+    item_cursor: usize,
+    /// ExclusiveStartKey of each page shown so far. The last one is of the current page.
+    pages: Vec<Option<Item>>,
+    last_evaluated_key: Option<Item>,
+    /// Item opened in the detail pane, which has been read from the base table.
+    detail: Option<Item>,
+    detail_scroll: usize,
+    message: String,
+}
+
+impl Browser {
+    fn new(tables: Vec<String>) -> Browser {
+        Browser {
+            screen: Screen::Tables,
+            tables,
+            table_cursor: 0,
+            ts: None,
+            index: None,
+            query: None,
+            items: vec![],
+            item_cursor: 0,
+            pages: vec![None],
+            last_evaluated_key: None,
+            detail: None,
+            detail_scroll: 0,
+            message: String::new(),
+        }
+    }
+
+    /// Moves cursors or switches screens by the key, and returns the action to perform.
+    fn on_key(&mut self, key: &Key) -> Action {
+        self.message.clear();
+        match (self.screen, key) {
+            (_, Key::Char('q')) => Action::Quit,
+
+            (Screen::Tables, Key::ArrowUp | Key::Char('k')) => {
+                self.table_cursor = self.table_cursor.saturating_sub(1);
+                Action::None
+            }
+            (Screen::Tables, Key::ArrowDown | Key::Char('j')) => {
+                self.table_cursor =
+                    (self.table_cursor + 1).min(self.tables.len().saturating_sub(1));
+                Action::None
+            }
+            (Screen::Tables, Key::Enter) if !self.tables.is_empty() => Action::OpenTable,
+
+            (Screen::Items, Key::ArrowUp | Key::Char('k')) => {
+                self.item_cursor = self.item_cursor.saturating_sub(1);
+                Action::None
+            }
+            (Screen::Items, Key::ArrowDown | Key::Char('j')) => {
+                self.item_cursor = (self.item_cursor + 1).min(self.items.len().saturating_sub(1));
+                Action::None
+            }
+            (Screen::Items, Key::ArrowRight | Key::PageDown | Key::Char('n')) => {
+                match self.last_evaluated_key.take() {
+                    Some(key) => {
+                        self.pages.push(Some(key));
+                        Action::LoadPage
+                    }
+                    None => {
+                        self.message = String::from("This is the last page.");
+                        Action::None
+                    }
+                }
+            }
+            (Screen::Items, Key::ArrowLeft | Key::PageUp | Key::Char('p')) => {
+                if self.pages.len() > 1 {
+                    self.pages.pop();
+                    Action::LoadPage
+                } else {
+                    self.message = String::from("This is the first page.");
+                    Action::None
+                }
+            }
+            (Screen::Items, Key::Char('i')) => {
+                self.index = self.next_index();
+                // The partition key of Query differs between the table and indexes.
+                self.query = None;
+                self.pages = vec![None];
+                Action::LoadPage
+            }
+            (Screen::Items, Key::Char('/')) => Action::Query,
+            (Screen::Items, Key::Char('s')) if self.query.is_some() => {
+                self.query = None;
+                self.pages = vec![None];
+                Action::LoadPage
+            }
+            (Screen::Items, Key::Char('t') | Key::Escape | Key::Backspace) => {
+                self.screen = Screen::Tables;
+                Action::None
+            }
+            (Screen::Items, Key::Enter) if !self.items.is_empty() => Action::OpenItem,
+
+            (Screen::Detail, Key::ArrowUp | Key::Char('k')) => {
+                self.detail_scroll = self.detail_scroll.saturating_sub(1);
+                Action::None
+            }
+            (Screen::Detail, Key::ArrowDown | Key::Char('j')) => {
+                self.detail_scroll += 1;
+                Action::None
+            }
+            (Screen::Detail, Key::Char('e')) => Action::EditItem,
+            (Screen::Detail, Key::Escape | Key::Backspace | Key::Enter) => {
+                self.screen = Screen::Items;
+                Action::None
+            }
+
+            _ => Action::None,
+        }
+    }
+
+    /// Returns the index after the current one, or None (i.e. the base table) after the last index.
+    fn next_index(&self) -> Option<String> {
+        let names: Vec<&String> = self
+            .ts
+            .iter()
+            .flat_map(|ts| ts.indexes.iter().flatten())
+            .map(|idx| &idx.name)
+            .collect();
+        match &self.index {
+            None => names.first().map(|name| name.to_string()),
+            Some(current) => names
+                .iter()
+                .skip_while(|name| **name != current)
+                .nth(1)
+                .map(|name| name.to_string()),
+        }
+    }
+
+    /// Renders the current screen into lines which fit in the terminal.
+    fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let body_height = height.saturating_sub(CHROME_LINES).max(1);
+        let (title, header, body, cursor, help) = match self.screen {
+            Screen::Tables => (
+                format!("Tables ({})", self.tables.len()),
+                None,
+                self.tables.clone(),
+                Some(self.table_cursor),
+                "↑/↓ move  Enter open  q quit",
+            ),
+            Screen::Items => {
+                let ts = self.ts.as_ref().expect("a table should be opened");
+                let mut title = format!("Table: {}", ts.name);
+                if let Some(index) = &self.index {
+                    title.push_str(&format!("  Index: {}", index));
+                }
+                match &self.query {
+                    Some(pval) => title.push_str(&format!("  Query: {}", pval)),
+                    None => title.push_str("  Scan"),
+                }
+                title.push_str(&format!("  Page: {}", self.pages.len()));
+                let (header, body) = if self.items.is_empty() {
+                    (None, vec![String::from("No item to show.")])
+                } else {
+                    let mut lines =
+                        output::table_lines(self.items.clone(), ts, width.saturating_sub(2));
+                    (Some(lines.remove(0)), lines)
+                };
+                let cursor = (!self.items.is_empty()).then_some(self.item_cursor);
+                (
+                    title,
+                    header,
+                    body,
+                    cursor,
+                    "↑/↓ move  ←/→ page  Enter open  / query  s scan  i index  t tables  q quit",
+                )
+            }
+            Screen::Detail => {
+                let ts = self.ts.as_ref().expect("a table should be opened");
+                let json = self.detail.as_ref().map(output::pretty_json);
+                (
+                    format!("Item in the table: {}", ts.name),
+                    None,
+                    json.iter()
+                        .flat_map(|json| json.lines())
+                        .skip(self.detail_scroll)
+                        .map(str::to_owned)
+                        .collect(),
+                    None,
+                    "↑/↓ scroll  e edit  Esc back  q quit",
+                )
+            }
+        };
+
+        // The header of the table stays at the top, and the body is scrolled so that the cursor is always shown.
+        let mut lines = vec![title];
+        let body_height = match header {
+            Some(header) => {
+                lines.push(format!("  {}", header));
+                body_height.saturating_sub(1).max(1)
+            }
+            None => body_height,
+        };
+        let offset = cursor.map_or(0, |c| (c + 1).saturating_sub(body_height));
+        lines.extend(
+            body.into_iter()
+                .enumerate()
+                .skip(offset)
+                .take(body_height)
+                .map(|(i, line)| {
+                    let marker = if Some(i) == cursor { "> " } else { "  " };
+                    format!("{}{}", marker, line)
+                }),
+        );
+        lines.resize(height.saturating_sub(2).max(2), String::new());
+        lines.push(self.message.clone());
+        lines.push(help.to_owned());
+        lines
+            .into_iter()
+            .map(|line| truncate_str(&line, width, "…").into_owned())
+            .collect()
+    }
+
+    /// Context whose target is the opened table.
+    fn table_cx(&self, cx: &app::Context) -> app::Context {
+        let ts = self.ts.as_ref().expect("a table should be opened");
+        cx.clone().with_table(&ts.name)
+    }
+
+    /// Opens the table, or stays on the current screen if its schema cannot be retrieved. Returns true if opened.
+    async fn open_table(&mut self, cx: &app::Context, name: &str) -> bool {
+        let cx = cx.clone().with_table(name);
+        match app::cached_table_schema(&cx).await {
+            Ok(ts) => self.ts = Some(ts),
+            Err(_) => {
+                self.message = format!("Failed to open the table '{}'.", name);
+                return false;
+            }
+        }
+        self.index = None;
+        self.query = None;
+        self.pages = vec![None];
+        self.screen = Screen::Items;
+        true
+    }
+
+    async fn load_page(&mut self, cx: &app::Context, page_size: i32) {
+        let cx = self.table_cx(cx);
+        let ts = self.ts.as_ref().expect("a table should be opened");
+        let esk = self.pages.last().cloned().flatten();
+        let (items, last_evaluated_key) = match &self.query {
+            None => match data::scan_page(&cx, ts, self.index.clone(), page_size, esk).await {
+                Ok(res) => (res.items, res.last_evaluated_key),
+                Err(_) => {
+                    self.message = format!("Failed to scan the table '{}'.", ts.name);
+                    return;
+                }
+            },
+            Some(pval) => {
+                let params = data::QueryParams {
+                    pval: pval.to_owned(),
+                    sort_key_expression: None,
+                    index: self.index.clone(),
+                    limit: Some(page_size),
+                    consistent_read: false,
+                    descending: false,
+                    attributes: None,
+                    keys_only: false,
+                };
+                match data::query_page(&cx, ts, &params, esk, None).await {
+                    Ok(res) => (res.items, res.last_evaluated_key),
+                    Err(e) => {
+                        self.message = match e {
+                            data::DyneinQueryParamsError::Exit(_) => {
+                                format!("Failed to query the table '{}'.", ts.name)
+                            }
+                            e => e.to_string(),
+                        };
+                        self.query = None;
+                        self.pages = vec![None];
+                        return;
+                    }
+                }
+            }
+        };
+        self.items = items.unwrap_or_default();
+        self.item_cursor = 0;
+        self.last_evaluated_key = last_evaluated_key;
+    }
+
+    /// Reads the item under the cursor from the base table, as items of an index may not have all attributes.
+    async fn read_item(&mut self, cx: &app::Context) -> Option<Item> {
+        let ts = self.ts.as_ref().expect("a table should be opened");
+        let key = data::item_key(ts, &self.items[self.item_cursor]);
+        match data::get_item_api(&self.table_cx(cx), ts, key, true).await {
+            Ok(Some(item)) => Some(item),
+            Ok(None) => {
+                self.message = String::from("The item no longer exists.");
+                None
+            }
+            Err(e) => {
+                self.message = format!("{}", DisplayErrorContext(&e));
+                None
+            }
+        }
+    }
+
+    /// Edits the item in $EDITOR and applies the difference by UpdateItem on condition that it has not been changed since it was read.
+    async fn edit_item(&mut self, cx: &app::Context, term: &Term) -> io::Result<()> {
+        let Some(original) = self.read_item(cx).await else {
+            return Ok(());
+        };
+        let ts = self.ts.as_ref().expect("a table should be opened");
+        term.clear_screen()?;
+        let edited = match data::edit_in_editor(ts, &original) {
+            Ok(Some(item)) => item,
+            Ok(None) => {
+                self.message = String::from("The item was not saved in the editor.");
+                return Ok(());
+            }
+            Err(e) => {
+                self.message = e.to_string();
+                return Ok(());
+            }
+        };
+        match data::update_item_if_unchanged(&self.table_cx(cx), ts, &original, &edited).await {
+            Ok(None) => {
+                self.message = String::from("No change to write.");
+                self.detail = Some(original);
+            }
+            Ok(Some(res)) => {
+                self.message = format!("Successfully updated an item in the table '{}'.", ts.name);
+                self.detail = Some(res.attributes.unwrap_or(edited));
+            }
+            Err(data::DyneinEditError::Update(e))
+                if e.as_service_error()
+                    .map_or(false, |e| e.is_conditional_check_failed_exception()) =>
+            {
+                self.message = String::from(
+                    "The item has been changed since it was read. Open it again and retry.",
+                );
+            }
+            Err(e) => self.message = e.to_string(),
+        }
+        Ok(())
+    }
+
+    fn draw(&self, term: &Term) -> io::Result<()> {
+        let (height, width) = term.size();
+        let lines = self.render(width as usize, height as usize);
+        term.clear_screen()?;
+        let last = lines.len() - 1;
+        for (i, line) in lines.into_iter().enumerate() {
+            match i {
+                0 => term.write_line(&style(line).bold().to_string())?,
+                _ if i == last => term.write_str(&style(line).dim().to_string())?,
+                _ => term.write_line(&line)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/* =================================================
+Public functions
+================================================= */
+
+/// Runs the terminal UI until `q` is pressed.
+pub async fn tui(cx: &app::Context) -> Result<(), app::DyneinExit> {
+    let term = Term::stdout();
+    if !term.is_term() || !io::stdin().is_terminal() {
+        return Err(app::bye(1, "dy tui requires an interactive terminal."));
+    }
+    let tables = control::try_list_tables_api(cx, None)
+        .await
+        .map_err(|e| app::bye_with_sdk_error(1, e))?;
+    let mut browser = Browser::new(tables);
+    if let Some(ts) = cx.cached_using_table_schema().await {
+        browser.table_cursor = browser
+            .tables
+            .iter()
+            .position(|t| *t == ts.name)
+            .unwrap_or_default();
+        if browser.open_table(cx, &ts.name).await {
+            browser.load_page(cx, page_size(&term)).await;
+        }
+    }
+
+    run(cx, &mut browser, &term)
+        .await
+        .map_err(app::bye_with_error)
+}
+
+/* =================================================
+Private functions
+================================================= */
+
+async fn run(cx: &app::Context, browser: &mut Browser, term: &Term) -> io::Result<()> {
+    loop {
+        browser.draw(term)?;
+        match browser.on_key(&term.read_key()?) {
+            Action::None => (),
+            Action::Quit => break,
+            Action::OpenTable => {
+                let name = browser.tables[browser.table_cursor].clone();
+                if browser.open_table(cx, &name).await {
+                    browser.load_page(cx, page_size(term)).await;
+                }
+            }
+            Action::LoadPage => browser.load_page(cx, page_size(term)).await,
+            Action::Query => {
+                term.write_line("")?;
+                term.write_str("Partition key value: ")?;
+                let pval = term.read_line()?;
+                if !pval.is_empty() {
+                    browser.query = Some(pval);
+                    browser.pages = vec![None];
+                    browser.load_page(cx, page_size(term)).await;
+                }
+            }
+            Action::OpenItem => {
+                if let Some(item) = browser.read_item(cx).await {
+                    browser.detail = Some(item);
+                    browser.detail_scroll = 0;
+                    browser.screen = Screen::Detail;
+                }
+            }
+            Action::EditItem => browser.edit_item(cx, term).await?,
+        }
+    }
+    term.clear_screen()
+}
+
+/// Number of items in a page, which fit in the body of the screen below the header of the table.
+fn page_size(term: &Term) -> i32 {
+    let (height, _) = term.size();
+    (height as usize).saturating_sub(CHROME_LINES + 1).max(1) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddb::{key, table};
+
+    fn browser() -> Browser {
+        let mut browser = Browser::new(vec![String::from("t1"), String::from("t2")]);
+        browser.ts = Some(app::TableSchema {
+            region: String::from("local"),
+            name: String::from("t1"),
+            pk: key::Key {
+                name: String::from("pk"),
+                kind: key::KeyType::S,
+            },
+            sk: None,
+            indexes: Some(vec![app::IndexSchema {
+                name: String::from("idx"),
+                kind: app::IndexType::Gsi,
+                pk: key::Key {
+                    name: String::from("v"),
+                    kind: key::KeyType::S,
+                },
+                sk: None,
+            }]),
+            mode: table::Mode::OnDemand,
+            cached_at: None,
+        });
+        browser
+    }
+
+    fn item(pk: &str) -> Item {
+        HashMap::from([(String::from("pk"), AttributeValue::S(pk.to_owned()))])
+    }
+
+    #[test]
+    fn test_on_key() {
+        let mut browser = browser();
+        assert_eq!(browser.on_key(&Key::ArrowDown), Action::None);
+        assert_eq!(browser.on_key(&Key::ArrowDown), Action::None);
+        assert_eq!(browser.table_cursor, 1);
+        assert_eq!(browser.on_key(&Key::Enter), Action::OpenTable);
+
+        browser.screen = Screen::Items;
+        browser.items = vec![item("a"), item("b")];
+        browser.last_evaluated_key = Some(item("b"));
+        assert_eq!(browser.on_key(&Key::Char('j')), Action::None);
+        assert_eq!(browser.item_cursor, 1);
+        assert_eq!(browser.on_key(&Key::ArrowRight), Action::LoadPage);
+        assert_eq!(browser.pages, vec![None, Some(item("b"))]);
+        assert_eq!(browser.on_key(&Key::ArrowRight), Action::None);
+        assert_eq!(browser.message, "This is the last page.");
+        assert_eq!(browser.on_key(&Key::ArrowLeft), Action::LoadPage);
+        assert_eq!(browser.pages, vec![None]);
+
+        // `i` cycles the base table and indexes.
+        assert_eq!(browser.on_key(&Key::Char('i')), Action::LoadPage);
+        assert_eq!(browser.index, Some(String::from("idx")));
+        assert_eq!(browser.on_key(&Key::Char('i')), Action::LoadPage);
+        assert_eq!(browser.index, None);
+
+        assert_eq!(browser.on_key(&Key::Enter), Action::OpenItem);
+        browser.screen = Screen::Detail;
+        assert_eq!(browser.on_key(&Key::Char('e')), Action::EditItem);
+        assert_eq!(browser.on_key(&Key::Escape), Action::None);
+        assert_eq!(browser.screen, Screen::Items);
+        assert_eq!(browser.on_key(&Key::Char('q')), Action::Quit);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut browser = browser();
+        let lines = browser.render(40, 6);
+        assert_eq!(
+            lines,
+            vec![
+                "Tables (2)",
+                "> t1",
+                "  t2",
+                "",
+                "",
+                "↑/↓ move  Enter open  q quit",
+            ]
+        );
+
+        browser.screen = Screen::Items;
+        browser.items = vec![item("a"), item("b"), item("c")];
+        browser.item_cursor = 2;
+        let lines = browser.render(30, 6);
+        // The body is scrolled to show the cursor, and long lines are truncated.
+        assert_eq!(lines[0], "Table: t1  Scan  Page: 1");
+        assert_eq!(&lines[1..4], &["  pk", "  b", "> c"]);
+        assert_eq!(lines[5], "↑/↓ move  ←/→ page  Enter ope…");
+    }
+}
@@ -17,6 +17,7 @@
 pub mod util;
 
 use assert_cmd::prelude::*; // Add methods on commands
+use std::io::Write;
 
 #[tokio::test]
 async fn test_config_dump() -> Result<(), Box<dyn std::error::Error>> {
@@ -83,8 +84,11 @@ async fn assert_config_use_dump(
 
     let mut c = tm.command()?;
     let cmd = c.args(["config", "dump"]);
-    cmd.assert().success().stdout(format!(
-        "tables:
+    // cached_at is the time when the table was described.
+    let expected = format!(
+        "^{}\\S+\n{}$",
+        regex::escape(&format!(
+            "tables:
   local/{table_name}:
     region: local
     name: {table_name}
@@ -94,7 +98,10 @@ async fn assert_config_use_dump(
     sk: null
     indexes: null
     mode: OnDemand
-
+    cached_at: "
+        )),
+        regex::escape(&format!(
+            "
 using_region: local
 using_table: {table_name}
 using_port: 8000
@@ -103,7 +110,337 @@ query:
 retry: null
 
 "
+        ))
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::is_match(expected)?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_config_use_context() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup_with_lock().await?;
+    let table_name = tm.create_temporary_table("pk", None).await?;
+
+    let mut config_path = tm.default_config_dir();
+    config_path.push("config.yml");
+    std::fs::write(
+        config_path,
+        "using_region: us-east-1
+using_table: toptable
+using_port: null
+retry: null
+contexts:
+  docker:
+    region: local
+    port: 8000
+  staging:
+    region: us-west-2
+",
+    )?;
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "get-contexts"]);
+    cmd.assert()
+        .success()
+        .stdout("  docker local\n  staging us-west-2\n");
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "use-context", "unknown"]);
+    cmd.assert().failure();
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "use-context", "docker"]);
+    cmd.assert()
+        .success()
+        .stdout("Switched to context 'docker'.\n");
+
+    // `dy use` saves the table into the current context.
+    let mut c = tm.command()?;
+    let cmd = c.args(["use", &table_name]);
+    cmd.assert().success().stdout(format!(
+        "Now you're using the table '{table_name}' (local).\n"
+    ));
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "get-contexts"]);
+    cmd.assert()
+        .success()
+        .stdout("* docker local\n  staging us-west-2\n");
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "use-context"]);
+    cmd.assert()
+        .success()
+        .stdout("Switched to the default settings without a context.\n");
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "dump"]);
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains(format!(
+            "using_region: us-east-1
+using_table: toptable
+using_port: null
+query:
+  strict_mode: false
+retry: null
+contexts:
+  docker:
+    region: local
+    port: 8000
+    table: {table_name}
+  staging:
+    region: us-west-2
+"
+        )));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_config_set_get_unset_validate() -> Result<(), Box<dyn std::error::Error>> {
+    let tm = util::setup_with_lock().await?;
+
+    let mut config_path = tm.default_config_dir();
+    config_path.push("config.yml");
+    std::fs::write(
+        &config_path,
+        "using_region: null
+using_table: null
+using_port: null
+retry:
+  default:
+    max_attempts: 0
+future_option: kept
+",
+    )?;
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "validate"]);
+    cmd.assert().failure().stderr(predicates::str::contains(
+        "invalid value for 'retry.default': max_attempts should be greater than zero",
+    ));
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "set", "retry.default.max_attempts", "5"]);
+    cmd.assert().success();
+    // Keys unknown to this version are kept.
+    assert!(std::fs::read_to_string(&config_path)?.contains("future_option: kept"));
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "get", "retry.default.max_attempts"]);
+    cmd.assert().success().stdout("5\n");
+
+    // An invalid value fails the command, but not the rest of the script.
+    let mut script = tempfile::Builder::new().suffix(".dy").tempfile()?;
+    writeln!(script, "config set retry.default.max_attempts five")?;
+    writeln!(script, "config get retry.default.max_attempts")?;
+    let mut c = tm.command()?;
+    let cmd = c.args(["--script", script.path().to_str().unwrap()]);
+    cmd.assert()
+        .failure()
+        .stdout("5\n")
+        .stderr(predicates::str::contains("1 command(s) failed."));
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "set", "retry.default.max_attempts", "five"]);
+    cmd.assert().failure().stderr(predicates::str::contains(
+        "invalid value for 'retry.default.max_attempts'",
+    ));
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "set", "contexts.dev.regoin", "us-west-2"]);
+    cmd.assert().failure().stderr(predicates::str::contains(
+        "unknown key 'contexts.dev.regoin'",
+    ));
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "unset", "retry.default.max_attempts"]);
+    cmd.assert().success();
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "unset", "future_option"]);
+    cmd.assert().success();
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "get", "retry.default.max_attempts"]);
+    cmd.assert().failure().stderr(predicates::str::contains(
+        "'retry.default.max_attempts' is not set",
+    ));
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "validate"]);
+    cmd.assert().success().stdout("The config file is valid.\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_config_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup_with_lock().await?;
+    let table_name = tm.create_temporary_table("pk", None).await?;
+
+    let mut c = tm.command()?;
+    let cmd = c.args([
+        "--region",
+        "local",
+        "config",
+        "cache",
+        "refresh",
+        &table_name,
+    ]);
+    cmd.assert().success().stdout(format!(
+        "Refreshed the cached schema of the table '{table_name}' (local).\n"
     ));
 
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "dump"]);
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains(format!("local/{table_name}:")));
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["--region", "local", "config", "cache", "clear", &table_name]);
+    cmd.assert()
+        .success()
+        .stdout("Removed the cached schema.\n");
+
+    let mut c = tm.command()?;
+    let cmd = c.args(["--region", "local", "config", "cache", "clear", &table_name]);
+    cmd.assert()
+        .success()
+        .stdout("No cached schema is found.\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_config_project_layer() -> Result<(), Box<dyn std::error::Error>> {
+    let tm = util::setup_with_lock().await?;
+
+    let mut config_path = tm.default_config_dir();
+    config_path.push("config.yml");
+    std::fs::write(
+        &config_path,
+        "using_region: us-east-1
+using_table: usertbl
+using_port: null
+retry: null
+",
+    )?;
+    let project_dir = tempfile::tempdir()?;
+    std::fs::write(
+        project_dir.path().join(".dynein.yml"),
+        "using_region: local
+query:
+  strict_mode: true
+",
+    )?;
+    let sub_dir = project_dir.path().join("sub");
+    std::fs::create_dir(&sub_dir)?;
+
+    // .dynein.yml in a parent directory is found as well.
+    let mut c = tm.command()?;
+    let cmd = c
+        .current_dir(&sub_dir)
+        .args(["config", "get", "using_region"]);
+    cmd.assert().success().stdout("local\n");
+
+    let mut c = tm.command()?;
+    let cmd = c.current_dir(&sub_dir).args(["config", "dump"]);
+    cmd.assert().success().stdout(predicates::str::contains(
+        "using_region: local  # project
+using_table: usertbl  # user
+using_port: null  # user
+query:
+  strict_mode: true  # project
+retry: null  # user
+",
+    ));
+
+    // The project file doesn't change config.yml.
+    let mut c = tm.command()?;
+    let cmd = c.args(["config", "get", "using_region"]);
+    cmd.assert().success().stdout("us-east-1\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_parallel_cache_writes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup_with_lock().await?;
+    let mut table_names = vec![];
+    for _ in 0..4 {
+        table_names.push(tm.create_temporary_table("pk", None).await?);
+    }
+
+    // Each `dy desc` saves the table schema into cache.yml, and none of them should be lost.
+    let mut children = vec![];
+    for table_name in &table_names {
+        let mut c = std::process::Command::new(assert_cmd::cargo::cargo_bin("dy"));
+        c.env("DYNEIN_CONFIG_DIR", tm.default_config_dir())
+            .args(["--region", "local", "desc", table_name])
+            .stdout(std::process::Stdio::null());
+        children.push(c.spawn()?);
+    }
+    for mut child in children {
+        assert!(child.wait()?.success());
+    }
+
+    let mut c = tm.command()?;
+    let mut assert = c.args(["config", "dump"]).assert().success();
+    for table_name in &table_names {
+        assert = assert.stdout(predicates::str::contains(format!("local/{table_name}:")));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_index_created_after_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup_with_lock().await?;
+    let table_name = tm.create_temporary_table("pk", None).await?;
+
+    let mut c = tm.command()?;
+    c.args(["--region", "local", "use", &table_name])
+        .assert()
+        .success();
+    // The cached schema doesn't know the index created after `dy use`.
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "admin",
+        "create",
+        "index",
+        "--table",
+        &table_name,
+        "idx",
+        "--keys",
+        "gsi,S",
+    ])
+    .assert()
+    .success();
+
+    // Commands taking --index refresh the cached schema instead of failing.
+    for args in [
+        vec!["stats", "--index", "idx"],
+        vec!["schema", "infer", "--index", "idx"],
+        vec!["scan", "--index", "idx"],
+        vec!["query", "--index", "idx", "abc"],
+    ] {
+        let mut c = tm.command()?;
+        c.args(["--region", "local"]).args(args).assert().success();
+    }
+
+    let mut c = tm.command()?;
+    c.args(["--region", "local", "scan", "--index", "no-such-index"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("No index named 'no-such-index'"));
+
     Ok(())
 }
@@ -0,0 +1,231 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod util;
+
+use crate::util::TemporaryItem;
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::time::Duration;
+use tempfile::tempdir;
+use tokio::time::sleep;
+
+#[tokio::test]
+async fn test_copy() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let source = tm
+        .create_temporary_table_with_items(
+            "pk,S",
+            Some("sk,N"),
+            [
+                TemporaryItem::new("a", Some("1"), Some("{'name':'x'}")),
+                TemporaryItem::new("b", Some("1"), Some("{'tags':<<'x','y'>>}")),
+                TemporaryItem::new("c", Some("2"), None),
+            ],
+        )
+        .await?;
+    let target = tm.create_temporary_table("pk,S", Some("sk,N")).await?;
+
+    // The target table exists, so --create-table doesn't create it.
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "copy",
+        "--from",
+        &source,
+        "--to",
+        &format!("{}@local:8000", target),
+        "--create-table",
+        "--segments",
+        "2",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains("already exists"))
+    .stdout(predicate::str::contains(format!(
+        "Copied 3 items from '{}@local:8000' to '{}@local:8000'.",
+        source, target
+    )));
+
+    // Each side can be given with an endpoint.
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "copy",
+        "--from",
+        &format!("{}@http://localhost:8000", source),
+        "--to",
+        &format!("{}@http://localhost:8000", target),
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(format!(
+        "Copied 3 items from '{}@http://localhost:8000'",
+        source
+    )));
+
+    let mut c = tm.command()?;
+    c.args(["--region", "local", "diff", &source, &target, "--exit-code"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "0 added, 0 removed, 0 changed, 3 same",
+        ));
+
+    // A table cannot be copied into itself.
+    let mut c = tm.command()?;
+    c.args([
+        "--region", "local", "copy", "--from", &source, "--to", &source,
+    ])
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains("the same table"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_copy_rename_and_resume() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let source = tm
+        .create_temporary_table_with_items(
+            "pk,S",
+            None,
+            [
+                TemporaryItem::new("a", None, Some("{'name':'x'}")),
+                TemporaryItem::new("b", None, None),
+            ],
+        )
+        .await?;
+    let target = tm.create_temporary_table("PK,S", None).await?;
+
+    let dir = tempdir()?;
+    let resume_file = dir.path().join("copy.json");
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "copy",
+        "--from",
+        &source,
+        "--to",
+        &target,
+        "--rename",
+        "pk=PK",
+        "--resume-file",
+        resume_file.to_str().unwrap(),
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains("Copied 2 items"));
+    // The progress is removed when the copy completes.
+    assert!(!resume_file.exists());
+
+    let mut c = tm.command()?;
+    c.args(["--region", "local", "--table", &target, "get", "a"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""PK": "a""#))
+        .stdout(predicate::str::contains(r#""name": "x""#));
+
+    // A copy finished in a previous run is not repeated.
+    std::fs::write(
+        &resume_file,
+        format!(
+            r#"{{"from":{{"table":"{}","region":null,"port":null}},"to":{{"table":"{}","region":null,"port":null}},"rename":["pk=PK"],"segments":[{{"copied":2,"done":true}}]}}"#,
+            source, target
+        ),
+    )?;
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "copy",
+        "--from",
+        &source,
+        "--to",
+        &target,
+        "--rename",
+        "pk=PK",
+        "--resume-file",
+        resume_file.to_str().unwrap(),
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains("2 items have been copied"))
+    .stdout(predicate::str::contains("Copied 2 items"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_copy_create_table_with_indexes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let source = tm
+        .create_temporary_table_with_items(
+            "pk,S",
+            None,
+            [TemporaryItem::new("a", None, Some("{'gsi':1}"))],
+        )
+        .await?;
+    tm.command()?
+        .args([
+            "-r", "local", "admin", "create", "index", "--table", &source, "idx", "--keys", "gsi,N",
+        ])
+        .assert()
+        .success();
+    sleep(Duration::from_secs(1)).await;
+
+    let target = format!("{}-copy", source);
+    tm.add_tables_to_delete([target.to_owned()]);
+    tm.command()?
+        .args([
+            "--region",
+            "local",
+            "copy",
+            "--from",
+            &source,
+            "--to",
+            &target,
+            "--create-table",
+            "--rename",
+            "pk=PK",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Copied 1 items"));
+
+    // The target table has the same index, which can be queried right after the copy.
+    tm.command()?
+        .args(["-r", "local", "info", "--table", &target])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("pk: PK (S)"))
+        .stdout(predicate::str::contains(
+            "gsi:\n- name: idx\n  schema:\n    pk: gsi (N)",
+        ));
+    tm.command()?
+        .args([
+            "-r", "local", "--table", &target, "query", "--index", "idx", "1",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""PK": "a""#));
+
+    Ok(())
+}
@@ -81,7 +81,7 @@ async fn test_del_existent_item() -> Result<(), Box<dyn std::error::Error>> {
         scan_cmd
             .assert()
             .success()
-            .stdout(predicate::str::diff("pk  attributes\nb\n"));
+            .stdout(predicate::str::diff("pk\nb\n"));
     }
 
     Ok(())
@@ -119,9 +119,10 @@ async fn test_del_existent_item_with_sk() -> Result<(), Box<dyn std::error::Erro
 
     let mut c = tm.command()?;
     let scan_cmd = c.args(["--region", "local", "--table", &table_name, "scan"]);
-    scan_cmd.assert().success().stdout(predicate::str::diff(
-        "pk   sk  attributes\nabc  1\nabc  3\n",
-    ));
+    scan_cmd
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("pk   sk\nabc  1\nabc  3\n"));
 
     Ok(())
 }
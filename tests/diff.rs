@@ -0,0 +1,150 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod util;
+
+use crate::util::TemporaryItem;
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::fs;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_diff_tables() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let source = tm
+        .create_temporary_table_with_items(
+            "pk,S",
+            Some("sk,N"),
+            [
+                TemporaryItem::new("a", Some("1"), Some("{'name':'x'}")),
+                TemporaryItem::new("b", Some("1"), Some("{'address':{'city':'Tokyo'}}")),
+                TemporaryItem::new("c", Some("1"), None),
+            ],
+        )
+        .await?;
+    let target = tm
+        .create_temporary_table_with_items(
+            "pk,S",
+            Some("sk,N"),
+            [
+                TemporaryItem::new("a", Some("1"), Some("{'name':'x'}")),
+                TemporaryItem::new("b", Some("1"), Some("{'address':{'city':'Osaka'}}")),
+                TemporaryItem::new("d", Some("2"), None),
+            ],
+        )
+        .await?;
+
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "diff",
+        &source,
+        &format!("{}@local:8000", target),
+        "--exit-code",
+    ])
+    .assert()
+    .failure()
+    .code(1)
+    .stdout(predicate::str::contains(r#"- {"pk":"c","sk":1}"#))
+    .stdout(predicate::str::contains(r#"+ {"pk":"d","sk":2}"#))
+    .stdout(predicate::str::contains(r#"~ {"pk":"b","sk":1}"#))
+    .stdout(predicate::str::contains(
+        r#"    address.city: "Tokyo" -> "Osaka""#,
+    ))
+    .stdout(predicate::str::contains(
+        "1 added, 1 removed, 1 changed, 1 same",
+    ));
+
+    // Each side can be given with an endpoint.
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "diff",
+        &format!("{}@http://localhost:8000", source),
+        &format!("{}@http://localhost:8000", target),
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(format!(
+        "--- {}@http://localhost:8000 (source)",
+        source
+    )))
+    .stdout(predicate::str::contains(
+        "1 added, 1 removed, 1 changed, 1 same",
+    ));
+
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "diff",
+        &source,
+        &source,
+        "--exit-code",
+        "-o",
+        "json",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(r#""same": 3"#));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_diff_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm
+        .create_temporary_table_with_items(
+            "pk,S",
+            None,
+            [
+                TemporaryItem::new("a", None, Some("{'tags':<<'x','y'>>}")),
+                TemporaryItem::new("b", None, Some("{'n':1}")),
+            ],
+        )
+        .await?;
+
+    let dir = tempdir()?;
+    let file = dir.path().join("items.jsonl");
+    fs::write(
+        &file,
+        "{\"pk\":\"a\",\"tags\":[\"x\",\"y\"]}\n{\"pk\":\"b\",\"n\":2}\n",
+    )?;
+
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "diff",
+        &table_name,
+        "--file",
+        file.to_str().unwrap(),
+        "--format",
+        "jsonl",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(r#"    n: 1 -> 2"#))
+    .stdout(predicate::str::contains(
+        "0 added, 0 removed, 1 changed, 1 same",
+    ));
+
+    Ok(())
+}
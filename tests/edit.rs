@@ -0,0 +1,111 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod util;
+
+use crate::util::TemporaryItem;
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+
+#[tokio::test]
+async fn test_edit() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm
+        .create_temporary_table_with_items(
+            "pk,S",
+            Some("sk,N"),
+            [TemporaryItem::new(
+                "a",
+                Some("1"),
+                Some("{'name':'x','address':{'city':'Tokyo','zip':'100'}}"),
+            )],
+        )
+        .await?;
+
+    // The "editor" rewrites a nested attribute and removes another one.
+    let mut c = tm.command()?;
+    c.env("VISUAL", r#"sed -i -e s/Tokyo/Osaka/ -e /name/d"#)
+        .args([
+            "--region",
+            "local",
+            "--table",
+            &table_name,
+            "edit",
+            "a",
+            "1",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Successfully updated an item in the table",
+        ))
+        .stdout(predicate::str::contains(
+            r#""address":{"city":"Osaka","zip":"100"}"#,
+        ))
+        .stdout(predicate::str::contains("name").not());
+
+    // Nothing is updated if the item is not changed in the editor.
+    let mut c = tm.command()?;
+    c.env("VISUAL", "touch")
+        .args([
+            "--region",
+            "local",
+            "--table",
+            &table_name,
+            "edit",
+            "a",
+            "1",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No change to the item."));
+
+    // The primary key cannot be changed.
+    let mut c = tm.command()?;
+    c.env("VISUAL", "sed -i -e s/a/b/")
+        .args([
+            "--region",
+            "local",
+            "--table",
+            &table_name,
+            "edit",
+            "a",
+            "1",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The primary key of an item cannot be changed.",
+        ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_edit_not_found() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm
+        .create_temporary_table_with_items("pk,S", None, [TemporaryItem::new("a", None, None)])
+        .await?;
+
+    let mut c = tm.command()?;
+    c.args(["--region", "local", "--table", &table_name, "edit", "b"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("No item found."));
+
+    Ok(())
+}
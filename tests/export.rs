@@ -17,7 +17,9 @@
 pub mod util;
 
 use assert_cmd::prelude::*; // Add methods on commands
+use flate2::read::GzDecoder;
 use predicates::prelude::*; // Used for writing assertions
+use std::io::Read;
 use tempfile::tempdir;
 
 #[tokio::test]
@@ -116,3 +118,299 @@ async fn test_export_with_items() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_export_with_compression() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm
+        .create_temporary_table_with_items(
+            "pk",
+            None,
+            vec![
+                util::TemporaryItem::new("abc", None, None),
+                util::TemporaryItem::new("def", None, Some(r#"{"a": 1}"#)),
+            ],
+        )
+        .await?;
+
+    let base_dir = tempdir()?;
+    // The compression is chosen by the extension of the output file.
+    let temp_path = base_dir.path().join(format!("{}.jsonl.gz", table_name));
+
+    let mut c = tm.command()?;
+    let cmd = c.args(&[
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "export",
+        "--format",
+        "jsonl",
+        "--output-file",
+        temp_path.to_str().unwrap(),
+    ]);
+    cmd.assert().success();
+
+    let mut export_content = String::new();
+    GzDecoder::new(std::fs::File::open(&temp_path)?).read_to_string(&mut export_content)?;
+    let mut items = export_content
+        .lines()
+        .map(|line| line.parse::<serde_json::Value>())
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    items.sort_by_key(|item| item["pk"].to_string());
+    assert_eq!(
+        items,
+        vec![
+            serde_json::json!({"pk":"abc"}),
+            serde_json::json!({"pk":"def","a":1}),
+        ]
+    );
+
+    // The compressed file can be imported into another table as is.
+    let import_table_name = tm.create_temporary_table("pk", None).await?;
+    let mut c = tm.command()?;
+    let cmd = c.args(&[
+        "--region",
+        "local",
+        "--table",
+        &import_table_name,
+        "import",
+        "--format",
+        "jsonl",
+        "--input-file",
+        temp_path.to_str().unwrap(),
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2 items processed"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_export_parquet() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm
+        .create_temporary_table_with_items(
+            "pk",
+            Some("sk,N"),
+            vec![
+                util::TemporaryItem::new("abc", Some("1"), None),
+                util::TemporaryItem::new("abc", Some("2"), Some(r#"{"a": 1, "b": "x"}"#)),
+            ],
+        )
+        .await?;
+
+    let base_dir = tempdir()?;
+    let temp_path = base_dir.path().join(format!("{}.parquet", table_name));
+
+    let mut c = tm.command()?;
+    let cmd = c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "export",
+        "--format",
+        "parquet",
+        "--output-file",
+        temp_path.to_str().unwrap(),
+    ]);
+    cmd.assert().success();
+
+    // Import the exported file into another table with the same key schema.
+    let import_table_name = tm.create_temporary_table("pk", Some("sk,N")).await?;
+    let mut c = tm.command()?;
+    let cmd = c.args([
+        "--region",
+        "local",
+        "--table",
+        &import_table_name,
+        "import",
+        "--format",
+        "parquet",
+        "--input-file",
+        temp_path.to_str().unwrap(),
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2 items processed"));
+
+    let mut c = tm.command()?;
+    let get_cmd = c.args([
+        "--region",
+        "local",
+        "--table",
+        &import_table_name,
+        "get",
+        "abc",
+        "2",
+    ]);
+    util::assert_eq_cmd_json(get_cmd, r#"{"pk":"abc","sk":2,"a":1,"b":"x"}"#);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_export_partition_with_filter() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm
+        .create_temporary_table_with_items(
+            "pk",
+            Some("sk,N"),
+            vec![
+                util::TemporaryItem::new("abc", Some("1"), Some(r#"{"price": 10}"#)),
+                util::TemporaryItem::new("abc", Some("2"), Some(r#"{"price": 200}"#)),
+                util::TemporaryItem::new("abc", Some("3"), Some(r#"{"price": 300}"#)),
+                util::TemporaryItem::new("def", Some("1"), Some(r#"{"price": 400}"#)),
+            ],
+        )
+        .await?;
+
+    let base_dir = tempdir()?;
+    let temp_path = base_dir.path().join(&table_name);
+
+    let mut c = tm.command()?;
+    let cmd = c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "export",
+        "--format",
+        "jsonl",
+        "--pval",
+        "abc",
+        "--sort-key",
+        "<= 2",
+        "--filter",
+        "#price > :min",
+        "--filter-values",
+        r#"{":min": 100}"#,
+        "--output-file",
+        temp_path.to_str().unwrap(),
+    ]);
+    cmd.assert().success();
+
+    let export_content = std::fs::read_to_string(temp_path)?;
+    assert_eq!(
+        export_content
+            .lines()
+            .map(|line| line.parse::<serde_json::Value>())
+            .collect::<Result<Vec<serde_json::Value>, _>>()?,
+        vec![serde_json::json!({"pk":"abc","sk":2,"price":200})]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_export_undefined_filter_value() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm.create_temporary_table("pk", None).await?;
+
+    let base_dir = tempdir()?;
+    let temp_path = base_dir.path().join(&table_name);
+
+    let mut c = tm.command()?;
+    let cmd = c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "export",
+        "--filter",
+        "price > :min",
+        "--output-file",
+        temp_path.to_str().unwrap(),
+    ]);
+    cmd.assert().failure().stdout(predicate::str::contains(
+        "The value of ':min' used in --filter is not given.",
+    ));
+    assert!(!temp_path.exists());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_export_existing_output_file_non_interactive() -> Result<(), Box<dyn std::error::Error>>
+{
+    let mut tm = util::setup().await?;
+    let table_name = tm
+        .create_temporary_table_with_items(
+            "pk",
+            None,
+            vec![util::TemporaryItem::new("abc", None, None)],
+        )
+        .await?;
+
+    let base_dir = tempdir()?;
+    let temp_path = base_dir.path().join(&table_name);
+    std::fs::write(&temp_path, "existing content")?;
+
+    // stdin of the command is not a terminal, so the confirmation is not shown.
+    let mut c = tm.command()?;
+    let cmd = c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "export",
+        "--output-file",
+        temp_path.to_str().unwrap(),
+    ]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Use --yes to overwrite the output file.",
+    ));
+    assert_eq!(std::fs::read_to_string(&temp_path)?, "existing content");
+
+    let mut c = tm.command()?;
+    let cmd = c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "export",
+        "--format",
+        "jsonl",
+        "--output-file",
+        temp_path.to_str().unwrap(),
+        "--yes",
+    ]);
+    cmd.assert().success();
+    assert_eq!(
+        std::fs::read_to_string(&temp_path)?.trim(),
+        r#"{"pk":"abc"}"#
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_export_failing_query_keeps_output_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm.create_temporary_table("pk,N", None).await?;
+
+    let base_dir = tempdir()?;
+    let temp_path = base_dir.path().join(&table_name);
+    std::fs::write(&temp_path, "existing content")?;
+
+    // The partition key is a number, so the Query fails.
+    let mut c = tm.command()?;
+    let cmd = c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "export",
+        "--pval",
+        "abc",
+        "--output-file",
+        temp_path.to_str().unwrap(),
+        "--yes",
+    ]);
+    cmd.assert().failure();
+    assert_eq!(std::fs::read_to_string(&temp_path)?, "existing content");
+
+    Ok(())
+}
@@ -157,6 +157,56 @@ async fn test_get_item_output_raw() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_get_item_show_types() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = prepare_table_with_item(&mut tm).await?;
+
+    for (format, expected) in [
+        (
+            "json",
+            "{\n  \"flag\": true,  // BOOL\n  \"pk\": \"42\"  // S\n}\n",
+        ),
+        ("yaml", "flag: true  # BOOL\npk: '42'  # S\n"),
+    ] {
+        let mut c = tm.command()?;
+        c.args([
+            "--region",
+            "local",
+            "--table",
+            &table_name,
+            "get",
+            "42",
+            "--show-types",
+            "-o",
+            format,
+            "--color",
+            "never",
+        ])
+        .assert()
+        .success()
+        .stdout(expected);
+    }
+
+    // --color always colorizes the output even if stdout is not a terminal.
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "--color",
+        "always",
+        "get",
+        "42",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains("\u{1b}["));
+
+    Ok(())
+}
+
 async fn prepare_table_with_item<'a>(
     tm: &mut util::TestManager<'a>,
 ) -> Result<String, Box<dyn std::error::Error>> {
@@ -18,10 +18,39 @@ pub mod util;
 
 use crate::util::assert_eq_cmd_json;
 use assert_cmd::prelude::*;
+use base64::{engine::general_purpose, Engine as _};
+use flate2::{write::GzEncoder, Compression};
+use md5::{Digest, Md5};
 use predicates::prelude::*;
 use std::fs;
+use std::io::Write;
+use std::path::Path;
 use tempfile::tempdir;
 
+/// Creates a directory which has the same layout as DynamoDB's "Export to S3" output, then returns the MD5 checksum of the data file.
+fn write_export_dir(
+    export_dir: &Path,
+    lines: &[&str],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    for line in lines {
+        writeln!(encoder, "{}", line)?;
+    }
+    let compressed = encoder.finish()?;
+    let checksum = general_purpose::STANDARD.encode(Md5::digest(&compressed));
+
+    fs::create_dir_all(export_dir.join("data"))?;
+    fs::write(export_dir.join("data").join("abcdefg.json.gz"), compressed)?;
+    fs::write(
+        export_dir.join("manifest-summary.json"),
+        format!(
+            r#"{{"version":"2020-06-30","itemCount":{},"outputFormat":"DYNAMODB_JSON","manifestFilesS3Key":"AWSDynamoDB/01234/manifest-files.json"}}"#,
+            lines.len()
+        ),
+    )?;
+    Ok(checksum)
+}
+
 #[tokio::test]
 async fn test_import_json() -> Result<(), Box<dyn std::error::Error>> {
     let mut tm = util::setup().await?;
@@ -230,3 +259,87 @@ async fn test_import_jsonl_with_set_inference() -> Result<(), Box<dyn std::error
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_import_from_export_dir() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let tbl = tm.create_temporary_table("pk", Some("sk,N")).await?;
+    let base_dir = tempdir()?;
+
+    let checksum = write_export_dir(
+        base_dir.path(),
+        &[
+            r#"{"Item":{"pk":{"S":"pk1"},"sk":{"N":"1"},"ss":{"SS":["a","b"]}}}"#,
+            r#"{"Item":{"pk":{"S":"pk2"},"sk":{"N":"2"},"m":{"M":{"flag":{"BOOL":true}}}}}"#,
+        ],
+    )?;
+    fs::write(
+        base_dir.path().join("manifest-files.json"),
+        format!(
+            r#"{{"itemCount":2,"md5Checksum":"{}","etag":"x","dataFileS3Key":"AWSDynamoDB/01234/data/abcdefg.json.gz"}}"#,
+            checksum
+        ),
+    )?;
+
+    tm.command()?
+        .args([
+            "-r",
+            "local",
+            "import",
+            "-t",
+            &tbl,
+            "--from-export-dir",
+            base_dir.path().to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("items processed"));
+
+    assert_eq_cmd_json(
+        tm.command()?
+            .args(["-r", "local", "get", "-t", &tbl, "pk1", "1", "-o", "raw"]),
+        r#"{"pk":{"S":"pk1"},"sk":{"N":"1"},"ss":{"SS":["a","b"]}}"#,
+    );
+    assert_eq_cmd_json(
+        tm.command()?
+            .args(["-r", "local", "get", "-t", &tbl, "pk2", "2"]),
+        r#"{"pk":"pk2","sk":2,"m":{"flag":true}}"#,
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_import_from_export_dir_checksum_mismatch() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let tbl = tm.create_temporary_table("pk", None).await?;
+    let base_dir = tempdir()?;
+
+    write_export_dir(base_dir.path(), &[r#"{"Item":{"pk":{"S":"pk1"}}}"#])?;
+    fs::write(
+        base_dir.path().join("manifest-files.json"),
+        r#"{"itemCount":1,"md5Checksum":"AAAAAAAAAAAAAAAAAAAAAA==","etag":"x","dataFileS3Key":"AWSDynamoDB/01234/data/abcdefg.json.gz"}"#,
+    )?;
+
+    tm.command()?
+        .args([
+            "-r",
+            "local",
+            "import",
+            "-t",
+            &tbl,
+            "--from-export-dir",
+            base_dir.path().to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("checksum mismatch"));
+
+    // Nothing should be written when verification fails
+    tm.command()?
+        .args(["-r", "local", "get", "-t", &tbl, "pk1"])
+        .assert()
+        .stdout(predicate::str::contains("No item found"));
+
+    Ok(())
+}
@@ -42,9 +42,7 @@ async fn test_simple_query() -> Result<(), Box<dyn std::error::Error>> {
         query_cmd
             .assert()
             .success()
-            .stdout(predicate::str::contains(
-                "pk   sk  attributes\nabc  1\nabc  2",
-            ));
+            .stdout(predicate::str::contains("pk   sk\nabc  1\nabc  2"));
     }
 
     Ok(())
@@ -77,9 +75,7 @@ async fn test_simple_desc_query() -> Result<(), Box<dyn std::error::Error>> {
     query_cmd
         .assert()
         .success()
-        .stdout(predicate::str::contains(
-            "pk   sk  attributes\nabc  2\nabc  1",
-        ));
+        .stdout(predicate::str::contains("pk   sk\nabc  2\nabc  1"));
 
     Ok(())
 }
@@ -112,7 +108,7 @@ async fn test_query_limit() -> Result<(), Box<dyn std::error::Error>> {
     query_cmd
         .assert()
         .success()
-        .stdout(predicate::str::contains("pk   sk  attributes\nabc  1"));
+        .stdout(predicate::str::contains("pk   sk\nabc  1"));
 
     Ok(())
 }
@@ -144,7 +140,7 @@ async fn test_query_with_sort_key() -> Result<(), Box<dyn std::error::Error>> {
         "2",
     ]);
     query_cmd.assert().success().stdout(
-        predicate::str::is_match("pk +sk +attributes\n")
+        predicate::str::is_match("pk +sk\n")
             .unwrap()
             .and(predicate::str::is_match("abc +1\n").unwrap().not())
             .and(predicate::str::is_match("abc +2\n").unwrap())
@@ -305,9 +301,10 @@ async fn test_query_with_sort_key_order() -> Result<(), Box<dyn std::error::Erro
             "-s",
             "< 5",
         ]);
-        query_cmd.assert().success().stdout(
-            predicate::str::is_match("pk +sk +attributes\nabc +1\nabc +2\nabc +3\n").unwrap(),
-        );
+        query_cmd
+            .assert()
+            .success()
+            .stdout(predicate::str::is_match("pk +sk\nabc +1\nabc +2\nabc +3\n").unwrap());
     }
 
     Ok(())
@@ -340,7 +337,7 @@ async fn test_query_with_sort_key_le() -> Result<(), Box<dyn std::error::Error>>
         "<=2",
     ]);
     query_cmd.assert().success().stdout(
-        predicate::str::is_match("pk +sk +attributes\n")
+        predicate::str::is_match("pk +sk\n")
             .unwrap()
             .and(predicate::str::is_match("abc +1\n").unwrap())
             .and(predicate::str::is_match("abc +2\n").unwrap())
@@ -379,7 +376,7 @@ async fn test_query_using_between_string() -> Result<(), Box<dyn std::error::Err
         "between 11 21",
     ]);
     query_cmd.assert().success().stdout(
-        predicate::str::is_match("pk +sk +attributes\n")
+        predicate::str::is_match("pk +sk\n")
             .unwrap()
             .and(predicate::str::is_match("abc +1\n").unwrap().not())
             .and(predicate::str::is_match("abc +11\n").unwrap())
@@ -419,7 +416,7 @@ async fn test_query_using_between_number() -> Result<(), Box<dyn std::error::Err
         "between 11 21",
     ]);
     query_cmd.assert().success().stdout(
-        predicate::str::is_match("pk +sk +attributes\n")
+        predicate::str::is_match("pk +sk\n")
             .unwrap()
             .and(predicate::str::is_match("abc +1\n").unwrap().not())
             .and(predicate::str::is_match("abc +11\n").unwrap())
@@ -458,7 +455,7 @@ async fn test_query_using_begins_with() -> Result<(), Box<dyn std::error::Error>
         "begins_with 1",
     ]);
     query_cmd.assert().success().stdout(
-        predicate::str::is_match("pk +sk +attributes\n")
+        predicate::str::is_match("pk +sk\n")
             .unwrap()
             .and(predicate::str::is_match("abc +1\n").unwrap())
             .and(predicate::str::is_match("abc +11\n").unwrap())
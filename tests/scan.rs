@@ -72,7 +72,7 @@ async fn test_simple_scan() -> Result<(), Box<dyn std::error::Error>> {
         scan_cmd
             .assert()
             .success()
-            .stdout(predicate::str::contains("pk  attributes\nabc"));
+            .stdout(predicate::str::contains("pk\nabc"));
     }
 
     Ok(())
@@ -227,3 +227,161 @@ async fn test_scan_with_limits() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_scan_output_formats() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm
+        .create_temporary_table_with_items(
+            "pk,S",
+            None,
+            [TemporaryItem::new("1", None, Some("{'opt1':'1','opt2':2}"))],
+        )
+        .await?;
+
+    for (format, expected) in [
+        ("jsonl", "{\"opt1\":\"1\",\"opt2\":2,\"pk\":\"1\"}\n"),
+        ("csv", "pk,opt1,opt2\n\"1\",\"1\",2\n"),
+    ] {
+        let mut c = tm.command()?;
+        c.args([
+            "--region",
+            "local",
+            "--table",
+            &table_name,
+            "scan",
+            "--output",
+            format,
+        ])
+        .assert()
+        .success()
+        .stdout(expected);
+
+        // The top-level --output sets the default format.
+        let mut c = tm.command()?;
+        c.args([
+            "--region",
+            "local",
+            "--table",
+            &table_name,
+            "--output",
+            format,
+            "scan",
+        ])
+        .assert()
+        .success()
+        .stdout(expected);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_scan_with_columns() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let long = "xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx";
+    let table_name = tm
+        .create_temporary_table_with_items(
+            "pk,S",
+            None,
+            [TemporaryItem::new(
+                "1",
+                None,
+                Some("{'address':{'city':'Tokyo'},'tags':['a','b'],'price':10,'note':'xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx'}"),
+            )],
+        )
+        .await?;
+
+    // Each attribute is a column by default, and long cells are truncated.
+    let mut c = tm.command()?;
+    c.args(["--region", "local", "--table", &table_name, "scan"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::is_match(r#"pk +address +note +price +tags\n1 +\{"city":"Tokyo"\} +x{47}\.\.\. +10 +\["a","b"\]\n"#)
+                .unwrap(),
+        );
+
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "scan",
+        "--wide",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(long));
+
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "scan",
+        "--columns",
+        "address.city,tags[1],price",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicate::str::is_match(r"^pk +address.city +tags\[1\] +price\n1 +Tokyo +b +10\n$")
+            .unwrap(),
+    );
+
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "scan",
+        "--columns",
+        "tags[",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains("--columns syntax is invalid"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_scan_show_capacity() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm
+        .create_temporary_table_with_items(
+            "pk,S",
+            None,
+            [
+                TemporaryItem::new("1", None, Some("{'opt':1}")),
+                TemporaryItem::new("2", None, Some("{'opt':2}")),
+            ],
+        )
+        .await?;
+
+    // Metrics are written to stderr, so stdout has the items only.
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "scan",
+        "--output",
+        "jsonl",
+        "--show-capacity",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains("{\"opt\":1,\"pk\":\"1\"}\n"))
+    .stdout(predicate::str::contains("Requests: ").not())
+    .stderr(predicate::str::contains("Requests: 1, latency: "))
+    .stderr(predicate::str::contains(format!("  {}: ", table_name)))
+    .stderr(predicate::str::contains("Items: 2 scanned, 2 returned"));
+
+    Ok(())
+}
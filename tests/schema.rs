@@ -0,0 +1,82 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+pub mod util;
+
+use crate::util::TemporaryItem;
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+
+#[tokio::test]
+async fn test_schema_infer() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm
+        .create_temporary_table_with_items(
+            "pk,S",
+            None,
+            [
+                TemporaryItem::new("a", None, Some("{'name':'x','info':{'rank':1}}")),
+                TemporaryItem::new("b", None, Some("{'info':{'rank':2}}")),
+            ],
+        )
+        .await?;
+
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "schema",
+        "infer",
+        "--format",
+        "typescript",
+        "--name",
+        "Item",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(format!(
+        "// Inferred from all 2 items of the table '{}'.\n",
+        table_name
+    )))
+    .stdout(predicate::str::contains(
+        "  /** 50.0% present, types: S 1, e.g. \"x\" */\n  name?: string;\n",
+    ))
+    .stdout(predicate::str::contains(
+        "export interface ItemInfo {\n  /** 100.0% present, types: N 2, e.g. 1, 2 */\n  rank: number;\n}\n",
+    ));
+
+    // JSON Schema is the default.
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "schema",
+        "infer",
+        "--sample",
+        "1",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(
+        r#""$schema": "https://json-schema.org/draft/2020-12/schema""#,
+    ))
+    .stdout(predicate::str::contains("Inferred from the first 1 items"));
+
+    Ok(())
+}
@@ -47,3 +47,221 @@ async fn test_shell_mode() -> Result<(), Box<dyn std::error::Error>> {
 
     tm.cleanup(vec![table_name])
 }
+
+#[tokio::test]
+async fn test_shell_builtins() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{Seek, SeekFrom};
+
+    let mut tm = util::setup().await?;
+    let table_name = tm
+        .create_temporary_table_with_items(
+            "pk",
+            None,
+            vec![util::TemporaryItem::new("abc", None, Some(r#"{"a": 1}"#))],
+        )
+        .await?;
+
+    // A script to be executed by `source`.
+    let mut script = Builder::new().tempfile()?;
+    writeln!(script, "get abc")?;
+
+    let mut c = tm.command()?;
+    let shell_session = c.args(["--region", "local", "--shell"]);
+    let mut tmpfile = Builder::new().tempfile()?.into_file();
+    writeln!(tmpfile, "use {}", table_name)?;
+    writeln!(tmpfile, "set output raw")?;
+    writeln!(tmpfile, "\\timing on")?;
+    writeln!(tmpfile, "source {}", script.path().to_str().unwrap())?;
+    tmpfile.seek(SeekFrom::Start(0))?;
+    shell_session
+        .stdin(tmpfile)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "Now you're using the table '{}' (local).",
+            table_name
+        )))
+        .stdout(predicate::str::contains("Timing is on."))
+        // raw output shows DynamoDB JSON.
+        .stdout(predicate::str::contains(r#""S": "abc""#))
+        .stdout(predicate::str::contains("Time: "))
+        .stdout(predicate::str::contains("Requests: "));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_shell_multiline_command() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{Seek, SeekFrom};
+
+    let mut tm = util::setup().await?;
+    let table_name = tm.create_temporary_table("pk", None).await?;
+
+    let mut c = tm.command()?;
+    let shell_session = c.args(["--region", "local", "--table", &table_name, "--shell"]);
+    let mut tmpfile = Builder::new().tempfile()?.into_file();
+    // A formatted item pasted as is
+    writeln!(
+        tmpfile,
+        r#"put abc --item {{
+  "title": "Lost in Translation",
+  "tags": ["drama", "comedy"]
+}}"#
+    )?;
+    writeln!(tmpfile, "# comments are ignored")?;
+    writeln!(tmpfile, "get abc \\")?;
+    writeln!(tmpfile, "  --output json")?;
+    tmpfile.seek(SeekFrom::Start(0))?;
+    shell_session
+        .stdin(tmpfile)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""title": "Lost in Translation""#,
+        ))
+        .stdout(predicate::str::contains(r#""comedy""#));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_script() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm.create_temporary_table("pk", None).await?;
+
+    let mut script = Builder::new().suffix(".dy").tempfile()?;
+    writeln!(script, "# put an item and read it")?;
+    writeln!(script, "put ${{PK}} --item '{{\"a\": 1}}'")?;
+    writeln!(script, "no-such-command")?;
+    writeln!(script, "get ${{PK}} --output raw")?;
+    let script_path = script.path().to_str().unwrap();
+
+    // The rest of commands are executed after the failure, but the exit status is non-zero.
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "--script",
+        script_path,
+        "--var",
+        "PK=abc",
+    ])
+    .assert()
+    .failure()
+    .stdout(predicate::str::contains(r#""S": "abc""#))
+    .stderr(predicate::str::contains("Command at line 3 failed."))
+    .stderr(predicate::str::contains("1 command(s) failed."));
+
+    // --stop-on-error skips commands after the failure.
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "--script",
+        script_path,
+        "--var",
+        "PK=abc",
+        "--stop-on-error",
+    ])
+    .assert()
+    .failure()
+    .stdout(predicate::str::contains(r#""S": "abc""#).not());
+
+    // Undefined variables fail the command.
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "--script",
+        script_path,
+        "--stop-on-error",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains("variable 'PK' is not defined"));
+
+    // A file which cannot be read by `source` fails the command.
+    let mut script = Builder::new().suffix(".dy").tempfile()?;
+    writeln!(script, "source no-such-file.dy")?;
+    writeln!(script, "get abc --output raw")?;
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "--script",
+        script.path().to_str().unwrap(),
+        "--stop-on-error",
+    ])
+    .assert()
+    .failure()
+    .stdout(predicate::str::contains(r#""S": "abc""#).not())
+    .stderr(predicate::str::contains("Failed to read 'no-such-file.dy'"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_shell_source_itself() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm.create_temporary_table("pk", None).await?;
+
+    // A file sourcing itself fails instead of being read forever.
+    let mut script = Builder::new().suffix(".dy").tempfile()?;
+    let script_path = script.path().to_str().unwrap().to_owned();
+    writeln!(script, "source {}", script_path)?;
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "--script",
+        &script_path,
+    ])
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains(
+        "source is nested more than 16 levels",
+    ))
+    .stderr(predicate::str::contains("1 command(s) failed."));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_script_failing_request() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm.create_temporary_table("pk", None).await?;
+
+    // Commands failing by errors of DynamoDB or invalid keys don't stop the script.
+    let mut script = Builder::new().suffix(".dy").tempfile()?;
+    writeln!(script, "put abc")?;
+    writeln!(script, "get abc --table no-such-table")?;
+    writeln!(script, "get abc xyz")?;
+    writeln!(script, "get abc --output raw")?;
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "--script",
+        script.path().to_str().unwrap(),
+    ])
+    .assert()
+    .failure()
+    .stdout(predicate::str::contains(r#""S": "abc""#))
+    .stderr(predicate::str::contains("Command at line 2 failed."))
+    .stderr(predicate::str::contains("Command at line 3 failed."))
+    .stderr(predicate::str::contains("2 command(s) failed."));
+
+    Ok(())
+}
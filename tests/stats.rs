@@ -0,0 +1,79 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+pub mod util;
+
+use crate::util::TemporaryItem;
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+
+#[tokio::test]
+async fn test_stats() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tm = util::setup().await?;
+    let table_name = tm
+        .create_temporary_table_with_items(
+            "pk,S",
+            Some("sk,N"),
+            [
+                TemporaryItem::new("a", Some("1"), None),
+                TemporaryItem::new("a", Some("2"), Some("{'name':'x'}")),
+                TemporaryItem::new("b", Some("1"), Some("{'name':'a long name'}")),
+            ],
+        )
+        .await?;
+
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "stats",
+        "--top",
+        "1",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(format!("table: {}\n", table_name)))
+    .stdout(predicate::str::contains("sampled: false\nitems: 3\n"))
+    .stdout(predicate::str::contains(
+        "top_partition_keys_by_items:\n- key: a\n  items: 2\n",
+    ))
+    .stdout(predicate::str::contains(
+        "top_partition_keys_by_bytes:\n- key: b\n  items: 1\n",
+    ))
+    .stdout(predicate::str::contains(
+        "- name: name\n  items: 2\n  percent: 66.7\n  types:\n    S: 2\n",
+    ));
+
+    // --sample stops scanning before the end of the table.
+    let mut c = tm.command()?;
+    c.args([
+        "--region",
+        "local",
+        "--table",
+        &table_name,
+        "stats",
+        "--sample",
+        "2",
+        "-o",
+        "jsonl",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(r#""sampled":true,"items":2,"#));
+
+    Ok(())
+}